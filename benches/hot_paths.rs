@@ -0,0 +1,94 @@
+use chrono::{NaiveDate, TimeZone, Utc};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use nsw_closest_display::data::location::{LocationId, LocationManager};
+use nsw_closest_display::data::shared_booking::{BookingData, LocationBookings, TestType, TimeSlot, BOOKING_DATA_VERSION};
+use nsw_closest_display::utils::sort::{compare_locations, SortColumn, SortDirection};
+
+const SYDNEY: (f64, f64) = (-33.8688197, 151.2092955);
+
+fn make_slots(count: usize) -> Vec<TimeSlot> {
+    (0..count)
+        .map(|i| TimeSlot {
+            availability: i % 3 != 0,
+            slot_number: None,
+            start_time: NaiveDate::from_ymd_opt(2025, 1 + (i as u32 / 28) % 12, 1 + i as u32 % 28)
+                .unwrap()
+                .and_hms_opt(8 + i as u32 % 9, (i as u32 * 5) % 60, 0)
+                .unwrap(),
+        })
+        .collect()
+}
+
+fn make_booking_data(locations: usize, slots_per_location: usize) -> BookingData {
+    BookingData {
+        results: (0..locations)
+            .map(|i| LocationBookings {
+                location: LocationId(i as u32 + 1),
+                test_type: TestType::Car,
+                slots: make_slots(slots_per_location),
+                next_available_date: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap().and_hms_opt(8, 0, 0),
+            })
+            .collect(),
+        last_updated: Some(Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()),
+        schema_version: BOOKING_DATA_VERSION,
+    }
+}
+
+fn bench_calculate_hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("calculate_hash");
+    // roughly the number of centres scraped, with one to several weeks of slots
+    for slots in [10, 50, 200] {
+        let data = make_booking_data(110, slots);
+        group.bench_with_input(BenchmarkId::from_parameter(slots), &data, |b, data| {
+            b.iter(|| black_box(data).calculate_hash())
+        });
+    }
+    group.finish();
+}
+
+fn bench_earliest_slot(c: &mut Criterion) {
+    let data = make_booking_data(110, 50);
+    c.bench_function("earliest_slot", |b| {
+        b.iter(|| {
+            black_box(&data)
+                .results
+                .iter()
+                .map(|location| location.earliest_slot().cloned())
+                .collect::<Vec<_>>()
+        })
+    });
+}
+
+fn bench_sorted_locations(c: &mut Criterion) {
+    let location_manager = LocationManager::new();
+    let data = make_booking_data(200, 50);
+
+    let rows: Vec<_> = location_manager
+        .get_by_distance(SYDNEY.0, SYDNEY.1)
+        .into_iter()
+        .map(|(loc, distance)| {
+            let earliest_slot = data
+                .results
+                .iter()
+                .find(|booking| booking.location == loc.id)
+                .and_then(|booking| booking.earliest_slot().cloned());
+            (loc, distance, earliest_slot)
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("sorted_locations");
+    for column in [SortColumn::Name, SortColumn::Distance, SortColumn::EarliestSlot, SortColumn::PassRate] {
+        group.bench_with_input(BenchmarkId::from_parameter(format!("{:?}", column)), &column, |b, &column| {
+            b.iter(|| {
+                let mut rows = rows.clone();
+                rows.sort_by(|a, b| compare_locations(a, b, column, SortDirection::Ascending));
+                rows
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_calculate_hash, bench_earliest_slot, bench_sorted_locations);
+criterion_main!(benches);
@@ -0,0 +1,460 @@
+//! Optional accounts so preferences follow a user across devices. Users sign
+//! in with a one-time link sent to their email address; the server keeps the
+//! account, its preferences and its sessions in `accounts.json`.
+//!
+//! Favourites and filters also live in the browser's localStorage. Both
+//! copies are `SyncedPreferences`, whose entries carry the time they were
+//! last changed, so any two copies merge to the same result.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::data::location::LocationId;
+use crate::data::shared_booking::TestType;
+use crate::notifications::NotificationRule;
+
+#[cfg(feature = "ssr")]
+pub use self::store::{
+    account_for_session, complete_login, load, logout, request_login, save_preferences, session_from_headers,
+    start_notifications, sync, SESSION_COOKIE, SESSION_DAYS,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedAddress {
+    pub label: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Filters {
+    pub test_type: TestType,
+    /// Hide centres further away than this
+    pub max_distance_km: Option<f64>,
+    /// Hide slots on or next to a NSW public holiday
+    pub hide_near_holidays: bool,
+}
+
+/// A value with the time it was last changed. Merging keeps the newer one.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stamped<T> {
+    pub value: T,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl<T: Clone + Serialize> Stamped<T> {
+    /// Keep the newer value. Changes made at the same instant keep the one
+    /// that serializes greater, so either side merging gets the same result.
+    fn merge(&mut self, other: &Self) {
+        let newer = match other.updated_at.cmp(&self.updated_at) {
+            std::cmp::Ordering::Equal => serde_json::to_string(&other.value).ok() > serde_json::to_string(&self.value).ok(),
+            ordering => ordering.is_gt(),
+        };
+        if newer {
+            *self = other.clone();
+        }
+    }
+}
+
+/// Favourites and filters in a form that merges without conflicts. Removing
+/// a favourite keeps its entry as `false`, so the removal wins over an older
+/// add from another device.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncedPreferences {
+    pub favourites: BTreeMap<LocationId, Stamped<bool>>,
+    pub filters: Stamped<Filters>,
+}
+
+impl SyncedPreferences {
+    pub fn favourites(&self) -> Vec<LocationId> {
+        self.favourites
+            .iter()
+            .filter(|(_, favourite)| favourite.value)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    pub fn is_favourite(&self, id: LocationId) -> bool {
+        self.favourites.get(&id).is_some_and(|favourite| favourite.value)
+    }
+
+    pub fn set_favourite(&mut self, id: LocationId, favourite: bool, now: DateTime<Utc>) {
+        self.favourites.insert(id, Stamped { value: favourite, updated_at: now });
+    }
+
+    pub fn set_filters(&mut self, filters: Filters, now: DateTime<Utc>) {
+        self.filters = Stamped { value: filters, updated_at: now };
+    }
+
+    /// Fold `other` into this copy. Commutative, associative and idempotent,
+    /// so the order devices sync in doesn't matter. Equal timestamps keep a
+    /// favourite, `true` serializing greater than `false`.
+    pub fn merge(&mut self, other: &SyncedPreferences) {
+        for (id, theirs) in &other.favourites {
+            match self.favourites.get_mut(id) {
+                Some(ours) => ours.merge(theirs),
+                None => {
+                    self.favourites.insert(*id, theirs.clone());
+                }
+            }
+        }
+        self.filters.merge(&other.filters);
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub watched_locations: Vec<LocationId>,
+    pub filters: Filters,
+    pub saved_addresses: Vec<SavedAddress>,
+    /// Webhooks sent the earlier slots found at `watched_locations`
+    pub notification_rules: Vec<NotificationRule>,
+    /// Stamped copy of `watched_locations` and `filters` for syncing
+    pub synced: SyncedPreferences,
+}
+
+impl Preferences {
+    /// Stamp edits made to `watched_locations` and `filters` directly, e.g.
+    /// from the account page, into `synced`.
+    pub fn record_edits(&mut self, now: DateTime<Utc>) {
+        let favourites = self.synced.favourites();
+        for id in &self.watched_locations {
+            if !favourites.contains(id) {
+                self.synced.set_favourite(*id, true, now);
+            }
+        }
+        for id in favourites {
+            if !self.watched_locations.contains(&id) {
+                self.synced.set_favourite(id, false, now);
+            }
+        }
+        if self.filters != self.synced.filters.value {
+            self.synced.set_filters(self.filters.clone(), now);
+        }
+    }
+
+    /// Merge another copy into `synced` and update the plain fields from it.
+    pub fn merge_synced(&mut self, other: &SyncedPreferences) {
+        self.synced.merge(other);
+        self.watched_locations = self.synced.favourites();
+        self.filters = self.synced.filters.value.clone();
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub email: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub preferences: Preferences,
+}
+
+/// What the client learns about the current visitor.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountState {
+    pub enabled: bool,
+    pub account: Option<Account>,
+}
+
+/// Loose sanity check; the sign-in link is the real verification.
+pub fn is_plausible_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !email.chars().any(|c| c.is_whitespace() || c == '<' || c == '>')
+}
+
+#[cfg(feature = "ssr")]
+mod store {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, OnceLock, RwLock};
+
+    use axum::http::{header, HeaderMap};
+    use chrono::{DateTime, Duration, Utc};
+    use rand::Rng;
+    use serde::{Deserialize, Serialize};
+
+    use super::{Account, Preferences, SyncedPreferences};
+    use crate::data::booking::BookingManager;
+    use crate::data::location::LocationId;
+    use crate::notifications::{self, Notification, NotificationRule};
+    use crate::settings::Settings;
+    use crate::utils::task::spawn_named;
+
+    pub const SESSION_COOKIE: &str = "session";
+    pub const SESSION_DAYS: i64 = 30;
+    const LOGIN_LINK_MINUTES: i64 = 15;
+    /// Wait before another sign-in link can be mailed to the same address.
+    const LOGIN_COOLDOWN_SECONDS: i64 = 60;
+    /// Sign-in links outstanding at once, across all addresses.
+    const MAX_PENDING_LOGINS: usize = 500;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct Session {
+        email: String,
+        expires_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct AccountStore {
+        accounts: HashMap<String, Account>,
+        sessions: HashMap<String, Session>,
+        /// Outstanding sign-in links, token to email and expiry
+        #[serde(skip)]
+        logins: HashMap<String, (String, DateTime<Utc>)>,
+        /// When a sign-in link was last mailed to each address
+        #[serde(skip)]
+        login_requests: HashMap<String, DateTime<Utc>>,
+        #[serde(skip)]
+        path: Option<PathBuf>,
+    }
+
+    impl AccountStore {
+        fn save(&self) -> Result<(), String> {
+            let Some(path) = &self.path else {
+                return Ok(());
+            };
+            let contents = serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize accounts: {}", e))?;
+            fs::write(path, contents).map_err(|e| format!("Failed to write to file: {}", e))
+        }
+    }
+
+    static ACCOUNTS: OnceLock<Arc<RwLock<AccountStore>>> = OnceLock::new();
+
+    fn get_accounts() -> &'static Arc<RwLock<AccountStore>> {
+        ACCOUNTS.get_or_init(|| Arc::new(RwLock::new(AccountStore::default())))
+    }
+
+    fn new_token() -> String {
+        let mut rng = rand::thread_rng();
+        (0..48)
+            .map(|_| format!("{:x}", rng.gen_range(0..16u8)))
+            .collect()
+    }
+
+    /// Load accounts and sessions from `path`, which is also where changes are
+    /// saved. A missing file means no accounts yet.
+    pub fn load(path: &Path) -> Result<(), String> {
+        let mut store = if path.exists() {
+            let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+            serde_json::from_str(&contents).map_err(|e| format!("Failed to parse JSON: {}", e))?
+        } else {
+            AccountStore::default()
+        };
+
+        let now = Utc::now();
+        store.sessions.retain(|_, session| session.expires_at > now);
+        store.path = Some(path.to_path_buf());
+        *get_accounts().write().unwrap() = store;
+        Ok(())
+    }
+
+    /// Create a sign-in link for `email` and send it through
+    /// `login_mail_webhook`. Each address gets at most one link per
+    /// `LOGIN_COOLDOWN_SECONDS`, and no more than `MAX_PENDING_LOGINS` are
+    /// outstanding, so the form can't be used to flood an inbox.
+    pub async fn request_login(settings: &Settings, email: &str) -> Result<(), String> {
+        let email = email.trim().to_lowercase();
+        if !super::is_plausible_email(&email) {
+            return Err("Please enter a valid email address".into());
+        }
+        let Some(webhook) = settings.login_mail_webhook.as_deref() else {
+            return Err("Sign-in emails are not configured on this server".into());
+        };
+        let Some(base_url) = settings.public_url.as_deref().map(|url| url.trim_end_matches('/')) else {
+            return Err("Sign-in emails are not configured on this server".into());
+        };
+
+        let token = new_token();
+        {
+            let mut store = get_accounts().write().unwrap();
+            let now = Utc::now();
+            store.logins.retain(|_, (_, expires_at)| *expires_at > now);
+            let cooldown_start = now - Duration::seconds(LOGIN_COOLDOWN_SECONDS);
+            store.login_requests.retain(|_, requested_at| *requested_at > cooldown_start);
+            if store.login_requests.contains_key(&email) {
+                return Err("A sign-in link was just sent, please wait a minute before asking again".into());
+            }
+            if store.logins.len() >= MAX_PENDING_LOGINS {
+                return Err("Too many sign-in requests right now, please try again later".into());
+            }
+            store.login_requests.insert(email.clone(), now);
+            store
+                .logins
+                .insert(token.clone(), (email.clone(), now + Duration::minutes(LOGIN_LINK_MINUTES)));
+        }
+
+        let link = format!("{}/login/{}", base_url, token);
+        let mail = serde_json::json!({
+            "to": email,
+            "subject": "Your sign-in link",
+            "text": format!(
+                "Open this link to sign in. It expires in {} minutes and works once.\n\n{}",
+                LOGIN_LINK_MINUTES, link
+            ),
+        });
+
+        reqwest::Client::new()
+            .post(webhook)
+            .json(&mail)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| {
+                eprintln!("ERROR: Failed to send sign-in email: {}", e);
+                "Failed to send the sign-in email".to_string()
+            })?;
+
+        println!("INFO: Sent sign-in link");
+        Ok(())
+    }
+
+    /// Redeem a sign-in link. Creates the account on first sign-in and
+    /// returns a new session token.
+    pub fn complete_login(token: &str) -> Result<String, String> {
+        let mut store = get_accounts().write().unwrap();
+        let (email, expires_at) = store
+            .logins
+            .remove(token)
+            .ok_or_else(|| "This sign-in link is unknown or has already been used".to_string())?;
+        let now = Utc::now();
+        if expires_at < now {
+            return Err("This sign-in link has expired".into());
+        }
+
+        store.accounts.entry(email.clone()).or_insert_with(|| Account {
+            email: email.clone(),
+            created_at: now,
+            preferences: Preferences::default(),
+        });
+
+        let session = new_token();
+        store.sessions.insert(
+            session.clone(),
+            Session {
+                email,
+                expires_at: now + Duration::days(SESSION_DAYS),
+            },
+        );
+        store.save()?;
+        Ok(session)
+    }
+
+    pub fn account_for_session(session: &str) -> Option<Account> {
+        let store = get_accounts().read().unwrap();
+        let session = store.sessions.get(session).filter(|session| session.expires_at > Utc::now())?;
+        store.accounts.get(&session.email).cloned()
+    }
+
+    pub fn save_preferences(session: &str, preferences: Preferences) -> Result<Account, String> {
+        let mut store = get_accounts().write().unwrap();
+        let email = store
+            .sessions
+            .get(session)
+            .filter(|session| session.expires_at > Utc::now())
+            .map(|session| session.email.clone())
+            .ok_or_else(|| "Not signed in".to_string())?;
+
+        for rule in &preferences.notification_rules {
+            notifications::check_account_webhook(&rule.url)?;
+        }
+
+        let account = store.accounts.get_mut(&email).ok_or_else(|| "Not signed in".to_string())?;
+        let mut preferences = preferences;
+        preferences.synced = account.preferences.synced.clone();
+        preferences.record_edits(Utc::now());
+        account.preferences = preferences;
+        let account = account.clone();
+        store.save()?;
+        Ok(account)
+    }
+
+    /// Merge a device's favourites and filters into the account and return
+    /// the merged copy for the device to keep.
+    pub fn sync(session: &str, local: &SyncedPreferences) -> Result<SyncedPreferences, String> {
+        let mut store = get_accounts().write().unwrap();
+        let email = store
+            .sessions
+            .get(session)
+            .filter(|session| session.expires_at > Utc::now())
+            .map(|session| session.email.clone())
+            .ok_or_else(|| "Not signed in".to_string())?;
+
+        let account = store.accounts.get_mut(&email).ok_or_else(|| "Not signed in".to_string())?;
+        account.preferences.merge_synced(local);
+        let merged = account.preferences.synced.clone();
+        store.save()?;
+        Ok(merged)
+    }
+
+    /// Watched locations and notification rules of each account with rules.
+    fn notification_targets() -> Vec<(Vec<LocationId>, Vec<NotificationRule>)> {
+        get_accounts()
+            .read()
+            .unwrap()
+            .accounts
+            .values()
+            .filter(|account| !account.preferences.notification_rules.is_empty())
+            .map(|account| {
+                (
+                    account.preferences.watched_locations.clone(),
+                    account.preferences.notification_rules.clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// Send each account's notification rules the earlier slots found at
+    /// its watched centres after every update.
+    pub fn start_notifications() {
+        let mut events = BookingManager::subscribe_changes();
+        spawn_named("account-notifications", async move {
+            loop {
+                match events.recv().await {
+                    Ok(changes) => {
+                        for (watched, rules) in notification_targets() {
+                            if let Some(notification) = Notification::earlier_slots(&changes.for_locations(&watched)) {
+                                notifications::dispatch_to(&rules, &notification).await;
+                            }
+                        }
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(missed)) => {
+                        eprintln!("WARN: Account notifications fell behind, skipped {} updates", missed);
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    pub fn logout(session: &str) -> Result<(), String> {
+        let mut store = get_accounts().write().unwrap();
+        if store.sessions.remove(session).is_some() {
+            store.save()?;
+        }
+        Ok(())
+    }
+
+    /// The session token from the request's cookie header, if any.
+    pub fn session_from_headers(headers: &HeaderMap) -> Option<String> {
+        headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .filter_map(|pair| pair.trim().split_once('='))
+            .find(|(name, _)| *name == SESSION_COOKIE)
+            .map(|(_, value)| value.to_string())
+    }
+}
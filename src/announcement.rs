@@ -0,0 +1,93 @@
+//! Operator announcement shown as a banner above the availability table and
+//! returned with the scraper status, e.g. "RTA portal under maintenance
+//! tonight; data may be stale".
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::notifications::NotificationLevel;
+
+#[cfg(feature = "server")]
+pub use self::store::{current, load, set};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Announcement {
+    pub message: String,
+    pub level: NotificationLevel,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Announcement {
+    /// Identifies the announcement for dismissing it: a hash of the message,
+    /// so a dismissed one stays hidden across restarts but the next one shows.
+    /// FNV-1a, the same in the browser and on the server across builds.
+    pub fn key(&self) -> String {
+        let hash = self
+            .message
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3));
+        format!("{:016x}", hash)
+    }
+}
+
+#[cfg(feature = "server")]
+mod store {
+    use std::fs;
+    use std::sync::{Arc, OnceLock, RwLock};
+
+    use super::Announcement;
+    use crate::notifications::NotificationLevel;
+    use crate::settings::Settings;
+
+    static ANNOUNCEMENT: OnceLock<Arc<RwLock<Option<Announcement>>>> = OnceLock::new();
+
+    fn get_announcement() -> &'static Arc<RwLock<Option<Announcement>>> {
+        ANNOUNCEMENT.get_or_init(|| Arc::new(RwLock::new(None)))
+    }
+
+    /// Load the announcement set from the admin dashboard, falling back to
+    /// `announcement` in settings when the dashboard has never set one.
+    pub fn load(settings: &Settings) -> Result<(), String> {
+        let path = settings.announcement_file();
+        let loaded = if path.exists() {
+            let contents = fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+            serde_json::from_str(&contents).map_err(|e| format!("Failed to parse JSON: {}", e))?
+        } else {
+            settings
+                .announcement
+                .as_deref()
+                .map(str::trim)
+                .filter(|message| !message.is_empty())
+                .map(|message| Announcement {
+                    message: message.to_string(),
+                    level: NotificationLevel::Warning,
+                    updated_at: chrono::Utc::now(),
+                })
+        };
+
+        *get_announcement().write().unwrap() = loaded;
+        Ok(())
+    }
+
+    pub fn current() -> Option<Announcement> {
+        get_announcement().read().unwrap().clone()
+    }
+
+    /// Replace or, with an empty message, clear the announcement. The choice
+    /// is saved and takes precedence over settings from then on.
+    pub fn set(settings: &Settings, message: &str, level: NotificationLevel) -> Result<Option<Announcement>, String> {
+        let message = message.trim();
+        let announcement = (!message.is_empty()).then(|| Announcement {
+            message: message.to_string(),
+            level,
+            updated_at: chrono::Utc::now(),
+        });
+
+        let contents =
+            serde_json::to_string_pretty(&announcement).map_err(|e| format!("Failed to serialize announcement: {}", e))?;
+        fs::write(settings.announcement_file(), contents).map_err(|e| format!("Failed to write to file: {}", e))?;
+
+        *get_announcement().write().unwrap() = announcement.clone();
+        Ok(announcement)
+    }
+}
@@ -0,0 +1,239 @@
+//! Plain REST endpoints served next to the Leptos routes, for external
+//! consumers and container orchestration.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, Query};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Serialize;
+
+use crate::data::booking::BookingManager;
+use crate::data::history::{self, CentreStats, HistoryStats, WeekdayStats};
+use crate::data::location::LocationId;
+use crate::data::shared_booking::{LocationCapacity, TestType};
+use crate::settings::Settings;
+use crate::utils::sort::LocationsQuery;
+
+pub fn router<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let router = Router::new()
+        .route("/api/data", get(|| async { booking_data(TestType::Car) }))
+        .route("/api/data/:test_type", get(|Path(test_type): Path<TestType>| async move {
+            booking_data(test_type)
+        }))
+        .route("/api/data/:test_type/locations/:location", get(
+            |Path((test_type, location)): Path<(TestType, LocationId)>, headers: HeaderMap| async move {
+                location_data(test_type, location, &headers)
+            },
+        ))
+        .route("/api/status", get(|| async { Json(BookingManager::get_status(TestType::Car)) }))
+        .route("/api/status/:test_type", get(|Path(test_type): Path<TestType>| async move {
+            Json(BookingManager::get_status(test_type))
+        }))
+        .route("/api/capacity/:test_type", get(|Path(test_type): Path<TestType>| async move {
+            let capacity: Vec<LocationCapacity> =
+                BookingManager::get_data(test_type).0.results.iter().map(|bookings| bookings.capacity()).collect();
+            Json(capacity)
+        }))
+        .route("/api/locations", get(|Query(query): Query<LocationsQuery>| async move {
+            Json(BookingManager::sorted_locations(&query))
+        }))
+        .route("/api/changes/:test_type", get(|Path(test_type): Path<TestType>| async move {
+            match BookingManager::latest_changes(test_type) {
+                Some(changes) => Json(changes).into_response(),
+                None => StatusCode::NO_CONTENT.into_response(),
+            }
+        }))
+        .route("/api/auto-find/allocations", get(|| async { Json(crate::data::auto_find::allocations()) }))
+        .route("/api/stats/:test_type", get(|Path(test_type): Path<TestType>| async move {
+            with_stats(test_type, |stats| Json(stats.centres.clone()).into_response())
+        }))
+        .route("/api/stats/:test_type/weekdays", get(|Path(test_type): Path<TestType>| async move {
+            with_stats(test_type, |stats| Json(stats.weekdays.clone()).into_response())
+        }))
+        .route("/api/stats/:test_type/locations/:location", get(
+            |Path((test_type, location)): Path<(TestType, LocationId)>| async move {
+                with_stats(test_type, |stats| location_stats(stats, location))
+            },
+        ))
+        // Mail scanners prefetch links, only the page's POST redeems the token
+        .route(
+            "/login/:token",
+            get(|| async { action_page("Sign in", "Continue to sign in to your account.", "Sign in") })
+                .post(|Path(token): Path<String>| async move { complete_login(&token) }),
+        )
+        .route("/health", get(|| async { StatusCode::OK }))
+        .route("/ready", get(|| async {
+            // The WebDriver is shared, every test type reports the same
+            if BookingManager::get_status(TestType::Car).is_ready() {
+                StatusCode::OK
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+        }));
+
+    // The links only open a page, link scanners and chat previews follow
+    // them too. The decision is the page's POST
+    #[cfg(feature = "scraper")]
+    let router = router
+        .route(
+            "/api/confirm/:token",
+            get(|Path(token): Path<String>| async move { held_booking_page(&token, true) })
+                .post(|Path(token): Path<String>| async move { resolve_held_booking(&token, true) }),
+        )
+        .route(
+            "/api/decline/:token",
+            get(|Path(token): Path<String>| async move { held_booking_page(&token, false) })
+                .post(|Path(token): Path<String>| async move { resolve_held_booking(&token, false) }),
+        );
+
+    router
+}
+
+/// Booking data with the scraper health in `x-data-degraded` and
+/// `x-data-stale` headers, so consumers can tell when not to trust it.
+fn booking_data(test_type: TestType) -> Response {
+    let status = BookingManager::get_status(test_type);
+    let mut response = Json(BookingManager::get_data(test_type).0).into_response();
+
+    let flag = |set: bool| HeaderValue::from_static(if set { "true" } else { "false" });
+    let headers = response.headers_mut();
+    headers.insert("x-data-degraded", flag(status.degraded));
+    headers.insert("x-data-stale", flag(status.stale));
+    response
+}
+
+/// One location's bookings with an `ETag`. A matching `If-None-Match` is
+/// answered with 304 without copying the slots.
+fn location_data(test_type: TestType, location: LocationId, headers: &HeaderMap) -> Response {
+    let Some(etag) = BookingManager::location_etag(test_type, location) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let etag = format!("\"{}\"", etag);
+    if headers.get(header::IF_NONE_MATCH).is_some_and(|value| value.as_bytes() == etag.as_bytes()) {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+
+    match BookingManager::get_location_data(test_type, location) {
+        Some((bookings, etag)) => ([(header::ETAG, format!("\"{}\"", etag))], Json(bookings)).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Redeem a sign-in link from its page's POST: set the session cookie and
+/// send the user home.
+fn complete_login(token: &str) -> Response {
+    use crate::accounts;
+
+    let settings = match Settings::load() {
+        Ok(settings) if settings.accounts_enabled => settings,
+        Ok(_) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            eprintln!("ERROR: Failed to load settings: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    match accounts::complete_login(token) {
+        Ok(session) => {
+            let secure = settings.public_url.as_deref().is_some_and(|url| url.starts_with("https://"));
+            let cookie = format!(
+                "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}{}",
+                accounts::SESSION_COOKIE,
+                session,
+                accounts::SESSION_DAYS * 24 * 60 * 60,
+                if secure { "; Secure" } else { "" },
+            );
+            (StatusCode::SEE_OTHER, [(header::SET_COOKIE, cookie), (header::LOCATION, "/account".to_string())])
+                .into_response()
+        }
+        Err(message) => (StatusCode::BAD_REQUEST, message).into_response(),
+    }
+}
+
+/// Statistics for one centre, with its figures broken down by weekday.
+#[derive(Serialize)]
+struct LocationStats {
+    centre: CentreStats,
+    weekdays: Vec<WeekdayStats>,
+}
+
+fn with_stats(test_type: TestType, respond: impl FnOnce(&HistoryStats) -> Response) -> Response {
+    let stats: Result<Arc<HistoryStats>, String> = Settings::load()
+        .map_err(|e| format!("Failed to load settings: {}", e))
+        .and_then(|settings| history::cached_stats(&settings.history_file(), settings.history_cutoff(), test_type));
+
+    match stats {
+        Ok(stats) => respond(&stats),
+        Err(e) => {
+            eprintln!("ERROR: Failed to load history statistics: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+fn location_stats(stats: &HistoryStats, location: LocationId) -> Response {
+    let centre = stats.centres.iter().find(|centre| centre.location == location);
+    let weekdays = stats.weekdays_by_location.get(&location);
+
+    match (centre, weekdays) {
+        (Some(centre), Some(weekdays)) => Json(LocationStats {
+            centre: centre.clone(),
+            weekdays: weekdays.clone(),
+        })
+        .into_response(),
+        _ => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// A page with one button that POSTs back to the URL it was opened at.
+fn action_page(title: &str, message: &str, button: &str) -> Html<String> {
+    Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head><meta charset="utf-8"><meta name="viewport" content="width=device-width, initial-scale=1"><title>{title}</title></head>
+<body style="font-family: sans-serif; max-width: 32rem; margin: 4rem auto; padding: 0 1rem">
+<h1>{title}</h1>
+<p>{message}</p>
+<form method="post"><button type="submit" style="font-size: 1.1rem; padding: 0.5rem 1.5rem">{button}</button></form>
+</body>
+</html>"#
+    ))
+}
+
+#[cfg(feature = "scraper")]
+fn held_booking_page(token: &str, confirmed: bool) -> Response {
+    use crate::utils::date::format_slot_time;
+
+    let Some((location, start_time)) = crate::data::confirmation::peek(token) else {
+        return (StatusCode::NOT_FOUND, "This link is unknown or has expired").into_response();
+    };
+    let slot = format!("the slot at {} on {}", location, format_slot_time(&start_time));
+    if confirmed {
+        action_page("Confirm booking", &format!("Book {}?", slot), "Book it").into_response()
+    } else {
+        action_page("Release slot", &format!("Release {}?", slot), "Release it").into_response()
+    }
+}
+
+#[cfg(feature = "scraper")]
+fn resolve_held_booking(token: &str, confirmed: bool) -> (StatusCode, String) {
+    use crate::utils::date::format_slot_time;
+
+    match crate::data::confirmation::resolve(token, confirmed) {
+        Some((location, start_time)) if confirmed => (
+            StatusCode::OK,
+            format!("Booking the slot at {} on {}", location, format_slot_time(&start_time)),
+        ),
+        Some((location, start_time)) => (
+            StatusCode::OK,
+            format!("Released the slot at {} on {}", location, format_slot_time(&start_time)),
+        ),
+        None => (StatusCode::NOT_FOUND, "This link is unknown or has expired".to_string()),
+    }
+}
@@ -5,7 +5,10 @@ use leptos_router::{
     StaticSegment,
 };
 
+use crate::pages::account::AccountPage;
+use crate::pages::admin::AdminPage;
 use crate::pages::home::HomePage;
+use crate::pages::stats::StatsPage;
 
 pub fn shell(options: LeptosOptions) -> impl IntoView {
     view! {
@@ -39,6 +42,9 @@ pub fn App() -> impl IntoView {
         <Router>
             <FlatRoutes fallback=|| "Page not found.">
                 <Route path=StaticSegment("") view=HomePage/>
+                <Route path=StaticSegment("stats") view=StatsPage/>
+                <Route path=StaticSegment("admin") view=AdminPage/>
+                <Route path=StaticSegment("account") view=AccountPage/>
             </FlatRoutes>
         </Router>
     }
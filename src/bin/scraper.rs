@@ -0,0 +1,64 @@
+//! Headless scraper daemon: keeps the booking data file up to date and sends
+//! alerts, without building the Leptos frontend.
+
+use nsw_closest_display::data::booking::BookingManager;
+use nsw_closest_display::data::location::LocationManager;
+use nsw_closest_display::data::shared_booking::TestType;
+use nsw_closest_display::settings::Settings;
+use tracing::{error, info};
+
+#[tokio::main]
+async fn main() {
+    nsw_closest_display::utils::logging::init();
+
+    let mut settings = Settings::load().unwrap();
+
+    if settings.manage_driver {
+        if let Err(e) = nsw_closest_display::selenium::driver_manager::start(&mut settings).await {
+            eprintln!("ERROR: Failed to start chromedriver: {}", e);
+        }
+    }
+
+    nsw_closest_display::data::storage::start_mirror(&settings).await;
+    nsw_closest_display::data::leader::start(&settings).await;
+
+    for &test_type in &settings.test_types {
+        match BookingManager::load_data(test_type, &settings) {
+            Ok(_) => info!("BookingManager initialized {} data from {:?} store", test_type.label(), settings.data_store),
+            Err(e) => error!("Failed to initialize BookingManager from {:?} store: {}", settings.data_store, e),
+        }
+    }
+
+    if let Err(e) = nsw_closest_display::notifications::load_rules(&settings.notification_rules_file()) {
+        eprintln!("ERROR: Failed to load notification rules: {}", e);
+    }
+
+    if let Err(e) = BookingManager::load_runs(&settings) {
+        eprintln!("ERROR: Failed to load scrape run log: {}", e);
+    }
+
+    if let Err(e) = BookingManager::load_booking_history(&settings) {
+        error!("Failed to load booking history: {}", e);
+    }
+
+    let location_ids = LocationManager::new().scrape_list(&settings);
+    if let Some((_, _, radius_km)) = settings.scrape_area() {
+        println!("INFO: Scraping {} centres within {} km of home", location_ids.len(), radius_km);
+    }
+
+    match BookingManager::restore_auto_find(&settings) {
+        Ok(0) => {}
+        Ok(count) => info!("Restored {} auto-find jobs", count),
+        Err(e) => error!("Failed to restore auto-find jobs: {}", e),
+    }
+    BookingManager::start_change_notifications(&settings);
+    BookingManager::start_compaction(&settings);
+    BookingManager::start_booking_queue(&settings);
+    BookingManager::start_submission_intake(&settings);
+    BookingManager::start_background_updates(location_ids, settings);
+
+    nsw_closest_display::utils::task::shutdown_signal().await;
+    println!("Shutting down scraper");
+    BookingManager::stop_background_updates();
+    nsw_closest_display::data::storage::flush_mirror().await;
+}
@@ -0,0 +1,89 @@
+//! Append-only record of what the bot did on its own: every scrape run,
+//! auto-find round and booking attempt appends one line to `audit.jsonl` with
+//! what it was asked to do and how it ended. Nothing is ever rewritten, so
+//! the file shows exactly what happened overnight.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Scrape,
+    AutoFind,
+    Booking,
+}
+
+impl AuditAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditAction::Scrape => "Scrape",
+            AuditAction::AutoFind => "Auto-find",
+            AuditAction::Booking => "Booking",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub action: AuditAction,
+    /// What the action was asked to do, e.g. the test type and centres
+    pub parameters: Value,
+    pub success: bool,
+    /// How it ended, in a few words
+    pub outcome: String,
+}
+
+impl AuditEntry {
+    pub fn new(action: AuditAction, parameters: Value, success: bool, outcome: impl Into<String>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            action,
+            parameters,
+            success,
+            outcome: outcome.into(),
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+pub use self::store::{append, recent, record};
+
+#[cfg(feature = "server")]
+mod store {
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use tracing::error;
+
+    use super::AuditEntry;
+    use crate::data::jsonl;
+    use crate::settings::Settings;
+
+    /// Keeps lines from concurrent actions whole.
+    static APPEND_LOCK: Mutex<()> = Mutex::new(());
+
+    pub fn append(path: &Path, entry: &AuditEntry) -> Result<(), String> {
+        let _guard = APPEND_LOCK.lock().unwrap();
+        jsonl::append(path, std::slice::from_ref(entry), "audit log")
+    }
+
+    /// The newest `limit` entries, oldest first. Unparseable lines are skipped.
+    pub fn recent(path: &Path, limit: usize) -> Result<Vec<AuditEntry>, String> {
+        let mut entries: Vec<AuditEntry> = jsonl::read(path, "audit log")?;
+        let overflow = entries.len().saturating_sub(limit);
+        entries.drain(..overflow);
+        Ok(entries)
+    }
+
+    /// Append to the audit log in the data directory. A failure is logged,
+    /// never returned: the action itself already happened.
+    pub fn record(settings: &Settings, entry: AuditEntry) {
+        let path = settings.audit_log_file();
+        if let Err(e) = append(&path, &entry) {
+            error!("Failed to record {} in '{}': {}", entry.action.label(), path.display(), e);
+        }
+    }
+}
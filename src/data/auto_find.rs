@@ -0,0 +1,429 @@
+//! Auto-find jobs and the coordinator that hands every slot found to exactly
+//! one of them. Each booking has at most one job; all jobs share a single
+//! search loop so two jobs watching the same centre never race each other.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::location::{LocationId, LocationManager, SearchArea};
+use super::scoring::SlotScoring;
+use super::strategy::{SlotPreferences, StrategyConfig};
+use super::shared_booking::{JobStatus, TimeSlot};
+use super::storage::Storage;
+use crate::settings::Settings;
+
+/// Allocation decisions kept for inspection.
+const MAX_ALLOCATIONS: usize = 100;
+
+/// Job changes followers leave for the leader, in the shared storage.
+pub const SUBMISSIONS_KEY: &str = "auto_find_submissions.json";
+
+/// Tries at appending a submission while other replicas write it too.
+const SUBMIT_ATTEMPTS: usize = 5;
+
+pub type JobId = u64;
+
+/// What a job is looking for. Higher `priority` wins a contested slot; among
+/// equal priorities the job that has waited longest for a slot goes first,
+/// so queued bookings take turns.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoFindJob {
+    pub id: JobId,
+    pub booking_id: String,
+    pub locations: Vec<LocationId>,
+    /// Centres within this area are searched on top of `locations`
+    #[serde(default)]
+    pub area: Option<SearchArea>,
+    pub before: NaiveDate,
+    pub priority: i32,
+    pub created_at: DateTime<Utc>,
+    pub scoring: SlotScoring,
+    pub strategy: StrategyConfig,
+    #[serde(default)]
+    pub preferences: SlotPreferences,
+    /// Hold a slot found for this job until the user approves booking it
+    #[serde(default)]
+    pub needs_approval: bool,
+    /// When the coordinator last handed this job a slot
+    #[serde(default)]
+    pub last_served: Option<DateTime<Utc>>,
+    /// Search rounds the job has been part of
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub last_attempt: Option<DateTime<Utc>>,
+}
+
+impl AutoFindJob {
+    pub fn wants(&self, location: LocationId, slot: &TimeSlot) -> bool {
+        slot.availability
+            && slot.start_time.date() <= self.before
+            && self.covers(location)
+            && self.preferences.accepts(slot)
+    }
+
+    pub fn covers(&self, location: LocationId) -> bool {
+        self.locations.contains(&location)
+            || self.area.is_some_and(|area| {
+                LocationManager::new().get_by_id(location).is_some_and(|details| area.contains(&details))
+            })
+    }
+
+    /// The listed centres plus those currently inside `area`.
+    pub fn search_locations(&self) -> Vec<LocationId> {
+        let mut locations = self.locations.clone();
+        if let Some(area) = &self.area {
+            for location in LocationManager::new().within(area) {
+                if !locations.contains(&location) {
+                    locations.push(location);
+                }
+            }
+        }
+        locations
+    }
+}
+
+/// A job as kept in `auto_find_jobs.json`. Its settings are rebuilt from
+/// `settings.yaml` on restore, so only the sign-in name is saved with it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedJob {
+    pub job: AutoFindJob,
+    pub last_name: String,
+    #[serde(default)]
+    pub status: JobStatus,
+}
+
+/// A slot handed to one job, with every job that wanted it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Allocation {
+    pub job_id: JobId,
+    pub location: LocationId,
+    pub start_time: NaiveDateTime,
+    pub contenders: Vec<JobId>,
+    pub decided_at: DateTime<Utc>,
+}
+
+static JOBS: OnceLock<Arc<RwLock<Vec<(AutoFindJob, Settings)>>>> = OnceLock::new();
+static ALLOCATIONS: OnceLock<Arc<RwLock<Vec<Allocation>>>> = OnceLock::new();
+static STATUSES: OnceLock<Arc<RwLock<HashMap<String, JobStatus>>>> = OnceLock::new();
+static NEXT_ROUND: OnceLock<Arc<RwLock<Option<DateTime<Utc>>>>> = OnceLock::new();
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+
+fn get_jobs() -> &'static Arc<RwLock<Vec<(AutoFindJob, Settings)>>> {
+    JOBS.get_or_init(|| Arc::new(RwLock::new(Vec::new())))
+}
+
+fn get_allocations() -> &'static Arc<RwLock<Vec<Allocation>>> {
+    ALLOCATIONS.get_or_init(|| Arc::new(RwLock::new(Vec::new())))
+}
+
+fn get_statuses() -> &'static Arc<RwLock<HashMap<String, JobStatus>>> {
+    STATUSES.get_or_init(|| Arc::new(RwLock::new(HashMap::new())))
+}
+
+fn get_next_round() -> &'static Arc<RwLock<Option<DateTime<Utc>>>> {
+    NEXT_ROUND.get_or_init(|| Arc::new(RwLock::new(None)))
+}
+
+/// Register a job for `settings.booking_id`, replacing any job that booking
+/// already had.
+pub fn add_job(
+    locations: Vec<LocationId>,
+    area: Option<SearchArea>,
+    before: NaiveDate,
+    priority: i32,
+    settings: Settings,
+) -> JobId {
+    let job = new_job(locations, area, before, priority, &settings);
+    let id = job.id;
+    set_status(&job.booking_id, JobStatus::Searching);
+
+    let mut jobs = get_jobs().write().unwrap();
+    jobs.retain(|(existing, _)| existing.booking_id != job.booking_id);
+    jobs.push((job, settings));
+    id
+}
+
+/// A job for `settings.booking_id` with a fresh id, not registered yet.
+pub fn new_job(
+    locations: Vec<LocationId>,
+    area: Option<SearchArea>,
+    before: NaiveDate,
+    priority: i32,
+    settings: &Settings,
+) -> AutoFindJob {
+    AutoFindJob {
+        id: NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed),
+        booking_id: settings.booking_id.clone(),
+        locations,
+        area,
+        before,
+        priority,
+        created_at: Utc::now(),
+        scoring: settings.scoring.clone(),
+        strategy: settings.find_strategy.clone(),
+        preferences: settings.slot_preferences.clone(),
+        needs_approval: settings.confirm_bookings,
+        last_served: None,
+        attempts: 0,
+        last_attempt: None,
+    }
+}
+
+/// Put a saved job back with its id, attempts and status, signed in with its
+/// own booking and using its own criteria on top of `settings`.
+pub fn restore(saved: SavedJob, settings: &Settings) -> JobId {
+    let SavedJob { job, last_name, status } = saved;
+    let mut settings = settings.clone();
+    settings.booking_id = job.booking_id.clone();
+    settings.last_name = last_name;
+    settings.scoring = job.scoring.clone();
+    settings.find_strategy = job.strategy.clone();
+    settings.slot_preferences = job.preferences.clone();
+    settings.confirm_bookings = job.needs_approval;
+
+    let id = job.id;
+    NEXT_JOB_ID.fetch_max(id + 1, Ordering::Relaxed);
+    set_status(&job.booking_id, status);
+
+    let mut jobs = get_jobs().write().unwrap();
+    jobs.retain(|(existing, _)| existing.booking_id != job.booking_id && existing.id != id);
+    jobs.push((job, settings));
+    id
+}
+
+/// Register a job a follower submitted. It keeps its id unless another
+/// booking's job already has it.
+pub fn accept(mut saved: SavedJob, settings: &Settings) -> JobId {
+    let taken = get_jobs()
+        .read()
+        .unwrap()
+        .iter()
+        .any(|(job, _)| job.id == saved.job.id && job.booking_id != saved.job.booking_id);
+    if taken {
+        saved.job.id = NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed);
+    }
+    restore(saved, settings)
+}
+
+/// A change to the jobs asked of a follower, carried out by the leader.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Submission {
+    Start(SavedJob),
+    Stop { booking_id: String, last_name: String },
+}
+
+/// Leave `submission` in `storage` for the leader to pick up.
+pub async fn submit(storage: &dyn Storage, submission: Submission) -> Result<(), String> {
+    for _ in 0..SUBMIT_ATTEMPTS {
+        let current = storage.get_versioned(SUBMISSIONS_KEY).await?;
+        let mut submissions: Vec<Submission> = match &current {
+            Some((contents, _)) => serde_json::from_slice(contents)
+                .map_err(|e| format!("Failed to parse auto-find submissions: {}", e))?,
+            None => Vec::new(),
+        };
+        submissions.push(submission.clone());
+
+        let contents = serde_json::to_vec(&submissions)
+            .map_err(|e| format!("Failed to serialize auto-find submissions: {}", e))?;
+        let expected = current.as_ref().map(|(_, version)| version.as_str());
+        if storage.put_if(SUBMISSIONS_KEY, contents, expected).await? {
+            return Ok(());
+        }
+    }
+    Err("Other replicas kept changing the auto-find submissions, please try again".to_string())
+}
+
+/// Take the waiting submissions out of `storage`, oldest first. Empty if a
+/// follower added one meanwhile, they are all taken on the next call.
+pub async fn take_submissions(storage: &dyn Storage) -> Result<Vec<Submission>, String> {
+    let Some((contents, version)) = storage.get_versioned(SUBMISSIONS_KEY).await? else {
+        return Ok(Vec::new());
+    };
+    let submissions: Vec<Submission> =
+        serde_json::from_slice(&contents).map_err(|e| format!("Failed to parse auto-find submissions: {}", e))?;
+    if submissions.is_empty() || !storage.put_if(SUBMISSIONS_KEY, b"[]".to_vec(), Some(&version)).await? {
+        return Ok(Vec::new());
+    }
+    Ok(submissions)
+}
+
+/// Write the current jobs to `path`, replacing what was there.
+pub fn save(path: &Path) -> Result<(), String> {
+    let saved: Vec<SavedJob> = get_jobs()
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(job, settings)| SavedJob {
+            job: job.clone(),
+            last_name: settings.last_name.clone(),
+            status: status(&job.booking_id),
+        })
+        .collect();
+    let contents =
+        serde_json::to_string_pretty(&saved).map_err(|e| format!("Failed to serialize auto-find jobs: {}", e))?;
+
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, contents).map_err(|e| format!("Failed to write auto-find jobs: {}", e))?;
+    fs::rename(&temp_path, path).map_err(|e| format!("Failed to replace auto-find jobs file: {}", e))
+}
+
+/// Jobs written by `save`. A missing file means there were none.
+pub fn load(path: &Path) -> Result<Vec<SavedJob>, String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse '{}': {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("Failed to read '{}': {}", path.display(), e)),
+    }
+}
+
+pub fn remove_job(id: JobId) {
+    get_jobs().write().unwrap().retain(|(job, _)| job.id != id);
+}
+
+pub fn remove_jobs_for(booking_id: &str) {
+    get_jobs().write().unwrap().retain(|(job, _)| job.booking_id != booking_id);
+}
+
+pub fn clear_jobs() {
+    get_jobs().write().unwrap().clear();
+}
+
+pub fn has_jobs() -> bool {
+    !get_jobs().read().unwrap().is_empty()
+}
+
+/// Whether `booking_id` has a job and `last_name` is the one it signs in
+/// with, i.e. the caller owns the booking.
+pub fn owned_by(booking_id: &str, last_name: &str) -> bool {
+    get_jobs().read().unwrap().iter().any(|(job, settings)| {
+        job.booking_id == booking_id && settings.last_name.eq_ignore_ascii_case(last_name.trim())
+    })
+}
+
+pub fn job_for(booking_id: &str) -> Option<AutoFindJob> {
+    get_jobs()
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(job, _)| job.booking_id == booking_id)
+        .map(|(job, _)| job.clone())
+}
+
+/// Count a search round against every current job.
+pub fn record_round(at: DateTime<Utc>) {
+    for (job, _) in get_jobs().write().unwrap().iter_mut() {
+        job.attempts += 1;
+        job.last_attempt = Some(at);
+    }
+}
+
+/// When the search loop runs next, `None` while it is stopped.
+pub fn next_round() -> Option<DateTime<Utc>> {
+    *get_next_round().read().unwrap()
+}
+
+pub fn set_next_round(at: Option<DateTime<Utc>>) {
+    *get_next_round().write().unwrap() = at;
+}
+
+/// Current jobs, highest priority and oldest first.
+pub fn jobs() -> Vec<(AutoFindJob, Settings)> {
+    let mut jobs = get_jobs().read().unwrap().clone();
+    jobs.sort_by(|(a, _), (b, _)| rank(a, b));
+    jobs
+}
+
+/// Status of the job for `booking_id`. Kept after the job ends, so a booked
+/// entry still shows how it went.
+pub fn status(booking_id: &str) -> JobStatus {
+    get_statuses().read().unwrap().get(booking_id).cloned().unwrap_or_default()
+}
+
+pub fn set_status(booking_id: &str, status: JobStatus) {
+    get_statuses().write().unwrap().insert(booking_id.to_string(), status);
+}
+
+/// Keep an allocation and move its job to the back of its priority.
+pub fn record(allocation: Allocation) {
+    if let Some((job, _)) = get_jobs().write().unwrap().iter_mut().find(|(job, _)| job.id == allocation.job_id) {
+        job.last_served = Some(allocation.decided_at);
+        set_status(
+            &job.booking_id,
+            JobStatus::Booking { location: allocation.location, start_time: allocation.start_time },
+        );
+    }
+
+    let mut allocations = get_allocations().write().unwrap();
+    allocations.push(allocation);
+    let overflow = allocations.len().saturating_sub(MAX_ALLOCATIONS);
+    allocations.drain(..overflow);
+}
+
+pub fn allocations() -> Vec<Allocation> {
+    get_allocations().read().unwrap().clone()
+}
+
+fn rank(a: &AutoFindJob, b: &AutoFindJob) -> std::cmp::Ordering {
+    b.priority
+        .cmp(&a.priority)
+        .then(a.last_served.cmp(&b.last_served))
+        .then(a.created_at.cmp(&b.created_at))
+        .then(a.id.cmp(&b.id))
+}
+
+/// Decide which job gets which slot. Jobs pick in rank order, each taking the
+/// slot its strategy ranks best among those no better-ranked job has taken, so
+/// no slot goes to two jobs and no job gets two slots in one round.
+pub fn allocate(jobs: &[AutoFindJob], slots: &[(LocationId, TimeSlot)], now: NaiveDateTime) -> Vec<Allocation> {
+    let mut ranked: Vec<&AutoFindJob> = jobs.iter().collect();
+    ranked.sort_by(|a, b| rank(a, b));
+
+    let location_manager = LocationManager::new();
+    let mut taken: HashSet<(LocationId, NaiveDateTime)> = HashSet::new();
+    let mut assigned: HashSet<JobId> = HashSet::new();
+    let mut allocations = Vec::new();
+
+    for job in &ranked {
+        let strategy = job.strategy.build(&job.scoring);
+        let best = slots
+            .iter()
+            .filter(|(location, slot)| job.wants(*location, slot) && !taken.contains(&(*location, slot.start_time)))
+            .filter_map(|(location, slot)| {
+                let details = location_manager.get_by_id(*location);
+                strategy
+                    .accepts(details.as_ref(), slot)
+                    .then(|| (location, slot, strategy.score(details.as_ref(), slot, now)))
+            })
+            .max_by(|a, b| a.2.total_cmp(&b.2).then(b.1.cmp(a.1)));
+
+        let Some((location, slot, _)) = best else {
+            continue;
+        };
+
+        let contenders: Vec<JobId> = ranked
+            .iter()
+            .filter(|other| !assigned.contains(&other.id) && other.wants(*location, slot))
+            .map(|other| other.id)
+            .collect();
+
+        taken.insert((*location, slot.start_time));
+        assigned.insert(job.id);
+        allocations.push(Allocation {
+            job_id: job.id,
+            location: *location,
+            start_time: slot.start_time,
+            contenders,
+            decided_at: Utc::now(),
+        });
+    }
+
+    allocations
+}
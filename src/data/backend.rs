@@ -0,0 +1,96 @@
+//! The scrape backends behind one interface. Each walks the portal its own
+//! way but hands back the same `ScrapeResults`, and `scrape` picks the one
+//! named by `scrape_backend`.
+
+use super::location::LocationId;
+use super::rta_http::ScrapeBackend;
+use super::shared_booking::{ScrapeResults, TestType};
+use crate::settings::Settings;
+
+/// A way of scraping the available slots for a set of locations.
+#[allow(async_fn_in_trait)]
+pub trait Backend {
+    async fn scrape(
+        &self,
+        locations: Vec<LocationId>,
+        test_type: TestType,
+        settings: &Settings,
+    ) -> Result<ScrapeResults, String>;
+}
+
+/// Chrome through the WebDriver at `selenium_driver_url`.
+pub struct WebDriverBackend;
+
+impl Backend for WebDriverBackend {
+    async fn scrape(
+        &self,
+        locations: Vec<LocationId>,
+        test_type: TestType,
+        settings: &Settings,
+    ) -> Result<ScrapeResults, String> {
+        super::rta::scrape_rta_timeslots(locations, test_type, settings)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Plain HTTP requests, existing bookings only.
+pub struct HttpBackend;
+
+impl Backend for HttpBackend {
+    async fn scrape(
+        &self,
+        locations: Vec<LocationId>,
+        test_type: TestType,
+        settings: &Settings,
+    ) -> Result<ScrapeResults, String> {
+        super::rta_http::scrape_timeslots(locations, test_type, settings).await
+    }
+}
+
+/// Chrome over the DevTools Protocol, see `rta_cdp`.
+#[cfg(feature = "cdp")]
+pub struct CdpBackend;
+
+#[cfg(feature = "cdp")]
+impl Backend for CdpBackend {
+    async fn scrape(
+        &self,
+        locations: Vec<LocationId>,
+        test_type: TestType,
+        settings: &Settings,
+    ) -> Result<ScrapeResults, String> {
+        super::rta_cdp::scrape_timeslots(locations, test_type, settings).await
+    }
+}
+
+/// Replays recorded data, see `mock`.
+pub struct MockBackend;
+
+impl Backend for MockBackend {
+    async fn scrape(
+        &self,
+        locations: Vec<LocationId>,
+        test_type: TestType,
+        settings: &Settings,
+    ) -> Result<ScrapeResults, String> {
+        super::mock::scrape_timeslots(locations, test_type, settings).await
+    }
+}
+
+/// Scrape `locations` with the configured `scrape_backend`.
+pub async fn scrape(
+    locations: Vec<LocationId>,
+    test_type: TestType,
+    settings: &Settings,
+) -> Result<ScrapeResults, String> {
+    match settings.scrape_backend {
+        ScrapeBackend::Browser => WebDriverBackend.scrape(locations, test_type, settings).await,
+        ScrapeBackend::Http => HttpBackend.scrape(locations, test_type, settings).await,
+        ScrapeBackend::Mock => MockBackend.scrape(locations, test_type, settings).await,
+        #[cfg(feature = "cdp")]
+        ScrapeBackend::Cdp => CdpBackend.scrape(locations, test_type, settings).await,
+        #[cfg(not(feature = "cdp"))]
+        ScrapeBackend::Cdp => Err("scrape_backend is cdp but this build has no cdp feature".to_string()),
+    }
+}
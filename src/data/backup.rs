@@ -0,0 +1,160 @@
+//! Rotating backups of the booking data files. After a successful scrape the
+//! data file is copied into `data_dir/backups` as
+//! `<file stem>.<UTC timestamp>.<extension>`, the oldest copies beyond
+//! `backup_count` are deleted, and the admin dashboard can restore any of them.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::shared_booking::TestType;
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// When a successful scrape also takes a backup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupSchedule {
+    /// At most one backup per test type a day
+    #[default]
+    Daily,
+    EveryRun,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub test_type: TestType,
+    pub file_name: String,
+    pub created_at: DateTime<Utc>,
+    pub size_bytes: u64,
+}
+
+/// Backup file name for a data file called `data_file_name`.
+pub fn backup_name(data_file_name: &str, created_at: DateTime<Utc>) -> String {
+    let (stem, extension) = data_file_name.split_once('.').unwrap_or((data_file_name, "json"));
+    format!("{}.{}.{}", stem, created_at.format(TIMESTAMP_FORMAT), extension)
+}
+
+/// When the backup `file_name` of `data_file_name` was taken, or `None` if
+/// it is not one of its backups.
+pub fn parse_backup_name(data_file_name: &str, file_name: &str) -> Option<DateTime<Utc>> {
+    let (stem, extension) = data_file_name.split_once('.').unwrap_or((data_file_name, "json"));
+    let timestamp = file_name
+        .strip_prefix(stem)?
+        .strip_prefix('.')?
+        .strip_suffix(extension)?
+        .strip_suffix('.')?;
+    NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+#[cfg(feature = "server")]
+pub use self::store::{list, restore, take};
+
+#[cfg(feature = "server")]
+mod store {
+    use std::fs;
+    use std::path::Path;
+
+    use chrono::{DateTime, Utc};
+
+    use super::{backup_name, parse_backup_name, BackupInfo, BackupSchedule};
+    use crate::data::booking::BookingManager;
+    use crate::data::database::DataStore;
+    use crate::data::shared_booking::TestType;
+    use crate::settings::Settings;
+
+    fn file_name(path: &Path) -> Result<String, String> {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .map(str::to_string)
+            .ok_or_else(|| format!("Invalid data file path '{}'", path.display()))
+    }
+
+    /// Every backup of `test_type`, oldest first.
+    fn backups_of(settings: &Settings, test_type: TestType) -> Result<Vec<BackupInfo>, String> {
+        let data_file_name = file_name(&settings.bookings_file(test_type))?;
+        let entries = match fs::read_dir(settings.backups_dir()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to read backups: {}", e)),
+        };
+
+        let mut backups: Vec<BackupInfo> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let file_name = entry.file_name().into_string().ok()?;
+                let created_at = parse_backup_name(&data_file_name, &file_name)?;
+                let size_bytes = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+                Some(BackupInfo { test_type, file_name, created_at, size_bytes })
+            })
+            .collect();
+        backups.sort_by_key(|backup| backup.created_at);
+        Ok(backups)
+    }
+
+    /// Every backup, newest first.
+    pub fn list(settings: &Settings) -> Result<Vec<BackupInfo>, String> {
+        let mut backups = Vec::new();
+        for test_type in TestType::ALL {
+            backups.extend(backups_of(settings, test_type)?);
+        }
+        backups.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(backups)
+    }
+
+    /// Back up the data file of `test_type` if `backup_schedule` says one is
+    /// due, then delete the oldest backups beyond `backup_count`.
+    pub fn take(settings: &Settings, test_type: TestType, now: DateTime<Utc>) -> Result<(), String> {
+        if settings.backup_count == 0 {
+            return Ok(());
+        }
+
+        let data_file = settings.bookings_file(test_type);
+        let mut backups = backups_of(settings, test_type)?;
+        let due = match (settings.backup_schedule, backups.last()) {
+            (BackupSchedule::Daily, Some(newest)) => now - newest.created_at >= chrono::Duration::days(1),
+            _ => true,
+        };
+
+        if due {
+            let backups_dir = settings.backups_dir();
+            fs::create_dir_all(&backups_dir).map_err(|e| format!("Failed to create backups directory: {}", e))?;
+            let name = backup_name(&file_name(&data_file)?, now);
+            match settings.data_store {
+                DataStore::Json => {
+                    fs::copy(&data_file, backups_dir.join(&name))
+                        .map_err(|e| format!("Failed to copy data file: {}", e))?;
+                }
+                // The database holds every test type, so back up this one as
+                // the data file it would have been
+                DataStore::Sqlite => BookingManager::save_to_file(test_type, &backups_dir.join(&name))?,
+            }
+            println!("INFO: Backed up {} data to '{}'", test_type.label(), name);
+            backups = backups_of(settings, test_type)?;
+        }
+
+        let overflow = backups.len().saturating_sub(settings.backup_count);
+        for backup in &backups[..overflow] {
+            if let Err(e) = fs::remove_file(settings.backups_dir().join(&backup.file_name)) {
+                eprintln!("WARN: Failed to delete old backup '{}': {}", backup.file_name, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Make the backup `file_name` the current data for `test_type` and save
+    /// it to the data store. The replaced data stays available for rollback.
+    pub fn restore(settings: &Settings, test_type: TestType, file_name: &str) -> Result<(), String> {
+        // Only names that list() would return, never a path
+        let backup = backups_of(settings, test_type)?
+            .into_iter()
+            .find(|backup| backup.file_name == file_name)
+            .ok_or_else(|| format!("No {} backup named '{}'", test_type.label(), file_name))?;
+
+        BookingManager::init_from_file(test_type, &settings.backups_dir().join(&backup.file_name))?;
+        BookingManager::save_data(test_type, settings)?;
+        println!("INFO: Restored {} data from backup '{}'", test_type.label(), backup.file_name);
+        Ok(())
+    }
+}
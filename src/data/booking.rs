@@ -1,22 +1,146 @@
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::{self, File};
-use std::hash::{DefaultHasher, Hasher};
-use std::path::Path;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{BufRead, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, OnceLock, RwLock};
 use std::time::{Duration, Instant};
-use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveDateTime};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tracing::{error, info, warn};
 
-use super::shared_booking::{BookingData, LocationBookings, TimeSlot};
+use super::audit::{self, AuditAction, AuditEntry};
+use super::auto_find::{self, JobId};
+use super::changes::{self, SlotChanges};
+use super::cooldown::LocationCooldowns;
+use super::database::DataStore;
+#[cfg(feature = "sqlite")]
+use super::database::Database;
+use super::backup;
+use super::history;
+use super::leader;
+use super::storage;
+use super::challenge;
+use super::checkpoint::{self, ScrapeCheckpoint};
+use super::maintenance;
+use super::metrics::ScrapeMetrics;
+use super::migration;
+use super::rta_http::ScrapeBackend;
+use super::scrape_runs::{self, RunQuery};
+use super::location::{LocationId, LocationManager, SearchArea};
+use super::shared_booking::{
+    clean_results, BookingData, BookingOutcome, BookingRecord, JobStatus, BOOKING_DATA_VERSION, LocationBookingViewModel, LocationBookings, ScrapeResults, ScrapeRun, ScraperStatus, TestType, TimeSlot,
+    UnavailableSlots,
+};
+use crate::notifications::{self, Notification, NotificationLevel};
+use crate::selenium::health::{self, DriverHealth};
 use crate::settings::Settings;
+use crate::utils::date::sydney_now;
+use crate::utils::holidays::{is_business_day, is_near_public_holiday};
+use crate::utils::sort::{sort_locations, LocationsQuery, SortedLocation};
+use crate::utils::task::spawn_named;
 
-static BOOKING_DATA: OnceLock<Arc<RwLock<(BookingData, String)>>> = OnceLock::new();
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Scrape runs kept in memory for the admin dashboard. Older ones are only
+/// in the run log.
+const MAX_RUNS: usize = 50;
+
+/// Name recorded in the run log for the scraper that produced a run.
+const SCRAPE_BACKEND: &str = "selenium";
+
+/// How often the leader picks up auto-find jobs submitted to followers.
+#[cfg(feature = "scraper")]
+const SUBMISSION_POLL: Duration = Duration::from_secs(30);
+
+static BOOKING_DATA: OnceLock<Arc<RwLock<HashMap<TestType, BookingSnapshot>>>> = OnceLock::new();
 static BACKGROUND_RUNNING: OnceLock<Arc<RwLock<bool>>> = OnceLock::new();
 static AUTO_FIND_RUNNING: OnceLock<Arc<RwLock<bool>>> = OnceLock::new();
+static SCRAPER_STATUS: OnceLock<Arc<RwLock<HashMap<TestType, ScraperStatus>>>> = OnceLock::new();
+static RECENT_RUNS: OnceLock<Arc<RwLock<Vec<ScrapeRun>>>> = OnceLock::new();
+static MANUAL_SCRAPE_RUNNING: OnceLock<Arc<RwLock<bool>>> = OnceLock::new();
+static LOCATION_COOLDOWNS: OnceLock<Arc<RwLock<HashMap<TestType, LocationCooldowns>>>> = OnceLock::new();
+static SCRAPE_METRICS: OnceLock<Arc<RwLock<HashMap<TestType, ScrapeMetrics>>>> = OnceLock::new();
+static BOOKING_HISTORY: OnceLock<Arc<RwLock<Vec<BookingRecord>>>> = OnceLock::new();
+static LATEST_CHANGES: OnceLock<Arc<RwLock<HashMap<TestType, Arc<SlotChanges>>>>> = OnceLock::new();
+static CHANGE_EVENTS: OnceLock<tokio::sync::broadcast::Sender<Arc<SlotChanges>>> = OnceLock::new();
+
+/// Change events a slow subscriber can fall behind by before it misses some.
+const CHANGE_EVENT_CAPACITY: usize = 16;
+
+/// The current dataset together with everything derived from it. Readers
+/// clone the `Arc`s, so serving a request never copies the slot lists.
+#[derive(Clone, Default)]
+struct BookingSnapshot {
+    data: Arc<BookingData>,
+    etag: String,
+    summaries: Arc<Vec<LocationBookingViewModel>>,
+    /// Position in `data.results` and ETag for each location, hashed once
+    /// per update so detail requests only look it up.
+    index: HashMap<LocationId, (usize, String)>,
+    /// The dataset this one replaced, for rolling back a bad scrape.
+    previous: Option<Arc<BookingData>>,
+}
+
+impl BookingSnapshot {
+    fn new(data: BookingData) -> Self {
+        let summaries = data
+            .results
+            .iter()
+            .map(|location_booking| LocationBookingViewModel {
+                location: location_booking.location,
+                earliest_slot: location_booking.earliest_slot().cloned(),
+            })
+            .collect();
+        let index = data
+            .results
+            .iter()
+            .enumerate()
+            .map(|(i, location_booking)| {
+                (location_booking.location, (i, location_booking.calculate_hash()))
+            })
+            .collect::<HashMap<_, _>>();
+
+        // Combined from the location ETags rather than hashing every slot again
+        let mut hasher = DefaultHasher::new();
+        for location_booking in &data.results {
+            hasher.write(index[&location_booking.location].1.as_bytes());
+        }
+        data.last_updated.hash(&mut hasher);
+        data.schema_version.hash(&mut hasher);
+        let etag = hasher.finish().to_string();
+
+        Self {
+            data: Arc::new(data),
+            etag,
+            summaries: Arc::new(summaries),
+            index,
+            previous: None,
+        }
+    }
+
+    fn location(&self, location_id: LocationId) -> Option<(&LocationBookings, &str)> {
+        let (i, hash) = self.index.get(&location_id)?;
+        Some((&self.data.results[*i], hash.as_str()))
+    }
+}
+
+fn get_booking_data() -> &'static Arc<RwLock<HashMap<TestType, BookingSnapshot>>> {
+    BOOKING_DATA.get_or_init(|| Arc::new(RwLock::new(HashMap::new())))
+}
 
-fn get_booking_data() -> &'static Arc<RwLock<(BookingData, String)>> {
-    BOOKING_DATA.get_or_init(|| Arc::new(RwLock::new((BookingData::default(), String::new()))))
+/// Run `f` against the snapshot for `test_type`, or an empty one if that test
+/// type has no data yet.
+fn with_snapshot<R>(test_type: TestType, f: impl FnOnce(&BookingSnapshot) -> R) -> R {
+    let snapshots = get_booking_data().read().unwrap();
+    match snapshots.get(&test_type) {
+        Some(snapshot) => f(snapshot),
+        None => f(&BookingSnapshot::default()),
+    }
 }
 
 fn get_background_status() -> &'static Arc<RwLock<bool>> {
@@ -27,115 +151,739 @@ fn get_auto_status() -> &'static Arc<RwLock<bool>> {
     AUTO_FIND_RUNNING.get_or_init(|| Arc::new(RwLock::new(false)))
 }
 
+fn get_scraper_status() -> &'static Arc<RwLock<HashMap<TestType, ScraperStatus>>> {
+    SCRAPER_STATUS.get_or_init(|| Arc::new(RwLock::new(HashMap::new())))
+}
+
+fn get_recent_runs() -> &'static Arc<RwLock<Vec<ScrapeRun>>> {
+    RECENT_RUNS.get_or_init(|| Arc::new(RwLock::new(Vec::new())))
+}
+
+fn get_manual_scrape_status() -> &'static Arc<RwLock<bool>> {
+    MANUAL_SCRAPE_RUNNING.get_or_init(|| Arc::new(RwLock::new(false)))
+}
+
+fn get_location_cooldowns() -> &'static Arc<RwLock<HashMap<TestType, LocationCooldowns>>> {
+    LOCATION_COOLDOWNS.get_or_init(|| Arc::new(RwLock::new(HashMap::new())))
+}
+
+fn get_scrape_metrics() -> &'static Arc<RwLock<HashMap<TestType, ScrapeMetrics>>> {
+    SCRAPE_METRICS.get_or_init(|| Arc::new(RwLock::new(HashMap::new())))
+}
+
+fn get_latest_changes() -> &'static Arc<RwLock<HashMap<TestType, Arc<SlotChanges>>>> {
+    LATEST_CHANGES.get_or_init(|| Arc::new(RwLock::new(HashMap::new())))
+}
+
+fn get_change_events() -> &'static tokio::sync::broadcast::Sender<Arc<SlotChanges>> {
+    CHANGE_EVENTS.get_or_init(|| tokio::sync::broadcast::channel(CHANGE_EVENT_CAPACITY).0)
+}
+
+fn get_booking_history() -> &'static Arc<RwLock<Vec<BookingRecord>>> {
+    BOOKING_HISTORY.get_or_init(|| Arc::new(RwLock::new(Vec::new())))
+}
+
 pub struct BookingManager;
 
 impl BookingManager {
-    pub fn get_data() -> (BookingData, String) {
-        get_booking_data().read().unwrap().clone()
+    pub fn get_data(test_type: TestType) -> (Arc<BookingData>, String) {
+        with_snapshot(test_type, |snapshot| (Arc::clone(&snapshot.data), snapshot.etag.clone()))
     }
 
-    pub fn get_location_data(location_id: String) -> Option<(LocationBookings, String)> {
-        Self::get_data()
-            .0
-            .results
-            .iter()
-            .find(|booking| booking.location == location_id)
-            .and_then(|booking| Some((booking.clone(), booking.calculate_hash())))
+    /// Per-location earliest slots, precomputed whenever the data changes.
+    pub fn get_summaries(test_type: TestType) -> (Arc<Vec<LocationBookingViewModel>>, String) {
+        with_snapshot(test_type, |snapshot| {
+            (Arc::clone(&snapshot.summaries), snapshot.etag.clone())
+        })
     }
 
-    pub fn get_location_slots(location_code: &str) -> Option<Vec<TimeSlot>> {
-        let data_guard = get_booking_data().read().unwrap();
-        data_guard
-            .0
-            .results
-            .iter()
-            .find(|loc| loc.location == location_code)
-            .map(|loc| loc.slots.clone())
+    /// The locations table for a search point, joined and sorted server-side.
+    pub fn sorted_locations(query: &LocationsQuery) -> Vec<SortedLocation> {
+        let summaries = if query.hide_near_holidays {
+            let data = Self::get_data(query.test_type).0;
+            Arc::new(
+                data.results
+                    .iter()
+                    .map(|bookings| LocationBookingViewModel {
+                        location: bookings.location,
+                        earliest_slot: bookings
+                            .slots
+                            .iter()
+                            .filter(|slot| slot.availability && !is_near_public_holiday(slot.start_time.date()))
+                            .min()
+                            .cloned(),
+                    })
+                    .collect(),
+            )
+        } else {
+            Self::get_summaries(query.test_type).0
+        };
+        let locations = LocationManager::new().get_by_distance(query.lat, query.lng);
+        sort_locations(locations, &summaries, query)
+    }
+
+    /// A location's ETag, without copying its slots.
+    pub fn location_etag(test_type: TestType, location_id: LocationId) -> Option<String> {
+        with_snapshot(test_type, |snapshot| snapshot.location(location_id).map(|(_, etag)| etag.to_string()))
+    }
+
+    /// A location's bookings and its precomputed hash.
+    pub fn get_location_data(test_type: TestType, location_id: LocationId) -> Option<(LocationBookings, String)> {
+        with_snapshot(test_type, |snapshot| {
+            snapshot
+                .location(location_id)
+                .map(|(booking, hash)| (booking.clone(), hash.to_string()))
+        })
     }
 
-    pub fn get_available_slots() -> Vec<(String, TimeSlot)> {
-        let data_guard = get_booking_data().read().unwrap();
-        let mut available = Vec::new();
+    pub fn get_location_slots(test_type: TestType, location_id: LocationId) -> Option<Vec<TimeSlot>> {
+        with_snapshot(test_type, |snapshot| {
+            snapshot
+                .location(location_id)
+                .map(|(booking, _)| booking.slots.clone())
+        })
+    }
+
+    pub fn get_available_slots(test_type: TestType) -> Vec<(LocationId, TimeSlot)> {
+        with_snapshot(test_type, |snapshot| {
+            let mut available = Vec::new();
 
-        for loc in &data_guard.0.results {
-            for slot in &loc.slots {
-                if slot.availability {
-                    available.push((loc.location.clone(), slot.clone()));
+            for loc in &snapshot.data.results {
+                for slot in &loc.slots {
+                    if slot.availability {
+                        available.push((loc.location, slot.clone()));
+                    }
                 }
             }
-        }
 
-        available
+            available
+        })
     }
 
-    pub fn init_from_file(file_path: &str) -> Result<(), String> {
-        if !Path::new(file_path).exists() {
-            println!("No path for booking data");
+    /// Load booking data from disk. Gzip-compressed files are detected by
+    /// their magic bytes whatever their name; `load_data` is what finds a
+    /// file saved under the other `compress_data` extension. Files in an
+    /// older format are migrated, and the original
+    /// is kept next to it as `<file>.v<version>.bak` before the upgraded data
+    /// is written back. An unreadable file is moved aside to
+    /// `<file>.corrupt` and the last good copy loaded instead, so the next
+    /// save doesn't replace that copy with the broken file.
+    pub fn init_from_file(test_type: TestType, file_path: &Path) -> Result<(), String> {
+        let last_good = Self::last_good_path(file_path);
+        if !file_path.exists() && !last_good.exists() {
+            warn!("No path for booking data");
             return Ok(());
         }
 
-        fs::read_to_string(file_path)
-            .map_err(|e| format!("Failed to read file: {}", e))
-            .and_then(|json_str| {
-                serde_json::from_str::<BookingData>(&json_str)
-                    .map_err(|e| format!("Failed to parse JSON: {}", e))
-                    .map(|data| {
-                        let hash = data.calculate_hash();
-                        let mut data_guard = get_booking_data().write().unwrap();
-                        *data_guard = (data, hash);
-                    })
-            })
+        let (read_path, (data, from_version)) = match Self::read_data_file(file_path) {
+            Ok(read) => (file_path, read),
+            Err(e) if last_good.exists() => {
+                warn!(
+                    "Failed to load '{}' ({}), falling back to the last good copy '{}'",
+                    file_path.display(), e, last_good.display()
+                );
+                let read = Self::read_data_file(&last_good)?;
+                if file_path.exists() {
+                    let mut corrupt_name = file_path.as_os_str().to_owned();
+                    corrupt_name.push(".corrupt");
+                    if let Err(e) = fs::rename(file_path, &corrupt_name) {
+                        warn!("Failed to move '{}' aside: {}", file_path.display(), e);
+                    }
+                }
+                (last_good.as_path(), read)
+            }
+            Err(e) => return Err(e),
+        };
+        Self::set_data(test_type, data);
+
+        if from_version < BOOKING_DATA_VERSION {
+            let mut backup_name = file_path.as_os_str().to_owned();
+            backup_name.push(format!(".v{}.bak", from_version));
+            let backup_path = Path::new(&backup_name);
+            fs::copy(read_path, backup_path).map_err(|e| format!("Failed to back up file before migrating: {}", e))?;
+            Self::save_to_file(test_type, file_path)?;
+            info!(
+                "Migrated {} from format version {} to {}, original kept at '{}'",
+                file_path.display(), from_version, BOOKING_DATA_VERSION, backup_path.display()
+            );
+        }
+        Ok(())
     }
 
-    pub fn save_to_file(file_path: &str) -> Result<(), String> {
-        let data_guard = get_booking_data().read().unwrap();
+    /// Read a data file, plain or gzipped, upgraded to the current schema.
+    /// Also returns the version it was written in.
+    pub fn read_data_file(file_path: &Path) -> Result<(BookingData, u32), String> {
+        let file = File::open(file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+        let mut reader = BufReader::new(file);
+        let is_gzip = reader
+            .fill_buf()
+            .map(|buf| buf.starts_with(&GZIP_MAGIC))
+            .map_err(|e| format!("Failed to read file: {}", e))?;
 
-        serde_json::to_string_pretty(&data_guard.0)
-            .map_err(|e| format!("Failed to serialize data: {}", e))
-            .and_then(|json_str| {
-                fs::write(file_path, json_str)
-                    .map_err(|e| format!("Failed to write to file: {}", e))
-            })
+        let value = if is_gzip {
+            serde_json::from_reader::<_, serde_json::Value>(BufReader::new(GzDecoder::new(reader)))
+        } else {
+            serde_json::from_reader::<_, serde_json::Value>(reader)
+        }
+        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+
+        migration::migrate(value)
     }
 
-    fn clean_data(results: Vec<LocationBookings>) -> Vec<LocationBookings> {
-        results.into_iter().map(|mut location| {
-            location.slots.retain(|slot| slot.availability);
-            location
-        }).collect()
+    /// Where the file `save_to_file` replaced is kept, `<file>.bak`.
+    pub fn last_good_path(file_path: &Path) -> PathBuf {
+        let mut name = file_path.as_os_str().to_owned();
+        name.push(".bak");
+        PathBuf::from(name)
     }
 
-    pub fn update_date() {
-        let (cloned_results, new_hash_data) = {
-            let data_read_guard = get_booking_data().read().unwrap();
+    /// Stream the current data to `file_path`, gzip-compressed when the path
+    /// ends in `.gz`. The data is written to `<file>.tmp` and renamed over
+    /// the file, so a crash mid-write leaves the old file intact, and the
+    /// replaced file is kept as `<file>.bak`.
+    pub fn save_to_file(test_type: TestType, file_path: &Path) -> Result<(), String> {
+        let data = Self::get_data(test_type).0;
 
-            let new_data = BookingData {
-                results: data_read_guard.0.results.clone(),
-                last_updated: Some(chrono::Utc::now().to_rfc3339()),
-            };
+        let mut temp_name = file_path.as_os_str().to_owned();
+        temp_name.push(".tmp");
+        let temp_path = PathBuf::from(temp_name);
+
+        let file = File::create(&temp_path).map_err(|e| format!("Failed to write to file: {}", e))?;
+        let writer = BufWriter::new(file);
 
-            let new_hash = new_data.calculate_hash();
-            (new_data, new_hash)
+        let result = if file_path.extension().is_some_and(|ext| ext == "gz") {
+            let mut encoder = GzEncoder::new(writer, Compression::default());
+            serde_json::to_writer(&mut encoder, &*data)
+                .map_err(|e| format!("Failed to serialize data: {}", e))?;
+            encoder.finish().and_then(|writer| writer.into_inner().map_err(|e| e.into_error()))
+        } else {
+            let mut writer = writer;
+            serde_json::to_writer_pretty(&mut writer, &*data)
+                .map_err(|e| format!("Failed to serialize data: {}", e))?;
+            writer.into_inner().map_err(|e| e.into_error())
         };
+        result
+            .and_then(|file| file.sync_all())
+            .map_err(|e| format!("Failed to write to file: {}", e))?;
 
-        let mut data_guard = get_booking_data().write().unwrap();
-        *data_guard = (cloned_results, new_hash_data);
+        if file_path.exists() {
+            // A hard link keeps the data file in place until the rename
+            let last_good = Self::last_good_path(file_path);
+            fs::remove_file(&last_good).ok();
+            fs::hard_link(file_path, &last_good)
+                .or_else(|_| fs::copy(file_path, &last_good).map(|_| ()))
+                .map_err(|e| format!("Failed to keep the last good file: {}", e))?;
+        }
+        fs::rename(&temp_path, file_path).map_err(|e| format!("Failed to replace data file: {}", e))
     }
 
-    pub fn update_data(mut new_results: Vec<LocationBookings>) {
-        new_results = Self::clean_data(new_results);
+    /// Load the data of `test_type` from the configured `data_store`. A JSON
+    /// file saved with the other `compress_data` setting is loaded and
+    /// rewritten under the current name. When the database has no data for
+    /// it yet, the JSON file is imported so switching to SQLite keeps the
+    /// existing data.
+    pub fn load_data(test_type: TestType, settings: &Settings) -> Result<(), String> {
+        match settings.data_store {
+            DataStore::Json => {
+                let file = settings.existing_bookings_file(test_type);
+                Self::init_from_file(test_type, &file)?;
+
+                let preferred = settings.bookings_file(test_type);
+                if file != preferred {
+                    Self::save_to_file(test_type, &preferred)?;
+                    fs::remove_file(&file).map_err(|e| format!("Failed to remove '{}': {}", file.display(), e))?;
+                    info!("Moved booking data from '{}' to '{}'", file.display(), preferred.display());
+                }
+                Ok(())
+            }
+            #[cfg(feature = "sqlite")]
+            DataStore::Sqlite => {
+                let mut database = Database::open(&settings.database_file())?;
+                match database.load(test_type)? {
+                    Some(data) => Self::set_data(test_type, data),
+                    None if settings.existing_bookings_file(test_type).exists() => {
+                        Self::init_from_file(test_type, &settings.existing_bookings_file(test_type))?;
+                        database.save(test_type, &Self::get_data(test_type).0)?;
+                        info!("Imported {} data from the JSON file into the database", test_type.label());
+                    }
+                    None => warn!("No {} data in the database", test_type.label()),
+                }
+                Ok(())
+            }
+            #[cfg(not(feature = "sqlite"))]
+            DataStore::Sqlite => Err("data_store is sqlite but this build has no sqlite feature".to_string()),
+        }
+    }
+
+    /// Save the current data of `test_type` to the configured `data_store`.
+    pub fn save_data(test_type: TestType, settings: &Settings) -> Result<(), String> {
+        match settings.data_store {
+            DataStore::Json => Self::save_to_file(test_type, &settings.bookings_file(test_type)),
+            #[cfg(feature = "sqlite")]
+            DataStore::Sqlite => Database::open(&settings.database_file())?.save(test_type, &Self::get_data(test_type).0),
+            #[cfg(not(feature = "sqlite"))]
+            DataStore::Sqlite => Err("data_store is sqlite but this build has no sqlite feature".to_string()),
+        }
+    }
+
+    /// Where `save_data` writes, for log messages.
+    fn data_location(test_type: TestType, settings: &Settings) -> PathBuf {
+        match settings.data_store {
+            DataStore::Json => settings.bookings_file(test_type),
+            DataStore::Sqlite => settings.database_file(),
+        }
+    }
+
+    fn set_data(test_type: TestType, data: BookingData) {
+        let mut snapshot = BookingSnapshot::new(data);
+        let mut snapshots = get_booking_data().write().unwrap();
+        snapshot.previous = snapshots
+            .get(&test_type)
+            .filter(|current| current.data.last_updated.is_some())
+            .map(|current| Arc::clone(&current.data));
+        snapshots.insert(test_type, snapshot);
+        drop(snapshots);
+
+        #[cfg(feature = "ssr")]
+        crate::page_cache::invalidate();
+    }
+
+    fn clean_data(results: Vec<LocationBookings>, unavailable: UnavailableSlots) -> Vec<LocationBookings> {
+        let (cleaned, report) = clean_results(results, sydney_now(), unavailable);
+        if report.dropped() > 0 {
+            info!(
+                "Dropped {} slots while cleaning: {} unavailable, {} in the past, {} duplicates",
+                report.dropped(), report.unavailable, report.past, report.duplicates
+            );
+        }
+        cleaned
+    }
+
+    /// Swap the current dataset with the one it replaced. Rolling back twice
+    /// restores the original.
+    pub fn rollback(test_type: TestType) -> Result<(), String> {
+        let previous = with_snapshot(test_type, |snapshot| snapshot.previous.clone())
+            .ok_or_else(|| format!("No earlier {} data to roll back to", test_type.label()))?;
+
+        Self::set_data(test_type, previous.as_ref().clone());
+        info!("Rolled {} data back to the update from {:?}", test_type.label(), previous.last_updated);
+        Ok(())
+    }
+
+    /// The most recent scrape runs, newest last.
+    pub fn recent_runs() -> Vec<ScrapeRun> {
+        get_recent_runs().read().unwrap().clone()
+    }
+
+    /// Per-centre scrape metrics for `test_type` since the server started.
+    pub fn scrape_metrics(test_type: TestType) -> ScrapeMetrics {
+        get_scrape_metrics().read().unwrap().get(&test_type).cloned().unwrap_or_default()
+    }
+
+    #[cfg(feature = "scraper")]
+    fn record_metrics(test_type: TestType, results: &ScrapeResults) {
+        let now = chrono::Utc::now();
+        let mut metrics = get_scrape_metrics().write().unwrap();
+        let metrics = metrics.entry(test_type).or_default();
+        for attempt in &results.attempts {
+            let error = results.errors.get(&attempt.location).map(String::as_str);
+            metrics.record(attempt, error, now);
+        }
+    }
+
+    /// Bookings the bot made, oldest first.
+    pub fn booking_history() -> Vec<BookingRecord> {
+        get_booking_history().read().unwrap().clone()
+    }
+
+    /// Read `bookings_history.json` after a restart. A missing file is an
+    /// empty history.
+    pub fn load_booking_history(settings: &Settings) -> Result<(), String> {
+        let path = settings.bookings_history_file();
+        let records = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("Failed to parse '{}': {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("Failed to read '{}': {}", path.display(), e)),
+        };
+        *get_booking_history().write().unwrap() = records;
+        Ok(())
+    }
+
+    /// Add a booking to the history and rewrite `bookings_history.json`.
+    pub fn record_booking(record: BookingRecord, settings: &Settings) {
+        let mut history = get_booking_history().write().unwrap();
+        history.push(record);
+
+        let path = settings.bookings_history_file();
+        let written = serde_json::to_string_pretty(&*history)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| fs::write(&path, contents).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            error!("Failed to save booking history to '{}': {}", path.display(), e);
+        }
+    }
+
+    /// Fill the recent runs from the run log after a restart.
+    pub fn load_runs(settings: &Settings) -> Result<(), String> {
+        let query = RunQuery { limit: Some(MAX_RUNS), ..RunQuery::default() };
+        let runs = match settings.data_store {
+            DataStore::Json => scrape_runs::load(&settings.scrape_runs_file(), &query)?,
+            #[cfg(feature = "sqlite")]
+            DataStore::Sqlite => Database::open(&settings.database_file())?.load_runs(&query)?,
+            #[cfg(not(feature = "sqlite"))]
+            DataStore::Sqlite => return Err("data_store is sqlite but this build has no sqlite feature".to_string()),
+        };
+        *get_recent_runs().write().unwrap() = runs;
+        Ok(())
+    }
+
+    fn record_run(run: ScrapeRun, settings: &Settings) {
+        let (runs_file, appended) = match settings.data_store {
+            DataStore::Json => (settings.scrape_runs_file(), scrape_runs::append(&settings.scrape_runs_file(), &run)),
+            #[cfg(feature = "sqlite")]
+            DataStore::Sqlite => (
+                settings.database_file(),
+                Database::open(&settings.database_file()).and_then(|database| database.append_run(&run)),
+            ),
+            #[cfg(not(feature = "sqlite"))]
+            DataStore::Sqlite => (settings.database_file(), Err("this build has no sqlite feature".to_string())),
+        };
+        if let Err(e) = appended {
+            error!("Failed to record scrape run to '{}': {}", runs_file.display(), e);
+        }
+
+        let parameters = serde_json::json!({
+            "test_type": run.test_type,
+            "locations": run.requested,
+            "backend": run.backend,
+        });
+        let outcome = match &run.error {
+            Some(error) => error.clone(),
+            None => format!("Scraped {}/{} centres", run.scraped, run.requested),
+        };
+        audit::record(settings, AuditEntry::new(AuditAction::Scrape, parameters, run.error.is_none(), outcome));
+
+        let mut runs = get_recent_runs().write().unwrap();
+        runs.push(run);
+        let overflow = runs.len().saturating_sub(MAX_RUNS);
+        runs.drain(..overflow);
+    }
+
+    fn scrape_run(
+        test_type: TestType,
+        started_at: chrono::DateTime<chrono::Utc>,
+        locations: &[LocationId],
+        results: &HashMap<LocationId, LocationBookings>,
+        attempts: u64,
+        error: Option<String>,
+    ) -> ScrapeRun {
+        let (mut succeeded, mut failed): (Vec<LocationId>, Vec<LocationId>) =
+            locations.iter().partition(|location| results.contains_key(location));
+        succeeded.sort();
+        failed.sort();
+
+        ScrapeRun {
+            test_type,
+            started_at,
+            finished_at: chrono::Utc::now(),
+            requested: locations.len(),
+            scraped: results.len(),
+            error,
+            succeeded,
+            failed,
+            attempts,
+            backend: SCRAPE_BACKEND.to_string(),
+            cooling_down: Vec::new(),
+            failure_reasons: Default::default(),
+        }
+    }
+
+    /// Why scraping is paused right now: the portal's maintenance window, or
+    /// the cool-down after a challenge page.
+    fn paused_reason() -> Option<String> {
+        let now = sydney_now();
+        if let Some(window) = maintenance::active(now) {
+            return Some(format!("RTA portal under maintenance until {}", window.until));
+        }
+        challenge::active(now).map(|window| {
+            format!("Blocked by a challenge page ({}), paused until {}", window.marker, window.until)
+        })
+    }
+
+    /// Log a run skipped while scraping is paused. It isn't counted as a
+    /// failure, the data will be refreshed once the pause is over.
+    fn skip_paused(
+        test_type: TestType,
+        started_at: chrono::DateTime<chrono::Utc>,
+        locations: &[LocationId],
+        results: &HashMap<LocationId, LocationBookings>,
+        attempts: u64,
+        reason: String,
+        settings: &Settings,
+    ) {
+        info!("Skipping {} update: {}", test_type.label(), reason);
+        Self::record_run(
+            Self::scrape_run(test_type, started_at, locations, results, attempts, Some(reason)),
+            settings,
+        );
+    }
+
+    #[cfg(feature = "scraper")]
+    /// Scrape `test_type` now, outside the background schedule. Returns false
+    /// if a manual scrape is already running.
+    pub fn start_manual_scrape(test_type: TestType, locations: Vec<LocationId>, settings: Settings) -> bool {
+        {
+            let mut running = get_manual_scrape_status().write().unwrap();
+            if *running {
+                return false;
+            }
+            *running = true;
+        }
+
+        spawn_named("manual-scrape", async move {
+            BookingManager::perform_update(test_type, locations, settings).await;
+            *get_manual_scrape_status().write().unwrap() = false;
+        });
+        true
+    }
+
+    pub fn manual_scrape_running() -> bool {
+        *get_manual_scrape_status().read().unwrap()
+    }
+
+    pub fn update_date(test_type: TestType) {
+        let new_data = BookingData {
+            results: Self::get_data(test_type).0.results.clone(),
+            last_updated: Some(chrono::Utc::now()),
+            schema_version: BOOKING_DATA_VERSION,
+        };
+
+        Self::set_data(test_type, new_data);
+    }
+
+    pub fn update_data(test_type: TestType, mut new_results: Vec<LocationBookings>, unavailable: UnavailableSlots) {
+        new_results = Self::clean_data(new_results, unavailable);
         let updated_data = BookingData {
             results: new_results,
-            last_updated: Some(chrono::Utc::now().to_rfc3339()),
+            last_updated: Some(chrono::Utc::now()),
+            schema_version: BOOKING_DATA_VERSION,
         };
 
-        let hash = updated_data.calculate_hash();
+        let previous = Self::get_data(test_type).0;
+        let changes = changes::diff(test_type, &previous.results, &updated_data.results, chrono::Utc::now());
+        Self::set_data(test_type, updated_data);
+        Self::publish_changes(changes);
+    }
+
+    fn publish_changes(changes: SlotChanges) {
+        if !changes.is_empty() {
+            info!(
+                "{} locations changed in the {} update, {} with an earlier slot",
+                changes.locations.len(), changes.test_type.label(), changes.earlier().count()
+            );
+        }
+        let changes = Arc::new(changes);
+        get_latest_changes().write().unwrap().insert(changes.test_type, Arc::clone(&changes));
+        // No subscribers is not an error
+        let _ = get_change_events().send(changes);
+    }
+
+    /// What the last update of `test_type` changed, if it has been updated
+    /// since startup.
+    pub fn latest_changes(test_type: TestType) -> Option<Arc<SlotChanges>> {
+        get_latest_changes().read().unwrap().get(&test_type).cloned()
+    }
+
+    /// Receive the changes of every update from now on, including ones that
+    /// changed nothing.
+    pub fn subscribe_changes() -> tokio::sync::broadcast::Receiver<Arc<SlotChanges>> {
+        get_change_events().subscribe()
+    }
+
+    /// Notify about locations with earlier slots after every update, with
+    /// `notify_earlier_slots`.
+    pub fn start_change_notifications(settings: &Settings) {
+        if !settings.notify_earlier_slots {
+            return;
+        }
+
+        let settings = settings.clone();
+        let mut events = Self::subscribe_changes();
+        spawn_named("change-notifications", async move {
+            loop {
+                match events.recv().await {
+                    Ok(changes) => {
+                        if let Some(notification) = Notification::earlier_slots(&changes) {
+                            notifications::dispatch(&settings, &notification).await;
+                        }
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!("Change notifications fell behind, skipped {} updates", missed);
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    /// Compact history older than `history_retention_days` into daily
+    /// aggregates, drop aggregates past `daily_history_retention_days` and
+    /// scrape runs past `history_retention_days`.
+    pub fn compact_data(settings: &Settings) {
+        let history_file = settings.history_file();
+        let daily_since = (settings.daily_history_retention_days > 0).then(|| {
+            (sydney_now() - chrono::Duration::days(settings.daily_history_retention_days as i64)).date()
+        });
+        match history::compact(&history_file, &settings.daily_history_file(), settings.history_cutoff(), daily_since) {
+            Ok(compaction) if compaction.compacted_entries > 0 || compaction.aggregates_dropped > 0 => info!(
+                "Compacted {} history entries into {} daily aggregates, dropped {} old aggregates",
+                compaction.compacted_entries, compaction.aggregates_written, compaction.aggregates_dropped
+            ),
+            Ok(_) => {}
+            Err(e) => error!("Failed to compact history file '{}': {}", history_file.display(), e),
+        }
+
+        let runs_cutoff = chrono::Utc::now() - chrono::Duration::days(settings.history_retention_days as i64);
+        let pruned = match settings.data_store {
+            DataStore::Json => scrape_runs::prune(&settings.scrape_runs_file(), runs_cutoff),
+            #[cfg(feature = "sqlite")]
+            DataStore::Sqlite => Database::open(&settings.database_file())
+                .and_then(|database| database.prune_runs(runs_cutoff))
+                .map(|_| ()),
+            #[cfg(not(feature = "sqlite"))]
+            DataStore::Sqlite => Ok(()),
+        };
+        if let Err(e) = pruned {
+            let path = match settings.data_store {
+                DataStore::Json => settings.scrape_runs_file(),
+                DataStore::Sqlite => settings.database_file(),
+            };
+            error!("Failed to prune scrape runs in '{}': {}", path.display(), e);
+        }
+    }
+
+    /// Run `compact_data` now and every `compaction_interval_hours`, on the
+    /// leader only since followers share its files.
+    pub fn start_compaction(settings: &Settings) {
+        let settings = settings.clone();
+        spawn_named("compaction", async move {
+            let interval = Duration::from_secs(settings.compaction_interval_hours * 60 * 60);
+            loop {
+                if leader::is_leader() {
+                    Self::compact_data(&settings);
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    #[cfg(feature = "scraper")]
+    /// Scrape every configured test type in turn, one dataset per type.
+    pub fn start_background_updates(locations: Vec<LocationId>, settings: Settings) {
+        {
+            let mut running = get_background_status().write().unwrap();
+            if *running {
+                return;
+            }
+            *running = true;
+        }
+        Self::set_stale_after(settings.stale_after_minutes);
+
+        let running_status = Arc::clone(get_background_status());
+        let watcher_settings = settings.clone();
+
+        let updater = spawn_named("background-scraper", async move {
+            let update_interval = Duration::from_secs(settings.scrape_refresh_minutes * 60);
+            let mut pulled = HashMap::new();
+
+            while *running_status.read().unwrap() {
+                let now = sydney_now();
+                let today = now.date();
+                if settings.quiet_on_non_business_days && !is_business_day(today) {
+                    info!("Skipping background scrape, {} is not a business day", today);
+                    Self::set_next_run(now + chrono::Duration::from_std(update_interval).unwrap_or_default());
+                    tokio::time::sleep(update_interval).await;
+                    continue;
+                }
+                if let Some(window) = settings.scrape_window() {
+                    let opens = window.next_open(now);
+                    if opens > now {
+                        info!("Outside the scrape window, next background scrape at {}", opens);
+                        Self::set_next_run(opens);
+                        // Wake at least every interval so a stop is noticed
+                        let wait = (opens - now).to_std().unwrap_or(update_interval).min(update_interval);
+                        tokio::time::sleep(wait).await;
+                        continue;
+                    }
+                }
+
+                let is_leader = leader::is_leader();
+                if !is_leader {
+                    Self::pull_from_leader(&settings, &mut pulled).await;
+                }
+                for &test_type in &settings.test_types {
+                    if is_leader {
+                        BookingManager::perform_update(test_type, locations.clone(), settings.clone()).await;
+                    } else if let Err(e) = BookingManager::load_data(test_type, &settings) {
+                        // Followers pick up whatever the leader saved
+                        error!("Failed to reload {} data from the leader: {}", test_type.label(), e);
+                    }
+                }
+
+                // Runs at exact intervals are easy to spot in the portal's logs
+                let wait = update_interval + super::rta::start_jitter(&settings);
+                let next = sydney_now() + chrono::Duration::from_std(wait).unwrap_or_default();
+                Self::set_next_run(settings.scrape_window().map_or(next, |window| window.next_open(next)));
+                tokio::time::sleep(wait).await;
+            }
+        });
 
-        let mut data_guard = get_booking_data().write().unwrap();
-        *data_guard = (updated_data, hash);
+        spawn_named("background-scraper-watcher", async move {
+            if let Err(e) = updater.await {
+                *get_background_status().write().unwrap() = false;
+                for &test_type in &watcher_settings.test_types {
+                    Self::mark_degraded(
+                        test_type,
+                        format!("Background updater stopped unexpectedly: {}", e),
+                        &watcher_settings,
+                    )
+                    .await;
+                }
+            }
+        });
     }
 
-    pub fn start_background_updates(locations: Vec<String>, file_path: String, settings: Settings) {
+    /// Download what the leader uploaded since the last pull. Only needed
+    /// with `object_storage`, otherwise replicas share the data directory.
+    /// Pulled jobs replace this replica's, so it is up to date if it takes
+    /// over.
+    async fn pull_from_leader(settings: &Settings, pulled: &mut HashMap<PathBuf, String>) {
+        if settings.object_storage.is_none() {
+            return;
+        }
+
+        let storage = storage::shared(settings);
+        match storage::pull_changed(storage.as_ref(), settings, pulled).await {
+            Ok(changed) => {
+                #[cfg(feature = "scraper")]
+                if changed.contains(&settings.auto_find_jobs_file()) {
+                    auto_find::clear_jobs();
+                    if let Err(e) = Self::restore_auto_find(settings) {
+                        error!("Failed to reload auto-find jobs from the leader: {}", e);
+                    }
+                }
+                if !changed.is_empty() {
+                    info!("Pulled {} files from the leader", changed.len());
+                }
+            }
+            Err(e) => error!("Failed to pull data from the leader: {}", e),
+        }
+    }
+
+    /// Mirror booking data from another instance's `/api/data/{test_type}`
+    /// endpoints instead of scraping, for web-only deployments.
+    pub fn start_remote_sync(api_url: String, settings: Settings) {
         {
             let mut running = get_background_status().write().unwrap();
             if *running {
@@ -143,16 +891,45 @@ impl BookingManager {
             }
             *running = true;
         }
+        Self::set_stale_after(settings.stale_after_minutes);
 
         let running_status = Arc::clone(get_background_status());
 
-        tokio::spawn(async move {
+        spawn_named("remote-sync", async move {
             let update_interval = Duration::from_secs(settings.scrape_refresh_minutes * 60);
+            let client = reqwest::Client::new();
 
             while *running_status.read().unwrap() {
-                BookingManager::perform_update(locations.clone(), &file_path, settings.clone())
+                for &test_type in &settings.test_types {
+                    let url = format!("{}/api/data/{}", api_url.trim_end_matches('/'), test_type.as_str());
+                    let fetched: reqwest::Result<BookingData> = async {
+                        client.get(&url).send().await?.error_for_status()?.json().await
+                    }
                     .await;
 
+                    match fetched {
+                        Ok(data) => {
+                            let previous = Self::get_data(test_type).0;
+                            let changes = changes::diff(test_type, &previous.results, &data.results, chrono::Utc::now());
+                            // Fetching the same update again is not a new one
+                            let updated = previous.last_updated != data.last_updated;
+                            Self::set_data(test_type, data);
+                            if updated {
+                                Self::publish_changes(changes);
+                            }
+                            Self::mark_healthy(test_type, &settings).await;
+                            if let Err(e) = Self::save_data(test_type, &settings) {
+                                let path = Self::data_location(test_type, &settings);
+                                error!("Failed to save booking data to '{}': {}", path.display(), e);
+                            }
+                        }
+                        Err(e) => {
+                            Self::mark_degraded(test_type, format!("Failed to fetch data from {}: {}", url, e), &settings)
+                                .await;
+                        }
+                    }
+                }
+
                 tokio::time::sleep(update_interval).await;
             }
         });
@@ -163,16 +940,256 @@ impl BookingManager {
         *running = false;
     }
 
+    /// The scraper's health for one test type's dataset. One dataset failing
+    /// doesn't make the others degraded or stale.
+    pub fn get_status(test_type: TestType) -> ScraperStatus {
+        let mut status = get_scraper_status().read().unwrap().get(&test_type).cloned().unwrap_or_default();
+        status.background_running = *get_background_status().read().unwrap();
+        if !status.background_running {
+            status.next_run = None;
+        }
+
+        // Before the first update of this run, judge by the data loaded from file
+        let newest = status.last_success.or_else(|| Self::get_data(test_type).0.last_updated);
+        status.stale = match (status.stale_after_minutes, newest) {
+            (Some(minutes), Some(newest)) => chrono::Utc::now() - newest > chrono::Duration::minutes(minutes as i64),
+            _ => false,
+        };
+        status.announcement = crate::announcement::current();
+        status.maintenance = maintenance::active(sydney_now());
+        status.blocked = challenge::active(sydney_now());
+
+        status
+    }
+
+    /// Update what all test types share: the WebDriver, the schedule.
+    fn update_every_status(update: impl Fn(&mut ScraperStatus)) {
+        let mut statuses = get_scraper_status().write().unwrap();
+        for test_type in TestType::ALL {
+            update(statuses.entry(test_type).or_default());
+        }
+    }
+
+    fn set_driver_health(health: DriverHealth) {
+        Self::update_every_status(|status| {
+            status.driver_ready = Some(health.ready);
+            status.driver_health = Some(health.clone());
+        });
+    }
+
+    fn set_next_run(at: NaiveDateTime) {
+        Self::update_every_status(|status| status.next_run = Some(at));
+    }
+
+    fn set_stale_after(minutes: u64) {
+        Self::update_every_status(|status| status.stale_after_minutes = Some(minutes));
+    }
+
+    /// Count a failed update and, once `degraded_after_failures` have failed in
+    /// a row, flag the data as degraded and alert the notification channels.
+    /// Only the transition into the degraded state raises an alert, to avoid a
+    /// notification every refresh interval while the portal is down.
+    async fn mark_degraded(test_type: TestType, reason: String, settings: &Settings) {
+        let (newly_degraded, failures) = {
+            let mut statuses = get_scraper_status().write().unwrap();
+            let status = statuses.entry(test_type).or_default();
+            status.consecutive_failures += 1;
+            status.reason = Some(reason.clone());
+            status.last_failure = Some(chrono::Utc::now());
+
+            let newly_degraded =
+                !status.degraded && status.consecutive_failures >= settings.degraded_after_failures.max(1);
+            if newly_degraded {
+                status.degraded = true;
+            }
+            (newly_degraded, status.consecutive_failures)
+        };
+
+        if newly_degraded {
+            error!("{} scraper degraded after {} failed updates: {}", test_type.label(), failures, reason);
+        } else {
+            warn!("{} update failed ({} in a row): {}", test_type.label(), failures, reason);
+        }
+
+        if newly_degraded {
+            let title = format!("{} scraper degraded", test_type.label());
+            let notification = Notification::new(NotificationLevel::Error, title, reason);
+            notifications::dispatch(settings, &notification).await;
+        }
+    }
+
+    async fn mark_healthy(test_type: TestType, settings: &Settings) {
+        let was_degraded = {
+            let mut statuses = get_scraper_status().write().unwrap();
+            let status = statuses.entry(test_type).or_default();
+            let was_degraded = status.degraded;
+            status.degraded = false;
+            status.consecutive_failures = 0;
+            status.reason = None;
+            status.last_success = Some(chrono::Utc::now());
+            maintenance::clear();
+            challenge::clear();
+            was_degraded
+        };
+
+        if was_degraded {
+            let notification = Notification::new(
+                NotificationLevel::Info,
+                format!("{} scraper recovered", test_type.label()),
+                format!("{} booking data is being updated again.", test_type.label()),
+            );
+            notifications::dispatch(settings, &notification).await;
+        }
+    }
+
     pub fn auto_find_running() -> bool {
-        *get_auto_status().read().unwrap()
+        *get_auto_status().read().unwrap() && auto_find::has_jobs()
     }
 
-    pub fn stop_auto_find() {
-        let mut running = get_auto_status().write().unwrap();
-        *running = false;
+    /// Stop the auto-finder for every booking.
+    pub fn stop_auto_find(settings: &Settings) {
+        auto_find::clear_jobs();
+        *get_auto_status().write().unwrap() = false;
+        Self::save_auto_find_jobs(settings);
     }
 
-    pub fn start_auto_find(locations: Vec<String>, before: chrono::NaiveDate, settings: Settings) {
+    /// Stop the auto-finder for one booking, the loop keeps running for others.
+    pub fn stop_auto_find_for(booking_id: &str, settings: &Settings) {
+        auto_find::remove_jobs_for(booking_id);
+        Self::save_auto_find_jobs(settings);
+    }
+
+    /// Rewrite `auto_find_jobs.json` so the current jobs survive a restart.
+    pub fn save_auto_find_jobs(settings: &Settings) {
+        let path = settings.auto_find_jobs_file();
+        if let Err(e) = auto_find::save(&path) {
+            error!("Failed to save auto-find jobs to '{}': {}", path.display(), e);
+        }
+    }
+
+    /// Bring back the jobs that were searching when the server stopped and
+    /// restart the search loop for them. Returns how many were restored.
+    #[cfg(feature = "scraper")]
+    pub fn restore_auto_find(settings: &Settings) -> Result<usize, String> {
+        let saved = auto_find::load(&settings.auto_find_jobs_file())?;
+        let count = saved.len();
+        for job in saved {
+            let booking_id = job.job.booking_id.clone();
+            let job_id = auto_find::restore(job, settings);
+            info!("Restored auto-find job {} for booking {}", job_id, booking_id);
+        }
+        if count > 0 {
+            Self::run_auto_find_loop(settings.clone());
+        }
+        Ok(count)
+    }
+
+    /// Add an auto-find job for `settings.booking_id` and make sure the shared
+    /// search loop is running. The job searches `locations` plus every centre
+    /// inside `area`.
+    #[cfg(feature = "scraper")]
+    pub fn start_auto_find(
+        locations: Vec<LocationId>,
+        area: Option<SearchArea>,
+        before: chrono::NaiveDate,
+        priority: i32,
+        settings: Settings,
+    ) -> JobId {
+        let loop_settings = settings.clone();
+        let job_id = auto_find::add_job(locations, area, before, priority, settings);
+        Self::save_auto_find_jobs(&loop_settings);
+        Self::run_auto_find_loop(loop_settings);
+        job_id
+    }
+
+    /// Add an auto-find job from a follower: it is handed to the leader
+    /// through the shared storage, which starts it within
+    /// `SUBMISSION_POLL`. Returns the id the job is submitted with.
+    #[cfg(feature = "scraper")]
+    pub async fn submit_auto_find(
+        locations: Vec<LocationId>,
+        area: Option<SearchArea>,
+        before: chrono::NaiveDate,
+        priority: i32,
+        settings: &Settings,
+    ) -> Result<JobId, String> {
+        let job = auto_find::new_job(locations, area, before, priority, settings);
+        let job_id = job.id;
+        let saved = auto_find::SavedJob {
+            job,
+            last_name: settings.last_name.clone(),
+            status: JobStatus::Searching,
+        };
+        auto_find::submit(storage::shared(settings).as_ref(), auto_find::Submission::Start(saved)).await?;
+        info!("Submitted auto-find job {} for booking {} to the leader", job_id, settings.booking_id);
+        Ok(job_id)
+    }
+
+    /// Stop the auto-finder for one booking on whichever replica runs it.
+    /// `last_name` must match the job's, so only its owner can stop it; the
+    /// leader checks again for submissions from followers.
+    pub async fn request_stop_auto_find(booking_id: &str, last_name: &str, settings: &Settings) -> Result<(), String> {
+        if leader::is_leader() {
+            if !auto_find::owned_by(booking_id, last_name) {
+                return Err("No auto-find job for that booking number and last name".to_string());
+            }
+            Self::stop_auto_find_for(booking_id, settings);
+            return Ok(());
+        }
+        let submission = auto_find::Submission::Stop { booking_id: booking_id.to_string(), last_name: last_name.to_string() };
+        auto_find::submit(storage::shared(settings).as_ref(), submission).await
+    }
+
+    /// With `leader_election`, have the leader carry out the job changes
+    /// followers submitted, checking every `SUBMISSION_POLL`.
+    #[cfg(feature = "scraper")]
+    pub fn start_submission_intake(settings: &Settings) {
+        if !settings.leader_election {
+            return;
+        }
+
+        let settings = settings.clone();
+        spawn_named("auto-find-intake", async move {
+            let storage = storage::shared(&settings);
+            loop {
+                tokio::time::sleep(SUBMISSION_POLL).await;
+                if !leader::is_leader() {
+                    continue;
+                }
+                let submissions = match auto_find::take_submissions(storage.as_ref()).await {
+                    Ok(submissions) => submissions,
+                    Err(e) => {
+                        error!("Failed to read auto-find submissions: {}", e);
+                        continue;
+                    }
+                };
+                for submission in submissions {
+                    match submission {
+                        auto_find::Submission::Start(saved) => {
+                            let booking_id = saved.job.booking_id.clone();
+                            let job_id = auto_find::accept(saved, &settings);
+                            Self::save_auto_find_jobs(&settings);
+                            info!("Started submitted auto-find job {} for booking {}", job_id, booking_id);
+                            Self::run_auto_find_loop(settings.clone());
+                        }
+                        auto_find::Submission::Stop { booking_id, last_name } => {
+                            if !auto_find::owned_by(&booking_id, &last_name) {
+                                warn!("Ignored a submitted stop for booking {} with the wrong last name", booking_id);
+                                continue;
+                            }
+                            Self::stop_auto_find_for(&booking_id, &settings);
+                            info!("Stopped auto-find for booking {} as submitted", booking_id);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Start the shared search loop unless it is already running. It stops
+    /// once no jobs are left.
+    #[cfg(feature = "scraper")]
+    fn run_auto_find_loop(settings: Settings) {
         {
             let mut running = get_auto_status().write().unwrap();
             if *running {
@@ -183,76 +1200,323 @@ impl BookingManager {
 
         let running_status = Arc::clone(get_auto_status());
 
-        tokio::spawn(async move {
-            let interval = Duration::from_secs(settings.scrape_refresh_minutes * 60);
-            while *running_status.read().unwrap() {
-                match super::rta::book_first_available(locations.clone(), before, &settings).await {
-                    Ok(Some((loc, time))) => println!("Found slot at {} on {}", loc, time),
-                    Ok(None) => println!("No slot found before {}", before),
-                    Err(e) => eprintln!("Error searching slots: {}", e),
+        spawn_named("auto-find", async move {
+            loop {
+                {
+                    // Checked under the flag's lock: a job added after this
+                    // sees the flag cleared and starts a new loop
+                    let mut running = running_status.write().unwrap();
+                    if !*running || !auto_find::has_jobs() {
+                        *running = false;
+                        auto_find::set_next_round(None);
+                        break;
+                    }
                 }
-                tokio::time::sleep(interval).await;
+                if leader::is_leader() {
+                    Self::run_auto_find_round().await;
+                    Self::save_auto_find_jobs(&settings);
+                }
+                // Rounds at exact intervals are easy to spot in the portal's logs
+                let wait = super::rta::auto_find_wait(&settings);
+                auto_find::set_next_round(chrono::Duration::from_std(wait).ok().map(|wait| chrono::Utc::now() + wait));
+                tokio::time::sleep(wait).await;
             }
         });
     }
 
-    pub async fn perform_update(locations: Vec<String>, file_path: &str, settings: Settings) {
+    /// Start an auto-find job for every `booking_queue` entry, each signed in
+    /// with its own booking. They share the search loop and take turns at
+    /// contested slots.
+    #[cfg(feature = "scraper")]
+    pub fn start_booking_queue(settings: &Settings) {
+        let all_locations = LocationManager::new().scrape_list(settings);
+        for entry in &settings.booking_queue {
+            let mut entry_settings = settings.clone();
+            entry_settings.booking_id = entry.booking_id.clone();
+            entry_settings.last_name = entry.last_name.clone();
+            let locations = if entry.locations.is_empty() {
+                all_locations.clone()
+            } else {
+                entry.locations.clone()
+            };
+
+            let job_id = Self::start_auto_find(locations, None, entry.target_date, 0, entry_settings);
+            info!("Queued booking {} as auto-find job {}", entry.booking_id, job_id);
+        }
+    }
+
+    /// Scrape the locations any job watches once, then try to book each slot
+    /// for the single job the coordinator assigned it to.
+    #[cfg(feature = "scraper")]
+    async fn run_auto_find_round() {
+        let jobs = auto_find::jobs();
+        let Some((_, scrape_settings)) = jobs.first() else {
+            return;
+        };
+        if Self::paused_reason().is_some() {
+            return;
+        }
+        auto_find::record_round(chrono::Utc::now());
+
+        let mut locations: Vec<LocationId> = jobs
+            .iter()
+            .flat_map(|(job, _)| job.search_locations())
+            .collect();
+        locations.sort();
+        locations.dedup();
+
+        let parameters = serde_json::json!({
+            "jobs": jobs.iter().map(|(job, _)| &job.booking_id).collect::<Vec<_>>(),
+            "locations": locations.len(),
+        });
+        let results = match Self::scrape(locations, scrape_settings.booking_test_type, scrape_settings).await {
+            Ok(results) => results.bookings,
+            Err(e) => {
+                error!("Error searching slots: {}", e);
+                for (job, _) in &jobs {
+                    auto_find::set_status(&job.booking_id, JobStatus::Failed { reason: e.clone() });
+                }
+                audit::record(scrape_settings, AuditEntry::new(AuditAction::AutoFind, parameters, false, e));
+                return;
+            }
+        };
+
+        let slots: Vec<(LocationId, TimeSlot)> = results
+            .into_values()
+            .flat_map(|booking| {
+                let location = booking.location;
+                booking.slots.into_iter().map(move |slot| (location, slot))
+            })
+            .collect();
+
+        let specs: Vec<_> = jobs.iter().map(|(job, _)| job.clone()).collect();
+        let allocations = auto_find::allocate(&specs, &slots, sydney_now());
+        if allocations.is_empty() {
+            info!("No slots found for {} auto-find jobs", jobs.len());
+        }
+        audit::record(
+            scrape_settings,
+            AuditEntry::new(
+                AuditAction::AutoFind,
+                parameters,
+                true,
+                format!("{} slots seen, {} assigned", slots.len(), allocations.len()),
+            ),
+        );
+
+        for allocation in allocations {
+            auto_find::record(allocation.clone());
+            info!(
+                "Assigned {} at {} to auto-find job {} (wanted by {:?})",
+                allocation.location, allocation.start_time, allocation.job_id, allocation.contenders
+            );
+
+            let Some((_, settings)) = jobs.iter().find(|(job, _)| job.id == allocation.job_id) else {
+                continue;
+            };
+            let Some((_, slot)) = slots
+                .iter()
+                .find(|(location, slot)| *location == allocation.location && slot.start_time == allocation.start_time)
+            else {
+                continue;
+            };
+
+            let booked = if settings.confirm_bookings || settings.hold_slots {
+                super::rta::book_slot_with_confirmation(allocation.location, slot, settings).await
+            } else {
+                super::rta::try_book_slot(allocation.location, slot, settings).await
+            };
+
+            let missed = |reason: String| JobStatus::Missed {
+                location: allocation.location,
+                start_time: allocation.start_time,
+                reason,
+            };
+            if let Some(notification) = booked.as_ref().ok().and_then(|receipt| Notification::booking(&settings.booking_id, receipt)) {
+                notifications::dispatch(settings, &notification).await;
+            }
+            match booked {
+                Ok(receipt) if receipt.secured() => {
+                    info!("Found slot at {} on {}", allocation.location, allocation.start_time);
+                    auto_find::remove_job(allocation.job_id);
+                    auto_find::set_status(&settings.booking_id, JobStatus::Booked { receipt });
+                }
+                Ok(receipt) if matches!(receipt.outcome, BookingOutcome::ChangeFeeApplies | BookingOutcome::ChangeLimitReached) => {
+                    // No slot will get past this, stop searching for the booking
+                    warn!("Stopping auto-find job {}: {:?}", allocation.job_id, receipt.outcome);
+                    auto_find::remove_job(allocation.job_id);
+                    let reason = match receipt.outcome {
+                        BookingOutcome::ChangeFeeApplies => "Changing the booking would cost a fee",
+                        _ => "The booking can't be changed any more",
+                    };
+                    auto_find::set_status(&settings.booking_id, JobStatus::Failed { reason: reason.to_string() });
+                }
+                Ok(receipt) => {
+                    warn!(
+                        "Slot at {} on {} was not booked for job {}: {:?}",
+                        allocation.location, allocation.start_time, allocation.job_id, receipt.outcome
+                    );
+                    auto_find::set_status(&settings.booking_id, missed(format!("{:?}", receipt.outcome)));
+                }
+                Err(e) => {
+                    error!("Error booking slot at {}: {}", allocation.location, e);
+                    auto_find::set_status(&settings.booking_id, missed(e.to_string()));
+                }
+            }
+        }
+    }
+
+    /// Scrape `locations` with the configured `scrape_backend`.
+    #[cfg(feature = "scraper")]
+    async fn scrape(
+        locations: Vec<LocationId>,
+        test_type: TestType,
+        settings: &Settings,
+    ) -> Result<ScrapeResults, String> {
+        super::backend::scrape(locations, test_type, settings).await
+    }
+
+    #[cfg(feature = "scraper")]
+    #[tracing::instrument(
+        name = "scrape_run",
+        skip_all,
+        fields(test_type = test_type.as_str(), backend = ?settings.scrape_backend, attempt = tracing::field::Empty)
+    )]
+    pub async fn perform_update(test_type: TestType, locations: Vec<LocationId>, settings: Settings) {
         let max_retries = settings.retries;
+        let started_at = chrono::Utc::now();
+
+        let mut final_results: HashMap<LocationId, LocationBookings> = HashMap::new();
+        let mut failure_reasons = BTreeMap::new();
+        let mut attempts = 0;
 
-        let mut final_results: HashMap<String, LocationBookings> = HashMap::new();
+        if let Some(reason) = Self::paused_reason() {
+            Self::skip_paused(test_type, started_at, &locations, &final_results, attempts, reason, &settings);
+            return;
+        }
+
+        let (locations, cooling_down) = get_location_cooldowns()
+            .write()
+            .unwrap()
+            .entry(test_type)
+            .or_default()
+            .start_run(locations);
+        if !cooling_down.is_empty() {
+            info!("Skipping {} locations on cooldown: {:?}", cooling_down.len(), cooling_down);
+        }
         let mut remaining_locations = locations.clone();
 
+        let checkpoint_file = settings.checkpoint_file(test_type);
+        if settings.checkpoint_max_age_minutes > 0 {
+            let max_age = chrono::Duration::minutes(settings.checkpoint_max_age_minutes as i64);
+            match checkpoint::load(&checkpoint_file) {
+                Some(previous) if previous.resumable(test_type, started_at, max_age) => {
+                    for bookings in previous.scraped {
+                        if locations.contains(&bookings.location) {
+                            final_results.insert(bookings.location, bookings);
+                        }
+                    }
+                    failure_reasons.extend(previous.errors);
+                    remaining_locations.retain(|loc| !final_results.contains_key(loc));
+                    info!(
+                        resumed = final_results.len(),
+                        "Resuming the interrupted {} run from {:?}, {} locations left",
+                        test_type.label(), previous.stage, remaining_locations.len()
+                    );
+                }
+                _ => {}
+            }
+            let mut current = ScrapeCheckpoint::new(test_type, remaining_locations.clone(), started_at);
+            current.scraped = final_results.values().cloned().collect();
+            checkpoint::save(&checkpoint_file, &current);
+        }
+
         for attempt in 1..=max_retries {
             if remaining_locations.is_empty() {
-                println!("INFO: All locations successfully scraped.");
+                info!("All locations successfully scraped.");
                 break;
             }
+            attempts = attempt;
+            tracing::Span::current().record("attempt", attempt);
 
-            println!(
-                "INFO: Scraping attempt {}/{} for {} locations...", 
-                attempt, max_retries, remaining_locations.len()
+            if settings.scrape_backend == ScrapeBackend::Browser {
+                let health = health::ensure_ready(&settings).await;
+                let ready = health.ready;
+                Self::set_driver_health(health);
+                if !ready {
+                    warn!(
+                        "WebDriver at {} not ready after {}s, skipping attempt {}/{}.",
+                        settings.selenium_driver_url, settings.driver_wait_seconds, attempt, max_retries
+                    );
+                    continue;
+                }
+            }
+
+            info!(
+                "Scraping {} attempt {}/{} for {} locations...",
+                test_type.label(), attempt, max_retries, remaining_locations.len()
             );
             
-            match super::rta::scrape_rta_timeslots(remaining_locations.clone(), &settings).await {
-                Ok(result_map) => {
-                    println!(
-                        "INFO: Successfully scraped {}/{} locations in attempt {}.",
-                        result_map.len(), remaining_locations.len(), attempt
+            match Self::scrape(remaining_locations.clone(), test_type, &settings).await {
+                Ok(results) => {
+                    Self::record_metrics(test_type, &results);
+                    info!(
+                        "Successfully scraped {}/{} locations in attempt {}.",
+                        results.bookings.len(), remaining_locations.len(), attempt
                     );
                     
-                    for (k, v) in result_map {
-                        final_results.insert(k.to_string(), v);
+                    for (k, v) in results.bookings {
+                        failure_reasons.remove(&k);
+                        final_results.insert(k, v);
                     }
+                    failure_reasons.extend(results.errors);
                     
                     remaining_locations.retain(|loc| !final_results.contains_key(loc));
                     
                     if remaining_locations.is_empty() {
-                        println!("INFO: All locations successfully scraped after {} attempts.", attempt);
+                        info!("All locations successfully scraped after {} attempts.", attempt);
                         break;
                     } else {
-                        println!(
-                            "WARN: {} locations still need to be scraped.",
+                        warn!(
+                            "{} locations still need to be scraped.",
                             remaining_locations.len()
                         );
                     }
                 }
                 Err(e) => {
-                    eprintln!(
-                        "ERROR: Scraping failed on attempt {}/{}: {:?}",
+                    error!(
+                        "Scraping failed on attempt {}/{}: {}",
                         attempt, max_retries, e
                     );
                     
                     if attempt == max_retries {
-                        eprintln!(
-                            "ERROR: Failed to scrape {} locations after {} attempts.",
+                        error!(
+                            "Failed to scrape {} locations after {} attempts.",
                             remaining_locations.len(), max_retries
                         );
                         if final_results.is_empty() {
-                            eprintln!("ERROR: No data was successfully scraped. No update will be performed.");
+                            error!("No data was successfully scraped. No update will be performed.");
+                            Self::record_run(
+                                Self::scrape_run(
+                                    test_type,
+                                    started_at,
+                                    &locations,
+                                    &final_results,
+                                    attempts,
+                                    Some(e.clone()),
+                                ),
+                                &settings,
+                            );
+                            Self::mark_degraded(
+                                test_type,
+                                format!("All {} {} scrape attempts failed, last error: {}", max_retries, test_type.label(), e),
+                                &settings,
+                            )
+                            .await;
                             return;
                         } else {
-                            eprintln!(
-                                "WARNING: Partial data collected. Successfully scraped {}/{} locations.",
+                            warn!(
+                                "Partial data collected. Successfully scraped {}/{} locations.",
                                 final_results.len(), locations.len()
                             );
                         }
@@ -260,20 +1524,91 @@ impl BookingManager {
                 }
             }
             
+            // Retrying is pointless until the portal is back or has stopped
+            // challenging us, keep whatever was scraped before that
+            if let Some(reason) = Self::paused_reason() {
+                if final_results.is_empty() {
+                    Self::skip_paused(test_type, started_at, &locations, &final_results, attempts, reason, &settings);
+                    return;
+                }
+                break;
+            }
+
             if attempt < max_retries && !remaining_locations.is_empty() {
                 tokio::time::sleep(Duration::from_secs(5)).await;
             }
         }
 
+        checkpoint::clear(&checkpoint_file);
+
+        let mut run = Self::scrape_run(
+            test_type,
+            started_at,
+            &locations,
+            &final_results,
+            attempts,
+            final_results.is_empty().then(|| "No locations were scraped".to_string()),
+        );
+        run.cooling_down = cooling_down;
+        run.failure_reasons = failure_reasons
+            .into_iter()
+            .filter(|(location, _)| run.failed.contains(location))
+            .collect();
+        info!(
+            succeeded = run.succeeded.len(),
+            failed = run.failed.len(),
+            attempts,
+            duration_ms = (chrono::Utc::now() - started_at).num_milliseconds(),
+            "Scrape run finished"
+        );
+        // A run that scraped nothing failed before reaching the centres and
+        // says nothing about any one of them
         if !final_results.is_empty() {
+            let started = get_location_cooldowns().write().unwrap().entry(test_type).or_default().record(
+                &run.succeeded,
+                &run.failed,
+                settings.location_failure_threshold,
+                settings.location_cooldown_runs,
+            );
+            for location in started {
+                warn!(
+                    "Location {} failed {} runs in a row, skipping it for {} runs",
+                    location, settings.location_failure_threshold, settings.location_cooldown_runs
+                );
+            }
+        }
+        Self::record_run(run, &settings);
+
+        let scraped_any = !final_results.is_empty();
+        if !scraped_any {
+            Self::mark_degraded(
+                test_type,
+                format!("No {} locations were scraped after {} attempts", test_type.label(), max_retries),
+                &settings,
+            )
+            .await;
+        } else {
             let all_results: Vec<LocationBookings> = final_results.into_values().collect();
-            Self::update_data(all_results);
+            Self::update_data(test_type, all_results, settings.unavailable_slots);
+            Self::mark_healthy(test_type, &settings).await;
+
+            let history_file = settings.history_file();
+            let recorded_at = sydney_now();
+            if let Err(e) = history::append(&history_file, &Self::get_data(test_type).0.results, recorded_at) {
+                error!("Failed to record history to '{}': {}", history_file.display(), e);
+            }
         }
 
-        if let Err(e) = Self::save_to_file(file_path) {
-            eprintln!("ERROR: Failed to save booking data to file '{}': {}", file_path, e);
+        let file_path = Self::data_location(test_type, &settings);
+        if let Err(e) = Self::save_data(test_type, &settings) {
+            error!("Failed to save booking data to '{}': {}", file_path.display(), e);
         } else {
-            println!("INFO: Update process complete. Data saved to '{}'.", file_path);
+            info!("Update process complete. Data saved to '{}'.", file_path.display());
+            if scraped_any {
+                if let Err(e) = backup::take(&settings, test_type, chrono::Utc::now()) {
+                    error!("Failed to back up {} data: {}", test_type.label(), e);
+                }
+            }
         }
     }
 }
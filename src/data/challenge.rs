@@ -0,0 +1,89 @@
+//! Detection of captcha and "unusual activity" pages. Once the portal's bot
+//! protection has flagged the scraper, every retry only deepens the block, so
+//! the run stops at the first challenge and scraping stays off for
+//! `blocked_cooldown_minutes`.
+
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+/// Markers of known challenge pages, matched against the lowercase page
+/// source so embedded captcha frames are caught as well as visible text.
+const CHALLENGE_MARKERS: [&str; 12] = [
+    "g-recaptcha",
+    "recaptcha/api",
+    "h-captcha",
+    "hcaptcha.com",
+    "challenge-platform",
+    "cf-challenge",
+    "_incapsula_resource",
+    "request unsuccessful. incapsula",
+    "unusual activity",
+    "unusual traffic",
+    "verify you are human",
+    "are you a robot",
+];
+
+#[cfg(feature = "server")]
+pub use self::store::{active, clear, record};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockedWindow {
+    /// The marker that identified the challenge page
+    pub marker: String,
+    pub detected_at: NaiveDateTime,
+    /// When scraping resumes
+    pub until: NaiveDateTime,
+}
+
+impl BlockedWindow {
+    /// A window lasting `cooldown` if `page_source` is a challenge page,
+    /// `None` for any other page.
+    pub fn detect(page_source: &str, now: NaiveDateTime, cooldown: Duration) -> Option<Self> {
+        let source = page_source.to_lowercase();
+        let marker = CHALLENGE_MARKERS.iter().find(|marker| source.contains(*marker))?;
+
+        Some(Self {
+            marker: marker.to_string(),
+            detected_at: now,
+            until: now + cooldown,
+        })
+    }
+
+    pub fn is_active(&self, now: NaiveDateTime) -> bool {
+        self.until > now
+    }
+}
+
+#[cfg(feature = "server")]
+mod store {
+    use std::sync::{Arc, OnceLock, RwLock};
+
+    use chrono::NaiveDateTime;
+    use tracing::warn;
+
+    use super::BlockedWindow;
+
+    static BLOCKED: OnceLock<Arc<RwLock<Option<BlockedWindow>>>> = OnceLock::new();
+
+    fn get_blocked() -> &'static Arc<RwLock<Option<BlockedWindow>>> {
+        BLOCKED.get_or_init(|| Arc::new(RwLock::new(None)))
+    }
+
+    pub fn record(window: BlockedWindow) {
+        warn!(marker = %window.marker, until = %window.until, "RTA portal showed a challenge page, pausing scraping");
+        *get_blocked().write().unwrap() = Some(window);
+    }
+
+    /// The block the scraper is still cooling down from, if any.
+    pub fn active(now: NaiveDateTime) -> Option<BlockedWindow> {
+        get_blocked()
+            .read()
+            .unwrap()
+            .clone()
+            .filter(|window| window.is_active(now))
+    }
+
+    pub fn clear() {
+        *get_blocked().write().unwrap() = None;
+    }
+}
@@ -0,0 +1,119 @@
+//! What changed between two scrapes. `BookingManager::update_data` diffs the
+//! new results against the ones they replace and publishes the `SlotChanges`
+//! to subscribers, so notifications, the API and the UI all work from the
+//! same diff instead of each comparing datasets themselves.
+
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::location::LocationId;
+use super::shared_booking::{LocationBookings, TestType};
+
+/// How the bookable slots of one location changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocationChange {
+    pub location: LocationId,
+    /// Start times offered now that weren't before, earliest first
+    pub added: Vec<NaiveDateTime>,
+    /// Start times no longer offered, earliest first
+    pub removed: Vec<NaiveDateTime>,
+    pub earliest_before: Option<NaiveDateTime>,
+    pub earliest_after: Option<NaiveDateTime>,
+}
+
+impl LocationChange {
+    /// Whether the location now has an earlier slot than it had, including
+    /// one where it had none.
+    pub fn earlier(&self) -> bool {
+        match (self.earliest_before, self.earliest_after) {
+            (Some(before), Some(after)) => after < before,
+            (None, Some(_)) => true,
+            _ => false,
+        }
+    }
+
+    pub fn earliest_changed(&self) -> bool {
+        self.earliest_before != self.earliest_after
+    }
+}
+
+/// Every location whose bookable slots changed in one update.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlotChanges {
+    pub test_type: TestType,
+    pub detected_at: DateTime<Utc>,
+    /// Ordered by location
+    pub locations: Vec<LocationChange>,
+}
+
+impl SlotChanges {
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+    pub fn location(&self, location: LocationId) -> Option<&LocationChange> {
+        self.locations.iter().find(|change| change.location == location)
+    }
+
+    /// The same update narrowed down to `locations`.
+    pub fn for_locations(&self, locations: &[LocationId]) -> SlotChanges {
+        SlotChanges {
+            test_type: self.test_type,
+            detected_at: self.detected_at,
+            locations: self
+                .locations
+                .iter()
+                .filter(|change| locations.contains(&change.location))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Locations that gained an earlier slot.
+    pub fn earlier(&self) -> impl Iterator<Item = &LocationChange> {
+        self.locations.iter().filter(|change| change.earlier())
+    }
+}
+
+fn bookable(bookings: &LocationBookings) -> BTreeSet<NaiveDateTime> {
+    bookings
+        .slots
+        .iter()
+        .filter(|slot| slot.availability)
+        .map(|slot| slot.start_time)
+        .collect()
+}
+
+/// Diff `current` against the `previous` results. Only bookable slots count.
+/// A location missing from `current` wasn't scraped this time rather than
+/// emptied, so it is left out instead of reported as losing every slot.
+pub fn diff(
+    test_type: TestType,
+    previous: &[LocationBookings],
+    current: &[LocationBookings],
+    detected_at: DateTime<Utc>,
+) -> SlotChanges {
+    let previous: HashMap<LocationId, BTreeSet<NaiveDateTime>> =
+        previous.iter().map(|bookings| (bookings.location, bookable(bookings))).collect();
+
+    let mut locations: Vec<LocationChange> = current
+        .iter()
+        .filter_map(|bookings| {
+            let after = bookable(bookings);
+            let before = previous.get(&bookings.location).cloned().unwrap_or_default();
+            let change = LocationChange {
+                location: bookings.location,
+                added: after.difference(&before).copied().collect(),
+                removed: before.difference(&after).copied().collect(),
+                earliest_before: before.first().copied(),
+                earliest_after: after.first().copied(),
+            };
+            (!change.added.is_empty() || !change.removed.is_empty()).then_some(change)
+        })
+        .collect();
+    locations.sort_by_key(|change| change.location);
+
+    SlotChanges { test_type, detected_at, locations }
+}
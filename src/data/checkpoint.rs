@@ -0,0 +1,164 @@
+//! Checkpoints of a scrape run in progress. The flow is a small state
+//! machine, sign in → test selection → the location loop, and every step and
+//! every finished location is written to `checkpoint-<type>.json`. After a
+//! crash or restart, `perform_update` picks a recent checkpoint back up and
+//! only scrapes the locations it hadn't reached.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::location::LocationId;
+use super::shared_booking::{LocationBookings, TestType};
+
+#[cfg(feature = "server")]
+pub use self::store::{clear, enter, load, location_done, location_failed, save};
+
+/// Where in the portal flow a run got to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrapeStage {
+    #[default]
+    SignIn,
+    TestSelection,
+    Locations,
+    Done,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrapeCheckpoint {
+    pub test_type: TestType,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub stage: ScrapeStage,
+    /// Locations still to scrape, in no particular order
+    pub pending: Vec<LocationId>,
+    pub scraped: Vec<LocationBookings>,
+    /// Why the last try at each pending location failed
+    #[serde(default)]
+    pub errors: BTreeMap<LocationId, String>,
+}
+
+impl ScrapeCheckpoint {
+    pub fn new(test_type: TestType, locations: Vec<LocationId>, now: DateTime<Utc>) -> Self {
+        ScrapeCheckpoint {
+            test_type,
+            started_at: now,
+            updated_at: now,
+            stage: ScrapeStage::SignIn,
+            pending: locations,
+            scraped: Vec::new(),
+            errors: BTreeMap::new(),
+        }
+    }
+
+    /// Move to `stage`. A lost session sends the run back to `SignIn`, and
+    /// nothing leaves `Done`.
+    pub fn enter(&mut self, stage: ScrapeStage, now: DateTime<Utc>) {
+        if self.stage == ScrapeStage::Done {
+            return;
+        }
+        self.stage = stage;
+        self.updated_at = now;
+    }
+
+    pub fn location_done(&mut self, bookings: LocationBookings, now: DateTime<Utc>) {
+        self.pending.retain(|&location| location != bookings.location);
+        self.errors.remove(&bookings.location);
+        self.scraped.retain(|scraped| scraped.location != bookings.location);
+        self.scraped.push(bookings);
+        if self.pending.is_empty() {
+            self.stage = ScrapeStage::Done;
+        }
+        self.updated_at = now;
+    }
+
+    pub fn location_failed(&mut self, location: LocationId, reason: String, now: DateTime<Utc>) {
+        self.errors.insert(location, reason);
+        self.updated_at = now;
+    }
+
+    /// Whether a new run should pick this one up: it's unfinished and was
+    /// still making progress within `max_age`.
+    pub fn resumable(&self, test_type: TestType, now: DateTime<Utc>, max_age: Duration) -> bool {
+        self.test_type == test_type
+            && self.stage != ScrapeStage::Done
+            && !self.pending.is_empty()
+            && now - self.updated_at <= max_age
+    }
+}
+
+#[cfg(feature = "server")]
+mod store {
+    use std::fs;
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use chrono::Utc;
+    use tracing::warn;
+
+    use super::{ScrapeCheckpoint, ScrapeStage};
+    use crate::data::location::LocationId;
+    use crate::data::shared_booking::LocationBookings;
+
+    /// Workers of one run share the file
+    static CHECKPOINT_LOCK: Mutex<()> = Mutex::new(());
+
+    pub fn load(path: &Path) -> Option<ScrapeCheckpoint> {
+        let contents = fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&contents) {
+            Ok(checkpoint) => Some(checkpoint),
+            Err(e) => {
+                warn!("Ignoring unreadable checkpoint '{}': {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn save(path: &Path, checkpoint: &ScrapeCheckpoint) {
+        let _guard = CHECKPOINT_LOCK.lock().unwrap();
+        write(path, checkpoint);
+    }
+
+    pub fn clear(path: &Path) {
+        let _guard = CHECKPOINT_LOCK.lock().unwrap();
+        if let Err(e) = fs::remove_file(path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("Failed to remove checkpoint '{}': {}", path.display(), e);
+            }
+        }
+    }
+
+    pub fn enter(path: &Path, stage: ScrapeStage) {
+        update(path, |checkpoint| checkpoint.enter(stage, Utc::now()));
+    }
+
+    pub fn location_done(path: &Path, bookings: &LocationBookings) {
+        update(path, |checkpoint| checkpoint.location_done(bookings.clone(), Utc::now()));
+    }
+
+    pub fn location_failed(path: &Path, location: LocationId, reason: &str) {
+        update(path, |checkpoint| checkpoint.location_failed(location, reason.to_string(), Utc::now()));
+    }
+
+    /// Apply `change` to the checkpoint on disk. Without one, as when
+    /// checkpoints are off or a booking flow is scraping, this does nothing.
+    fn update(path: &Path, change: impl FnOnce(&mut ScrapeCheckpoint)) {
+        let _guard = CHECKPOINT_LOCK.lock().unwrap();
+        let Some(mut checkpoint) = load(path) else {
+            return;
+        };
+        change(&mut checkpoint);
+        write(path, &checkpoint);
+    }
+
+    fn write(path: &Path, checkpoint: &ScrapeCheckpoint) {
+        let result = serde_json::to_string(checkpoint)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(path, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!("Failed to write checkpoint '{}': {}", path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Pending bookings waiting for the user to approve them. The auto-finder
+//! holds a slot at the confirmation page and registers it here; the links in
+//! the notification resolve it through the `/api/confirm` endpoints, and the
+//! auto-finder panel through its approve and decline buttons.
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rand::Rng;
+use tokio::sync::oneshot;
+
+use super::location::LocationId;
+use super::shared_booking::PendingApproval;
+
+pub struct PendingBooking {
+    pub booking_id: String,
+    pub location: LocationId,
+    pub start_time: NaiveDateTime,
+    pub expires_at: DateTime<Utc>,
+    decision: oneshot::Sender<bool>,
+}
+
+static PENDING_BOOKINGS: OnceLock<Arc<RwLock<HashMap<String, PendingBooking>>>> = OnceLock::new();
+
+fn get_pending_bookings() -> &'static Arc<RwLock<HashMap<String, PendingBooking>>> {
+    PENDING_BOOKINGS.get_or_init(|| Arc::new(RwLock::new(HashMap::new())))
+}
+
+fn new_token() -> String {
+    let mut rng = rand::thread_rng();
+    (0..32)
+        .map(|_| format!("{:x}", rng.gen_range(0..16u8)))
+        .collect()
+}
+
+/// Register a held slot. Returns the token for the confirm/decline links and
+/// a receiver that yields the user's decision.
+pub fn register(
+    booking_id: &str,
+    location: LocationId,
+    start_time: NaiveDateTime,
+    expires_at: DateTime<Utc>,
+) -> (String, oneshot::Receiver<bool>) {
+    let (sender, receiver) = oneshot::channel();
+    let token = new_token();
+
+    get_pending_bookings().write().unwrap().insert(
+        token.clone(),
+        PendingBooking {
+            booking_id: booking_id.to_string(),
+            location,
+            start_time,
+            expires_at,
+            decision: sender,
+        },
+    );
+
+    (token, receiver)
+}
+
+/// The slot behind `token`, if it is still waiting for an answer.
+pub fn peek(token: &str) -> Option<(LocationId, NaiveDateTime)> {
+    get_pending_bookings()
+        .read()
+        .unwrap()
+        .get(token)
+        .filter(|pending| pending.expires_at >= Utc::now())
+        .map(|pending| (pending.location, pending.start_time))
+}
+
+/// Deliver the user's decision. Returns the booking it applied to, or `None`
+/// for an unknown or expired token.
+pub fn resolve(token: &str, confirmed: bool) -> Option<(LocationId, NaiveDateTime)> {
+    let pending = get_pending_bookings().write().unwrap().remove(token)?;
+    if pending.expires_at < Utc::now() {
+        return None;
+    }
+
+    pending.decision.send(confirmed).ok()?;
+    Some((pending.location, pending.start_time))
+}
+
+/// The slot held for `booking_id`, if it is still waiting for an answer.
+pub fn pending_for(booking_id: &str) -> Option<PendingApproval> {
+    let now = Utc::now();
+    get_pending_bookings()
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(_, pending)| pending.booking_id == booking_id && pending.expires_at >= now)
+        .map(|(token, pending)| PendingApproval {
+            token: token.clone(),
+            location: pending.location,
+            start_time: pending.start_time,
+            expires_at: pending.expires_at,
+        })
+}
+
+/// Forget a pending booking once it has timed out.
+pub fn discard(token: &str) {
+    get_pending_bookings().write().unwrap().remove(token);
+}
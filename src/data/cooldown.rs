@@ -0,0 +1,71 @@
+//! Cooldowns for centres that keep failing to scrape, e.g. because they were
+//! renamed in the portal's dropdown or are temporarily closed. Rather than
+//! retrying them every run, which makes every run slower, a centre that fails
+//! `location_failure_threshold` runs in a row sits out the next
+//! `location_cooldown_runs` runs.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::location::LocationId;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CooldownEntry {
+    /// Runs in a row this centre was requested and returned nothing
+    pub consecutive_failures: u32,
+    /// Runs still to skip before trying the centre again
+    pub remaining_runs: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LocationCooldowns {
+    entries: HashMap<LocationId, CooldownEntry>,
+}
+
+impl LocationCooldowns {
+    /// Split this run's locations into those to scrape and those cooling
+    /// down, counting the run against each cooldown.
+    pub fn start_run(&mut self, locations: Vec<LocationId>) -> (Vec<LocationId>, Vec<LocationId>) {
+        let (cooling, scrape): (Vec<LocationId>, Vec<LocationId>) = locations
+            .into_iter()
+            .partition(|location| self.entries.get(location).is_some_and(|entry| entry.remaining_runs > 0));
+
+        for location in &cooling {
+            if let Some(entry) = self.entries.get_mut(location) {
+                entry.remaining_runs -= 1;
+            }
+        }
+        (scrape, cooling)
+    }
+
+    /// Record the outcome of a run. Returns the centres that just started a
+    /// cooldown. A threshold or cooldown of 0 disables cooldowns.
+    pub fn record(
+        &mut self,
+        succeeded: &[LocationId],
+        failed: &[LocationId],
+        threshold: u32,
+        cooldown_runs: u32,
+    ) -> Vec<LocationId> {
+        for location in succeeded {
+            self.entries.remove(location);
+        }
+
+        let mut started = Vec::new();
+        for &location in failed {
+            let entry = self.entries.entry(location).or_default();
+            entry.consecutive_failures += 1;
+            if threshold > 0 && cooldown_runs > 0 && entry.consecutive_failures >= threshold {
+                // The next failure after the cooldown starts a fresh count
+                *entry = CooldownEntry { consecutive_failures: 0, remaining_runs: cooldown_runs };
+                started.push(location);
+            }
+        }
+        started
+    }
+
+    pub fn get(&self, location: LocationId) -> Option<CooldownEntry> {
+        self.entries.get(&location).copied()
+    }
+}
@@ -0,0 +1,283 @@
+//! SQLite store for the booking data, an alternative to the `bookings.json`
+//! files selected with `data_store: sqlite` in builds with the `sqlite`
+//! feature.
+//!
+//! Every test type's dataset lives in one `bookings.sqlite3` in the data
+//! directory, as rows of locations and their slots, next to the log of scrape
+//! runs. Saving a dataset only rewrites what changed instead of the whole
+//! file, and the database is in WAL mode so other processes (the scraper
+//! binary, `sqlite3` on the command line) can read it while it is written.
+//! `BookingManager` still keeps the current data in memory and answers every
+//! request from there; the store is only read at startup and written after
+//! each update.
+
+use serde::{Deserialize, Serialize};
+
+/// Where `BookingManager` persists the booking data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataStore {
+    /// One JSON file per test type, optionally gzipped
+    #[default]
+    Json,
+    /// `bookings.sqlite3` in the data directory. Needs the `sqlite` feature
+    Sqlite,
+}
+
+#[cfg(feature = "sqlite")]
+pub use self::store::Database;
+
+#[cfg(feature = "sqlite")]
+mod store {
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use chrono::{DateTime, NaiveDateTime, Utc};
+    use rusqlite::{params, Connection, OptionalExtension};
+
+    use crate::data::location::LocationId;
+    use crate::data::scrape_runs::RunQuery;
+    use crate::data::shared_booking::{BookingData, LocationBookings, ScrapeRun, TestType, TimeSlot};
+
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS datasets (
+            test_type TEXT PRIMARY KEY,
+            last_updated TEXT,
+            schema_version INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS locations (
+            test_type TEXT NOT NULL,
+            location INTEGER NOT NULL,
+            next_available_date TEXT,
+            PRIMARY KEY (test_type, location)
+        );
+        CREATE TABLE IF NOT EXISTS slots (
+            test_type TEXT NOT NULL,
+            location INTEGER NOT NULL,
+            start_time TEXT NOT NULL,
+            slot_number INTEGER,
+            availability INTEGER NOT NULL,
+            PRIMARY KEY (test_type, location, start_time)
+        );
+        CREATE TABLE IF NOT EXISTS scrape_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            test_type TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            run TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS scrape_runs_started_at ON scrape_runs (started_at);
+    ";
+
+    /// How long a write waits for another writer before giving up.
+    const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn db_error(e: rusqlite::Error) -> String {
+        format!("Database error: {}", e)
+    }
+
+    /// An open connection to the booking database. Cheap enough to open for
+    /// every load or save.
+    pub struct Database {
+        connection: Connection,
+    }
+
+    impl Database {
+        /// Open the database at `path`, creating it and its tables if needed.
+        pub fn open(path: &Path) -> Result<Self, String> {
+            let connection = Connection::open(path).map_err(db_error)?;
+            connection.busy_timeout(BUSY_TIMEOUT).map_err(db_error)?;
+            connection
+                .pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+                .map_err(db_error)?;
+            connection.execute_batch(SCHEMA).map_err(db_error)?;
+            Ok(Self { connection })
+        }
+
+        /// The stored dataset of `test_type`, or `None` if it was never saved.
+        pub fn load(&self, test_type: TestType) -> Result<Option<BookingData>, String> {
+            let dataset = self
+                .connection
+                .query_row(
+                    "SELECT last_updated, schema_version FROM datasets WHERE test_type = ?1",
+                    params![test_type.as_str()],
+                    |row| Ok((row.get::<_, Option<DateTime<Utc>>>(0)?, row.get::<_, u32>(1)?)),
+                )
+                .optional()
+                .map_err(db_error)?;
+            let Some((last_updated, schema_version)) = dataset else {
+                return Ok(None);
+            };
+
+            let mut slots: HashMap<LocationId, Vec<TimeSlot>> = HashMap::new();
+            let mut statement = self
+                .connection
+                .prepare(
+                    "SELECT location, start_time, slot_number, availability FROM slots
+                     WHERE test_type = ?1 ORDER BY location, start_time",
+                )
+                .map_err(db_error)?;
+            let rows = statement
+                .query_map(params![test_type.as_str()], |row| {
+                    Ok((
+                        LocationId(row.get(0)?),
+                        TimeSlot {
+                            start_time: row.get(1)?,
+                            slot_number: row.get(2)?,
+                            availability: row.get(3)?,
+                        },
+                    ))
+                })
+                .map_err(db_error)?;
+            for row in rows {
+                let (location, slot) = row.map_err(db_error)?;
+                slots.entry(location).or_default().push(slot);
+            }
+
+            let mut statement = self
+                .connection
+                .prepare("SELECT location, next_available_date FROM locations WHERE test_type = ?1 ORDER BY location")
+                .map_err(db_error)?;
+            let results = statement
+                .query_map(params![test_type.as_str()], |row| {
+                    Ok((LocationId(row.get(0)?), row.get::<_, Option<NaiveDateTime>>(1)?))
+                })
+                .map_err(db_error)?
+                .map(|row| {
+                    row.map(|(location, next_available_date)| LocationBookings {
+                        location,
+                        test_type,
+                        slots: slots.remove(&location).unwrap_or_default(),
+                        next_available_date,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(db_error)?;
+
+            Ok(Some(BookingData { results, last_updated, schema_version }))
+        }
+
+        /// Replace the stored dataset of `test_type` with `data` in one
+        /// transaction. Locations whose slots are unchanged are left alone,
+        /// and locations no longer in `data` are removed.
+        pub fn save(&mut self, test_type: TestType, data: &BookingData) -> Result<(), String> {
+            let stored = self.load(test_type)?.map(|data| data.results).unwrap_or_default();
+            let stored: HashMap<LocationId, &LocationBookings> =
+                stored.iter().map(|bookings| (bookings.location, bookings)).collect();
+
+            let transaction = self.connection.transaction().map_err(db_error)?;
+            transaction
+                .execute(
+                    "INSERT INTO datasets (test_type, last_updated, schema_version) VALUES (?1, ?2, ?3)
+                     ON CONFLICT (test_type) DO UPDATE
+                     SET last_updated = excluded.last_updated, schema_version = excluded.schema_version",
+                    params![test_type.as_str(), data.last_updated, data.schema_version],
+                )
+                .map_err(db_error)?;
+
+            for bookings in &data.results {
+                let unchanged = stored.get(&bookings.location).is_some_and(|stored| {
+                    stored.next_available_date == bookings.next_available_date
+                        && stored.slots.len() == bookings.slots.len()
+                        && stored.slots.iter().zip(&bookings.slots).all(|(a, b)| {
+                            a.start_time == b.start_time
+                                && a.slot_number == b.slot_number
+                                && a.availability == b.availability
+                        })
+                });
+                if unchanged {
+                    continue;
+                }
+
+                transaction
+                    .execute(
+                        "INSERT INTO locations (test_type, location, next_available_date) VALUES (?1, ?2, ?3)
+                         ON CONFLICT (test_type, location) DO UPDATE
+                         SET next_available_date = excluded.next_available_date",
+                        params![test_type.as_str(), bookings.location.0, bookings.next_available_date],
+                    )
+                    .map_err(db_error)?;
+                transaction
+                    .execute(
+                        "DELETE FROM slots WHERE test_type = ?1 AND location = ?2",
+                        params![test_type.as_str(), bookings.location.0],
+                    )
+                    .map_err(db_error)?;
+                let mut insert = transaction
+                    .prepare_cached(
+                        "INSERT OR REPLACE INTO slots (test_type, location, start_time, slot_number, availability)
+                         VALUES (?1, ?2, ?3, ?4, ?5)",
+                    )
+                    .map_err(db_error)?;
+                for slot in &bookings.slots {
+                    insert
+                        .execute(params![
+                            test_type.as_str(),
+                            bookings.location.0,
+                            slot.start_time,
+                            slot.slot_number,
+                            slot.availability
+                        ])
+                        .map_err(db_error)?;
+                }
+            }
+
+            let current: Vec<LocationId> = data.results.iter().map(|bookings| bookings.location).collect();
+            for location in stored.keys().filter(|location| !current.contains(location)) {
+                for table in ["locations", "slots"] {
+                    transaction
+                        .execute(
+                            &format!("DELETE FROM {} WHERE test_type = ?1 AND location = ?2", table),
+                            params![test_type.as_str(), location.0],
+                        )
+                        .map_err(db_error)?;
+                }
+            }
+
+            transaction.commit().map_err(db_error)
+        }
+
+        pub fn append_run(&self, run: &ScrapeRun) -> Result<(), String> {
+            let json = serde_json::to_string(run).map_err(|e| format!("Failed to serialize scrape run: {}", e))?;
+            self.connection
+                .execute(
+                    "INSERT INTO scrape_runs (test_type, started_at, run) VALUES (?1, ?2, ?3)",
+                    params![run.test_type.as_str(), run.started_at, json],
+                )
+                .map_err(db_error)?;
+            Ok(())
+        }
+
+        /// Write a consistent copy of the whole database to `path`, including
+        /// what is still in the WAL, for uploading. Replaces `path`.
+        pub fn snapshot(&self, path: &Path) -> Result<(), String> {
+            if path.exists() {
+                std::fs::remove_file(path).map_err(|e| format!("Failed to remove '{}': {}", path.display(), e))?;
+            }
+            let path = path.to_str().ok_or_else(|| format!("Invalid snapshot path '{}'", path.display()))?;
+            self.connection.execute("VACUUM INTO ?1", params![path]).map_err(db_error)?;
+            Ok(())
+        }
+
+        /// Drop runs that started before `since`. Returns how many.
+        pub fn prune_runs(&self, since: DateTime<Utc>) -> Result<usize, String> {
+            self.connection
+                .execute("DELETE FROM scrape_runs WHERE started_at < ?1", params![since])
+                .map_err(db_error)
+        }
+
+        /// Runs matching `query`, oldest first. Unparseable rows are skipped.
+        pub fn load_runs(&self, query: &RunQuery) -> Result<Vec<ScrapeRun>, String> {
+            let mut statement = self
+                .connection
+                .prepare("SELECT run FROM scrape_runs WHERE ?1 IS NULL OR started_at >= ?1 ORDER BY id")
+                .map_err(db_error)?;
+            let runs = statement
+                .query_map(params![query.since], |row| row.get::<_, String>(0))
+                .map_err(db_error)?
+                .filter_map(|row| row.ok())
+                .filter_map(|json| serde_json::from_str(&json).ok());
+            Ok(query.apply(runs))
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Optional capture of every XHR and fetch response the portal sends during a
+//! scrape. When parsing breaks after a site update, the captured payloads
+//! show exactly what changed. Responses are kept in a JSON lines file holding
+//! at most `diagnostics_max_entries`, the oldest dropped first.
+//!
+//! Separately, when a location fails to scrape, a screenshot and the page
+//! source are saved to the `debug` directory so broken selectors can be seen
+//! rather than guessed from log lines.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::location::LocationId;
+
+/// Longest response body kept, in characters.
+pub const MAX_BODY_CHARS: usize = 65536;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapturedResponse {
+    pub captured_at: DateTime<Utc>,
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    /// Truncated to `MAX_BODY_CHARS`, binary bodies are replaced by their type
+    pub body: String,
+}
+
+/// Installed into every page of a diagnostics run. Wraps `fetch` and
+/// `XMLHttpRequest` and buffers responses in `sessionStorage`, which unlike
+/// `window` survives the portal's full page navigations until drained.
+pub const CAPTURE_SCRIPT: &str = r#"
+(() => {
+    if (window.__nswCaptureInstalled) return;
+    window.__nswCaptureInstalled = true;
+    const KEY = '__nswCaptured';
+    const record = (method, url, status, body) => {
+        try {
+            const entries = JSON.parse(sessionStorage.getItem(KEY) || '[]');
+            entries.push({
+                captured_at: new Date().toISOString(),
+                method: String(method || 'GET').toUpperCase(),
+                url: String(url),
+                status: status || 0,
+                body: String(body).slice(0, 65536),
+            });
+            sessionStorage.setItem(KEY, JSON.stringify(entries.slice(-200)));
+        } catch (e) { console.debug('Error capturing response:', e); }
+    };
+
+    const originalFetch = window.fetch;
+    if (originalFetch) {
+        window.fetch = function (input, init) {
+            const method = (init && init.method) || (input && input.method) || 'GET';
+            const url = (input && input.url) || input;
+            return originalFetch.apply(this, arguments).then(response => {
+                response.clone().text().then(body => record(method, url, response.status, body), () => {});
+                return response;
+            });
+        };
+    }
+
+    const originalOpen = XMLHttpRequest.prototype.open;
+    XMLHttpRequest.prototype.open = function (method, url) {
+        this.addEventListener('loadend', () => {
+            let body = '';
+            try {
+                body = this.responseType === '' || this.responseType === 'text'
+                    ? this.responseText
+                    : '[' + this.responseType + ']';
+            } catch (e) { body = '[unreadable]'; }
+            record(method, url, this.status, body);
+        });
+        return originalOpen.apply(this, arguments);
+    };
+})();
+"#;
+
+/// Returns and clears the responses buffered by `CAPTURE_SCRIPT`.
+pub const DRAIN_SCRIPT: &str = r#"
+const entries = sessionStorage.getItem('__nswCaptured') || '[]';
+sessionStorage.removeItem('__nswCaptured');
+return JSON.parse(entries);
+"#;
+
+/// File name, without extension, for the capture of a failed location.
+/// Starts with the time so the files sort oldest first.
+pub fn failure_capture_stem(at: DateTime<Utc>, location: LocationId) -> String {
+    format!("{}-{}", at.format("%Y%m%d-%H%M%S"), location)
+}
+
+#[cfg(feature = "server")]
+pub use self::store::{append, load, prune_failure_captures};
+
+#[cfg(feature = "server")]
+mod store {
+    use std::fs;
+    use std::collections::BTreeSet;
+    use std::path::Path;
+
+    use super::{CapturedResponse, MAX_BODY_CHARS};
+    use crate::data::jsonl;
+
+    /// Captured responses, oldest first. Unparseable lines are skipped.
+    pub fn load(path: &Path) -> Result<Vec<CapturedResponse>, String> {
+        jsonl::read(path, "diagnostics file")
+    }
+
+    /// Add `responses` to the file, dropping the oldest entries beyond
+    /// `max_entries`.
+    pub fn append(path: &Path, responses: Vec<CapturedResponse>, max_entries: usize) -> Result<(), String> {
+        if responses.is_empty() {
+            return Ok(());
+        }
+
+        let mut entries = load(path)?;
+        entries.extend(responses.into_iter().map(|mut response| {
+            if response.body.chars().count() > MAX_BODY_CHARS {
+                response.body = response.body.chars().take(MAX_BODY_CHARS).collect();
+            }
+            response
+        }));
+        let excess = entries.len().saturating_sub(max_entries);
+        jsonl::write(path, &entries[excess..], "diagnostics file")
+    }
+
+    /// Delete all but the newest `keep` failure captures in `dir`. The
+    /// screenshot and page source of one failure are kept or dropped together.
+    pub fn prune_failure_captures(dir: &Path, keep: usize) -> Result<(), String> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(format!("Failed to read debug directory: {}", e)),
+        };
+
+        let paths: Vec<_> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect();
+        let stems: BTreeSet<String> = paths
+            .iter()
+            .filter_map(|path| path.file_stem()?.to_str().map(str::to_string))
+            .collect();
+        let excess = stems.len().saturating_sub(keep);
+        let dropped: BTreeSet<&String> = stems.iter().take(excess).collect();
+
+        for path in paths {
+            let stem = path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string);
+            if stem.is_some_and(|stem| dropped.contains(&stem)) {
+                fs::remove_file(&path).map_err(|e| format!("Failed to remove '{}': {}", path.display(), e))?;
+            }
+        }
+        Ok(())
+    }
+}
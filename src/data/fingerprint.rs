@@ -0,0 +1,59 @@
+//! The user agent and window size each browser session presents. A single
+//! fixed, years-old Chrome string on every session stands out in the
+//! portal's logs, so sessions pick from a pool of current ones instead.
+
+/// Recent desktop Chrome releases. Only Chrome: the browser really is Chrome,
+/// and a Firefox or Safari string on top of it is easy to catch out.
+pub const DEFAULT_USER_AGENTS: [&str; 6] = [
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/137.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/137.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/136.0.0.0 Safari/537.36",
+];
+
+/// Common desktop resolutions, `WIDTHxHEIGHT`.
+pub const DEFAULT_WINDOW_SIZES: [&str; 5] = ["1920x1080", "1536x864", "1440x900", "1366x768", "2560x1440"];
+
+pub fn default_user_agents() -> Vec<String> {
+    DEFAULT_USER_AGENTS.iter().map(|agent| agent.to_string()).collect()
+}
+
+pub fn default_window_sizes() -> Vec<String> {
+    DEFAULT_WINDOW_SIZES.iter().map(|size| size.to_string()).collect()
+}
+
+/// `(width, height)` from `WIDTHxHEIGHT`.
+pub fn parse_window_size(size: &str) -> Option<(u32, u32)> {
+    let (width, height) = size.trim().split_once(['x', 'X'])?;
+    let width = width.trim().parse().ok().filter(|&w| w > 0)?;
+    let height = height.trim().parse().ok().filter(|&h| h > 0)?;
+    Some((width, height))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    pub user_agent: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Fingerprint {
+    /// One user agent and one window size, `index(len)` choosing the
+    /// position in each list. Empty lists fall back to the defaults.
+    pub fn pick(user_agents: &[String], window_sizes: &[String], mut index: impl FnMut(usize) -> usize) -> Self {
+        let default_agents = default_user_agents();
+        let user_agents = if user_agents.is_empty() { &default_agents[..] } else { user_agents };
+        let sizes: Vec<(u32, u32)> = window_sizes.iter().filter_map(|size| parse_window_size(size)).collect();
+        let sizes = if sizes.is_empty() {
+            DEFAULT_WINDOW_SIZES.iter().filter_map(|size| parse_window_size(size)).collect()
+        } else {
+            sizes
+        };
+
+        let user_agent = user_agents[index(user_agents.len()) % user_agents.len()].clone();
+        let (width, height) = sizes[index(sizes.len()) % sizes.len()];
+        Fingerprint { user_agent, width, height }
+    }
+}
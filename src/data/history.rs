@@ -0,0 +1,445 @@
+//! Availability history for the public statistics page and API. Every scrape
+//! appends one line per centre to `history.jsonl`; `HistoryStats` folds those
+//! lines into per-centre and per-weekday figures. Only slot times are kept,
+//! nothing about bookings.
+//!
+//! Raw entries are kept for `history_retention_days`. Compaction folds the
+//! days before that into one `DailyAggregate` per centre in
+//! `history-daily.jsonl`, kept for `daily_history_retention_days` or forever.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+use super::location::LocationId;
+use super::shared_booking::{LocationBookings, TestType};
+
+/// Slots starting this soon after a scrape count as same-week.
+const SAME_WEEK_DAYS: i64 = 7;
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// What one scrape saw at one centre.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub recorded_at: NaiveDateTime,
+    pub test_type: TestType,
+    pub location: LocationId,
+    pub earliest_slot: Option<NaiveDateTime>,
+    /// Available slots starting within the next week
+    pub same_week: Vec<NaiveDateTime>,
+    /// Available slots per weekday, Monday first
+    pub by_weekday: [u32; 7],
+    /// Earliest available slot on each weekday, Monday first
+    #[serde(default)]
+    pub earliest_by_weekday: [Option<NaiveDateTime>; 7],
+}
+
+impl HistoryEntry {
+    pub fn from_bookings(bookings: &LocationBookings, recorded_at: NaiveDateTime) -> Self {
+        let available = bookings.slots.iter().filter(|slot| slot.availability);
+        let week_end = recorded_at + Duration::days(SAME_WEEK_DAYS);
+
+        let mut by_weekday = [0; 7];
+        let mut earliest_by_weekday: [Option<NaiveDateTime>; 7] = [None; 7];
+        let mut same_week = Vec::new();
+        for slot in available {
+            let day = weekday_index(slot.start_time);
+            by_weekday[day] += 1;
+            earliest_by_weekday[day] = Some(match earliest_by_weekday[day] {
+                Some(earliest) => earliest.min(slot.start_time),
+                None => slot.start_time,
+            });
+            if slot.start_time < week_end {
+                same_week.push(slot.start_time);
+            }
+        }
+        same_week.sort();
+
+        Self {
+            recorded_at,
+            test_type: bookings.test_type,
+            location: bookings.location,
+            earliest_slot: bookings.earliest_slot().map(|slot| slot.start_time),
+            same_week,
+            by_weekday,
+            earliest_by_weekday,
+        }
+    }
+}
+
+/// Public figures for one centre.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CentreStats {
+    pub location: LocationId,
+    pub observations: usize,
+    /// Median days from a scrape to the earliest slot it saw
+    pub median_lead_days: Option<f64>,
+    /// Same-week slots that appeared between scrapes, per week observed
+    pub same_week_cancellations_per_week: Option<f64>,
+    /// All seven weekdays, the fewest free slots first, so days that never
+    /// have slots lead
+    pub busiest_weekdays: Vec<Weekday>,
+}
+
+/// Figures for slots falling on one weekday.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeekdayStats {
+    pub weekday: Weekday,
+    /// Median days from a scrape to the earliest slot on this weekday
+    pub median_lead_days: Option<f64>,
+    /// Same-week cancellations on this weekday, per week observed
+    pub same_week_cancellations_per_week: Option<f64>,
+    /// Average free slots on this weekday seen by one scrape of one centre
+    pub free_slots_per_observation: f64,
+}
+
+/// Everything derived from the history of one test type.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HistoryStats {
+    pub centres: Vec<CentreStats>,
+    /// Across all centres
+    pub weekdays: Vec<WeekdayStats>,
+    pub weekdays_by_location: HashMap<LocationId, Vec<WeekdayStats>>,
+}
+
+impl HistoryStats {
+    pub fn from_entries(entries: &[HistoryEntry], test_type: TestType) -> Self {
+        let groups = group_by_location(entries, test_type);
+
+        let mut centres: Vec<CentreStats> = groups
+            .iter()
+            .map(|(&location, entries)| CentreStats {
+                location,
+                observations: entries.len(),
+                median_lead_days: median(entries.iter().filter_map(|entry| lead_days(entry, entry.earliest_slot))),
+                same_week_cancellations_per_week: per_week(
+                    appeared_by_weekday(entries).iter().sum(),
+                    span_days(entries.iter().copied()),
+                ),
+                busiest_weekdays: busiest_weekdays(entries),
+            })
+            .collect();
+        centres.sort_by_key(|stats| stats.location);
+
+        let weekdays_by_location = groups
+            .iter()
+            .map(|(&location, entries)| (location, weekday_stats(std::slice::from_ref(entries))))
+            .collect();
+        let all: Vec<Vec<&HistoryEntry>> = groups.into_values().collect();
+
+        Self {
+            centres,
+            weekdays: weekday_stats(&all),
+            weekdays_by_location,
+        }
+    }
+}
+
+/// One centre's history for one day, what is left of its entries once they
+/// are compacted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyAggregate {
+    pub date: NaiveDate,
+    pub test_type: TestType,
+    pub location: LocationId,
+    /// Entries recorded that day
+    pub observations: u32,
+    /// Mean days from a scrape to the earliest slot it saw, over the entries
+    /// that saw one
+    pub mean_lead_days: Option<f64>,
+    pub min_lead_days: Option<f64>,
+    pub max_lead_days: Option<f64>,
+    /// Mean available slots seen by one scrape
+    pub mean_available_slots: f64,
+}
+
+/// Fold `entries` into one aggregate per day, test type and centre, ordered
+/// by date and then centre.
+pub fn daily_aggregates(entries: &[HistoryEntry]) -> Vec<DailyAggregate> {
+    let mut days: BTreeMap<(NaiveDate, LocationId, &str), Vec<&HistoryEntry>> = BTreeMap::new();
+    for entry in entries {
+        days.entry((entry.recorded_at.date(), entry.location, entry.test_type.as_str())).or_default().push(entry);
+    }
+
+    days.into_iter()
+        .map(|((date, location, _), entries)| {
+            let test_type = entries[0].test_type;
+            let leads: Vec<f64> = entries.iter().filter_map(|entry| lead_days(entry, entry.earliest_slot)).collect();
+            let available: u64 = entries
+                .iter()
+                .map(|entry| entry.by_weekday.iter().map(|&count| count as u64).sum::<u64>())
+                .sum();
+            DailyAggregate {
+                date,
+                test_type,
+                location,
+                observations: entries.len() as u32,
+                mean_lead_days: (!leads.is_empty()).then(|| leads.iter().sum::<f64>() / leads.len() as f64),
+                min_lead_days: leads.iter().copied().reduce(f64::min),
+                max_lead_days: leads.iter().copied().reduce(f64::max),
+                mean_available_slots: available as f64 / entries.len() as f64,
+            }
+        })
+        .collect()
+}
+
+/// Per-centre statistics for `test_type`.
+pub fn centre_stats(entries: &[HistoryEntry], test_type: TestType) -> Vec<CentreStats> {
+    HistoryStats::from_entries(entries, test_type).centres
+}
+
+fn weekday_index(time: NaiveDateTime) -> usize {
+    time.weekday().num_days_from_monday() as usize
+}
+
+/// Entries for `test_type` per centre, oldest first.
+fn group_by_location(entries: &[HistoryEntry], test_type: TestType) -> HashMap<LocationId, Vec<&HistoryEntry>> {
+    let mut groups: HashMap<LocationId, Vec<&HistoryEntry>> = HashMap::new();
+    for entry in entries.iter().filter(|entry| entry.test_type == test_type) {
+        groups.entry(entry.location).or_default().push(entry);
+    }
+    for entries in groups.values_mut() {
+        entries.sort_by_key(|entry| entry.recorded_at);
+    }
+    groups
+}
+
+fn lead_days(entry: &HistoryEntry, slot: Option<NaiveDateTime>) -> Option<f64> {
+    Some((slot? - entry.recorded_at).num_minutes() as f64 / (24.0 * 60.0))
+}
+
+fn span_days<'a>(entries: impl Iterator<Item = &'a HistoryEntry>) -> f64 {
+    let (first, last) = entries.fold((None, None), |(first, last): (Option<NaiveDateTime>, Option<NaiveDateTime>), entry| {
+        (
+            Some(first.map_or(entry.recorded_at, |first| first.min(entry.recorded_at))),
+            Some(last.map_or(entry.recorded_at, |last| last.max(entry.recorded_at))),
+        )
+    });
+    match (first, last) {
+        (Some(first), Some(last)) => (last - first).num_minutes() as f64 / (24.0 * 60.0),
+        _ => 0.0,
+    }
+}
+
+fn per_week(count: usize, span_days: f64) -> Option<f64> {
+    (span_days > 0.0).then(|| count as f64 / (span_days / SAME_WEEK_DAYS as f64))
+}
+
+fn median(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let mut values: Vec<f64> = values.collect();
+    if values.is_empty() {
+        return None;
+    }
+
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    Some(if values.len() % 2 == 0 {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    })
+}
+
+/// Same-week cancellations per weekday for one centre's entries, oldest
+/// first. A same-week slot counts when it was already inside the previous
+/// scrape's week but not offered then, so slots merely rolling into the
+/// window are not counted.
+fn appeared_by_weekday(entries: &[&HistoryEntry]) -> [usize; 7] {
+    let mut appeared = [0; 7];
+    for pair in entries.windows(2) {
+        let (previous, current) = (pair[0], pair[1]);
+        let seen: HashSet<&NaiveDateTime> = previous.same_week.iter().collect();
+        let previous_week_end = previous.recorded_at + Duration::days(SAME_WEEK_DAYS);
+        for start in &current.same_week {
+            if *start < previous_week_end && !seen.contains(start) {
+                appeared[weekday_index(*start)] += 1;
+            }
+        }
+    }
+    appeared
+}
+
+fn busiest_weekdays(entries: &[&HistoryEntry]) -> Vec<Weekday> {
+    let mut totals = [0u64; 7];
+    for entry in entries {
+        for (total, count) in totals.iter_mut().zip(entry.by_weekday) {
+            *total += count as u64;
+        }
+    }
+
+    let mut days: Vec<(Weekday, u64)> = WEEKDAYS.into_iter().zip(totals).collect();
+    days.sort_by_key(|(day, total)| (*total, day.num_days_from_monday()));
+    days.into_iter().map(|(day, _)| day).collect()
+}
+
+/// Weekday figures over one or more centres' entries.
+fn weekday_stats(groups: &[Vec<&HistoryEntry>]) -> Vec<WeekdayStats> {
+    let observations: usize = groups.iter().map(Vec::len).sum();
+    let span = span_days(groups.iter().flatten().copied());
+
+    let mut appeared = [0; 7];
+    for entries in groups {
+        for (total, count) in appeared.iter_mut().zip(appeared_by_weekday(entries)) {
+            *total += count;
+        }
+    }
+
+    WEEKDAYS
+        .into_iter()
+        .enumerate()
+        .map(|(day, weekday)| {
+            let free: u64 = groups.iter().flatten().map(|entry| entry.by_weekday[day] as u64).sum();
+            WeekdayStats {
+                weekday,
+                median_lead_days: median(
+                    groups
+                        .iter()
+                        .flatten()
+                        .filter_map(|entry| lead_days(entry, entry.earliest_by_weekday[day])),
+                ),
+                same_week_cancellations_per_week: per_week(appeared[day], span),
+                free_slots_per_observation: if observations == 0 { 0.0 } else { free as f64 / observations as f64 },
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "server")]
+pub use self::store::{append, cached_stats, compact, load, load_daily, Compaction};
+
+#[cfg(feature = "server")]
+mod store {
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use std::path::Path;
+    use std::sync::{Arc, OnceLock, RwLock};
+    use std::time::SystemTime;
+
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    use super::{daily_aggregates, DailyAggregate, HistoryEntry, HistoryStats};
+    use crate::data::jsonl;
+    use crate::data::location::LocationId;
+    use crate::data::shared_booking::{LocationBookings, TestType};
+
+    type StatsCache = HashMap<TestType, (SystemTime, Arc<HistoryStats>)>;
+
+    static STATS_CACHE: OnceLock<RwLock<StatsCache>> = OnceLock::new();
+
+    fn get_stats_cache() -> &'static RwLock<StatsCache> {
+        STATS_CACHE.get_or_init(|| RwLock::new(HashMap::new()))
+    }
+
+    /// Append one entry per centre in `results`.
+    pub fn append(path: &Path, results: &[LocationBookings], recorded_at: NaiveDateTime) -> Result<(), String> {
+        let entries: Vec<HistoryEntry> =
+            results.iter().map(|bookings| HistoryEntry::from_bookings(bookings, recorded_at)).collect();
+        jsonl::append(path, &entries, "history file")
+    }
+
+    /// Every readable entry recorded at or after `since`.
+    pub fn load(path: &Path, since: NaiveDateTime) -> Result<Vec<HistoryEntry>, String> {
+        let entries: Vec<HistoryEntry> = jsonl::read(path, "history file")?;
+        Ok(entries.into_iter().filter(|entry| entry.recorded_at >= since).collect())
+    }
+
+    /// Every readable daily aggregate, oldest first.
+    pub fn load_daily(path: &Path) -> Result<Vec<DailyAggregate>, String> {
+        let mut aggregates: Vec<DailyAggregate> = jsonl::read(path, "daily history file")?;
+        aggregates.sort_by_key(|aggregate| aggregate.date);
+        Ok(aggregates)
+    }
+
+    /// What one `compact` did.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct Compaction {
+        /// Raw entries folded into daily aggregates
+        pub compacted_entries: usize,
+        pub aggregates_written: usize,
+        /// Aggregates older than `daily_since`
+        pub aggregates_dropped: usize,
+    }
+
+    /// Fold the raw entries in `path` from before the day of `raw_since` into
+    /// daily aggregates in `daily_path` and drop them from `path`. Only whole
+    /// days are compacted and days already aggregated are skipped, so running
+    /// it again, even after a crash halfway, never counts a day twice. Then
+    /// drop aggregates from before `daily_since`, if set.
+    pub fn compact(
+        path: &Path,
+        daily_path: &Path,
+        raw_since: NaiveDateTime,
+        daily_since: Option<NaiveDate>,
+    ) -> Result<Compaction, String> {
+        let cutoff = raw_since.date().and_hms_opt(0, 0, 0).unwrap_or(raw_since);
+        let (old, recent): (Vec<HistoryEntry>, Vec<HistoryEntry>) = jsonl::read::<HistoryEntry>(path, "history file")?
+            .into_iter()
+            .partition(|entry| entry.recorded_at < cutoff);
+
+        let mut compaction = Compaction::default();
+        let mut aggregates = load_daily(daily_path)?;
+        let mut changed = false;
+        if !old.is_empty() {
+            let done: HashSet<(NaiveDate, LocationId, TestType)> = aggregates
+                .iter()
+                .map(|aggregate| (aggregate.date, aggregate.location, aggregate.test_type))
+                .collect();
+            let new: Vec<DailyAggregate> = daily_aggregates(&old)
+                .into_iter()
+                .filter(|aggregate| !done.contains(&(aggregate.date, aggregate.location, aggregate.test_type)))
+                .collect();
+            compaction.compacted_entries = old.len();
+            compaction.aggregates_written = new.len();
+            aggregates.extend(new);
+            changed = true;
+        }
+
+        if let Some(since) = daily_since {
+            let before = aggregates.len();
+            aggregates.retain(|aggregate| aggregate.date >= since);
+            compaction.aggregates_dropped = before - aggregates.len();
+            changed |= compaction.aggregates_dropped > 0;
+        }
+
+        // Aggregates first, so a crash in between leaves the raw entries to
+        // compact again rather than losing them
+        if changed {
+            jsonl::write(daily_path, &aggregates, "daily history file")?;
+        }
+        if !old.is_empty() {
+            jsonl::write(path, &recent, "history file")?;
+        }
+        Ok(compaction)
+    }
+
+    /// `HistoryStats` over the entries since `since`, recomputed only when the
+    /// history file has changed since the last call.
+    pub fn cached_stats(path: &Path, since: NaiveDateTime, test_type: TestType) -> Result<Arc<HistoryStats>, String> {
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        if let Some((cached_at, stats)) = get_stats_cache().read().unwrap().get(&test_type) {
+            if *cached_at == modified {
+                return Ok(Arc::clone(stats));
+            }
+        }
+
+        let stats = Arc::new(HistoryStats::from_entries(&load(path, since)?, test_type));
+        get_stats_cache()
+            .write()
+            .unwrap()
+            .insert(test_type, (modified, Arc::clone(&stats)));
+        Ok(stats)
+    }
+}
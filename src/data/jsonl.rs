@@ -0,0 +1,57 @@
+//! Files of one JSON value per line: the history, the scrape run log, the
+//! audit log and the captured portal responses. Values are appended, or the
+//! whole file is replaced through a temporary file so readers never see half
+//! of it. Reading skips lines that don't parse, so one torn write or invalid
+//! UTF-8 line doesn't hide the rest.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Every readable value in `path`, in file order. A missing file is empty.
+/// `name` describes the file in errors, e.g. "audit log".
+pub fn read<T: DeserializeOwned>(path: &Path, name: &str) -> Result<Vec<T>, String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to open {}: {}", name, e)),
+    };
+
+    Ok(BufReader::new(file)
+        .lines()
+        .filter_map(|line| line.ok())
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect())
+}
+
+fn to_lines<T: Serialize>(items: &[T], name: &str) -> Result<Vec<u8>, String> {
+    let mut lines = Vec::new();
+    for item in items {
+        serde_json::to_writer(&mut lines, item).map_err(|e| format!("Failed to serialize {} entry: {}", name, e))?;
+        lines.push(b'\n');
+    }
+    Ok(lines)
+}
+
+/// Add one line per item to `path`, creating it if needed. The lines go out
+/// in one write, so concurrent appends don't interleave within them.
+pub fn append<T: Serialize>(path: &Path, items: &[T], name: &str) -> Result<(), String> {
+    let lines = to_lines(items, name)?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(&lines))
+        .map_err(|e| format!("Failed to write {}: {}", name, e))
+}
+
+/// Replace `path` with one line per item.
+pub fn write<T: Serialize>(path: &Path, items: &[T], name: &str) -> Result<(), String> {
+    let lines = to_lines(items, name)?;
+    let temp_path = path.with_extension("jsonl.tmp");
+    fs::write(&temp_path, lines).map_err(|e| format!("Failed to write {}: {}", name, e))?;
+    fs::rename(&temp_path, path).map_err(|e| format!("Failed to replace {}: {}", name, e))
+}
@@ -0,0 +1,133 @@
+//! Leader election for deployments running several replicas against one
+//! shared data store. Only the replica holding the lease scrapes and runs
+//! auto-find jobs; the others serve whatever the leader last saved. The
+//! lease is a small file next to the data (or in the object storage bucket)
+//! that the leader renews every third of `leader_lease_seconds`. If the
+//! leader dies the lease expires and the next replica to check takes over.
+//! Every write is conditional on the version that was read, so two replicas
+//! racing for the lease can't both win it.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Name of the lease file in the data directory or bucket.
+pub const LEASE_KEY: &str = "leader.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lease {
+    pub holder: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Lease {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+
+    /// Whether `holder` may write a new lease over this one: either it already
+    /// holds it or the previous holder stopped renewing.
+    pub fn can_acquire(&self, holder: &str, now: DateTime<Utc>) -> bool {
+        self.holder == holder || self.is_expired(now)
+    }
+}
+
+#[cfg(feature = "server")]
+pub use self::election::{instance_id, is_leader, start, try_acquire};
+
+#[cfg(feature = "server")]
+mod election {
+    use std::sync::{Arc, OnceLock, RwLock};
+    use std::time::Duration;
+
+    use chrono::{DateTime, Utc};
+
+    use super::{Lease, LEASE_KEY};
+    use crate::data::storage::{self, Storage};
+    use crate::settings::Settings;
+    use crate::utils::task::spawn_named;
+
+    static IS_LEADER: OnceLock<Arc<RwLock<bool>>> = OnceLock::new();
+
+    fn get_leader_status() -> &'static Arc<RwLock<bool>> {
+        // Without leader election every instance is its own leader
+        IS_LEADER.get_or_init(|| Arc::new(RwLock::new(true)))
+    }
+
+    pub fn is_leader() -> bool {
+        *get_leader_status().read().unwrap()
+    }
+
+    /// Identifies this process in the lease, e.g. `web-1-4711`.
+    pub fn instance_id() -> String {
+        let host = std::env::var("FLY_MACHINE_ID")
+            .or_else(|_| std::env::var("HOSTNAME"))
+            .unwrap_or_else(|_| "instance".to_string());
+        format!("{}-{}", host, std::process::id())
+    }
+
+    /// Take or renew the lease for `holder`. Returns whether `holder` holds it
+    /// afterwards. The new lease is only written if the stored one is still
+    /// the version that was checked, so of two replicas racing for an expired
+    /// lease exactly one wins.
+    pub async fn try_acquire(
+        storage: &dyn Storage,
+        holder: &str,
+        duration: chrono::Duration,
+        now: DateTime<Utc>,
+    ) -> Result<bool, String> {
+        let current = storage.get_versioned(LEASE_KEY).await?;
+        if let Some((contents, _)) = &current {
+            // A torn or hand-edited lease is treated as no lease at all
+            let lease: Option<Lease> = serde_json::from_slice(contents).ok();
+            if lease.is_some_and(|lease| !lease.can_acquire(holder, now)) {
+                return Ok(false);
+            }
+        }
+
+        let lease = Lease { holder: holder.to_string(), expires_at: now + duration };
+        let contents = serde_json::to_vec(&lease).map_err(|e| format!("Failed to serialize lease: {}", e))?;
+        let expected = current.as_ref().map(|(_, version)| version.as_str());
+        storage.put_if(LEASE_KEY, contents, expected).await
+    }
+
+    /// With `leader_election` on, contend for the lease now and keep renewing
+    /// or retrying it in the background. Call before starting any scraping.
+    pub async fn start(settings: &Settings) {
+        if !settings.leader_election {
+            return;
+        }
+
+        let storage = storage::shared(settings);
+        let holder = instance_id();
+        let duration = chrono::Duration::seconds(settings.leader_lease_seconds as i64);
+        let interval = Duration::from_secs((settings.leader_lease_seconds / 3).max(1));
+
+        *get_leader_status().write().unwrap() = false;
+        contend(storage.as_ref(), &holder, duration).await;
+
+        spawn_named("leader-election", async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                contend(storage.as_ref(), &holder, duration).await;
+            }
+        });
+    }
+
+    async fn contend(storage: &dyn Storage, holder: &str, duration: chrono::Duration) {
+        let leader = match try_acquire(storage, holder, duration, Utc::now()).await {
+            Ok(leader) => leader,
+            Err(e) => {
+                // Step down rather than risk two replicas scraping at once
+                eprintln!("ERROR: Failed to renew leader lease: {}", e);
+                false
+            }
+        };
+
+        let was_leader = std::mem::replace(&mut *get_leader_status().write().unwrap(), leader);
+        match (was_leader, leader) {
+            (false, true) => println!("INFO: {} is now the leader", holder),
+            (true, false) => println!("INFO: {} is no longer the leader", holder),
+            _ => {}
+        }
+    }
+}
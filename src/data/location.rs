@@ -1,6 +1,54 @@
-use std::{collections::HashMap, sync::{Arc, OnceLock, RwLock}};
+use std::{collections::HashMap, fmt, sync::{Arc, OnceLock, RwLock}};
 
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::settings::Settings;
+
+/// Identifier of a test centre, matching the value of the portal's location dropdown.
+///
+/// Serialized as a string (the booking data and server functions have always
+/// used `"18"`), but accepts either a string or a number when deserializing so
+/// `centres.json` can keep its numeric ids.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LocationId(pub u32);
+
+impl fmt::Display for LocationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::str::FromStr for LocationId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim().parse().map(LocationId)
+    }
+}
+
+impl Serialize for LocationId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for LocationId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(u32),
+            Text(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Number(id) => Ok(LocationId(id)),
+            Raw::Text(id) => id
+                .parse()
+                .map_err(|_| de::Error::custom(format!("invalid location id '{}'", id))),
+        }
+    }
+}
 
 static LOCATION_STORE: OnceLock<Arc<RwLock<LocationStore>>> = OnceLock::new();
 
@@ -10,26 +58,36 @@ fn get_location_store() -> &'static Arc<RwLock<LocationStore>> {
     })
 }
 
+/// The centre list is only embedded in native builds. The WASM client starts
+/// with an empty store and is filled from the `GetLocations` server function,
+/// keeping the dataset out of the bundle.
 fn initialize_location_store() {
-    fn parse_locations() -> Vec<Location> {
-        let json_data = include_str!("../../data/centres.json");
-        serde_json::from_str(json_data).unwrap_or_else(|e| {
-            log::error!("Failed to parse locations: {}", e);
-            Vec::new()
-        })
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        fn parse_locations() -> Vec<Location> {
+            let json_data = include_str!("../../data/centres.json");
+            serde_json::from_str(json_data).unwrap_or_else(|e| {
+                log::error!("Failed to parse locations: {}", e);
+                Vec::new()
+            })
+        }
+
+        load_into_store(parse_locations());
     }
-    
+}
+
+fn load_into_store(locations: Vec<Location>) {
     let store = get_location_store();
     if let Ok(mut store) = store.try_write() {
         if store.get_all_locations().is_empty() {
-            store.load_locations(parse_locations());
+            store.load_locations(locations);
         }
     }
 }
 
 struct LocationStore {
     locations: Vec<Location>,
-    location_by_id: HashMap<u32, usize>,
+    location_by_id: HashMap<LocationId, usize>,
 }
 
 impl LocationStore {
@@ -57,7 +115,7 @@ impl LocationStore {
     }
     
     #[inline]
-    fn get_by_id(&self, id: u32) -> Option<&Location> {
+    fn get_by_id(&self, id: LocationId) -> Option<&Location> {
         self.location_by_id.get(&id).map(|&idx| &self.locations[idx])
     }
     
@@ -78,7 +136,7 @@ impl LocationStore {
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Location {
-    pub id: u32,
+    pub id: LocationId,
     pub name: String,
     pub latitude: f64,
     pub longitude: f64,
@@ -112,7 +170,7 @@ impl Location {
 }
 
 
-#[derive(Clone)]
+#[derive(Clone, Copy)]
 pub struct LocationManager;
 
 impl LocationManager {
@@ -121,6 +179,11 @@ impl LocationManager {
         Self
     }
     
+    /// Fill the store with locations fetched from the server. No-op once loaded.
+    pub fn load(&self, locations: Vec<Location>) {
+        load_into_store(locations);
+    }
+
     pub fn get_by_distance(&self, lat: f64, lng: f64) -> Vec<(Location, f64)> {
         match get_location_store().read() {
             Ok(store) => store.get_locations_by_distance(lat, lng),
@@ -135,11 +198,57 @@ impl LocationManager {
         }
     }
     
-    pub fn get_by_id(&self, id: u32) -> Option<Location> {
+    /// Centres the scraper should visit: those within `scrape_radius_km` of
+    /// the home point plus `priority_locations`, or every centre when no home
+    /// point is set.
+    pub fn scrape_list(&self, settings: &Settings) -> Vec<LocationId> {
+        let Some((lat, lng, radius_km)) = settings.scrape_area() else {
+            return self.get_all().into_iter().map(|location| location.id).collect();
+        };
+
+        let mut locations: Vec<LocationId> = self
+            .get_by_distance(lat, lng)
+            .into_iter()
+            .take_while(|(_, distance)| *distance <= radius_km)
+            .map(|(location, _)| location.id)
+            .collect();
+        for &location in &settings.priority_locations {
+            if !locations.contains(&location) {
+                locations.push(location);
+            }
+        }
+        locations
+    }
+
+    pub fn get_by_id(&self, id: LocationId) -> Option<Location> {
         get_location_store()
             .read()
             .ok()?
             .get_by_id(id)
             .cloned()
     }
+
+    /// Centres inside `area`, nearest first.
+    pub fn within(&self, area: &SearchArea) -> Vec<LocationId> {
+        self.get_by_distance(area.latitude, area.longitude)
+            .into_iter()
+            .take_while(|(_, distance)| *distance <= area.radius_km)
+            .map(|(location, _)| location.id)
+            .collect()
+    }
+}
+
+/// Every centre within `radius_km` of a point. Resolved against the centre
+/// list each time it is used, so centres added later are covered.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SearchArea {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub radius_km: f64,
+}
+
+impl SearchArea {
+    pub fn contains(&self, location: &Location) -> bool {
+        location.distance_from(self.latitude, self.longitude) <= self.radius_km
+    }
 }
@@ -0,0 +1,139 @@
+//! Detection of the RTA portal's maintenance page. The portal goes down most
+//! nights; rather than burning every retry on it, the scraper notes when it
+//! expects the portal back and skips runs until then.
+
+use chrono::{Duration, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+/// Phrases the portal's maintenance and outage pages use, lowercase.
+const MAINTENANCE_PHRASES: [&str; 6] = [
+    "scheduled maintenance",
+    "undergoing maintenance",
+    "system maintenance",
+    "maintenance is being performed",
+    "currently unavailable",
+    "temporarily unavailable",
+];
+
+#[cfg(feature = "server")]
+pub use self::store::{active, clear, record};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    /// The line of the page announcing the outage
+    pub message: String,
+    pub detected_at: NaiveDateTime,
+    /// When the portal said it would be back, or `maintenance_backoff_minutes`
+    /// after detection if it didn't say
+    pub until: NaiveDateTime,
+}
+
+impl MaintenanceWindow {
+    /// A window if `page_text` is a maintenance page, `None` for any other
+    /// page.
+    pub fn detect(page_text: &str, now: NaiveDateTime, fallback: Duration) -> Option<Self> {
+        let line = page_text.lines().map(str::trim).find(|line| {
+            let line = line.to_lowercase();
+            MAINTENANCE_PHRASES.iter().any(|phrase| line.contains(phrase))
+        })?;
+
+        let until = match parse_end_time(page_text) {
+            Some(end) => {
+                let today = now.date().and_time(end);
+                if today > now { today } else { today + Duration::days(1) }
+            }
+            None => now + fallback,
+        };
+
+        Some(Self {
+            message: line.chars().take(200).collect(),
+            detected_at: now,
+            until,
+        })
+    }
+
+    pub fn is_active(&self, now: NaiveDateTime) -> bool {
+        self.until > now
+    }
+}
+
+/// The first time of day following "until", e.g. "unavailable until 6am" or
+/// "until 5:30 a.m. Sunday".
+pub fn parse_end_time(text: &str) -> Option<NaiveTime> {
+    let lower = text.to_lowercase();
+    lower
+        .match_indices("until")
+        .find_map(|(i, keyword)| parse_time_prefix(lower[i + keyword.len()..].trim_start()))
+}
+
+fn parse_time_prefix(text: &str) -> Option<NaiveTime> {
+    let digits: String = text.chars().take_while(|c| c.is_ascii_digit()).collect();
+    let mut hour: u32 = digits.parse().ok()?;
+    let mut rest = &text[digits.len()..];
+
+    let mut minute = None;
+    if let Some(after_colon) = rest.strip_prefix(':').or_else(|| rest.strip_prefix('.')) {
+        let digits: String = after_colon.chars().take_while(|c| c.is_ascii_digit()).collect();
+        if digits.len() == 2 {
+            minute = digits.parse().ok();
+            rest = &after_colon[2..];
+        }
+    }
+
+    let rest = rest.trim_start();
+    let pm = if rest.starts_with("am") || rest.starts_with("a.m") {
+        Some(false)
+    } else if rest.starts_with("pm") || rest.starts_with("p.m") {
+        Some(true)
+    } else {
+        None
+    };
+
+    match pm {
+        Some(pm) => {
+            if hour == 0 || hour > 12 {
+                return None;
+            }
+            hour = hour % 12 + if pm { 12 } else { 0 };
+        }
+        // A bare number is only a time with minutes, "until 2025" is a year
+        None if minute.is_none() => return None,
+        None => {}
+    }
+
+    NaiveTime::from_hms_opt(hour, minute.unwrap_or(0), 0)
+}
+
+#[cfg(feature = "server")]
+mod store {
+    use std::sync::{Arc, OnceLock, RwLock};
+
+    use chrono::NaiveDateTime;
+    use tracing::info;
+
+    use super::MaintenanceWindow;
+
+    static MAINTENANCE: OnceLock<Arc<RwLock<Option<MaintenanceWindow>>>> = OnceLock::new();
+
+    fn get_maintenance() -> &'static Arc<RwLock<Option<MaintenanceWindow>>> {
+        MAINTENANCE.get_or_init(|| Arc::new(RwLock::new(None)))
+    }
+
+    pub fn record(window: MaintenanceWindow) {
+        info!(until = %window.until, notice = %window.message, "RTA portal under maintenance");
+        *get_maintenance().write().unwrap() = Some(window);
+    }
+
+    /// The maintenance window the portal is still in, if any.
+    pub fn active(now: NaiveDateTime) -> Option<MaintenanceWindow> {
+        get_maintenance()
+            .read()
+            .unwrap()
+            .clone()
+            .filter(|window| window.is_active(now))
+    }
+
+    pub fn clear() {
+        *get_maintenance().write().unwrap() = None;
+    }
+}
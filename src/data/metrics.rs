@@ -0,0 +1,86 @@
+//! Running per-centre scrape metrics: how often each centre scrapes
+//! successfully, how long it takes and how many slots it turns up. The run
+//! log says which centres failed in a run; this shows which ones fail or
+//! drag every time.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::location::LocationId;
+use super::shared_booking::LocationAttempt;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LocationMetrics {
+    pub attempts: u32,
+    pub successes: u32,
+    pub total_duration_ms: u64,
+    pub slowest_ms: u64,
+    /// Slots over all successful attempts
+    pub total_slots: u64,
+    pub last_slots: Option<usize>,
+    pub last_error: Option<String>,
+    pub last_attempt: Option<DateTime<Utc>>,
+}
+
+impl LocationMetrics {
+    pub fn failures(&self) -> u32 {
+        self.attempts - self.successes
+    }
+
+    /// Share of attempts that returned data, 0 to 1.
+    pub fn success_rate(&self) -> f64 {
+        if self.attempts == 0 {
+            return 0.0;
+        }
+        self.successes as f64 / self.attempts as f64
+    }
+
+    pub fn average_duration_ms(&self) -> u64 {
+        self.total_duration_ms.checked_div(self.attempts as u64).unwrap_or(0)
+    }
+
+    /// Slots per successful attempt.
+    pub fn average_slots(&self) -> f64 {
+        if self.successes == 0 {
+            return 0.0;
+        }
+        self.total_slots as f64 / self.successes as f64
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScrapeMetrics {
+    pub locations: BTreeMap<LocationId, LocationMetrics>,
+}
+
+impl ScrapeMetrics {
+    /// Count one attempt. `error` is why it failed, if known.
+    pub fn record(&mut self, attempt: &LocationAttempt, error: Option<&str>, at: DateTime<Utc>) {
+        let metrics = self.locations.entry(attempt.location).or_default();
+        metrics.attempts += 1;
+        metrics.total_duration_ms += attempt.duration_ms;
+        metrics.slowest_ms = metrics.slowest_ms.max(attempt.duration_ms);
+        metrics.last_attempt = Some(at);
+        match attempt.slots {
+            Some(slots) => {
+                metrics.successes += 1;
+                metrics.total_slots += slots as u64;
+                metrics.last_slots = Some(slots);
+            }
+            None => metrics.last_error = error.map(str::to_string),
+        }
+    }
+
+    /// Centres worst first: lowest success rate, then slowest on average.
+    pub fn ranked(&self) -> Vec<(LocationId, LocationMetrics)> {
+        let mut ranked: Vec<_> = self.locations.iter().map(|(&location, metrics)| (location, metrics.clone())).collect();
+        ranked.sort_by(|(_, a), (_, b)| {
+            a.success_rate()
+                .total_cmp(&b.success_rate())
+                .then(b.average_duration_ms().cmp(&a.average_duration_ms()))
+        });
+        ranked
+    }
+}
@@ -0,0 +1,54 @@
+//! Upgrades for booking data files written by older versions. Each step
+//! rewrites the raw JSON from one `schema_version` to the next, so a file of
+//! any supported age is brought up to `BOOKING_DATA_VERSION` before it is
+//! deserialized.
+
+use serde_json::Value;
+
+use super::shared_booking::{BookingData, BOOKING_DATA_VERSION};
+
+/// Version of a raw data file. Files without `schema_version` predate
+/// versioning and are version 1.
+pub fn schema_version(data: &Value) -> Result<u32, String> {
+    match data.get("schema_version") {
+        None => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| format!("Invalid schema_version {}", version)),
+    }
+}
+
+/// Bring `data` from `from` to `from + 1`.
+fn upgrade(from: u32, data: &mut Value) -> Result<(), String> {
+    match from {
+        // Version 1 has the same shape as version 2, it only lacks the version
+        // field. Legacy test type names and numeric location ids are still
+        // accepted by their deserializers.
+        1 => Ok(()),
+        _ => Err(format!("No migration from schema_version {}", from)),
+    }
+}
+
+/// Upgrade and deserialize a data file, returning the data and the version
+/// the file was written in. Files from a newer version are refused rather
+/// than read partially and later overwritten.
+pub fn migrate(mut data: Value) -> Result<(BookingData, u32), String> {
+    let from = schema_version(&data)?;
+    if from > BOOKING_DATA_VERSION {
+        return Err(format!(
+            "Data file has schema_version {}, newer than the supported {}",
+            from, BOOKING_DATA_VERSION
+        ));
+    }
+
+    for version in from..BOOKING_DATA_VERSION {
+        upgrade(version, &mut data)?;
+    }
+    if let Some(object) = data.as_object_mut() {
+        object.insert("schema_version".to_string(), Value::from(BOOKING_DATA_VERSION));
+    }
+
+    let data = serde_json::from_value(data).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+    Ok((data, from))
+}
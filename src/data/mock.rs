@@ -0,0 +1,62 @@
+//! `scrape_backend: mock` (also `scrape_mode: mock`): answers scrapes from
+//! recorded data files instead of the portal, with a delay per location like
+//! a real run, so the UI and `BookingManager` can be worked on without
+//! Selenium, a network connection or real credentials.
+//!
+//! A fixture is a data file as the scraper saves it, plain or gzipped, named
+//! `<test type>.json` in `mock_fixtures_dir`, with `bookings.json` there used
+//! for any test type that has no file of its own.
+
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use tracing::info;
+
+use super::booking::BookingManager;
+use super::location::LocationId;
+use super::rta::random_sleep;
+use super::shared_booking::{LocationBookings, ScrapeResults, TestType};
+use crate::settings::Settings;
+
+/// The fixture for `test_type` in `dir`, if there is one.
+pub fn fixture_file(dir: &Path, test_type: TestType) -> Option<PathBuf> {
+    [format!("{}.json", test_type.as_str()), format!("{}.json.gz", test_type.as_str()), "bookings.json".to_string()]
+        .into_iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.exists())
+}
+
+/// The requested locations' bookings from `recorded`. Locations with no
+/// recording fail, as a centre missing from the portal's dropdown would.
+pub fn replay(recorded: &[LocationBookings], locations: &[LocationId], test_type: TestType) -> ScrapeResults {
+    let mut results = ScrapeResults::default();
+    for &location in locations {
+        match recorded.iter().find(|bookings| bookings.location == location) {
+            Some(bookings) => results.succeeded(LocationBookings { test_type, ..bookings.clone() }),
+            None => results.failed(location, format!("No recorded data for location {}", location)),
+        }
+    }
+    results
+}
+
+pub async fn scrape_timeslots(
+    locations: Vec<LocationId>,
+    test_type: TestType,
+    settings: &Settings,
+) -> Result<ScrapeResults, String> {
+    let dir = &settings.mock_fixtures_dir;
+    let path = fixture_file(dir, test_type)
+        .ok_or_else(|| format!("No {} fixture in '{}'", test_type.label(), dir.display()))?;
+    let (recorded, _) = BookingManager::read_data_file(&path)?;
+    info!("Replaying {} locations from '{}'", locations.len(), path.display());
+
+    let mut results = replay(&recorded.results, &locations, test_type);
+    let latency = settings.mock_latency_ms;
+    for &location in &locations {
+        let started = Instant::now();
+        random_sleep(latency / 2, latency + latency / 2 + 1).await;
+        let slots = results.bookings.get(&location).map(|bookings| bookings.slots.len());
+        results.attempted(location, started.elapsed().as_millis() as u64, slots);
+    }
+    Ok(results)
+}
@@ -1,7 +1,42 @@
 pub mod location;
 pub mod shared_booking;
+pub mod scoring;
+pub mod strategy;
+pub mod history;
+#[cfg(feature = "server")]
+pub mod jsonl;
+pub mod stats;
+pub mod scrape_runs;
+pub mod audit;
+pub mod migration;
+pub mod backup;
+pub mod storage;
+pub mod database;
+pub mod leader;
+pub mod maintenance;
+pub mod metrics;
+pub mod challenge;
+pub mod checkpoint;
+pub mod cooldown;
+pub mod diagnostics;
+pub mod fingerprint;
+pub mod rate_limit;
+pub mod selectors;
+pub mod schedule;
+pub mod changes;
+pub mod rta_http;
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "scraper")]
 pub mod rta;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "scraper")]
+pub mod confirmation;
+#[cfg(feature = "scraper")]
+pub mod backend;
+#[cfg(feature = "scraper")]
+pub mod mock;
+#[cfg(feature = "cdp")]
+pub mod rta_cdp;
+#[cfg(feature = "server")]
 pub mod booking;
+#[cfg(feature = "server")]
+pub mod auto_find;
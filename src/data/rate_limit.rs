@@ -0,0 +1,89 @@
+//! A token bucket shared by everything that talks to myrta.com. The
+//! background updater, `book_first_available` and the auto-finder each pace
+//! themselves, but when they overlap their combined rate is what the portal
+//! sees; taking a token before each page request keeps that total under
+//! `portal_requests_per_minute`.
+
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "server")]
+pub use self::store::acquire;
+
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    per_second: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// A full bucket refilling at `per_minute`, holding up to `burst` tokens.
+    pub fn new(per_minute: u32, burst: u32, now: Instant) -> Self {
+        let capacity = burst.max(1) as f64;
+        TokenBucket {
+            capacity,
+            tokens: capacity,
+            per_second: per_minute as f64 / 60.0,
+            updated: now,
+        }
+    }
+
+    /// Take a token, or say how long until one is available.
+    pub fn try_take(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        if self.per_second <= 0.0 {
+            return Err(Duration::MAX);
+        }
+        Err(Duration::from_secs_f64((1.0 - self.tokens) / self.per_second))
+    }
+
+    fn is_configured_as(&self, per_minute: u32, burst: u32) -> bool {
+        self.per_second == per_minute as f64 / 60.0 && self.capacity == burst.max(1) as f64
+    }
+}
+
+#[cfg(feature = "server")]
+mod store {
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    use tracing::debug;
+
+    use super::TokenBucket;
+    use crate::settings::Settings;
+
+    static BUCKET: Mutex<Option<TokenBucket>> = Mutex::new(None);
+
+    /// Wait for a token before a request to the portal. Does nothing when
+    /// `portal_requests_per_minute` is 0.
+    pub async fn acquire(settings: &Settings) {
+        let (per_minute, burst) = (settings.portal_requests_per_minute, settings.portal_request_burst);
+        if per_minute == 0 {
+            return;
+        }
+
+        loop {
+            let wait = {
+                let mut bucket = BUCKET.lock().unwrap();
+                let now = Instant::now();
+                if !bucket.as_ref().is_some_and(|bucket| bucket.is_configured_as(per_minute, burst)) {
+                    *bucket = Some(TokenBucket::new(per_minute, burst, now));
+                }
+                match bucket.as_mut().unwrap().try_take(now) {
+                    Ok(()) => return,
+                    Err(wait) => wait,
+                }
+            };
+            debug!("Portal rate limit reached, waiting {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
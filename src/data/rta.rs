@@ -1,24 +1,267 @@
 use std::collections::HashMap;
 use std::fs::OpenOptions;
 use std::io::Write;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use thirtyfour::components::SelectElement;
-use thirtyfour::{By, DesiredCapabilities, WebDriver};
+use thirtyfour::{By, Cookie, DesiredCapabilities, WebDriver};
 use thirtyfour::prelude::*;
+use thirtyfour::extensions::cdp::ChromeDevTools;
 use rand::Rng;
+use rand::seq::SliceRandom;
+use chrono::NaiveDateTime;
+use tracing::{error, info, info_span, warn, Instrument};
+
+use crate::notifications::{self, Notification, NotificationLevel};
+use crate::settings::{LoginMode, Settings, Step};
+use crate::utils::task::spawn_named;
+use crate::utils::date::{format_slot_time, is_on_or_before_date, parse_slot_time, sydney_now};
+use super::audit::{self, AuditAction, AuditEntry};
+use super::checkpoint::{self, ScrapeStage};
+use super::confirmation;
+use super::location::{LocationId, LocationManager};
+use super::diagnostics::{self, CapturedResponse};
+use super::fingerprint::Fingerprint;
+use super::rate_limit;
+use super::selectors;
+use super::challenge::{self, BlockedWindow};
+use super::maintenance::{self, MaintenanceWindow};
+use super::shared_booking::{
+    BookingOutcome, BookingReceipt, BookingRecord, LocationBookings, RebookEligibility, ScrapeResults, TestType, TimeSlot,
+};
+
+/// Move the responses captured since the last call from the browser to the
+/// diagnostics file. Failures are logged, diagnostics must never break a run.
+async fn save_captured_responses(driver: &WebDriver, settings: &Settings) {
+    if !settings.diagnostics {
+        return;
+    }
+
+    let responses: Vec<CapturedResponse> = match driver.execute(diagnostics::DRAIN_SCRIPT, Vec::new()).await {
+        Ok(ret) => serde_json::from_value(ret.json().clone()).unwrap_or_default(),
+        Err(e) => {
+            warn!("Failed to collect captured responses: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = diagnostics::append(&settings.diagnostics_file(), responses, settings.diagnostics_max_entries) {
+        warn!("Failed to save captured responses: {}", e);
+    }
+}
+
+/// How long the page has to go without network activity to count as idle.
+const NETWORK_IDLE_MILLIS: u64 = 500;
+
+/// Installed into every page when `event_driven_waits` is on. Counts the
+/// portal's XHR and fetch requests still in flight.
+const REQUEST_TRACKER_SCRIPT: &str = r#"
+(() => {
+    if (window.__nswInFlight !== undefined) return;
+    window.__nswInFlight = 0;
+    window.__nswLastActivity = Date.now();
+    const start = () => { window.__nswInFlight++; window.__nswLastActivity = Date.now(); };
+    const end = () => {
+        window.__nswInFlight = Math.max(0, window.__nswInFlight - 1);
+        window.__nswLastActivity = Date.now();
+    };
+
+    const originalFetch = window.fetch;
+    if (originalFetch) {
+        window.fetch = function () {
+            start();
+            return originalFetch.apply(this, arguments).finally(end);
+        };
+    }
+
+    const originalSend = XMLHttpRequest.prototype.send;
+    XMLHttpRequest.prototype.send = function () {
+        start();
+        this.addEventListener('loadend', end);
+        return originalSend.apply(this, arguments);
+    };
+})();
+"#;
+
+/// Calls back once the page has loaded and had no requests in flight for
+/// `arguments[0]` ms, or with `timeout` after `arguments[1]` ms. Waits at
+/// least the idle period, so a request the last click is about to send isn't
+/// missed.
+const WAIT_FOR_IDLE_SCRIPT: &str = r#"
+const [idleMillis, timeoutMillis, done] = arguments;
+if (window.__nswInFlight === undefined) { done('unsupported'); return; }
+const startedAt = Date.now();
+const check = () => {
+    const now = Date.now();
+    const idle = document.readyState === 'complete'
+        && window.__nswInFlight === 0
+        && now - startedAt >= idleMillis
+        && now - window.__nswLastActivity >= idleMillis;
+    if (idle) done('idle');
+    else if (now - startedAt > timeoutMillis) done('timeout');
+    else setTimeout(check, 50);
+};
+check();
+"#;
+
+/// Wait for the page to settle after an action. With `event_driven_waits` and
+/// a driver that ran `REQUEST_TRACKER_SCRIPT`, that's until the portal's
+/// requests finish; otherwise, or if waiting fails, a fixed random sleep.
+async fn settle(driver: &WebDriver, settings: &Settings, fallback_min_millis: u64, fallback_max_millis: u64) {
+    if settings.event_driven_waits {
+        let timeout = settings.step_timeout(Step::Navigation);
+        let args = vec![json!(NETWORK_IDLE_MILLIS), json!(timeout.as_millis() as u64)];
+        match driver.execute_async(WAIT_FOR_IDLE_SCRIPT, args).await {
+            Ok(ret) if ret.json().as_str() == Some("idle") => {
+                // Reacting the instant a page is ready looks automated
+                random_sleep(200, 600).await;
+                return;
+            }
+            Ok(ret) if ret.json().as_str() == Some("timeout") => {
+                warn!("Page still busy after {:?}, falling back to a fixed wait.", timeout);
+            }
+            Ok(_) => {}
+            // Usually a navigation replaced the page mid-wait
+            Err(e) => warn!("Waiting for the page to go idle failed: {}", e),
+        }
+    }
+    random_sleep(fallback_min_millis, fallback_max_millis).await;
+}
+
+/// Fresh sessions started by one `scrape_rta_timeslots` call after losing
+/// track of the portal's state.
+const MAX_SESSION_RESTARTS: u32 = 2;
+
+/// How much slower every pause is with `debug_browser`.
+const DEBUG_SLOW_MOTION: u64 = 4;
+
+/// How long the browser is left on the failing page with `debug_browser`.
+const DEBUG_ERROR_PAUSE: Duration = Duration::from_secs(300);
+
+static SLOW_MOTION: AtomicBool = AtomicBool::new(false);
 
-use crate::settings::Settings;
-use super::shared_booking::{LocationBookings, TimeSlot};
+fn set_slow_motion(enabled: bool) {
+    SLOW_MOTION.store(enabled, AtomicOrdering::Relaxed);
+}
 
-async fn random_sleep(min_millis: u64, max_millis: u64) {
+pub(super) async fn random_sleep(min_millis: u64, max_millis: u64) {
+    let factor = if SLOW_MOTION.load(AtomicOrdering::Relaxed) { DEBUG_SLOW_MOTION } else { 1 };
     if min_millis >= max_millis {
-        tokio::time::sleep(Duration::from_millis(min_millis)).await;
+        tokio::time::sleep(Duration::from_millis(min_millis * factor)).await;
         return;
     }
     let duration = rand::thread_rng().gen_range(min_millis..max_millis);
-    tokio::time::sleep(Duration::from_millis(duration)).await;
+    tokio::time::sleep(Duration::from_millis(duration * factor)).await;
+}
+
+const STEP_BANNER_SCRIPT: &str = r#"
+    let banner = document.getElementById('__nswStepBanner');
+    if (!banner) {
+        banner = document.createElement('div');
+        banner.id = '__nswStepBanner';
+        banner.style.cssText = 'position:fixed;top:0;left:0;right:0;z-index:2147483647;padding:8px 16px;'
+            + 'font:bold 16px sans-serif;color:#fff;pointer-events:none;';
+        document.body.appendChild(banner);
+    }
+    banner.style.background = arguments[1] ? '#b91c1c' : '#1d4ed8';
+    banner.textContent = arguments[0];
+"#;
+
+/// Log the step the scraper is on and, with `debug_browser`, show it in a
+/// banner across the top of the page.
+async fn show_step(driver: &WebDriver, settings: &Settings, step: &str) {
+    if !settings.debug_browser {
+        return;
+    }
+    info!(step, "Debug step");
+    if let Err(e) = driver.execute(STEP_BANNER_SCRIPT, vec![json!(step), json!(false)]).await {
+        warn!("Failed to show step banner: {}", e);
+    }
+}
+
+/// Save a screenshot and the page source of a failed location under
+/// `debug/`. Failures are logged, a capture must never break a run.
+async fn save_failure_capture(driver: &WebDriver, settings: &Settings, location: LocationId) {
+    if !settings.failure_captures {
+        return;
+    }
+
+    let dir = settings.debug_dir();
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        error!("Failed to create '{}': {}", dir.display(), e);
+        return;
+    }
+
+    let stem = diagnostics::failure_capture_stem(chrono::Utc::now(), location);
+    let screenshot_path = dir.join(format!("{}.png", stem));
+    if let Err(e) = driver.screenshot(&screenshot_path).await {
+        warn!("Failed to save failure screenshot: {}", e);
+    }
+    match driver.source().await {
+        Ok(source) => {
+            if let Err(e) = std::fs::write(dir.join(format!("{}.html", stem)), source) {
+                warn!("Failed to save failure page source: {}", e);
+            }
+        }
+        Err(e) => warn!("Failed to read failure page source: {}", e),
+    }
+    info!("Saved failure capture for {} to {}", location, screenshot_path.display());
+
+    if let Err(e) = diagnostics::prune_failure_captures(&dir, settings.failure_captures_max) {
+        warn!("Failed to prune failure captures: {}", e);
+    }
+}
+
+/// With `debug_browser`, show the error on the page and leave the browser
+/// there for `DEBUG_ERROR_PAUSE` so the broken selector can be inspected.
+async fn pause_on_error(driver: &WebDriver, settings: &Settings, error: &WebDriverError) {
+    if !settings.debug_browser {
+        return;
+    }
+    let message = format!("Error, pausing for {}s: {}", DEBUG_ERROR_PAUSE.as_secs(), error);
+    info!("{}", message);
+    // The page may be half loaded, the pause matters more than the banner
+    let _ = driver.execute(STEP_BANNER_SCRIPT, vec![json!(message), json!(true)]).await;
+    tokio::time::sleep(DEBUG_ERROR_PAUSE).await;
+}
+
+/// The order to visit `locations` in this run. Centres in `priority_locations`
+/// come first; with `shuffle` each group is shuffled so no two runs walk the
+/// portal the same way.
+pub fn visit_order<R: Rng + ?Sized>(
+    mut locations: Vec<LocationId>,
+    priority_locations: &[LocationId],
+    shuffle: bool,
+    rng: &mut R,
+) -> Vec<LocationId> {
+    if shuffle {
+        locations.shuffle(rng);
+    }
+    locations.sort_by_key(|location| !priority_locations.contains(location));
+    locations
+}
+
+/// Random delay added before each background run, up to
+/// `start_jitter_minutes`.
+pub fn start_jitter(settings: &Settings) -> Duration {
+    let max_secs = settings.start_jitter_minutes * 60;
+    if max_secs == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_secs(rand::thread_rng().gen_range(0..=max_secs))
+}
+
+/// Time until the next auto-find round: `auto_find_interval_minutes` plus a
+/// random amount up to `auto_find_jitter_minutes`.
+pub fn auto_find_wait(settings: &Settings) -> Duration {
+    let interval = Duration::from_secs(settings.auto_find_interval_minutes * 60);
+    let max_secs = settings.auto_find_jitter_minutes * 60;
+    if max_secs == 0 {
+        return interval;
+    }
+    interval + Duration::from_secs(rand::thread_rng().gen_range(0..=max_secs))
 }
 
 async fn type_like_human(element: &WebElement, text: &str, min_delay_ms: u64, max_delay_ms: u64) -> WebDriverResult<()> {
@@ -29,28 +272,88 @@ async fn type_like_human(element: &WebElement, text: &str, min_delay_ms: u64, ma
     Ok(())
 }
 
-pub async fn scrape_rta_timeslots(
-    locations: Vec<String>,
-    settings: &Settings
-) -> WebDriverResult<HashMap<String, LocationBookings>> {
+/// A configured selector as a WebDriver locator.
+fn by(selector: &str) -> By {
+    if selectors::is_xpath(selector) {
+        By::XPath(selector.to_string())
+    } else {
+        By::Css(selector.to_string())
+    }
+}
+
+/// Portal ids of the licence class and test on the "Book test" page, filled
+/// into `selectors.licence_class` and `selectors.test_item`.
+pub(super) fn test_selectors(test_type: TestType) -> (&'static str, &'static str) {
+    match test_type {
+        TestType::Car => ("CAR", "DC"),
+        TestType::Rider => ("RIDER", "DR"),
+        TestType::HeavyVehicle => ("HEAVY", "DH"),
+        TestType::Dkt => ("CAR", "DKT"),
+        TestType::Hpt => ("CAR", "HPT"),
+    }
+}
 
-    let mut location_bookings: HashMap<String, LocationBookings> = HashMap::new();
+/// Check the current page for the portal's maintenance notice, recording the
+/// window so the scraper backs off until it ends.
+async fn under_maintenance(driver: &WebDriver, settings: &Settings) -> bool {
+    let Ok(body) = driver.find(By::Tag("body")).await else {
+        return false;
+    };
+    let text = body.text().await.unwrap_or_default();
+    let backoff = chrono::Duration::minutes(settings.maintenance_backoff_minutes as i64);
+
+    match MaintenanceWindow::detect(&text, sydney_now(), backoff) {
+        Some(window) => {
+            maintenance::record(window);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Check the page a step failed on for a captcha or bot check, recording the
+/// block so scraping stops and cools down. Only failed steps are checked, an
+/// invisible captcha on a page that works is no reason to stop.
+async fn challenged(driver: &WebDriver, settings: &Settings) -> bool {
+    let Ok(source) = driver.source().await else {
+        return false;
+    };
+    let cooldown = chrono::Duration::minutes(settings.blocked_cooldown_minutes as i64);
+
+    match BlockedWindow::detect(&source, sydney_now(), cooldown) {
+        Some(window) => {
+            challenge::record(window);
+            true
+        }
+        None => false,
+    }
+}
 
+/// Start a browser, sign in and get to the location picker for `test_type`.
+/// Returns `None` when the portal is under maintenance or challenged the
+/// sign-in.
+async fn open_scrape_session(test_type: TestType, settings: &Settings) -> WebDriverResult<Option<WebDriver>> {
     let mut caps = DesiredCapabilities::chrome();
-    if settings.headless {
+    if settings.headless && !settings.debug_browser {
         caps.add_arg("--headless=new")?;
     }
+    let fingerprint = next_fingerprint(settings);
     caps.add_arg("--no-sandbox")?;
     caps.add_arg("--disable-dev-shm-usage")?;
-    caps.add_arg("--window-size=1920,1080")?;
+    caps.add_arg(&format!("--window-size={},{}", fingerprint.width, fingerprint.height))?;
     caps.add_arg("--start-maximized")?;
-    caps.add_arg("--user-agent=Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/103.0.5060.114 Safari/537.36")?;
+    caps.add_arg(&format!("--user-agent={}", fingerprint.user_agent))?;
     caps.add_arg("--disable-blink-features=AutomationControlled")?;
     caps.add_experimental_option("excludeSwitches", vec!["enable-automation"]);
     caps.add_experimental_option("useAutomationExtension", false);
+    if let Some(proxy) = next_proxy(settings) {
+        info!("Starting {} session through proxy {}", test_type.label(), proxy);
+        caps.add_arg(&format!("--proxy-server={}", proxy))?;
+    }
 
 
     let driver = WebDriver::new(settings.selenium_driver_url.clone(), caps).await?;
+    set_slow_motion(settings.debug_browser);
 
     driver.execute(r#"
         Object.defineProperty(navigator, 'webdriver', { get: () => undefined });
@@ -66,353 +369,1581 @@ pub async fn scrape_rta_timeslots(
         } catch (e) { console.debug('Error removing cdc keys:', e); }
     "#, Vec::new()).await?;
 
+    if settings.event_driven_waits {
+        let dev_tools = ChromeDevTools::new(driver.handle.clone());
+        let installed = dev_tools
+            .execute_cdp_with_params(
+                "Page.addScriptToEvaluateOnNewDocument",
+                json!({ "source": REQUEST_TRACKER_SCRIPT }),
+            )
+            .await;
+        if let Err(e) = installed {
+            warn!("Driver doesn't support CDP, using fixed waits: {}", e);
+        }
+    }
 
-    let timeout = Duration::from_millis(settings.selenium_element_timout);
-    let polling = Duration::from_millis(settings.selenium_element_polling);
+    if settings.diagnostics {
+        let dev_tools = ChromeDevTools::new(driver.handle.clone());
+        dev_tools
+            .execute_cdp_with_params(
+                "Page.addScriptToEvaluateOnNewDocument",
+                json!({ "source": diagnostics::CAPTURE_SCRIPT }),
+            )
+            .await?;
+    }
+
+    if restore_session_cookies(&driver, test_type, settings).await {
+        info!("Restored the saved {} session, skipping sign-in.", test_type.label());
+        return Ok(Some(driver));
+    }
+
+    match sign_in(&driver, test_type, settings).await {
+        Ok(true) => {
+            save_session_cookies(&driver, test_type, settings).await;
+            Ok(Some(driver))
+        }
+        Ok(false) => {
+            driver.quit().await?;
+            Ok(None)
+        }
+        Err(e) => {
+            pause_on_error(&driver, settings, &e).await;
+            let blocked = challenged(&driver, settings).await;
+            // Already failing, the original error is the useful one
+            let _ = driver.quit().await;
+            if blocked {
+                return Ok(None);
+            }
+            Err(e)
+        }
+    }
+}
+
+static NEXT_PROXY: AtomicUsize = AtomicUsize::new(0);
+
+/// The proxy for a new session, taking each of `proxies` in turn so every
+/// run and every scrape worker comes from a different address.
+pub fn next_proxy(settings: &Settings) -> Option<&str> {
+    if settings.proxies.is_empty() {
+        return None;
+    }
+    let index = NEXT_PROXY.fetch_add(1, AtomicOrdering::Relaxed) % settings.proxies.len();
+    Some(&settings.proxies[index])
+}
+
+/// User agent and window size for a new session, picked at random from
+/// `user_agents` and `window_sizes`.
+pub fn next_fingerprint(settings: &Settings) -> Fingerprint {
+    let mut rng = rand::thread_rng();
+    Fingerprint::pick(&settings.user_agents, &settings.window_sizes, |len| rng.gen_range(0..len))
+}
+
+/// Signed-in sessions kept between runs with `keep_browser_session`, one per
+/// scrape worker and test type, each left on the location picker.
+static KEPT_SESSIONS: OnceLock<Mutex<HashMap<TestType, Vec<WebDriver>>>> = OnceLock::new();
+
+fn get_kept_sessions() -> &'static Mutex<HashMap<TestType, Vec<WebDriver>>> {
+    KEPT_SESSIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Reloads the current page in the background and reports whether the
+/// portal still considers the session signed in. The visible page can't be
+/// trusted, it stays on the picker long after the session expired.
+const SESSION_CHECK_SCRIPT: &str = r#"
+const done = arguments[arguments.length - 1];
+fetch(window.location.href, { credentials: 'include' })
+    .then(response => response.text().then(body =>
+        done(!response.url.includes('/login') && !body.includes('widget_bookingId'))))
+    .catch(() => done(false));
+"#;
+
+async fn still_signed_in(driver: &WebDriver, settings: &Settings) -> bool {
+    if driver.find(by(&settings.selectors.location_dropdown)).await.is_err() {
+        return false;
+    }
+    match driver.execute_async(SESSION_CHECK_SCRIPT, Vec::new()).await {
+        Ok(ret) => ret.json().as_bool().unwrap_or(false),
+        Err(_) => false,
+    }
+}
 
-    driver.goto("https://www.myrta.com/wps/portal/extvp/myrta/login/").await?;
+/// The session kept from the previous run if it is still signed in,
+/// otherwise a new one from `open_scrape_session`.
+async fn take_scrape_session(test_type: TestType, settings: &Settings) -> WebDriverResult<Option<WebDriver>> {
+    let kept = if settings.keep_browser_session {
+        get_kept_sessions().lock().unwrap().get_mut(&test_type).and_then(Vec::pop)
+    } else {
+        None
+    };
+
+    if let Some(driver) = kept {
+        if still_signed_in(&driver, settings).await {
+            info!("Reusing the signed-in {} session.", test_type.label());
+            set_slow_motion(settings.debug_browser);
+            return Ok(Some(driver));
+        }
+        info!("Kept {} session has expired, signing in again.", test_type.label());
+        if let Err(e) = driver.quit().await {
+            warn!("Failed to quit the expired session: {}", e);
+        }
+    }
+
+    open_scrape_session(test_type, settings).await
+}
+
+/// Keep the session for the next run with `keep_browser_session`, otherwise
+/// quit it.
+async fn release_scrape_session(driver: WebDriver, test_type: TestType, settings: &Settings) -> WebDriverResult<()> {
+    // The portal may have refreshed them during the run
+    save_session_cookies(&driver, test_type, settings).await;
+    if settings.keep_browser_session {
+        get_kept_sessions().lock().unwrap().entry(test_type).or_default().push(driver);
+        return Ok(());
+    }
+    driver.quit().await
+}
+
+/// Where a signed-in session was and the cookies that kept it signed in.
+#[derive(Serialize, Deserialize)]
+struct SavedCookies {
+    url: String,
+    cookies: Vec<Cookie>,
+}
+
+fn persists_cookies(settings: &Settings) -> bool {
+    // Several browsers sharing one portal session would trip over each other
+    settings.persist_session_cookies && settings.scrape_workers <= 1
+}
+
+/// Save the cookies of a session on the location picker. Failures are
+/// logged, the next run just signs in again.
+async fn save_session_cookies(driver: &WebDriver, test_type: TestType, settings: &Settings) {
+    if !persists_cookies(settings) {
+        return;
+    }
+
+    let saved = match (driver.current_url().await, driver.get_all_cookies().await) {
+        (Ok(url), Ok(cookies)) => SavedCookies { url: url.to_string(), cookies },
+        (Err(e), _) | (_, Err(e)) => {
+            warn!("Failed to read the session cookies: {}", e);
+            return;
+        }
+    };
+    let path = settings.session_cookies_file(test_type);
+    let result = serde_json::to_string(&saved)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| std::fs::write(&path, contents).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        warn!("Failed to save session cookies to '{}': {}", path.display(), e);
+    }
+}
+
+/// Load the saved cookies into a fresh browser and go back to the page they
+/// were saved on. False, with the cookies discarded, when there are none or
+/// the portal no longer accepts them.
+async fn restore_session_cookies(driver: &WebDriver, test_type: TestType, settings: &Settings) -> bool {
+    if !persists_cookies(settings) {
+        return false;
+    }
+
+    let path = settings.session_cookies_file(test_type);
+    let Ok(contents) = std::fs::read_to_string(&path) else {
+        return false;
+    };
+    let saved: SavedCookies = match serde_json::from_str(&contents) {
+        Ok(saved) => saved,
+        Err(e) => {
+            warn!("Ignoring unreadable session cookies '{}': {}", path.display(), e);
+            return false;
+        }
+    };
+
+    // Cookies can only be set for the page's own domain
+    rate_limit::acquire(settings).await;
+    if driver.goto(&settings.portal_login_url()).await.is_err() {
+        return false;
+    }
+    for cookie in saved.cookies {
+        if let Err(e) = driver.add_cookie(cookie).await {
+            warn!("Failed to restore a session cookie: {}", e);
+        }
+    }
+    if driver.goto(&saved.url).await.is_err() {
+        return false;
+    }
     random_sleep(1000, 2000).await;
 
-    // Use booking id and last name for login when modifying an existing booking
-    let booking_input = driver.query(By::Id("widget_bookingId")).first().await?;
-    booking_input.wait_until().wait(timeout, polling).displayed().await?;
-    random_sleep(200, 500).await;
-    type_like_human(&booking_input, &settings.booking_id, 60, 180).await?;
-    random_sleep(300, 700).await;
+    if still_signed_in(driver, settings).await {
+        return true;
+    }
+    info!("Saved {} session was rejected, signing in again.", test_type.label());
+    let _ = std::fs::remove_file(&path);
+    let _ = driver.delete_all_cookies().await;
+    false
+}
+
+/// Fill in the login form for `login_mode` and submit it.
+async fn enter_credentials(driver: &WebDriver, settings: &Settings) -> WebDriverResult<()> {
+    let timeout = settings.step_timeout(Step::Login);
+    let polling = Duration::from_millis(settings.selenium_element_polling);
 
-    let last_name_input = driver.query(By::Id("widget_lastName")).first().await?;
-    last_name_input.wait_until().wait(timeout, polling).displayed().await?;
-    random_sleep(200, 500).await;
-    type_like_human(&last_name_input, &settings.last_name, 60, 180).await?;
-    random_sleep(400, 800).await;
+    let fields = match settings.login_mode {
+        LoginMode::Booking => [
+            (&settings.selectors.booking_id_input, &settings.booking_id),
+            (&settings.selectors.last_name_input, &settings.last_name),
+        ],
+        LoginMode::Licence => [
+            (&settings.selectors.licence_number_input, &settings.licence_number),
+            (&settings.selectors.password_input, &settings.password),
+        ],
+    };
+
+    for (selector, value) in fields {
+        let input = driver.query(by(selector)).first().await?;
+        input.wait_until().wait(timeout, polling).displayed().await?;
+        random_sleep(200, 500).await;
+        type_like_human(&input, value, 60, 180).await?;
+        random_sleep(300, 800).await;
+    }
 
-    let next_button = driver.query(By::Id("nextButton")).first().await?;
+    let next_button = driver.query(by(&settings.selectors.next_button)).first().await?;
     next_button.wait_until().wait(timeout, polling).displayed().await?;
+    rate_limit::acquire(settings).await;
     // next_button.wait_until().wait(timeout, polling).has_attribute("aria-disabled", "false").await?; // Alternative if clickable() doesn't work
     random_sleep(250, 600).await;
-    next_button.click().await?;
+    next_button.click().await
+}
+
+/// A licence login sees every booking on the account, so "Manage booking"
+/// may list several; open the one for `booking_id`. A booking login, or an
+/// account with a single booking, is already on it.
+async fn open_booking_entry(driver: &WebDriver, settings: &Settings) -> WebDriverResult<()> {
+    if settings.login_mode != LoginMode::Licence || settings.booking_id.is_empty() {
+        return Ok(());
+    }
+
+    let entry = selectors::fill(&settings.selectors.booking_entry, "booking_id", &settings.booking_id);
+    if let Ok(entry) = driver.query(by(&entry)).nowait().first().await {
+        if entry.is_clickable().await.unwrap_or(false) {
+            random_sleep(200, 500).await;
+            entry.click().await?;
+            random_sleep(1500, 2500).await;
+        }
+    }
+    Ok(())
+}
+
+/// Sign in and navigate to the location picker. Returns false when the portal
+/// is under maintenance.
+async fn sign_in(driver: &WebDriver, test_type: TestType, settings: &Settings) -> WebDriverResult<bool> {
+    let timeout = settings.step_timeout(Step::Navigation);
+    let polling = Duration::from_millis(settings.selenium_element_polling);
+    let checkpoint_file = settings.checkpoint_file(test_type);
+
+    checkpoint::enter(&checkpoint_file, ScrapeStage::SignIn);
+    show_step(driver, settings, "Opening login page").await;
+    rate_limit::acquire(settings).await;
+    driver.goto(&settings.portal_login_url()).await?;
+    random_sleep(1000, 2000).await;
+
+    if under_maintenance(driver, settings).await {
+        return Ok(false);
+    }
+
+    show_step(driver, settings, "Signing in").await;
+    enter_credentials(driver, settings).await?;
 
     random_sleep(2000, 4000).await;
 
-    if settings.have_booking {
-        let manage_booking = driver.query(By::XPath("//*[text()=\"Manage booking\"]")).first().await?;
+    // Sometimes the outage page only appears once signed in
+    if under_maintenance(driver, settings).await {
+        return Ok(false);
+    }
+    checkpoint::enter(&checkpoint_file, ScrapeStage::TestSelection);
+
+    // "Manage booking" only lists slots for the booked test, other tests go through "Book test"
+    if settings.have_booking && test_type == settings.booking_test_type {
+        show_step(driver, settings, "Changing location of the existing booking").await;
+        let manage_booking = driver.query(by(&settings.selectors.manage_booking)).first().await?;
         manage_booking.wait_until().wait(timeout, polling).displayed().await?;
         random_sleep(200, 500).await;
         manage_booking.click().await?;
         random_sleep(1500, 2500).await;
+        open_booking_entry(driver, settings).await?;
 
-        let change_location = driver.query(By::Id("changeLocationButton")).first().await?;
+        let change_location = driver.query(by(&settings.selectors.change_location_button)).first().await?;
         change_location.wait_until().wait(timeout, polling).displayed().await?;
         random_sleep(200, 500).await;
         change_location.click().await?;
         random_sleep(1000, 2000).await;
 
     } else {
-         let book_test = driver.query(By::XPath("//*[text()=\"Book test\"]")).first().await?;
+         show_step(driver, settings, &format!("Booking a new {} test", test_type.label())).await;
+         let book_test = driver.query(by(&settings.selectors.book_test)).first().await?;
          book_test.wait_until().wait(timeout, polling).displayed().await?;
          random_sleep(200, 500).await;
          book_test.click().await?;
          random_sleep(1500, 2500).await;
 
-         let car_option = driver.query(By::Id("CAR")).first().await?;
-         car_option.wait_until().wait(timeout, polling).displayed().await?;
+         let (class_id, test_id) = test_selectors(test_type);
+
+         let class_option = driver.query(by(&selectors::fill(&settings.selectors.licence_class, "class", class_id))).first().await?;
+         class_option.wait_until().wait(timeout, polling).displayed().await?;
          random_sleep(200, 500).await;
-         car_option.click().await?;
+         class_option.click().await?;
          random_sleep(500, 1000).await;
 
-         let test_item = driver.query(By::XPath("//fieldset[@id='DC']/span[contains(@class, 'rms_testItemResult')]")).first().await?;
+         let test_item = driver.query(by(&selectors::fill(&settings.selectors.test_item, "test", test_id))).first().await?;
          test_item.wait_until().wait(timeout, polling).displayed().await?;
          random_sleep(200, 500).await;
          test_item.click().await?;
          random_sleep(500, 1000).await;
 
-         let next_button = driver.query(By::Id("nextButton")).first().await?;
+         let next_button = driver.query(by(&settings.selectors.next_button)).first().await?;
          next_button.wait_until().wait(timeout, polling).displayed().await?;
          random_sleep(200, 500).await;
          next_button.click().await?;
          random_sleep(1500, 2500).await;
 
-         let check_terms = driver.query(By::Id("checkTerms")).first().await?;
+         show_step(driver, settings, "Accepting terms").await;
+         let check_terms = driver.query(by(&settings.selectors.terms_checkbox)).first().await?;
          check_terms.wait_until().wait(timeout, polling).displayed().await?;
          random_sleep(100, 300).await;
          check_terms.click().await?;
          random_sleep(500, 1000).await;
 
-         let next_button_terms = driver.query(By::Id("nextButton")).first().await?;
+         let next_button_terms = driver.query(by(&settings.selectors.next_button)).first().await?;
          next_button_terms.wait_until().wait(timeout, polling).displayed().await?;
          random_sleep(200, 500).await;
          next_button_terms.click().await?;
          random_sleep(1000, 2000).await;
     }
 
-    for location in locations {
-        println!("INFO: Processing location: {}", location);
-        let process_result: WebDriverResult<LocationBookings> = async {
+    checkpoint::enter(&checkpoint_file, ScrapeStage::Locations);
+    Ok(true)
+}
 
-            random_sleep(1000, 2000).await;
+/// Move the slot calendar on a week. False when there is no enabled "next
+/// week" control, i.e. the end of the booking horizon.
+async fn next_week(driver: &WebDriver, settings: &Settings) -> bool {
+    let Ok(button) = driver.query(by(&settings.selectors.next_week_button)).nowait().first().await else {
+        return false;
+    };
+    if !button.is_clickable().await.unwrap_or(false) {
+        return false;
+    }
+    random_sleep(300, 700).await;
+    rate_limit::acquire(settings).await;
+    if let Err(e) = button.click().await {
+        warn!("Failed to click 'Next week': {}", e);
+        return false;
+    }
+    settle(driver, settings, 1500, 3000).await;
+    true
+}
 
-            let location_select_dropdown = driver.query(By::Id("rms_batLocLocSel")).first().await?;
-            location_select_dropdown.wait_until().wait(timeout, polling).displayed().await?;
-            random_sleep(200, 400).await;
-            location_select_dropdown.click().await?;
-            random_sleep(500, 1000).await;
+/// Read the page's `timeslots` data, waiting up to the `timeslots` step
+/// timeout for the portal's script to define it.
+async fn read_timeslots(driver: &WebDriver, settings: &Settings) -> WebDriverResult<Value> {
+    let timeout = settings.step_timeout(Step::Timeslots);
+    let polling = Duration::from_millis(settings.selenium_element_polling);
+    let started = Instant::now();
+    while started.elapsed() < timeout {
+        let ret = driver.execute("return typeof timeslots === 'undefined' ? null : timeslots", vec![]).await?;
+        if !ret.json().is_null() {
+            return Ok(ret.json().clone());
+        }
+        tokio::time::sleep(polling).await;
+    }
+    // Still missing, let the page's own error say so
+    Ok(driver.execute("return timeslots", vec![]).await?.json().clone())
+}
 
-            let select_element_query = driver.query(By::Id("rms_batLocationSelect2"));
-            let select_element = select_element_query.wait(timeout, polling).first().await?;
-            select_element.wait_until().wait(timeout, polling).displayed().await?;
-            let select_box = SelectElement::new(&select_element).await?;
+/// Slots and the next available date from the portal's `timeslots` object.
+/// Slots that don't parse are logged and skipped.
+pub fn parse_timeslots(timeslots: &Value, location: LocationId) -> (Vec<TimeSlot>, Option<NaiveDateTime>) {
+    let Some(ajax_slots) = timeslots.get("ajaxresult").and_then(|ajax| ajax.get("slots")) else {
+        return (Vec::new(), None);
+    };
+
+    let next_available_date = ajax_slots
+        .get("nextAvailableDate")
+        .and_then(|date| date.as_str())
+        .and_then(parse_slot_time);
+
+    let slots: Vec<TimeSlot> = ajax_slots
+        .get("listTimeSlot")
+        .and_then(|list| list.as_array())
+        .map(|list| {
+            list.iter()
+                .filter_map(|slot| match serde_json::from_value::<TimeSlot>(slot.clone()) {
+                    Ok(slot) => Some(slot),
+                    Err(e) => {
+                        warn!("Skipping unparsable slot {} for {}: {}", slot, location, e);
+                        None
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_else(Vec::new);
+
+    (slots, next_available_date)
+}
 
-            if let Err(e) = select_box.select_by_value(&location).await {
-                 eprintln!("ERROR: Failed to select location '{}' in dropdown: {}. Ensure the value is correct.", location, e);
-                 return Err(e);
-            }
+/// Whether fresh `timeslots` from the location page still offer `slot`.
+/// Slots on a day the page doesn't list (a later week) count as offered,
+/// the slot button lookup decides those.
+pub fn slot_still_offered(timeslots: &Value, location: LocationId, slot: &TimeSlot) -> bool {
+    let (slots, _) = parse_timeslots(timeslots, location);
+    let same_day: Vec<&TimeSlot> = slots
+        .iter()
+        .filter(|offered| offered.start_time.date() == slot.start_time.date())
+        .collect();
+    same_day.is_empty()
+        || same_day
+            .iter()
+            .any(|offered| offered.availability && offered.start_time == slot.start_time)
+}
 
-            println!("INFO: Selected location: {}", location);
-            random_sleep(2500, 4000).await;
+/// Wait before retry `attempt` + 1 of a location: `base_ms`, doubling with
+/// every failure, capped at a minute.
+pub fn location_backoff(attempt: u32, base_ms: u64) -> Duration {
+    let factor = 1u64 << attempt.saturating_sub(1).min(16);
+    Duration::from_millis(base_ms.saturating_mul(factor).min(60_000))
+}
 
-            let next_button_loc = driver.query(By::Id("nextButton")).first().await?;
-            next_button_loc.wait_until().wait(timeout, polling).displayed().await?;
-            random_sleep(200, 500).await;
-            next_button_loc.click().await?;
+/// Deal `locations` out to `workers` lists in turn, so each worker gets an
+/// even share of the priority locations at the front.
+pub fn partition<T>(locations: Vec<T>, workers: usize) -> Vec<Vec<T>> {
+    let mut parts: Vec<Vec<T>> = (0..workers.max(1)).map(|_| Vec::new()).collect();
+    let count = parts.len();
+    for (i, location) in locations.into_iter().enumerate() {
+        parts[i % count].push(location);
+    }
+    parts.retain(|part| !part.is_empty());
+    parts
+}
 
-            random_sleep(1000, 2000).await;
+/// Scrapes every location, split across `scrape_workers` browser sessions
+/// running at once. Workers that fail leave their remaining locations out of
+/// the results; an error is only returned when every worker failed.
+pub async fn scrape_rta_timeslots(
+    locations: Vec<LocationId>,
+    test_type: TestType,
+    settings: &Settings
+) -> WebDriverResult<ScrapeResults> {
+    if settings.scrape_workers <= 1 || locations.len() <= 1 {
+        return scrape_in_session(locations, test_type, settings).await;
+    }
 
-            match driver.query(By::Id("getEarliestTime")).first().await {
-                Ok(element) => {
-                     if element.is_clickable().await.unwrap_or(false) {
-                         println!("INFO: Found 'Get Earliest Time' button, attempting click.");
-                         random_sleep(200, 400).await;
-                         if let Err(e) = element.click().await {
-                            eprintln!("WARN: Failed to click 'Get Earliest Time' button for {}: {}. Proceeding anyway.", location, e);
-                         } else {
-                             println!("INFO: Clicked 'Get Earliest Time'.");
-                             random_sleep(2500, 4500).await;
-                         }
-                     } else {
-                         println!("INFO: 'Get Earliest Time' button found but not clickable (visible/enabled).");
-                         random_sleep(500, 1000).await;
-                     }
-                },
-                Err(_) => {
-                    println!("INFO: 'Get Earliest Time' button not found for {}. Proceeding.", location);
-                    random_sleep(500, 1000).await;
-                },
+    let locations = visit_order(
+        locations,
+        &settings.priority_locations,
+        settings.shuffle_locations,
+        &mut rand::thread_rng(),
+    );
+    let parts = partition(locations, settings.scrape_workers);
+    info!("Scraping {} with {} browser sessions.", test_type.label(), parts.len());
+
+    let workers: Vec<_> = parts
+        .into_iter()
+        .enumerate()
+        .map(|(worker, part)| {
+            let settings = settings.clone();
+            spawn_named("scrape-worker", async move {
+                // Signing in all at once looks nothing like a person
+                random_sleep(worker as u64 * 5000, worker as u64 * 5000 + 3000).await;
+                scrape_in_session(part, test_type, &settings).await
+            }.instrument(info_span!("worker", worker)))
+        })
+        .collect();
+
+    let mut results = ScrapeResults::default();
+    let mut last_error = None;
+    let mut succeeded = 0;
+    for worker in workers {
+        match worker.await {
+            Ok(Ok(worker_results)) => {
+                succeeded += 1;
+                results.merge(worker_results);
+            }
+            Ok(Err(e)) => {
+                error!("Scrape worker failed: {}", e);
+                last_error = Some(e);
             }
+            Err(e) => error!("Scrape worker panicked: {}", e),
+        }
+    }
 
-            random_sleep(1000, 2500).await;
+    match last_error {
+        Some(e) if succeeded == 0 => Err(e),
+        _ => Ok(results),
+    }
+}
 
-            let timeslots = driver.execute("return timeslots", vec![]).await?;
+/// Scrapes every location in one browser session, retrying a failed location
+/// up to `location_attempts` times with growing waits, and starting a fresh
+/// session if the portal loses track of where it was. Returns no results,
+/// rather than an error, when the portal is under maintenance or challenged
+/// the scraper; check `maintenance::active` and `challenge::active` to tell
+/// these apart.
+async fn scrape_in_session(
+    locations: Vec<LocationId>,
+    test_type: TestType,
+    settings: &Settings
+) -> WebDriverResult<ScrapeResults> {
 
-            let next_available_date = timeslots.json()
-                .get("ajaxresult")
-                .and_then(|ajax| ajax.get("slots"))
-                .and_then(|slots| slots.get("nextAvailableDate"))
-                .and_then(|date| date.as_str())
-                .map(|s| s.to_string());
-                
-            let slots: Vec<TimeSlot> = timeslots.json()
-                .get("ajaxresult")
-                .and_then(|ajax| ajax.get("slots"))
-                .and_then(|slots| slots.get("listTimeSlot"))
-                .and_then(|list| serde_json::from_value(list.clone()).ok())
-                .unwrap_or_else(Vec::new);
+    let mut results = ScrapeResults::default();
 
+    let Some(mut driver) = take_scrape_session(test_type, settings).await? else {
+        return Ok(results);
+    };
+    let checkpoint_file = settings.checkpoint_file(test_type);
+    // A kept session is already on the location picker
+    checkpoint::enter(&checkpoint_file, ScrapeStage::Locations);
+    let mut restarts = 0;
 
-            println!("INFO: Parsed {} slots for {}. Next available: {:?}", slots.len(), location, next_available_date);
+    let timeout = settings.step_timeout(Step::Navigation);
+    let dropdown_timeout = settings.step_timeout(Step::Dropdown);
+    let polling = Duration::from_millis(settings.selenium_element_polling);
 
-            let location_result = LocationBookings {
-                location: location.to_string(),
-                slots,
-                next_available_date,
-            };
+    let locations = visit_order(
+        locations,
+        &settings.priority_locations,
+        settings.shuffle_locations,
+        &mut rand::thread_rng(),
+    );
+    let max_attempts = settings.location_attempts.max(1);
+    'locations: for location in locations {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let span = info_span!("location", location = %location, attempt);
+            span.in_scope(|| info!("Processing location: {} (attempt {}/{})", location, attempt, max_attempts));
+            show_step(&driver, settings, &format!("Scraping location {}", location)).await;
+            rate_limit::acquire(settings).await;
+            let started = Instant::now();
+            let process_result: WebDriverResult<LocationBookings> = async {
+
+                random_sleep(1000, 2000).await;
+
+                let location_select_dropdown = driver.query(by(&settings.selectors.location_dropdown)).first().await?;
+                location_select_dropdown.wait_until().wait(dropdown_timeout, polling).displayed().await?;
+                random_sleep(200, 400).await;
+                location_select_dropdown.click().await?;
+                random_sleep(500, 1000).await;
+
+                let select_element_query = driver.query(by(&settings.selectors.location_select));
+                let select_element = select_element_query.wait(dropdown_timeout, polling).first().await?;
+                select_element.wait_until().wait(dropdown_timeout, polling).displayed().await?;
+                let select_box = SelectElement::new(&select_element).await?;
+
+                if let Err(e) = select_box.select_by_value(&location.to_string()).await {
+                     error!("Failed to select location '{}' in dropdown: {}. Ensure the value is correct.", location, e);
+                     return Err(e);
+                }
 
-            random_sleep(800, 1500).await;
+                info!("Selected location: {}", location);
+                settle(&driver, settings, 2500, 4000).await;
+
+                let next_button_loc = driver.query(by(&settings.selectors.next_button)).first().await?;
+                next_button_loc.wait_until().wait(timeout, polling).displayed().await?;
+                random_sleep(200, 500).await;
+                next_button_loc.click().await?;
+
+                settle(&driver, settings, 1000, 2000).await;
+
+                match driver.query(by(&settings.selectors.earliest_time_button)).first().await {
+                    Ok(element) => {
+                         if element.is_clickable().await.unwrap_or(false) {
+                             info!("Found 'Get Earliest Time' button, attempting click.");
+                             random_sleep(200, 400).await;
+                             if let Err(e) = element.click().await {
+                                warn!("Failed to click 'Get Earliest Time' button for {}: {}. Proceeding anyway.", location, e);
+                             } else {
+                                 info!("Clicked 'Get Earliest Time'.");
+                                 settle(&driver, settings, 2500, 4500).await;
+                             }
+                         } else {
+                             info!("'Get Earliest Time' button found but not clickable (visible/enabled).");
+                             random_sleep(500, 1000).await;
+                         }
+                    },
+                    Err(_) => {
+                        info!("'Get Earliest Time' button not found for {}. Proceeding.", location);
+                        random_sleep(500, 1000).await;
+                    },
+                }
 
-            let another_location_link = driver.query(By::Id("anotherLocationLink")).first().await?;
-            another_location_link.wait_until().wait(timeout, polling).displayed().await?;
-            random_sleep(200, 500).await;
-            another_location_link.click().await?;
+                settle(&driver, settings, 1000, 2500).await;
+
+                let timeslots = read_timeslots(&driver, settings).await?;
+                let (slots, next_available_date) = parse_timeslots(&timeslots, location);
+
+                info!("Parsed {} slots for {}. Next available: {:?}", slots.len(), location, next_available_date);
+
+                let mut location_result = LocationBookings {
+                    location,
+                    test_type,
+                    slots,
+                    next_available_date,
+                };
+
+                for week in 1..=settings.weeks_ahead {
+                    if !next_week(&driver, settings).await {
+                        info!("No further weeks for {} after week {}.", location, week);
+                        break;
+                    }
+                    let timeslots = read_timeslots(&driver, settings).await?;
+                    let (slots, _) = parse_timeslots(&timeslots, location);
+                    let added = location_result.add_slots(slots);
+                    info!("Week {} added {} slots for {}.", week + 1, added, location);
+                    if added == 0 {
+                        break;
+                    }
+                }
+
+                random_sleep(800, 1500).await;
 
-            Ok(location_result)
+                let another_location_link = driver.query(by(&settings.selectors.another_location_link)).first().await?;
+                another_location_link.wait_until().wait(timeout, polling).displayed().await?;
+                random_sleep(200, 500).await;
+                another_location_link.click().await?;
 
-        }.await;
+                Ok(location_result)
 
-        match process_result {
-            Ok(booking_data) => {
-                location_bookings.insert(location.clone(), booking_data);
+            }.instrument(span.clone()).await;
+            let duration_ms = started.elapsed().as_millis() as u64;
+
+            save_captured_responses(&driver, settings).await;
+
+            let e = match process_result {
+                Ok(booking_data) => {
+                    span.in_scope(|| info!(slots = booking_data.slots.len(), duration_ms, "Scraped location"));
+                    checkpoint::location_done(&checkpoint_file, &booking_data);
+                    results.attempted(location, duration_ms, Some(booking_data.slots.len()));
+                    results.succeeded(booking_data);
+                    break;
+                }
+                Err(e) => e,
+            };
+
+            span.in_scope(|| error!(duration_ms, "Failed processing location {}: {}", location, e));
+            save_failure_capture(&driver, settings, location).await;
+            pause_on_error(&driver, settings, &e).await;
+            checkpoint::location_failed(&checkpoint_file, location, &e.to_string());
+            results.attempted(location, duration_ms, None);
+            results.failed(location, e.to_string());
+            // Carrying on would only confirm the suspicion
+            if challenged(&driver, settings).await {
+                break 'locations;
             }
-            Err(e) => {
-                 eprintln!("ERROR: Failed processing location {}: {}", location, e);
-                 match driver.query(By::Id("anotherLocationLink")).first().await {
-                     Ok(link) => {
-                          if link.is_displayed().await.unwrap_or(false) {
-                              eprintln!("INFO: Attempting recovery click on 'Another Location'.");
-                              if let Err(click_err) = link.click().await {
-                                  eprintln!("WARN: Recovery click failed: {}", click_err);
-                              } else {
-                                  println!("INFO: Recovery click succeeded.");
-                              }
-                          } else {
-                              eprintln!("WARN: Recovery link found but not displayed.");
-                          }
-                     }
-                     Err(_) => {
-                         eprintln!("WARN: Recovery link ('anotherLocationLink') not found. State unclear.");
-                     }
-                 }
-                 random_sleep(2000, 3000).await;
-                 continue;
+            let recovered = match driver.query(by(&settings.selectors.another_location_link)).first().await {
+                Ok(link) => {
+                    if link.is_displayed().await.unwrap_or(false) {
+                        info!("Attempting recovery click on 'Another Location'.");
+                        if let Err(click_err) = link.click().await {
+                            warn!("Recovery click failed: {}", click_err);
+                            false
+                        } else {
+                            info!("Recovery click succeeded.");
+                            true
+                        }
+                    } else {
+                        warn!("Recovery link found but not displayed.");
+                        false
+                    }
+                }
+                Err(_) => {
+                    warn!("Recovery link ('anotherLocationLink') not found. State unclear.");
+                    false
+                }
+            };
+
+            // Every remaining location would fail the same way, start over
+            // from the login page instead
+            if !recovered {
+                if restarts == MAX_SESSION_RESTARTS {
+                    error!("Session lost again after {} restarts, leaving the remaining locations to the next attempt.", restarts);
+                    break 'locations;
+                }
+                restarts += 1;
+                warn!("Session lost, restarting it ({}/{}).", restarts, MAX_SESSION_RESTARTS);
+                if let Err(quit_err) = driver.quit().await {
+                    warn!("Failed to quit the lost session: {}", quit_err);
+                }
+                match open_scrape_session(test_type, settings).await {
+                    Ok(Some(new_driver)) => driver = new_driver,
+                    Ok(None) => return Ok(results),
+                    Err(session_err) => {
+                        error!("Failed to restart the session: {}", session_err);
+                        return Ok(results);
+                    }
+                }
+            }
+
+            if attempt >= max_attempts {
+                warn!("Giving up on location {} after {} attempts.", location, attempt);
+                break;
             }
+            let delay = location_backoff(attempt, settings.location_retry_delay_ms);
+            info!("Retrying location {} in {:?}.", location, delay);
+            tokio::time::sleep(delay).await;
         }
-         random_sleep(1500, 3000).await;
+        random_sleep(1500, 3000).await;
     }
 
-    println!("INFO: Finished scraping all locations. Quitting driver.");
-    driver.quit().await?;
+    info!("Finished scraping all locations.");
+    release_scrape_session(driver, test_type, settings).await?;
 
-    Ok(location_bookings)
+    Ok(results)
 }
 
-/// Search approved locations for a slot before a given date and attempt to book it.
+/// Search approved locations for the slot `find_strategy` ranks best before a
+/// given date and book it, moving on to the next best slot if one can't be
+/// booked. Returns the receipt of the booking made, if any. With `dry_run`
+/// every step short of the final confirm click is taken and the receipt is
+/// for the slot that would have been booked.
 /// The booking process is highly dependent on the Service NSW website and may
 /// require adjusting the element selectors.
 pub async fn book_first_available(
-    locations: Vec<String>,
+    locations: Vec<LocationId>,
     before: chrono::NaiveDate,
+    dry_run: bool,
     settings: &Settings,
-) -> WebDriverResult<Option<(String, String)>> {
-    let bookings = scrape_rta_timeslots(locations.clone(), settings).await?;
+) -> WebDriverResult<Option<BookingReceipt>> {
+    let booked = find_and_book(locations, before, dry_run, settings).await;
+    // The page that asked may be closed by now
+    if !dry_run {
+        let notification = match &booked {
+            Ok(Some(receipt)) => Notification::booking(&settings.booking_id, receipt),
+            Ok(None) => Some(Notification::new(
+                NotificationLevel::Info,
+                "No slot found",
+                format!("No slot before {} could be booked for booking {}", before.format("%d/%m/%Y"), settings.booking_id),
+            )),
+            Err(e) => Some(Notification::new(
+                NotificationLevel::Error,
+                "Booking failed",
+                format!("Booking {} failed: {}", settings.booking_id, e),
+            )),
+        };
+        if let Some(notification) = notification {
+            notifications::dispatch(settings, &notification).await;
+        }
+    }
+    booked
+}
 
-    for (loc, info) in bookings {
-        if let Some(slot) = info
-            .slots
-            .iter()
-            .filter(|s| s.availability)
-            .find(|s| {
-                chrono::NaiveDateTime::parse_from_str(&s.start_time, "%d/%m/%Y %H:%M")
-                    .map(|dt| dt.date() <= before)
-                    .unwrap_or(false)
-            })
-        {
-
-            match try_book_slot(&loc, &slot, settings).await {
-                Ok(_) => {
-                    println!("Booked slot {} at {}", loc, slot.start_time);
-                    return Ok(Some((loc, slot.start_time.clone())));
-                }
-                Err(e) => {
-                    eprintln!("Error booking slot at {}: {}", loc, e);
-                }
+async fn find_and_book(
+    locations: Vec<LocationId>,
+    before: chrono::NaiveDate,
+    dry_run: bool,
+    settings: &Settings,
+) -> WebDriverResult<Option<BookingReceipt>> {
+    let bookings = scrape_rta_timeslots(locations.clone(), settings.booking_test_type, settings).await?.bookings;
+
+    let location_manager = LocationManager::new();
+    let strategy = settings.find_strategy.build(&settings.scoring);
+    let now = sydney_now();
+    let mut candidates: Vec<(LocationId, TimeSlot, f64)> = bookings
+        .into_values()
+        .flat_map(|info| {
+            let loc = info.location;
+            info.slots
+                .into_iter()
+                .filter(move |s| {
+                    s.availability && is_on_or_before_date(s.start_time, before) && settings.slot_preferences.accepts(s)
+                })
+                .map(move |s| (loc, s))
+        })
+        .filter_map(|(loc, slot)| {
+            let details = location_manager.get_by_id(loc);
+            strategy
+                .accepts(details.as_ref(), &slot)
+                .then(|| {
+                    let score = strategy.score(details.as_ref(), &slot, now);
+                    (loc, slot, score)
+                })
+        })
+        .collect();
+    // Equal scores fall back to the earlier slot, then the lower centre id,
+    // so the pick doesn't depend on the order the scrape returned centres in
+    candidates.sort_by(|a, b| {
+        b.2.total_cmp(&a.2)
+            .then(a.1.start_time.cmp(&b.1.start_time))
+            .then(a.0.cmp(&b.0))
+    });
+
+    for (loc, slot, _) in candidates {
+        let booked = if dry_run {
+            preview_slot(loc, &slot, settings).await
+        } else if settings.confirm_bookings || settings.hold_slots {
+            book_slot_with_confirmation(loc, &slot, settings).await
+        } else {
+            try_book_slot(loc, &slot, settings).await
+        };
+
+        match booked {
+            Ok(receipt) if receipt.outcome == BookingOutcome::DryRun => {
+                info!("Dry run: would book slot {} at {}", loc, slot.start_time);
+                return Ok(Some(receipt));
+            }
+            Ok(receipt) if receipt.outcome == BookingOutcome::Booked => {
+                info!(
+                    "Booked slot {} at {}, confirmation number {}",
+                    loc,
+                    receipt.start_time,
+                    receipt.confirmation_number.as_deref().unwrap_or("unknown"),
+                );
+                return Ok(Some(receipt));
+            }
+            Ok(receipt) if receipt.outcome == BookingOutcome::PaymentRequired => {
+                info!("Slot {} at {} needs payment, left for the user to finish", loc, slot.start_time);
+                return Ok(Some(receipt));
+            }
+            // Every other candidate would be stopped the same way
+            Ok(receipt) if matches!(receipt.outcome, BookingOutcome::ChangeFeeApplies | BookingOutcome::ChangeLimitReached) => {
+                return Ok(Some(receipt));
+            }
+            // This one may have gone through, booking another could move it again
+            Ok(receipt) if receipt.outcome == BookingOutcome::Unconfirmed => {
+                warn!("Slot {} at {} was not confirmed, stopping", loc, slot.start_time);
+                return Ok(Some(receipt));
+            }
+            Ok(receipt) => {
+                warn!("Slot at {} on {} was not booked: {:?}", loc, slot.start_time, receipt.outcome);
+            }
+            Err(e) => {
+                error!("Error booking slot at {}: {}", loc, e);
             }
-
-            // TODO: implement DOM interaction to select the slot and confirm the booking
-            println!("Would attempt to book {} at {}", loc, slot.start_time);
-            return Ok(Some((loc, slot.start_time.clone())));
-
         }
     }
 
-    println!("No available slots before {} found in approved locations", before);
+    info!("No available slots before {} found in approved locations", before);
     Ok(None)
 }
 
 
 /// Attempt to book the given slot at the specified location using the provided settings.
+/// The outcome is `Unavailable` when the slot is not offered on the page any more.
 /// This implementation provides a best-effort attempt and may require adjusting
 /// element selectors to match the Service NSW website.
-async fn try_book_slot(location: &str, slot: &TimeSlot, settings: &Settings) -> WebDriverResult<()> {
+pub async fn try_book_slot(location: LocationId, slot: &TimeSlot, settings: &Settings) -> WebDriverResult<BookingReceipt> {
+    let booked = match open_slot(location, slot, settings).await {
+        Ok(Ok(held)) => confirm_open_slot(held, settings).await,
+        Ok(Err(outcome)) => Ok(BookingReceipt::new(outcome, location, slot.start_time)),
+        Err(e) => Err(e),
+    };
+    audit_booking("direct", location, slot, &booked, settings);
+    booked
+}
+
+/// Take `slot` as far as its confirmation page and check it against the
+/// booking guard, then quit without clicking confirm.
+pub async fn preview_slot(location: LocationId, slot: &TimeSlot, settings: &Settings) -> WebDriverResult<BookingReceipt> {
+    let previewed = open_slot_for_preview(location, slot, settings).await;
+    audit_booking("dry_run", location, slot, &previewed, settings);
+    previewed
+}
+
+async fn open_slot_for_preview(location: LocationId, slot: &TimeSlot, settings: &Settings) -> WebDriverResult<BookingReceipt> {
+    let held = match open_slot(location, slot, settings).await? {
+        Ok(held) => held,
+        Err(outcome) => return Ok(BookingReceipt::new(outcome, location, slot.start_time)),
+    };
+    let outcome = if passes_booking_guard(&held, settings) {
+        BookingOutcome::DryRun
+    } else {
+        BookingOutcome::NotAnImprovement
+    };
+    held.driver.quit().await?;
+    Ok(BookingReceipt::new(outcome, location, slot.start_time))
+}
+
+/// Re-request the current page in the background so the RTA session does not
+/// time out while a held slot waits for approval.
+const KEEP_ALIVE_SCRIPT: &str = "fetch(window.location.href, { credentials: 'include' });";
+
+/// Text of the portal's reservation countdown, if the page shows one.
+const RESERVATION_TIMER_SCRIPT: &str = r#"
+    const timer = document.querySelector('#timer, #countdown, .rms_timer, [id*="Timer"]');
+    return timer ? timer.textContent : null;
+"#;
+
+/// Time kept back from the portal's reservation window to click confirm.
+const RESERVATION_MARGIN: Duration = Duration::from_secs(30);
+
+/// Parse a countdown like `9:41` or `1:02:03`.
+fn parse_countdown(text: &str) -> Option<Duration> {
+    let parts = text
+        .trim()
+        .split(':')
+        .map(|part| part.trim().parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    let seconds = match parts.as_slice() {
+        [minutes, seconds] => minutes * 60 + seconds,
+        [hours, minutes, seconds] => hours * 3600 + minutes * 60 + seconds,
+        _ => return None,
+    };
+    Some(Duration::from_secs(seconds))
+}
+
+async fn read_reservation_timer(driver: &WebDriver) -> Option<Duration> {
+    let result = driver.execute(RESERVATION_TIMER_SCRIPT, Vec::new()).await.ok()?;
+    parse_countdown(result.json().as_str()?)
+}
+
+/// Hold the slot at the confirmation page, notify the user with confirm and
+/// decline links, and only complete the booking once they approve. The slot is
+/// released if they decline, `confirmation_timeout_minutes` passes, or the
+/// portal's own reservation timer is about to run out.
+pub async fn book_slot_with_confirmation(
+    location: LocationId,
+    slot: &TimeSlot,
+    settings: &Settings,
+) -> WebDriverResult<BookingReceipt> {
+    let booked = hold_slot_for_confirmation(location, slot, settings).await;
+    audit_booking("confirm", location, slot, &booked, settings);
+    booked
+}
+
+/// Append a booking attempt to the audit log. `mode` is how it was made:
+/// `direct`, `confirm` (held for the user's approval) or `dry_run`.
+fn audit_booking(
+    mode: &str,
+    location: LocationId,
+    slot: &TimeSlot,
+    booked: &WebDriverResult<BookingReceipt>,
+    settings: &Settings,
+) {
+    let parameters = json!({
+        "mode": mode,
+        "booking_id": settings.booking_id,
+        "test_type": settings.booking_test_type,
+        "location": location,
+        "slot": format_slot_time(&slot.start_time),
+    });
+    let (success, outcome) = match booked {
+        Ok(receipt) => {
+            let mut outcome = format!("{:?}", receipt.outcome);
+            if let Some(number) = &receipt.confirmation_number {
+                outcome.push_str(&format!(", booking number {}", number));
+            }
+            (receipt.secured() || receipt.outcome == BookingOutcome::DryRun, outcome)
+        }
+        Err(e) => (false, e.to_string()),
+    };
+    audit::record(settings, AuditEntry::new(AuditAction::Booking, parameters, success, outcome));
+}
+
+async fn hold_slot_for_confirmation(
+    location: LocationId,
+    slot: &TimeSlot,
+    settings: &Settings,
+) -> WebDriverResult<BookingReceipt> {
+    let held = match open_slot(location, slot, settings).await? {
+        Ok(held) => held,
+        Err(outcome) => return Ok(BookingReceipt::new(outcome, location, slot.start_time)),
+    };
+    if !passes_booking_guard(&held, settings) {
+        held.driver.quit().await?;
+        return Ok(BookingReceipt::new(BookingOutcome::NotAnImprovement, location, slot.start_time));
+    }
+    let driver = &held.driver;
+
+    let mut wait = Duration::from_secs(settings.confirmation_timeout_minutes * 60);
+    if let Some(remaining) = read_reservation_timer(driver).await {
+        info!("Portal reserves the slot for another {:?}", remaining);
+        wait = wait.min(remaining.saturating_sub(RESERVATION_MARGIN));
+    }
+    let expires_at = chrono::Utc::now() + chrono::Duration::from_std(wait).unwrap_or_default();
+    let (token, mut decision) = confirmation::register(&settings.booking_id, location, slot.start_time, expires_at);
+
+    let mut message = format!(
+        "A slot at {} on {} is being held for you.\n",
+        location,
+        format_slot_time(&slot.start_time),
+    );
+    // Validation requires public_url for confirm_bookings, jobs that ask
+    // first are approved from the auto-finder panel without it
+    match settings.public_url.as_deref().map(|url| url.trim_end_matches('/')) {
+        Some(base_url) => message.push_str(&format!(
+            "Confirm: {}/api/confirm/{}\nDecline: {}/api/decline/{}\n",
+            base_url, token, base_url, token
+        )),
+        None => message.push_str("Approve or decline it in the auto-finder panel.\n"),
+    }
+    message.push_str(&format!("It will be released in {}m {}s.", wait.as_secs() / 60, wait.as_secs() % 60));
+    if settings.hold_slots {
+        let saved = save_session(driver, settings, "held").await;
+        message.push_str(&format!(
+            "\nTo take over, open {} with the cookies saved in {}.",
+            saved.url,
+            saved.session_path.display(),
+        ));
+    }
+    notifications::dispatch(
+        settings,
+        &Notification::new(NotificationLevel::Info, "Confirm test booking", message),
+    )
+    .await;
+    info!("Holding slot at {} on {} for confirmation", location, slot.start_time);
+
+    let deadline = tokio::time::sleep(wait);
+    tokio::pin!(deadline);
+    let mut keep_alive = tokio::time::interval(Duration::from_secs(60));
+
+    let confirmed = loop {
+        tokio::select! {
+            result = &mut decision => break result.unwrap_or(false),
+            _ = &mut deadline => {
+                confirmation::discard(&token);
+                info!("No answer for held slot at {} on {}, releasing it", location, slot.start_time);
+                break false;
+            }
+            _ = keep_alive.tick() => {
+                if let Err(e) = driver.execute(KEEP_ALIVE_SCRIPT, Vec::new()).await {
+                    warn!("Failed to keep booking session alive: {}", e);
+                }
+            }
+        }
+    };
+
+    if confirmed {
+        confirm_open_slot(held, settings).await
+    } else {
+        held.driver.quit().await?;
+        Ok(BookingReceipt::new(BookingOutcome::Declined, location, slot.start_time))
+    }
+}
+
+/// Cancel the booking signed in with `settings` from its "Manage booking"
+/// page and stop any auto-find job still trying to change it. Returns the
+/// date and time that was cancelled, if the portal showed it.
+pub async fn cancel_booking(settings: &Settings) -> WebDriverResult<Option<NaiveDateTime>> {
+    let cancelled = cancel_current_booking(settings).await;
+    if cancelled.is_ok() {
+        super::booking::BookingManager::stop_auto_find_for(&settings.booking_id, settings);
+    }
+
+    let parameters = json!({
+        "mode": "cancel",
+        "booking_id": settings.booking_id,
+        "test_type": settings.booking_test_type,
+    });
+    let (success, outcome) = match &cancelled {
+        Ok(Some(start_time)) => (true, format!("Cancelled {}", format_slot_time(start_time))),
+        Ok(None) => (true, "Cancelled".to_string()),
+        Err(e) => (false, e.to_string()),
+    };
+    audit::record(settings, AuditEntry::new(AuditAction::Booking, parameters, success, outcome));
+    cancelled
+}
+
+async fn cancel_current_booking(settings: &Settings) -> WebDriverResult<Option<NaiveDateTime>> {
+    let driver = start_booking_session(settings).await?;
+    let timeout = settings.step_timeout(Step::Navigation);
+    let polling = Duration::from_millis(settings.selenium_element_polling);
+
+    let cancelled = async {
+        let current_booking = open_manage_booking(&driver, settings).await?;
+
+        for selector in [&settings.selectors.cancel_booking_button, &settings.selectors.confirm_cancel_button] {
+            let button = driver.query(by(selector)).first().await?;
+            button.wait_until().wait(timeout, polling).displayed().await?;
+            rate_limit::acquire(settings).await;
+            button.click().await?;
+            random_sleep(1500, 2500).await;
+        }
+
+        // Only count it once the portal says so
+        let confirmation = driver.query(by(&settings.selectors.cancel_confirmation)).first().await?;
+        confirmation.wait_until().wait(timeout, polling).displayed().await?;
+
+        info!("Cancelled booking {}", settings.booking_id);
+        Ok(current_booking)
+    }
+    .await;
+
+    driver.quit().await?;
+    cancelled
+}
+
+/// Move the booking signed in with `settings` to the best slot at `location`
+/// on or before `before`, through "Manage booking". The booking guard still
+/// applies, so the new slot has to beat the current one.
+pub async fn change_booking_location(
+    location: LocationId,
+    before: chrono::NaiveDate,
+    settings: &Settings,
+) -> WebDriverResult<Option<BookingReceipt>> {
+    let mut settings = settings.clone();
+    settings.have_booking = true;
+    settings.confirm_bookings = false;
+    settings.hold_slots = false;
+    book_first_available(vec![location], before, false, &settings).await
+}
+
+/// A slot selected in the portal, with the driver sitting on its confirmation page.
+struct HeldSlot {
+    driver: WebDriver,
+    location: LocationId,
+    slot: TimeSlot,
+    /// The existing booking as read from the portal while opening the slot
+    current_booking: Option<NaiveDateTime>,
+}
+
+/// Text of the existing booking's date and time on the "Manage booking" page.
+const CURRENT_BOOKING_SCRIPT: &str = r#"
+    const booking = document.querySelector('#bookingDateTime, .rms_bookingDateTime, [id*="testDateTime"]');
+    return booking ? booking.textContent : null;
+"#;
+
+async fn read_current_booking(driver: &WebDriver) -> Option<NaiveDateTime> {
+    let result = driver.execute(CURRENT_BOOKING_SCRIPT, Vec::new()).await.ok()?;
+    let text = result.json().as_str()?.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    parse_slot_time(&text)
+        .or_else(|| NaiveDateTime::parse_from_str(&text, "%A, %d %B %Y %I:%M %p").ok())
+        .or_else(|| NaiveDateTime::parse_from_str(&text, "%d %B %Y %I:%M %p").ok())
+}
+
+/// Hard guard against making an existing booking worse. Fails closed: if the
+/// current booking could not be read, nothing is changed. Rejections are
+/// logged and appended to `data_dir/aborted-bookings.jsonl`.
+fn passes_booking_guard(held: &HeldSlot, settings: &Settings) -> bool {
+    if !settings.have_booking {
+        return true;
+    }
+
+    let min_improvement = chrono::Duration::hours(settings.min_improvement_hours as i64);
+    let reason = match held.current_booking {
+        Some(current) if held.slot.improves_on(current, min_improvement) => return true,
+        Some(current) => format!(
+            "{} is not at least {}h earlier than the current booking {}",
+            format_slot_time(&held.slot.start_time),
+            settings.min_improvement_hours,
+            format_slot_time(&current),
+        ),
+        None => "could not read the current booking from the portal".to_string(),
+    };
+
+    warn!("Aborted booking at {}: {}", held.location, reason);
+
+    let entry = json!({
+        "timestamp": chrono::Utc::now(),
+        "location": held.location,
+        "candidate": format_slot_time(&held.slot.start_time),
+        "current_booking": held.current_booking.as_ref().map(format_slot_time),
+        "reason": reason,
+    });
+    let log_path = settings.data_dir.join("aborted-bookings.jsonl");
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .and_then(|mut file| writeln!(file, "{}", entry));
+    if let Err(e) = written {
+        error!("Failed to record aborted booking in '{}': {}", log_path.display(), e);
+    }
+
+    false
+}
+
+/// Start a browser for booking and sign in with `settings`' booking.
+async fn start_booking_session(settings: &Settings) -> WebDriverResult<WebDriver> {
     let mut caps = DesiredCapabilities::chrome();
     if settings.headless {
         caps.add_arg("--headless=new")?;
     }
+    let fingerprint = next_fingerprint(settings);
     caps.add_arg("--no-sandbox")?;
     caps.add_arg("--disable-dev-shm-usage")?;
-    caps.add_arg("--window-size=1920,1080")?;
+    caps.add_arg(&format!("--window-size={},{}", fingerprint.width, fingerprint.height))?;
     caps.add_arg("--start-maximized")?;
+    caps.add_arg(&format!("--user-agent={}", fingerprint.user_agent))?;
     caps.add_experimental_option("excludeSwitches", vec!["enable-automation"]);
     caps.add_experimental_option("useAutomationExtension", false);
 
     let driver = WebDriver::new(settings.selenium_driver_url.clone(), caps).await?;
 
-    let timeout = Duration::from_millis(settings.selenium_element_timout);
-    let polling = Duration::from_millis(settings.selenium_element_polling);
-
-    // Login using booking id and last name
-    driver.goto("https://www.myrta.com/wps/portal/extvp/myrta/login/").await?;
+    rate_limit::acquire(settings).await;
+    driver.goto(&settings.portal_login_url()).await?;
     random_sleep(1000, 2000).await;
 
-    let booking_input = driver.query(By::Id("widget_bookingId")).first().await?;
-    booking_input.wait_until().wait(timeout, polling).displayed().await?;
-    type_like_human(&booking_input, &settings.booking_id, 60, 180).await?;
-    random_sleep(300, 700).await;
+    enter_credentials(&driver, settings).await?;
+    random_sleep(1500, 2500).await;
+    Ok(driver)
+}
 
-    let last_name_input = driver.query(By::Id("widget_lastName")).first().await?;
-    last_name_input.wait_until().wait(timeout, polling).displayed().await?;
-    type_like_human(&last_name_input, &settings.last_name, 60, 180).await?;
-    random_sleep(400, 800).await;
+/// Open the booking on the "Manage booking" page and read its current date
+/// and time.
+async fn open_manage_booking(driver: &WebDriver, settings: &Settings) -> WebDriverResult<Option<NaiveDateTime>> {
+    let timeout = settings.step_timeout(Step::Navigation);
+    let polling = Duration::from_millis(settings.selenium_element_polling);
 
-    let next_button = driver.query(By::Id("nextButton")).first().await?;
-    next_button.wait_until().wait(timeout, polling).displayed().await?;
-    next_button.click().await?;
+    let manage_booking = driver.query(by(&settings.selectors.manage_booking)).first().await?;
+    manage_booking.wait_until().wait(timeout, polling).displayed().await?;
+    manage_booking.click().await?;
     random_sleep(1500, 2500).await;
+    open_booking_entry(driver, settings).await?;
+
+    Ok(read_current_booking(driver).await)
+}
+
+/// Stands in for the amount of a change fee notice that names none.
+pub const UNKNOWN_CHANGE_FEE: &str = "unknown amount";
+
+/// Pull the amount out of a change fee notice, e.g. `$27.00` from `A change
+/// fee of $27.00 applies`. Fails closed: only an explicit zero amount is
+/// `None`, a notice without a readable amount is `UNKNOWN_CHANGE_FEE`.
+pub fn parse_change_fee(text: &str) -> Option<String> {
+    let Some(start) = text.find('$') else {
+        return Some(UNKNOWN_CHANGE_FEE.to_string());
+    };
+    let amount: String = text[start + 1..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+        .collect();
+    let amount = amount.trim_end_matches(['.', ',']);
+    match amount.replace(',', "").parse::<f64>() {
+        Ok(value) if value <= 0.0 => None,
+        Ok(_) => Some(format!("${}", amount)),
+        Err(_) => Some(UNKNOWN_CHANGE_FEE.to_string()),
+    }
+}
+
+/// Read the change fee and change limit notices off the "Manage booking" page.
+/// A fee notice that is there but can't be read counts as a fee.
+async fn read_rebook_eligibility(driver: &WebDriver, settings: &Settings) -> RebookEligibility {
+    let change_fee = match driver.query(by(&settings.selectors.change_fee_notice)).nowait().first().await {
+        Ok(notice) => match notice.text().await {
+            Ok(text) => parse_change_fee(&text),
+            Err(_) => Some(UNKNOWN_CHANGE_FEE.to_string()),
+        },
+        Err(_) => None,
+    };
+    let limit_reached = driver
+        .query(by(&settings.selectors.change_limit_notice))
+        .nowait()
+        .exists()
+        .await
+        .unwrap_or(false);
+    RebookEligibility { change_fee, limit_reached }
+}
+
+/// Sign in and report whether the booking can be changed and at what cost,
+/// without changing anything.
+pub async fn check_rebook_eligibility(settings: &Settings) -> WebDriverResult<RebookEligibility> {
+    let driver = start_booking_session(settings).await?;
+    let eligibility = match open_manage_booking(&driver, settings).await {
+        Ok(_) => Ok(read_rebook_eligibility(&driver, settings).await),
+        Err(e) => Err(e),
+    };
+    driver.quit().await?;
+    eligibility
+}
+
+/// Log in and navigate to the confirmation page for `slot`. Returns the held
+/// slot, or once the driver has quit, why it wasn't held: `Unavailable` when
+/// the slot is not offered, or the change fee or limit stopping a rebooking.
+async fn open_slot(
+    location: LocationId,
+    slot: &TimeSlot,
+    settings: &Settings,
+) -> WebDriverResult<Result<HeldSlot, BookingOutcome>> {
+    let driver = start_booking_session(settings).await?;
+
+    match navigate_to_slot(&driver, location, slot, settings).await {
+        Ok(Ok(current_booking)) => Ok(Ok(HeldSlot {
+            driver,
+            location,
+            slot: slot.clone(),
+            current_booking,
+        })),
+        Ok(Err(outcome)) => {
+            driver.quit().await?;
+            Ok(Err(outcome))
+        }
+        Err(e) => {
+            driver.quit().await?;
+            Err(e)
+        }
+    }
+}
+
+/// The steps of `open_slot` after signing in. Returns the existing booking
+/// read on the way, if there is one.
+async fn navigate_to_slot(
+    driver: &WebDriver,
+    location: LocationId,
+    slot: &TimeSlot,
+    settings: &Settings,
+) -> WebDriverResult<Result<Option<NaiveDateTime>, BookingOutcome>> {
+    let timeout = settings.step_timeout(Step::Navigation);
+    let dropdown_timeout = settings.step_timeout(Step::Dropdown);
+    let polling = Duration::from_millis(settings.selenium_element_polling);
 
+    let mut current_booking = None;
     if settings.have_booking {
-        let manage_booking = driver.query(By::XPath("//*[text()='Manage booking']")).first().await?;
-        manage_booking.wait_until().wait(timeout, polling).displayed().await?;
-        manage_booking.click().await?;
-        random_sleep(1500, 2500).await;
+        current_booking = open_manage_booking(driver, settings).await?;
+
+        // Checked before anything is changed, the portal charges on confirm
+        let eligibility = read_rebook_eligibility(driver, settings).await;
+        if let Some(fee) = &eligibility.change_fee {
+            warn!("Changing booking {} costs {}", settings.booking_id, fee);
+        }
+        if let Some(outcome) = eligibility.blocks(settings.abort_on_change_fee) {
+            warn!("Not changing booking {}: {:?}", settings.booking_id, outcome);
+            return Ok(Err(outcome));
+        }
 
-        let change_location = driver.query(By::Id("changeLocationButton")).first().await?;
+        let change_location = driver.query(by(&settings.selectors.change_location_button)).first().await?;
         change_location.wait_until().wait(timeout, polling).displayed().await?;
         change_location.click().await?;
         random_sleep(1000, 2000).await;
     } else {
-        let book_test = driver.query(By::XPath("//*[text()='Book test']")).first().await?;
+        let book_test = driver.query(by(&settings.selectors.book_test)).first().await?;
         book_test.wait_until().wait(timeout, polling).displayed().await?;
         book_test.click().await?;
         random_sleep(1500, 2500).await;
     }
 
     // Select location
-    let dropdown = driver.query(By::Id("rms_batLocLocSelect")).first().await?;
-    dropdown.wait_until().wait(timeout, polling).displayed().await?;
+    let dropdown = driver.query(by(&settings.selectors.location_dropdown)).first().await?;
+    dropdown.wait_until().wait(dropdown_timeout, polling).displayed().await?;
     dropdown.click().await?;
     random_sleep(500, 1000).await;
 
-    let select_element_query = driver.query(By::Id("rms_batLocationSelect2"));
-    let select_element = select_element_query.wait(timeout, polling).first().await?;
+    let select_element_query = driver.query(by(&settings.selectors.location_select));
+    let select_element = select_element_query.wait(dropdown_timeout, polling).first().await?;
     let select_box = SelectElement::new(&select_element).await?;
-    select_box.select_by_value(location).await?;
+    select_box.select_by_value(&location.to_string()).await?;
     random_sleep(2500, 3500).await;
 
-    let next_button_loc = driver.query(By::Id("nextButton")).first().await?;
+    let next_button_loc = driver.query(by(&settings.selectors.next_button)).first().await?;
     next_button_loc.wait_until().wait(timeout, polling).displayed().await?;
     next_button_loc.click().await?;
     random_sleep(1500, 2500).await;
 
+    // The slot was picked from a scrape that may be many minutes old
+    match read_timeslots(driver, settings).await {
+        Ok(timeslots) if !slot_still_offered(&timeslots, location, slot) => {
+            info!("Slot at {} on {} has gone since the last scrape", location, slot.start_time);
+            return Ok(Err(BookingOutcome::Unavailable));
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Could not re-check the slots at {}: {}", location, e),
+    }
+
     // Attempt to select the desired timeslot
-    if let Some(slot_num) = slot.slot_number {
-        if let Ok(slot_button) = driver.query(By::Id(&format!("slot-{}", slot_num))).first().await {
-            slot_button.wait_until().wait(timeout, polling).displayed().await?;
-            slot_button.click().await?;
-            random_sleep(500, 1000).await;
+    let slot_query = match slot.slot_number {
+        Some(slot_num) => driver.query(by(&selectors::fill(&settings.selectors.slot_button, "slot", &slot_num.to_string()))),
+        None => driver.query(By::XPath(&format!("//*[contains(text(), '{}')]", format_slot_time(&slot.start_time)))),
+    };
+    let Ok(slot_button) = slot_query.first().await else {
+        return Ok(Err(BookingOutcome::Unavailable));
+    };
+    slot_button.wait_until().wait(timeout, polling).displayed().await?;
+    slot_button.click().await?;
+    random_sleep(500, 1000).await;
+
+    Ok(Ok(current_booking))
+}
+
+/// Confirmation pages clicked through before giving up on reaching the receipt.
+const MAX_CONFIRMATION_PAGES: usize = 3;
+
+/// Click through the confirmation pages up to the receipt and read the booking
+/// back from it. New bookings (unlike reschedules) continue to a payment step,
+/// the bot never touches it: it saves the session and a screenshot, tells the
+/// user to pay themselves and stops. Anything other than the receipt or the
+/// payment page at the end is `Unconfirmed`, with the session saved under
+/// `data_dir/unconfirmed` so a person can check the portal.
+async fn confirm_open_slot(held: HeldSlot, settings: &Settings) -> WebDriverResult<BookingReceipt> {
+    if !passes_booking_guard(&held, settings) {
+        held.driver.quit().await?;
+        return Ok(BookingReceipt::new(BookingOutcome::NotAnImprovement, held.location, held.slot.start_time));
+    }
+
+    let HeldSlot { driver, location, slot, .. } = held;
+    let timeout = settings.step_timeout(Step::Navigation);
+    let polling = Duration::from_millis(settings.selenium_element_polling);
+
+    let confirmed = async {
+        for _ in 0..MAX_CONFIRMATION_PAGES {
+            if is_payment_page(&driver, settings).await || is_receipt_page(&driver, settings).await {
+                break;
+            }
+            let Ok(confirm) = driver.query(by(&settings.selectors.confirm_button)).first().await else {
+                break;
+            };
+            confirm.wait_until().wait(timeout, polling).displayed().await?;
+            rate_limit::acquire(settings).await;
+            confirm.click().await?;
+            random_sleep(1000, 2000).await;
         }
-    } else {
-        if let Ok(slot_button) = driver.query(By::XPath(&format!("//*[contains(text(), '{}')]", slot.start_time))).first().await {
-            slot_button.wait_until().wait(timeout, polling).displayed().await?;
-            slot_button.click().await?;
-            random_sleep(500, 1000).await;
+
+        if is_payment_page(&driver, settings).await {
+            let screenshot = halt_for_payment(&driver, settings).await;
+            let receipt = BookingReceipt::new(BookingOutcome::PaymentRequired, location, slot.start_time);
+            return Ok((receipt, Some(screenshot)));
         }
-    }
 
-    if let Ok(confirm) = driver.query(By::Id("confirmButton")).first().await {
-        confirm.wait_until().wait(timeout, polling).displayed().await?;
-        confirm.click().await?;
-        random_sleep(1000, 2000).await;
+        if !is_receipt_page(&driver, settings).await {
+            let SavedSession { url, screenshot_path, .. } = save_session(&driver, settings, "unconfirmed").await;
+            warn!("No receipt after confirming {} at {}, stopped on {}", location, slot.start_time, url);
+            let receipt = BookingReceipt::new(BookingOutcome::Unconfirmed, location, slot.start_time);
+            return Ok((receipt, Some(screenshot_path)));
+        }
+
+        let receipt = read_receipt(&driver, location, &slot, settings).await;
+        let screenshot = save_receipt_screenshot(&driver, settings).await;
+        Ok((receipt, screenshot))
     }
+    .await;
 
     driver.quit().await?;
-    Ok(())
+    let (receipt, screenshot) = confirmed?;
+    record_booking(&receipt, screenshot, settings);
+    Ok(receipt)
+}
+
+/// Screenshot the receipt page into `data_dir/receipts`.
+async fn save_receipt_screenshot(driver: &WebDriver, settings: &Settings) -> Option<std::path::PathBuf> {
+    let dir = settings.data_dir.join("receipts");
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        error!("Failed to create '{}': {}", dir.display(), e);
+        return None;
+    }
+
+    let path = dir.join(format!("{}.png", chrono::Utc::now().format("%Y%m%d-%H%M%S")));
+    match driver.screenshot(&path).await {
+        Ok(()) => Some(path),
+        Err(e) => {
+            error!("Failed to save receipt screenshot: {}", e);
+            None
+        }
+    }
+}
+
+fn record_booking(receipt: &BookingReceipt, screenshot: Option<std::path::PathBuf>, settings: &Settings) {
+    let record = BookingRecord {
+        recorded_at: chrono::Utc::now(),
+        booking_id: settings.booking_id.clone(),
+        test_type: settings.booking_test_type,
+        receipt: receipt.clone(),
+        screenshot: screenshot.map(|path| path.display().to_string()),
+    };
+    super::booking::BookingManager::record_booking(record, settings);
+}
+
+async fn is_receipt_page(driver: &WebDriver, settings: &Settings) -> bool {
+    driver
+        .query(by(&settings.selectors.confirmation_number))
+        .nowait()
+        .exists()
+        .await
+        .unwrap_or(false)
+}
+
+/// Pull the booking number out of the receipt's text, e.g. the digits in
+/// `Booking number: 123456789`.
+pub fn parse_confirmation_number(text: &str) -> Option<String> {
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .find(|word| word.len() >= 5 && word.chars().any(|c| c.is_ascii_digit()))
+        .map(str::to_string)
+}
+
+/// Read the booking number and booked time off the receipt page, once
+/// `is_receipt_page` has matched. Falls back to the slot that was picked when
+/// the page doesn't show a time.
+async fn read_receipt(driver: &WebDriver, location: LocationId, slot: &TimeSlot, settings: &Settings) -> BookingReceipt {
+    let mut receipt = BookingReceipt::new(BookingOutcome::Booked, location, slot.start_time);
+
+    match driver.query(by(&settings.selectors.confirmation_number)).nowait().first().await {
+        Ok(element) => receipt.confirmation_number = element.text().await.ok().and_then(|text| parse_confirmation_number(&text)),
+        Err(_) => warn!("No booking number on the receipt for {} at {}", location, slot.start_time),
+    }
+
+    if let Some(booked) = read_current_booking(driver).await {
+        if booked != slot.start_time {
+            warn!("Portal booked {} at {} instead of the picked {}", location, booked, slot.start_time);
+        }
+        receipt.start_time = booked;
+    }
+
+    receipt
+}
+
+async fn is_payment_page(driver: &WebDriver, settings: &Settings) -> bool {
+    if let Ok(url) = driver.current_url().await {
+        if url.as_str().to_lowercase().contains("payment") {
+            return true;
+        }
+    }
+
+    driver
+        .query(by(&settings.selectors.payment_page))
+        .nowait()
+        .exists()
+        .await
+        .unwrap_or(false)
+}
+
+struct SavedSession {
+    url: String,
+    screenshot_path: std::path::PathBuf,
+    session_path: std::path::PathBuf,
+}
+
+/// Save the page URL, cookies and a screenshot under `data_dir/<kind>` so a
+/// person can pick the session up in their own browser.
+async fn save_session(driver: &WebDriver, settings: &Settings, kind: &str) -> SavedSession {
+    let dir = settings.data_dir.join(kind);
+    let stamp = chrono::Utc::now().format("%Y%m%d-%H%M%S").to_string();
+    let url = driver.current_url().await.map(|url| url.to_string()).unwrap_or_default();
+
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        error!("Failed to create '{}': {}", dir.display(), e);
+    }
+
+    let screenshot_path = dir.join(format!("{}.png", stamp));
+    if let Err(e) = driver.screenshot(&screenshot_path).await {
+        error!("Failed to save {} page screenshot: {}", kind, e);
+    }
+
+    let cookies = driver.get_all_cookies().await.unwrap_or_default();
+    let session_path = dir.join(format!("{}.json", stamp));
+    let session = json!({ "url": url, "cookies": cookies });
+    if let Err(e) = std::fs::write(&session_path, session.to_string()) {
+        error!("Failed to save {} session '{}': {}", kind, session_path.display(), e);
+    }
+
+    SavedSession { url, screenshot_path, session_path }
+}
+
+/// Save the session, then notify the user to finish the payment by hand.
+/// Returns where the payment page screenshot went.
+async fn halt_for_payment(driver: &WebDriver, settings: &Settings) -> std::path::PathBuf {
+    let SavedSession { url, screenshot_path, session_path } = save_session(driver, settings, "payment").await;
+
+    warn!("Payment required, stopped before the payment step. Session saved to '{}'", session_path.display());
+    notifications::dispatch(
+        settings,
+        &Notification::new(
+            NotificationLevel::Warning,
+            "Payment required",
+            format!(
+                "The booking reached a payment step and was stopped before paying. Complete the payment yourself at {}. A screenshot was saved to {}.",
+                url,
+                screenshot_path.display(),
+            ),
+        ),
+    )
+    .await;
+    screenshot_path
 }
-=======
 
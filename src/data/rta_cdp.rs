@@ -0,0 +1,464 @@
+//! Scraping over the Chrome DevTools Protocol instead of WebDriver. Chrome is
+//! launched directly, so there is no chromedriver and none of its `cdc_`
+//! markers, and `STEALTH_SCRIPT` is installed to run before any of the
+//! portal's own scripts on every page rather than patched in after load.
+//! Selected with `scrape_backend: cdp` in builds with the `cdp` feature.
+//!
+//! It walks the same flow as the WebDriver scraper using the same
+//! `selectors`; booking slots still goes through WebDriver.
+
+use std::time::{Duration, Instant};
+
+use chromiumoxide::browser::{Browser, BrowserConfig};
+use chromiumoxide::element::Element;
+use chromiumoxide::page::{Page, ScreenshotParams};
+use futures::StreamExt;
+use serde_json::Value;
+use tokio::task::JoinHandle;
+use tracing::{error, info, info_span, warn, Instrument};
+
+use super::challenge::{self, BlockedWindow};
+use super::checkpoint::{self, ScrapeStage};
+use super::diagnostics;
+use super::location::LocationId;
+use super::maintenance::{self, MaintenanceWindow};
+use super::rate_limit;
+use super::rta::{location_backoff, next_fingerprint, next_proxy, parse_timeslots, random_sleep, test_selectors, visit_order};
+use super::rta_http::page_text;
+use super::selectors;
+use super::shared_booking::{LocationBookings, ScrapeResults, TestType};
+use crate::settings::{LoginMode, Settings, Step};
+use crate::utils::date::sydney_now;
+use crate::utils::task::spawn_named;
+
+/// Installed with `Page.addScriptToEvaluateOnNewDocument`, so it runs before
+/// the portal's scripts on every navigation. Hides the automation flag and
+/// fills in what a headless Chrome leaves empty.
+pub const STEALTH_SCRIPT: &str = r#"
+(() => {
+    Object.defineProperty(Navigator.prototype, 'webdriver', { get: () => undefined });
+    window.chrome = window.chrome || {};
+    window.chrome.runtime = window.chrome.runtime || {};
+    Object.defineProperty(navigator, 'languages', { get: () => ['en-AU', 'en'] });
+    Object.defineProperty(navigator, 'plugins', { get: () => [1, 2, 3, 4, 5] });
+    const originalQuery = window.navigator.permissions && window.navigator.permissions.query;
+    if (originalQuery) {
+        window.navigator.permissions.query = parameters => parameters.name === 'notifications'
+            ? Promise.resolve({ state: Notification.permission })
+            : originalQuery(parameters);
+    }
+    const getParameter = WebGLRenderingContext.prototype.getParameter;
+    WebGLRenderingContext.prototype.getParameter = function (parameter) {
+        if (parameter === 37445) return 'Intel Inc.';
+        if (parameter === 37446) return 'Intel Iris OpenGL Engine';
+        return getParameter.call(this, parameter);
+    };
+})();
+"#;
+
+struct CdpSession<'a> {
+    browser: Browser,
+    handler: JoinHandle<()>,
+    page: Page,
+    settings: &'a Settings,
+}
+
+impl<'a> CdpSession<'a> {
+    async fn launch(settings: &'a Settings) -> Result<CdpSession<'a>, String> {
+        let fingerprint = next_fingerprint(settings);
+        let mut config = BrowserConfig::builder()
+            .window_size(fingerprint.width, fingerprint.height)
+            .args(["--no-sandbox", "--disable-dev-shm-usage", "--disable-blink-features=AutomationControlled"]);
+        if !settings.headless || settings.debug_browser {
+            config = config.with_head();
+        }
+        if let Some(binary) = &settings.chrome_binary {
+            config = config.chrome_executable(binary);
+        }
+        if let Some(proxy) = next_proxy(settings) {
+            info!("Starting CDP session through proxy {}", proxy);
+            config = config.arg(format!("--proxy-server={}", proxy));
+        }
+
+        let (browser, mut events) = Browser::launch(config.build()?)
+            .await
+            .map_err(|e| format!("Failed to launch Chrome: {}", e))?;
+        let handler = spawn_named("cdp-handler", async move {
+            while let Some(event) = events.next().await {
+                if event.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let page = browser.new_page("about:blank").await.map_err(|e| format!("Failed to open a tab: {}", e))?;
+        page.set_user_agent(&fingerprint.user_agent).await.map_err(|e| format!("Failed to set the user agent: {}", e))?;
+        page.evaluate_on_new_document(STEALTH_SCRIPT)
+            .await
+            .map_err(|e| format!("Failed to install the stealth script: {}", e))?;
+
+        Ok(CdpSession { browser, handler, page, settings })
+    }
+
+    async fn close(mut self) {
+        if let Err(e) = self.browser.close().await {
+            warn!("Failed to close Chrome: {}", e);
+        }
+        let _ = self.handler.await;
+    }
+
+    async fn find(&self, selector: &str) -> Option<Element> {
+        if selectors::is_xpath(selector) {
+            self.page.find_xpath(selector).await.ok()
+        } else {
+            self.page.find_element(selector).await.ok()
+        }
+    }
+
+    /// Wait for an element as the WebDriver scraper does, polling every
+    /// `selenium_element_polling` for up to the timeout for `step`.
+    async fn wait_for(&self, selector: &str, step: Step) -> Result<Element, String> {
+        let timeout = self.settings.step_timeout(step);
+        let polling = Duration::from_millis(self.settings.selenium_element_polling);
+        let start = Instant::now();
+        loop {
+            if let Some(element) = self.find(selector).await {
+                return Ok(element);
+            }
+            if start.elapsed() >= timeout {
+                return Err(format!("Timed out waiting for '{}' on {}", selector, self.url().await));
+            }
+            tokio::time::sleep(polling).await;
+        }
+    }
+
+    async fn click(&self, selector: &str, step: Step) -> Result<(), String> {
+        let element = self.wait_for(selector, step).await?;
+        random_sleep(200, 500).await;
+        element.click().await.map_err(|e| format!("Failed to click '{}': {}", selector, e))?;
+        Ok(())
+    }
+
+    async fn type_like_human(&self, selector: &str, text: &str) -> Result<(), String> {
+        let element = self.wait_for(selector, Step::Login).await?;
+        element.click().await.map_err(|e| format!("Failed to focus '{}': {}", selector, e))?;
+        for c in text.chars() {
+            element
+                .type_str(c.to_string())
+                .await
+                .map_err(|e| format!("Failed to type into '{}': {}", selector, e))?;
+            random_sleep(60, 180).await;
+        }
+        Ok(())
+    }
+
+    async fn url(&self) -> String {
+        self.page.url().await.ok().flatten().unwrap_or_default()
+    }
+
+    async fn content(&self) -> String {
+        self.page.content().await.unwrap_or_default()
+    }
+
+    async fn under_maintenance(&self) -> bool {
+        let backoff = chrono::Duration::minutes(self.settings.maintenance_backoff_minutes as i64);
+        match MaintenanceWindow::detect(&page_text(&self.content().await), sydney_now(), backoff) {
+            Some(window) => {
+                maintenance::record(window);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the page a step failed on is a captcha or bot check,
+    /// recording the block so scraping stops and cools down.
+    async fn challenged(&self) -> bool {
+        let cooldown = chrono::Duration::minutes(self.settings.blocked_cooldown_minutes as i64);
+        match BlockedWindow::detect(&self.content().await, sydney_now(), cooldown) {
+            Some(window) => {
+                challenge::record(window);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sign in and get to the location picker. Returns false when the portal
+    /// is under maintenance.
+    async fn sign_in(&self, test_type: TestType) -> Result<bool, String> {
+        let settings = self.settings;
+        let selectors = &settings.selectors;
+        let checkpoint_file = settings.checkpoint_file(test_type);
+
+        checkpoint::enter(&checkpoint_file, ScrapeStage::SignIn);
+        rate_limit::acquire(settings).await;
+        self.page.goto(settings.portal_login_url()).await.map_err(|e| format!("Failed to open the login page: {}", e))?;
+        random_sleep(1000, 2000).await;
+        if self.under_maintenance().await {
+            return Ok(false);
+        }
+
+        let fields = match settings.login_mode {
+            LoginMode::Booking => [
+                (&selectors.booking_id_input, &settings.booking_id),
+                (&selectors.last_name_input, &settings.last_name),
+            ],
+            LoginMode::Licence => [
+                (&selectors.licence_number_input, &settings.licence_number),
+                (&selectors.password_input, &settings.password),
+            ],
+        };
+        for (selector, value) in fields {
+            self.type_like_human(selector, value).await?;
+            random_sleep(300, 800).await;
+        }
+        rate_limit::acquire(settings).await;
+        self.click(&selectors.next_button, Step::Login).await?;
+        random_sleep(2000, 4000).await;
+        if self.under_maintenance().await {
+            return Ok(false);
+        }
+        checkpoint::enter(&checkpoint_file, ScrapeStage::TestSelection);
+
+        if settings.have_booking && test_type == settings.booking_test_type {
+            self.click(&selectors.manage_booking, Step::Navigation).await?;
+            random_sleep(1500, 2500).await;
+            if settings.login_mode == LoginMode::Licence && !settings.booking_id.is_empty() {
+                let entry = selectors::fill(&selectors.booking_entry, "booking_id", &settings.booking_id);
+                if let Some(entry) = self.find(&entry).await {
+                    entry.click().await.map_err(|e| format!("Failed to open booking {}: {}", settings.booking_id, e))?;
+                    random_sleep(1500, 2500).await;
+                }
+            }
+            self.click(&selectors.change_location_button, Step::Navigation).await?;
+            random_sleep(1000, 2000).await;
+        } else {
+            self.click(&selectors.book_test, Step::Navigation).await?;
+            random_sleep(1500, 2500).await;
+
+            let (class_id, test_id) = test_selectors(test_type);
+            self.click(&selectors::fill(&selectors.licence_class, "class", class_id), Step::Navigation).await?;
+            random_sleep(500, 1000).await;
+            self.click(&selectors::fill(&selectors.test_item, "test", test_id), Step::Navigation).await?;
+            random_sleep(500, 1000).await;
+            self.click(&selectors.next_button, Step::Navigation).await?;
+            random_sleep(1500, 2500).await;
+
+            self.click(&selectors.terms_checkbox, Step::Navigation).await?;
+            random_sleep(500, 1000).await;
+            self.click(&selectors.next_button, Step::Navigation).await?;
+            random_sleep(1000, 2000).await;
+        }
+
+        checkpoint::enter(&checkpoint_file, ScrapeStage::Locations);
+        Ok(true)
+    }
+
+    /// The page's `timeslots` data, waiting up to the `timeslots` step
+    /// timeout for the portal's script to define it.
+    async fn timeslots(&self, location: LocationId) -> Result<Value, String> {
+        let timeout = self.settings.step_timeout(Step::Timeslots);
+        let polling = Duration::from_millis(self.settings.selenium_element_polling);
+        let start = Instant::now();
+        loop {
+            let value: Value = self
+                .page
+                .evaluate("typeof timeslots === 'undefined' ? null : timeslots")
+                .await
+                .map_err(|e| format!("Failed to read timeslots for {}: {}", location, e))?
+                .into_value()
+                .map_err(|e| format!("Unreadable timeslots for {}: {}", location, e))?;
+            if !value.is_null() {
+                return Ok(value);
+            }
+            if start.elapsed() >= timeout {
+                return Err(format!("No timeslots on the page for {} after {:?}", location, timeout));
+            }
+            tokio::time::sleep(polling).await;
+        }
+    }
+
+    async fn scrape_location(&self, location: LocationId, test_type: TestType) -> Result<LocationBookings, String> {
+        let selectors = &self.settings.selectors;
+        rate_limit::acquire(self.settings).await;
+        random_sleep(1000, 2000).await;
+
+        self.click(&selectors.location_dropdown, Step::Dropdown).await?;
+        random_sleep(500, 1000).await;
+
+        // Set the value and fire `change` the way picking it by hand does
+        let select = self.wait_for(&selectors.location_select, Step::Dropdown).await?;
+        select
+            .call_js_fn(
+                format!(
+                    "function() {{ this.value = '{}'; this.dispatchEvent(new Event('change', {{ bubbles: true }})); }}",
+                    location
+                ),
+                false,
+            )
+            .await
+            .map_err(|e| format!("Failed to select location {}: {}", location, e))?;
+        random_sleep(2500, 4000).await;
+
+        self.click(&selectors.next_button, Step::Navigation).await?;
+        random_sleep(1000, 2000).await;
+
+        if let Some(button) = self.find(&selectors.earliest_time_button).await {
+            random_sleep(200, 400).await;
+            match button.click().await {
+                Ok(_) => random_sleep(2500, 4500).await,
+                Err(e) => warn!("Failed to click 'Get Earliest Time' for {}: {}. Proceeding anyway.", location, e),
+            }
+        }
+        random_sleep(1000, 2500).await;
+
+        let (slots, next_available_date) = parse_timeslots(&self.timeslots(location).await?, location);
+        info!("Parsed {} slots for {} over CDP. Next available: {:?}", slots.len(), location, next_available_date);
+        let mut bookings = LocationBookings {
+            location,
+            test_type,
+            slots,
+            next_available_date,
+        };
+
+        for week in 1..=self.settings.weeks_ahead {
+            let Some(button) = self.find(&selectors.next_week_button).await else {
+                break;
+            };
+            random_sleep(300, 700).await;
+            rate_limit::acquire(self.settings).await;
+            if button.click().await.is_err() {
+                break;
+            }
+            random_sleep(1500, 3000).await;
+            let (slots, _) = parse_timeslots(&self.timeslots(location).await?, location);
+            let added = bookings.add_slots(slots);
+            info!("Week {} added {} slots for {}.", week + 1, added, location);
+            if added == 0 {
+                break;
+            }
+        }
+
+        random_sleep(800, 1500).await;
+        self.click(&selectors.another_location_link, Step::Navigation).await?;
+        Ok(bookings)
+    }
+
+    /// Save a screenshot and the page source of a failed location under
+    /// `debug/`. Failures are logged, a capture must never break a run.
+    async fn save_failure_capture(&self, location: LocationId) {
+        if !self.settings.failure_captures {
+            return;
+        }
+
+        let dir = self.settings.debug_dir();
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            error!("Failed to create '{}': {}", dir.display(), e);
+            return;
+        }
+        let stem = diagnostics::failure_capture_stem(chrono::Utc::now(), location);
+        if let Err(e) = self.page.save_screenshot(ScreenshotParams::builder().build(), dir.join(format!("{}.png", stem))).await {
+            warn!("Failed to save failure screenshot: {}", e);
+        }
+        if let Err(e) = std::fs::write(dir.join(format!("{}.html", stem)), self.content().await) {
+            warn!("Failed to save failure page source: {}", e);
+        }
+        info!("Saved failure capture for {} to {}", location, dir.display());
+
+        if let Err(e) = diagnostics::prune_failure_captures(&dir, self.settings.failure_captures_max) {
+            warn!("Failed to prune failure captures: {}", e);
+        }
+    }
+}
+
+/// Launch Chrome and sign in. `None` when the portal is under maintenance or
+/// showed a challenge instead.
+async fn open_session(test_type: TestType, settings: &Settings) -> Result<Option<CdpSession<'_>>, String> {
+    let session = CdpSession::launch(settings).await?;
+    match session.sign_in(test_type).await {
+        Ok(true) => Ok(Some(session)),
+        Ok(false) => {
+            session.close().await;
+            Ok(None)
+        }
+        Err(e) => {
+            let blocked = session.challenged().await;
+            session.close().await;
+            if blocked {
+                return Ok(None);
+            }
+            Err(e)
+        }
+    }
+}
+
+/// The CDP counterpart of `rta::scrape_rta_timeslots`, in a single session. A
+/// failed location is retried, up to `location_attempts` times, from a freshly
+/// signed-in browser since the portal's place in the flow is then unknown.
+pub async fn scrape_timeslots(
+    locations: Vec<LocationId>,
+    test_type: TestType,
+    settings: &Settings,
+) -> Result<ScrapeResults, String> {
+    let mut results = ScrapeResults::default();
+    let Some(mut session) = open_session(test_type, settings).await? else {
+        return Ok(results);
+    };
+
+    let locations = visit_order(
+        locations,
+        &settings.priority_locations,
+        settings.shuffle_locations,
+        &mut rand::thread_rng(),
+    );
+    let max_attempts = settings.location_attempts.max(1);
+    let checkpoint_file = settings.checkpoint_file(test_type);
+    'locations: for location in locations {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let span = info_span!("location", location = %location, attempt);
+            span.in_scope(|| info!("Processing location over CDP: {} (attempt {}/{})", location, attempt, max_attempts));
+            let started = Instant::now();
+            let result = session.scrape_location(location, test_type).instrument(span.clone()).await;
+            let duration_ms = started.elapsed().as_millis() as u64;
+            let e = match result {
+                Ok(bookings) => {
+                    span.in_scope(|| info!(slots = bookings.slots.len(), duration_ms, "Scraped location"));
+                    checkpoint::location_done(&checkpoint_file, &bookings);
+                    results.attempted(location, duration_ms, Some(bookings.slots.len()));
+                    results.succeeded(bookings);
+                    break;
+                }
+                Err(e) => e,
+            };
+
+            span.in_scope(|| error!(duration_ms, "Failed processing location {} over CDP: {}", location, e));
+            session.save_failure_capture(location).await;
+            checkpoint::location_failed(&checkpoint_file, location, &e);
+            results.attempted(location, duration_ms, None);
+            results.failed(location, e);
+            if session.challenged().await || attempt >= max_attempts {
+                break 'locations;
+            }
+
+            let delay = location_backoff(attempt, settings.location_retry_delay_ms);
+            info!("Retrying location {} over CDP in {:?}.", location, delay);
+            session.close().await;
+            tokio::time::sleep(delay).await;
+            session = match open_session(test_type, settings).await {
+                Ok(Some(session)) => session,
+                Ok(None) => return Ok(results),
+                Err(e) => {
+                    error!("Failed to sign in again over CDP: {}", e);
+                    return Ok(results);
+                }
+            };
+        }
+        random_sleep(1500, 3000).await;
+    }
+
+    session.close().await;
+    Ok(results)
+}
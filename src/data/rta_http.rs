@@ -0,0 +1,516 @@
+//! Scraping with plain HTTP requests instead of a browser. The portal's pages
+//! are server-rendered forms, so signing in and picking each location is a
+//! matter of submitting them with the session cookies, and the slots arrive
+//! as the same `timeslots` object the browser scraper reads from the page.
+//! Selected with `scrape_backend: http`; no WebDriver is needed.
+//!
+//! Only the "change location of an existing booking" flow is supported. The
+//! "Book test" flow picks the test through script handlers with no form
+//! equivalent, so other test types still need the browser.
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[cfg(feature = "scraper")]
+pub use self::session::scrape_timeslots;
+
+/// How the scraper talks to the portal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrapeBackend {
+    /// Chrome through the WebDriver at `selenium_driver_url`
+    #[default]
+    Browser,
+    /// Plain HTTP requests, existing bookings only
+    Http,
+    /// Chrome driven directly over the DevTools Protocol, with stealth
+    /// patches applied before the portal's scripts. Needs the `cdp` feature
+    Cdp,
+    /// Recorded data from `mock_fixtures_dir`, for development without the
+    /// portal
+    Mock,
+}
+
+/// A form as the portal rendered it, with its default values filled in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Form {
+    pub action: String,
+    pub method: String,
+    pub fields: Vec<(String, String)>,
+}
+
+impl Form {
+    /// Set every field whose name ends with `suffix`. The portal prefixes
+    /// control names with generated portlet ids, the suffix is the stable part.
+    pub fn set(&mut self, suffix: &str, value: &str) -> bool {
+        let mut found = false;
+        for (name, field_value) in &mut self.fields {
+            if name.ends_with(suffix) {
+                *field_value = value.to_string();
+                found = true;
+            }
+        }
+        found
+    }
+}
+
+/// The value of attribute `name` in a tag's attribute text.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut search_from = 0;
+    while let Some(found) = lower[search_from..].find(name) {
+        let start = search_from + found;
+        search_from = start + name.len();
+
+        // Must be a whole attribute name followed by `=`
+        let whole_name = start == 0 || lower.as_bytes()[start - 1].is_ascii_whitespace();
+        let rest = lower[search_from..].trim_start();
+        if !whole_name || !rest.starts_with('=') {
+            continue;
+        }
+
+        // Lowercasing keeps byte offsets, so `rest` locates the value in `tag`
+        let value = tag[tag.len() - rest.len() + 1..].trim_start();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or_default(),
+            _ => value.split(|c: char| c.is_whitespace() || c == '>').next().unwrap_or_default(),
+        };
+        return Some(decode_entities(value));
+    }
+    None
+}
+
+/// Whether a boolean attribute such as `checked` is present.
+fn has_flag(tag: &str, name: &str) -> bool {
+    tag.to_ascii_lowercase()
+        .split(|c: char| c.is_whitespace() || c == '/')
+        .any(|token| token == name || token.starts_with(&format!("{}=", name)))
+}
+
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Byte ranges of the attribute text of every opening `<name ...>` tag.
+fn tag_spans(html: &str, name: &str) -> Vec<(usize, usize)> {
+    let lower = html.to_ascii_lowercase();
+    let open = format!("<{}", name);
+    let mut spans = Vec::new();
+    let mut search_from = 0;
+    while let Some(found) = lower[search_from..].find(&open) {
+        let start = search_from + found + open.len();
+        search_from = start;
+        let boundary = lower[start..].starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/');
+        if let (true, Some(end)) = (boundary, lower[start..].find('>')) {
+            spans.push((start, start + end));
+        }
+    }
+    spans
+}
+
+fn tags<'a>(html: &'a str, name: &str) -> impl Iterator<Item = &'a str> {
+    tag_spans(html, name).into_iter().map(move |(start, end)| &html[start..end])
+}
+
+/// The selected option's value, or the first option's when none is selected.
+fn selected_option(options: &str) -> String {
+    let options: Vec<&str> = tags(options, "option").collect();
+    options
+        .iter()
+        .find(|option| has_flag(option, "selected"))
+        .or(options.first())
+        .and_then(|option| attribute(option, "value"))
+        .unwrap_or_default()
+}
+
+/// The form containing an element with id `control`, or a field whose name
+/// ends with it, resolved against the page's `base` URL.
+pub fn find_form(html: &str, base: &Url, control: &str) -> Option<Form> {
+    let lower = html.to_ascii_lowercase();
+    for (form_start, form_tag_end) in tag_spans(html, "form") {
+        let end = lower[form_tag_end..].find("</form>").map_or(html.len(), |end| form_tag_end + end);
+        let form_tag = &html[form_start..form_tag_end];
+        let body = &html[form_tag_end..end];
+
+        let contains_control = ["input", "select", "button", "a", "div", "span"].iter().any(|name| {
+            tags(body, name).any(|tag| {
+                attribute(tag, "id").as_deref() == Some(control)
+                    || attribute(tag, "name").is_some_and(|name| name.ends_with(control))
+            })
+        });
+        if !contains_control {
+            continue;
+        }
+
+        let action = base.join(&attribute(form_tag, "action").unwrap_or_default()).ok()?.to_string();
+        let method = attribute(form_tag, "method").unwrap_or_else(|| "get".to_string()).to_ascii_lowercase();
+
+        let mut fields = Vec::new();
+        for input in tags(body, "input") {
+            let Some(name) = attribute(input, "name") else { continue };
+            match attribute(input, "type").unwrap_or_default().to_ascii_lowercase().as_str() {
+                "submit" | "button" | "image" | "reset" => continue,
+                "checkbox" | "radio" if !has_flag(input, "checked") => continue,
+                _ => fields.push((name, attribute(input, "value").unwrap_or_default())),
+            }
+        }
+        let body_lower = body.to_ascii_lowercase();
+        for (select_start, select_end) in tag_spans(body, "select") {
+            let Some(name) = attribute(&body[select_start..select_end], "name") else { continue };
+            let options_end = body_lower[select_end..].find("</select>").map_or(body.len(), |end| select_end + end);
+            fields.push((name, selected_option(&body[select_end..options_end])));
+        }
+
+        return Some(Form { action, method, fields });
+    }
+    None
+}
+
+/// The target of the first link with `id`, or whose text is `text`.
+pub fn find_link(html: &str, base: &Url, id: Option<&str>, text: Option<&str>) -> Option<Url> {
+    for (start, end) in tag_spans(html, "a") {
+        let tag = &html[start..end];
+        let link_text = html[end + 1..].split("</").next().unwrap_or_default().trim();
+
+        let matches = id.is_some_and(|id| attribute(tag, "id").as_deref() == Some(id))
+            || text.is_some_and(|text| link_text == text);
+        if matches {
+            return base.join(&attribute(tag, "href")?).ok();
+        }
+    }
+    None
+}
+
+/// The JSON assigned to the page's `timeslots` variable.
+pub fn extract_timeslots(html: &str) -> Option<Value> {
+    let mut search_from = 0;
+    while let Some(found) = html[search_from..].find("timeslots") {
+        let start = search_from + found + "timeslots".len();
+        search_from = start;
+        let rest = html[start..].trim_start();
+        let Some(rest) = rest.strip_prefix('=') else { continue };
+        let rest = rest.trim_start();
+        if !rest.starts_with('{') {
+            continue;
+        }
+        // Parse exactly one value, ignoring the script that follows it
+        if let Some(Ok(value)) = serde_json::Deserializer::from_str(rest).into_iter::<Value>().next() {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// The page's text with tags removed, for maintenance detection.
+pub fn page_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => {
+                in_tag = false;
+                text.push(' ');
+            }
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+#[cfg(feature = "scraper")]
+mod session {
+
+    use reqwest::{Client, Proxy, Url};
+
+    use super::{extract_timeslots, find_form, find_link, page_text, Form};
+    use crate::data::challenge::{self, BlockedWindow};
+    use crate::data::checkpoint;
+    use crate::data::diagnostics::{self, CapturedResponse};
+    use crate::data::location::LocationId;
+    use crate::data::maintenance::{self, MaintenanceWindow};
+    use crate::data::rate_limit;
+    use crate::data::rta::{location_backoff, next_fingerprint, next_proxy, parse_timeslots, random_sleep, visit_order};
+    use crate::data::shared_booking::{LocationBookings, ScrapeResults, TestType};
+    use crate::settings::{LoginMode, Settings};
+    use crate::utils::date::sydney_now;
+    use tracing::{error, info, info_span, warn, Instrument};
+
+    struct HttpSession<'a> {
+        client: Client,
+        settings: &'a Settings,
+        /// The last page fetched, and where it came from
+        page: String,
+        url: Url,
+        captured: Vec<CapturedResponse>,
+    }
+
+    impl<'a> HttpSession<'a> {
+        fn new(settings: &'a Settings) -> Result<Self, String> {
+            let mut client = Client::builder().cookie_store(true).user_agent(next_fingerprint(settings).user_agent);
+            if let Some(proxy) = next_proxy(settings) {
+                info!("Scraping over HTTP through proxy {}", proxy);
+                let proxy = Proxy::all(proxy).map_err(|e| format!("Invalid proxy '{}': {}", proxy, e))?;
+                client = client.proxy(proxy);
+            }
+            let client = client.build().map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+            let url = Url::parse(&settings.portal_login_url()).map_err(|e| format!("Invalid portal_url: {}", e))?;
+            Ok(Self {
+                client,
+                settings,
+                page: String::new(),
+                url,
+                captured: Vec::new(),
+            })
+        }
+
+        async fn load(&mut self, request: reqwest::RequestBuilder, method: &str) -> Result<(), String> {
+            rate_limit::acquire(self.settings).await;
+            let response = request.send().await.map_err(|e| format!("Request failed: {}", e))?;
+            let status = response.status();
+            self.url = response.url().clone();
+            self.page = response.text().await.map_err(|e| format!("Failed to read {}: {}", self.url, e))?;
+
+            if self.settings.diagnostics {
+                self.captured.push(CapturedResponse {
+                    captured_at: chrono::Utc::now(),
+                    method: method.to_string(),
+                    url: self.url.to_string(),
+                    status: status.as_u16(),
+                    body: self.page.chars().take(diagnostics::MAX_BODY_CHARS).collect(),
+                });
+            }
+            if !status.is_success() {
+                return Err(format!("{} responded with {}", self.url, status));
+            }
+            // Same pacing as the browser, the portal sees the same request rhythm
+            random_sleep(1000, 2500).await;
+            Ok(())
+        }
+
+        async fn get(&mut self, url: Url) -> Result<(), String> {
+            let request = self.client.get(url);
+            self.load(request, "GET").await
+        }
+
+        async fn submit(&mut self, form: Form) -> Result<(), String> {
+            let request = if form.method == "post" {
+                self.client.post(&form.action).form(&form.fields)
+            } else {
+                self.client.get(&form.action).query(&form.fields)
+            };
+            self.load(request, &form.method.to_ascii_uppercase()).await
+        }
+
+        fn form(&self, control: &str) -> Result<Form, String> {
+            find_form(&self.page, &self.url, control).ok_or_else(|| format!("No form with '{}' on {}", control, self.url))
+        }
+
+        fn link(&self, id: Option<&str>, text: Option<&str>) -> Result<Url, String> {
+            find_link(&self.page, &self.url, id, text)
+                .ok_or_else(|| format!("No '{}' link on {}", id.or(text).unwrap_or_default(), self.url))
+        }
+
+        fn under_maintenance(&self) -> bool {
+            let backoff = chrono::Duration::minutes(self.settings.maintenance_backoff_minutes as i64);
+            match MaintenanceWindow::detect(&page_text(&self.page), sydney_now(), backoff) {
+                Some(window) => {
+                    maintenance::record(window);
+                    true
+                }
+                None => false,
+            }
+        }
+
+        /// Save the page a location failed on under `debug/`. There is no
+        /// screenshot over HTTP, the page source is all there is.
+        fn save_failure_capture(&self, location: LocationId) {
+            if !self.settings.failure_captures {
+                return;
+            }
+
+            let dir = self.settings.debug_dir();
+            let path = dir.join(format!("{}.html", diagnostics::failure_capture_stem(chrono::Utc::now(), location)));
+            if let Err(e) = std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, &self.page)) {
+                warn!("Failed to save failure page source: {}", e);
+                return;
+            }
+            info!("Saved failure capture for {} to {}", location, path.display());
+
+            if let Err(e) = diagnostics::prune_failure_captures(&dir, self.settings.failure_captures_max) {
+                warn!("Failed to prune failure captures: {}", e);
+            }
+        }
+
+        /// Whether the page a step failed on is a captcha or bot check,
+        /// recording the block so scraping stops and cools down.
+        fn challenged(&self) -> bool {
+            let cooldown = chrono::Duration::minutes(self.settings.blocked_cooldown_minutes as i64);
+            match BlockedWindow::detect(&self.page, sydney_now(), cooldown) {
+                Some(window) => {
+                    challenge::record(window);
+                    true
+                }
+                None => false,
+            }
+        }
+
+        /// Sign in and get to the location picker of the existing booking.
+        /// Returns false when the portal is under maintenance.
+        async fn sign_in(&mut self) -> Result<bool, String> {
+            let login_url = Url::parse(&self.settings.portal_login_url()).map_err(|e| format!("Invalid portal_url: {}", e))?;
+            self.get(login_url).await?;
+            if self.under_maintenance() {
+                return Ok(false);
+            }
+
+            let fields = match self.settings.login_mode {
+                LoginMode::Booking => [("bookingId", &self.settings.booking_id), ("lastName", &self.settings.last_name)],
+                LoginMode::Licence => [("cardNumber", &self.settings.licence_number), ("password", &self.settings.password)],
+            };
+            let mut login = self.form(fields[0].0)?;
+            if !fields.iter().all(|(field, value)| login.set(field, value)) {
+                return Err(format!("Login form on {} has no {} or {} field", self.url, fields[0].0, fields[1].0));
+            }
+            self.submit(login).await?;
+            if self.under_maintenance() {
+                return Ok(false);
+            }
+
+            let manage_booking = self.link(None, Some("Manage booking"))?;
+            self.get(manage_booking).await?;
+            let change_location = self.form("changeLocationButton")?;
+            self.submit(change_location).await?;
+            Ok(true)
+        }
+
+        async fn scrape_location(&mut self, location: LocationId, test_type: TestType) -> Result<LocationBookings, String> {
+            let mut picker = self.form("rms_batLocationSelect2")?;
+            picker.set("rms_batLocationSelect2", &location.to_string());
+            self.submit(picker).await?;
+
+            let timeslots = extract_timeslots(&self.page)
+                .ok_or_else(|| format!("No timeslots on the page for {} ({})", location, self.url))?;
+            let (slots, next_available_date) = parse_timeslots(&timeslots, location);
+            info!("Parsed {} slots for {} over HTTP. Next available: {:?}", slots.len(), location, next_available_date);
+
+            let another_location = self.link(Some("anotherLocationLink"), None)?;
+            self.get(another_location).await?;
+
+            Ok(LocationBookings {
+                location,
+                test_type,
+                slots,
+                next_available_date,
+            })
+        }
+
+        fn save_captured_responses(&mut self) {
+            if self.captured.is_empty() {
+                return;
+            }
+            let responses = std::mem::take(&mut self.captured);
+            if let Err(e) = diagnostics::append(&self.settings.diagnostics_file(), responses, self.settings.diagnostics_max_entries) {
+                warn!("Failed to save captured responses: {}", e);
+            }
+        }
+    }
+
+    /// Sign in with a fresh session. `None` when the portal is under
+    /// maintenance or showed a challenge instead.
+    async fn open_session(settings: &Settings) -> Result<Option<HttpSession<'_>>, String> {
+        let mut session = HttpSession::new(settings)?;
+        let signed_in = session.sign_in().await;
+        session.save_captured_responses();
+        match signed_in {
+            Ok(true) => Ok(Some(session)),
+            Ok(false) => Ok(None),
+            Err(_) if session.challenged() => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The HTTP counterpart of `rta::scrape_rta_timeslots`. A location that fails
+    /// leaves the portal's place in the flow unknown, so it is retried, up to
+    /// `location_attempts` times, from a freshly signed-in session.
+    pub async fn scrape_timeslots(
+        locations: Vec<LocationId>,
+        test_type: TestType,
+        settings: &Settings,
+    ) -> Result<ScrapeResults, String> {
+        let mut results = ScrapeResults::default();
+
+        if !(settings.have_booking && test_type == settings.booking_test_type) {
+            return Err(format!(
+                "The HTTP backend can only scrape the existing booking's test, use scrape_backend: browser for {}",
+                test_type.label()
+            ));
+        }
+
+        let Some(mut session) = open_session(settings).await? else {
+            return Ok(results);
+        };
+
+        let locations = visit_order(
+            locations,
+            &settings.priority_locations,
+            settings.shuffle_locations,
+            &mut rand::thread_rng(),
+        );
+        let max_attempts = settings.location_attempts.max(1);
+        let checkpoint_file = settings.checkpoint_file(test_type);
+        for location in locations {
+            let mut attempt = 0;
+            loop {
+                attempt += 1;
+                let span = info_span!("location", location = %location, attempt);
+                span.in_scope(|| info!("Processing location over HTTP: {} (attempt {}/{})", location, attempt, max_attempts));
+                let started = std::time::Instant::now();
+                let result = session.scrape_location(location, test_type).instrument(span.clone()).await;
+                let duration_ms = started.elapsed().as_millis() as u64;
+                session.save_captured_responses();
+
+                let e = match result {
+                    Ok(bookings) => {
+                        span.in_scope(|| info!(slots = bookings.slots.len(), duration_ms, "Scraped location"));
+                        checkpoint::location_done(&checkpoint_file, &bookings);
+                        results.attempted(location, duration_ms, Some(bookings.slots.len()));
+                        results.succeeded(bookings);
+                        break;
+                    }
+                    Err(e) => e,
+                };
+
+                span.in_scope(|| error!(duration_ms, "Failed processing location {} over HTTP: {}", location, e));
+                session.save_failure_capture(location);
+                checkpoint::location_failed(&checkpoint_file, location, &e);
+                results.attempted(location, duration_ms, None);
+                results.failed(location, e);
+                if session.challenged() || attempt >= max_attempts {
+                    return Ok(results);
+                }
+
+                let delay = location_backoff(attempt, settings.location_retry_delay_ms);
+                info!("Retrying location {} over HTTP in {:?}.", location, delay);
+                tokio::time::sleep(delay).await;
+                session = match open_session(settings).await {
+                    Ok(Some(session)) => session,
+                    Ok(None) => return Ok(results),
+                    Err(e) => {
+                        error!("Failed to sign in again over HTTP: {}", e);
+                        return Ok(results);
+                    }
+                };
+            }
+        }
+
+        Ok(results)
+    }
+}
@@ -0,0 +1,53 @@
+//! The hours of the day background scrapes are allowed to run. The portal
+//! releases next to nothing overnight, so a window such as 06:00-23:00 saves
+//! the runs (and the attention) that would find nothing new.
+
+use chrono::{Duration, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+const TIME_FORMAT: &str = "%H:%M";
+
+/// Daily window, in Sydney time, in which background scrapes run. An `end`
+/// before `start` wraps past midnight; equal times allow the whole day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrapeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl ScrapeWindow {
+    /// Parse `HH:MM` bounds as written in settings.
+    pub fn parse(start: &str, end: &str) -> Result<Self, String> {
+        let parse = |name: &str, value: &str| {
+            NaiveTime::parse_from_str(value.trim(), TIME_FORMAT)
+                .map_err(|_| format!("{} must be a time like \"06:00\", got \"{}\"", name, value))
+        };
+        Ok(ScrapeWindow {
+            start: parse("scrape_window_start", start)?,
+            end: parse("scrape_window_end", end)?,
+        })
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start == self.end {
+            true
+        } else if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// `now` if it's inside the window, otherwise when the window next opens.
+    pub fn next_open(&self, now: NaiveDateTime) -> NaiveDateTime {
+        if self.contains(now.time()) {
+            return now;
+        }
+        let today = now.date().and_time(self.start);
+        if today > now {
+            today
+        } else {
+            today + Duration::days(1)
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! Ranking of candidate slots for auto-find. Each factor is multiplied by a
+//! user-configurable weight; with the defaults only the date counts, which is
+//! the plain earliest-first behaviour.
+
+use chrono::{NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+use super::location::Location;
+use super::shared_booking::TimeSlot;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlotScoring {
+    /// Penalty per day until the slot
+    pub date_weight: f64,
+    /// Penalty per km between `home_latitude`/`home_longitude` and the centre,
+    /// in a straight line. Travel time isn't scored: there is no routing
+    /// source, so distance is the only stand-in for it
+    pub distance_weight: f64,
+    /// Bonus per percentage point of the centre's pass rate
+    pub pass_rate_weight: f64,
+    /// Penalty per hour the slot starts outside the preferred hours
+    pub time_of_day_weight: f64,
+    pub home_latitude: Option<f64>,
+    pub home_longitude: Option<f64>,
+    /// Preferred start hours, inclusive, e.g. 9 to 15
+    pub preferred_start_hour: u32,
+    pub preferred_end_hour: u32,
+}
+
+impl Default for SlotScoring {
+    fn default() -> Self {
+        Self {
+            date_weight: 1.0,
+            distance_weight: 0.0,
+            pass_rate_weight: 0.0,
+            time_of_day_weight: 0.0,
+            home_latitude: None,
+            home_longitude: None,
+            preferred_start_hour: 0,
+            preferred_end_hour: 23,
+        }
+    }
+}
+
+impl SlotScoring {
+    /// Score of `slot` at `location` as seen at `now`. Higher is better.
+    pub fn score(&self, slot: &TimeSlot, location: Option<&Location>, now: NaiveDateTime) -> f64 {
+        let days = (slot.start_time - now).num_minutes() as f64 / (24.0 * 60.0);
+        let mut score = -days * self.date_weight;
+
+        if let Some(location) = location {
+            if let (Some(lat), Some(lng)) = (self.home_latitude, self.home_longitude) {
+                score -= location.distance_from(lat, lng) * self.distance_weight;
+            }
+            score += location.pass_rate * self.pass_rate_weight;
+        }
+
+        let hour = slot.start_time.hour();
+        let hours_outside = if hour < self.preferred_start_hour {
+            self.preferred_start_hour - hour
+        } else if hour > self.preferred_end_hour {
+            hour - self.preferred_end_hour
+        } else {
+            0
+        };
+        score -= hours_outside as f64 * self.time_of_day_weight;
+
+        score
+    }
+}
@@ -0,0 +1,69 @@
+//! Log of scrape runs. Every run appends one line to `scrape_runs.jsonl`
+//! with when it ran, which centres it tried, which of them failed, how many
+//! attempts it took and which backend did the scraping. The admin dashboard
+//! queries it through `RunQuery`.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::shared_booking::{ScrapeRun, TestType};
+
+/// Filter for the run log. Unset fields match every run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunQuery {
+    pub test_type: Option<TestType>,
+    /// Only runs that started at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only runs where at least one centre failed
+    pub failed_only: bool,
+    /// Keep only the newest `limit` matches
+    pub limit: Option<usize>,
+}
+
+impl RunQuery {
+    pub fn matches(&self, run: &ScrapeRun) -> bool {
+        self.test_type.map_or(true, |test_type| run.test_type == test_type)
+            && self.since.map_or(true, |since| run.started_at >= since)
+            && (!self.failed_only || run.has_failures())
+    }
+
+    /// Matching runs, oldest first, trimmed to `limit`.
+    pub fn apply(&self, runs: impl IntoIterator<Item = ScrapeRun>) -> Vec<ScrapeRun> {
+        let mut matching: Vec<ScrapeRun> = runs.into_iter().filter(|run| self.matches(run)).collect();
+        if let Some(limit) = self.limit {
+            let overflow = matching.len().saturating_sub(limit);
+            matching.drain(..overflow);
+        }
+        matching
+    }
+}
+
+#[cfg(feature = "server")]
+pub use self::store::{append, load, prune};
+
+#[cfg(feature = "server")]
+mod store {
+    use std::path::Path;
+
+    use chrono::{DateTime, Utc};
+
+    use super::RunQuery;
+    use crate::data::jsonl;
+    use crate::data::shared_booking::ScrapeRun;
+
+    pub fn append(path: &Path, run: &ScrapeRun) -> Result<(), String> {
+        jsonl::append(path, std::slice::from_ref(run), "scrape run log")
+    }
+
+    /// Runs matching `query`, oldest first. Unparseable lines are skipped.
+    pub fn load(path: &Path, query: &RunQuery) -> Result<Vec<ScrapeRun>, String> {
+        Ok(query.apply(jsonl::read(path, "scrape run log")?))
+    }
+
+    /// Drop runs that started before `since`.
+    pub fn prune(path: &Path, since: DateTime<Utc>) -> Result<(), String> {
+        let runs = load(path, &RunQuery { since: Some(since), ..RunQuery::default() })?;
+        jsonl::write(path, &runs, "scrape run log")
+    }
+}
@@ -0,0 +1,97 @@
+//! Where the browser scraper finds each element of the portal. Kept in
+//! settings under `selectors:` so a markup change can be patched in
+//! `settings.yaml` without rebuilding. Selectors are CSS, or XPath when they
+//! start with `/` or `(`.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Selectors {
+    pub booking_id_input: String,
+    pub last_name_input: String,
+    pub licence_number_input: String,
+    pub password_input: String,
+    /// The "Next" button shared by every step of the flow
+    pub next_button: String,
+    pub manage_booking: String,
+    pub change_location_button: String,
+    /// On the "Manage booking" page, starts cancelling the booking
+    pub cancel_booking_button: String,
+    /// Confirms the cancellation on the page that follows
+    pub confirm_cancel_button: String,
+    /// Only shown once the booking has been cancelled
+    pub cancel_confirmation: String,
+    /// On the "Manage booking" page, the notice naming the fee for a change
+    pub change_fee_notice: String,
+    /// On the "Manage booking" page, only shown once no more changes are allowed
+    pub change_limit_notice: String,
+    /// With `login_mode: licence`, the account's booking to manage,
+    /// `{booking_id}` is replaced by `booking_id`
+    pub booking_entry: String,
+    pub book_test: String,
+    /// Licence class radio button, `{class}` is replaced by e.g. `CAR`
+    pub licence_class: String,
+    /// Test to book, `{test}` is replaced by e.g. `DC`
+    pub test_item: String,
+    pub terms_checkbox: String,
+    /// Opens the location picker; its presence also means a kept session is
+    /// still on the picker
+    pub location_dropdown: String,
+    pub location_select: String,
+    pub earliest_time_button: String,
+    /// Moves the slot calendar on a week, clicked `weeks_ahead` times
+    pub next_week_button: String,
+    pub another_location_link: String,
+    /// A slot by its number, `{slot}` is replaced by the slot's number
+    pub slot_button: String,
+    pub confirm_button: String,
+    /// Any element that only appears on the payment step
+    pub payment_page: String,
+    /// The booking number on the receipt page
+    pub confirmation_number: String,
+}
+
+impl Default for Selectors {
+    fn default() -> Self {
+        Self {
+            booking_id_input: "#widget_bookingId".to_string(),
+            last_name_input: "#widget_lastName".to_string(),
+            licence_number_input: "#widget_cardNumber".to_string(),
+            password_input: "#widget_password".to_string(),
+            next_button: "#nextButton".to_string(),
+            manage_booking: "//*[text()=\"Manage booking\"]".to_string(),
+            change_location_button: "#changeLocationButton".to_string(),
+            cancel_booking_button: "#cancelBookingButton".to_string(),
+            confirm_cancel_button: "#confirmCancelButton".to_string(),
+            cancel_confirmation: "//*[contains(text(), 'has been cancelled') or contains(text(), 'has been canceled')]".to_string(),
+            change_fee_notice: "//*[contains(translate(text(), 'CF', 'cf'), 'change fee')]".to_string(),
+            change_limit_notice: "//*[contains(text(), 'no more changes') or contains(text(), 'maximum number of changes')]".to_string(),
+            booking_entry: "//*[contains(text(), '{booking_id}')]".to_string(),
+            book_test: "//*[text()=\"Book test\"]".to_string(),
+            licence_class: "#{class}".to_string(),
+            test_item: "//fieldset[@id='{test}']/span[contains(@class, 'rms_testItemResult')]".to_string(),
+            terms_checkbox: "#checkTerms".to_string(),
+            location_dropdown: "#rms_batLocLocSel".to_string(),
+            location_select: "#rms_batLocationSelect2".to_string(),
+            earliest_time_button: "#getEarliestTime".to_string(),
+            next_week_button: "//*[@id='nextWeek' or normalize-space(text())='Next week']".to_string(),
+            another_location_link: "#anotherLocationLink".to_string(),
+            slot_button: "#slot-{slot}".to_string(),
+            confirm_button: "#confirmButton".to_string(),
+            payment_page: "//form[contains(@id, 'payment') or contains(@action, 'payment')] | //input[contains(@name, 'cardNumber')] | //h1[contains(text(), 'Payment')] | //h2[contains(text(), 'Payment')]".to_string(),
+            confirmation_number: "#bookingReference, .rms_bookingReference, [id*='receiptNumber']".to_string(),
+        }
+    }
+}
+
+/// Whether a selector is XPath rather than CSS.
+pub fn is_xpath(selector: &str) -> bool {
+    let selector = selector.trim_start();
+    selector.starts_with('/') || selector.starts_with('(')
+}
+
+/// Fill a `{name}` placeholder in a selector template.
+pub fn fill(template: &str, name: &str, value: &str) -> String {
+    template.replace(&format!("{{{}}}", name), value)
+}
@@ -1,12 +1,83 @@
-use std::{cmp::Ordering, hash::{DefaultHasher, Hash, Hasher}};
+use std::{cmp::Ordering, collections::{BTreeMap, HashMap}, hash::{DefaultHasher, Hash, Hasher}};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::announcement::Announcement;
+use crate::data::location::{LocationId, SearchArea};
+use crate::data::strategy::{SlotPreferences, StrategyConfig};
+use crate::data::challenge::BlockedWindow;
+use crate::data::maintenance::MaintenanceWindow;
+use crate::selenium::health::DriverHealth;
+use crate::utils::date::{format_slot_time, slot_time_format, slot_time_format_opt, to_sydney};
+
+/// The RTA test a dataset describes. Each one is scraped and stored separately.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestType {
+    /// Car driving test, the only test scraped before test types existed
+    #[default]
+    #[serde(alias = "driving_test")]
+    Car,
+    Rider,
+    HeavyVehicle,
+    Dkt,
+    Hpt,
+}
+
+impl TestType {
+    pub const ALL: [TestType; 5] = [
+        TestType::Car,
+        TestType::Rider,
+        TestType::HeavyVehicle,
+        TestType::Dkt,
+        TestType::Hpt,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TestType::Car => "car",
+            TestType::Rider => "rider",
+            TestType::HeavyVehicle => "heavy_vehicle",
+            TestType::Dkt => "dkt",
+            TestType::Hpt => "hpt",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TestType::Car => "Car Driving Test",
+            TestType::Rider => "Rider Test",
+            TestType::HeavyVehicle => "Heavy Vehicle Test",
+            TestType::Dkt => "Knowledge Test (DKT)",
+            TestType::Hpt => "Hazard Perception Test (HPT)",
+        }
+    }
+}
+
+impl std::str::FromStr for TestType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "car" | "driving_test" => Ok(TestType::Car),
+            "rider" => Ok(TestType::Rider),
+            "heavy_vehicle" => Ok(TestType::HeavyVehicle),
+            "dkt" => Ok(TestType::Dkt),
+            "hpt" => Ok(TestType::Hpt),
+            other => Err(format!("unknown test type '{}'", other)),
+        }
+    }
+}
+
+// Equal slots are the same start time, but the hash covers every field so the
+// data hash changes when only a slot's availability does
+#[allow(clippy::derived_hash_with_manual_eq)]
 #[derive(Debug, Clone, Serialize, Deserialize, Hash)]
 pub struct TimeSlot {
     pub availability: bool,
     pub slot_number: Option<u32>,
-    #[serde(rename = "startTime")]
-    pub start_time: String,
+    #[serde(rename = "startTime", with = "slot_time_format")]
+    pub start_time: NaiveDateTime,
 }
 
 impl PartialEq for TimeSlot {
@@ -25,43 +96,186 @@ impl PartialOrd for TimeSlot {
 
 impl Ord for TimeSlot {
     fn cmp(&self, other: &Self) -> Ordering {
-        // self
-        let self_parts: Vec<&str> = self.start_time.split(' ').collect();
-        let self_date_parts: Vec<u32> = self_parts[0].split('/').map(|s| s.parse().unwrap()).collect();
-        let self_time_parts: Vec<u32> = self_parts[1].split(':').map(|s| s.parse().unwrap()).collect();
-        
-        // other
-        let other_parts: Vec<&str> = other.start_time.split(' ').collect();
-        let other_date_parts: Vec<u32> = other_parts[0].split('/').map(|s| s.parse().unwrap()).collect();
-        let other_time_parts: Vec<u32> = other_parts[1].split(':').map(|s| s.parse().unwrap()).collect();
-        
-        self_date_parts[2].cmp(&other_date_parts[2])
-            .then(self_date_parts[1].cmp(&other_date_parts[1]))
-            .then(self_date_parts[0].cmp(&other_date_parts[0]))
-            .then(self_time_parts[0].cmp(&other_time_parts[0]))
-            .then(self_time_parts[1].cmp(&other_time_parts[1]))
+        self.start_time.cmp(&other.start_time)
+    }
+}
+
+impl TimeSlot {
+    /// Whether moving a booking at `current` to this slot is strictly earlier
+    /// by at least `min_improvement`.
+    pub fn improves_on(&self, current: NaiveDateTime, min_improvement: chrono::Duration) -> bool {
+        self.start_time < current && current - self.start_time >= min_improvement
+    }
+
+    /// The slot's start as an instant, the portal's times being Sydney time.
+    pub fn starts_at(&self) -> DateTime<chrono_tz::Tz> {
+        to_sydney(self.start_time)
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Hash)]
 pub struct LocationBookings {
-    pub location: String,
+    pub location: LocationId,
+    #[serde(default)]
+    pub test_type: TestType,
     pub slots: Vec<TimeSlot>,
-    pub next_available_date: Option<String>,
+    #[serde(default, with = "slot_time_format_opt")]
+    pub next_available_date: Option<NaiveDateTime>,
 }
 
 impl LocationBookings {
+    /// The earliest bookable slot, as shown in the locations table.
+    pub fn earliest_slot(&self) -> Option<&TimeSlot> {
+        self.slots
+            .iter()
+            .filter(|slot| slot.availability)
+            .min()
+    }
+
+    /// Add the slots of a further calendar week, skipping start times already
+    /// held. Returns how many were new.
+    pub fn add_slots(&mut self, more: Vec<TimeSlot>) -> usize {
+        let before = self.slots.len();
+        for slot in more {
+            if !self.slots.contains(&slot) {
+                self.slots.push(slot);
+            }
+        }
+        self.slots.sort_by_key(|slot| slot.start_time);
+        self.slots.len() - before
+    }
+
     pub fn calculate_hash(&self) -> String {
         let mut hasher = DefaultHasher::new();
         self.hash(&mut hasher);
         hasher.finish().to_string()
     }
+
+    pub fn capacity(&self) -> LocationCapacity {
+        LocationCapacity {
+            location: self.location,
+            total_slots: self.slots.len(),
+            available_slots: self.slots.iter().filter(|slot| slot.availability).count(),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize, Hash)]
+/// Listed and bookable slot counts for one centre. Only meaningful with
+/// `unavailable_slots: keep`, otherwise every stored slot is bookable.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocationCapacity {
+    pub location: LocationId,
+    pub total_slots: usize,
+    pub available_slots: usize,
+}
+
+/// What `clean_results` does with slots the portal lists but can't be booked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnavailableSlots {
+    #[default]
+    Drop,
+    /// Keep them with `availability: false`, for comparing each centre's total
+    /// capacity with what is free. Nothing offers them for booking
+    Keep,
+}
+
+/// Entries `clean_results` dropped from a scrape. Slots with unparsable start
+/// times never get this far, the scraper skips them while parsing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CleanReport {
+    pub unavailable: usize,
+    pub past: usize,
+    pub duplicates: usize,
+}
+
+impl CleanReport {
+    pub fn dropped(&self) -> usize {
+        self.unavailable + self.past + self.duplicates
+    }
+}
+
+/// Prepare scraped results for storage: drop unavailable slots (unless
+/// `unavailable` says to keep them) and slots that started before `now`,
+/// merge repeated locations, drop duplicate (location, start time) pairs, and
+/// sort locations by id and slots by time so the same availability always
+/// hashes to the same ETag.
+pub fn clean_results(
+    results: Vec<LocationBookings>,
+    now: NaiveDateTime,
+    unavailable: UnavailableSlots,
+) -> (Vec<LocationBookings>, CleanReport) {
+    let mut report = CleanReport::default();
+    let mut cleaned: Vec<LocationBookings> = Vec::with_capacity(results.len());
+
+    for mut location in results {
+        if unavailable == UnavailableSlots::Drop {
+            let before = location.slots.len();
+            location.slots.retain(|slot| slot.availability);
+            report.unavailable += before - location.slots.len();
+        }
+
+        let before = location.slots.len();
+        location.slots.retain(|slot| slot.start_time >= now);
+        report.past += before - location.slots.len();
+
+        match cleaned.iter_mut().find(|existing| existing.location == location.location) {
+            Some(existing) => {
+                existing.slots.append(&mut location.slots);
+                existing.next_available_date = match (existing.next_available_date, location.next_available_date) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+            }
+            None => cleaned.push(location),
+        }
+    }
+
+    for location in &mut cleaned {
+        let before = location.slots.len();
+        // Available copies sort first and the sort is stable, so a duplicate
+        // keeps its first available copy
+        location.slots.sort_by(|a, b| a.start_time.cmp(&b.start_time).then(b.availability.cmp(&a.availability)));
+        location.slots.dedup();
+        report.duplicates += before - location.slots.len();
+    }
+    cleaned.sort_by_key(|location| location.location);
+
+    (cleaned, report)
+}
+
+/// Summary row sent to the client for the locations table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocationBookingViewModel {
+    pub location: LocationId,
+    pub earliest_slot: Option<TimeSlot>,
+}
+
+/// Format version of `BookingData` as written to disk. Bump it together with
+/// a new step in `data::migration` whenever the persisted shape changes.
+pub const BOOKING_DATA_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Hash)]
 pub struct BookingData {
     pub results: Vec<LocationBookings>,
-    pub last_updated: Option<String>,
+    pub last_updated: Option<DateTime<Utc>>,
+    /// Data from before versioning has no version and counts as version 1
+    #[serde(default = "unversioned")]
+    pub schema_version: u32,
+}
+
+fn unversioned() -> u32 {
+    1
+}
+
+impl Default for BookingData {
+    fn default() -> Self {
+        Self {
+            results: Vec::new(),
+            last_updated: None,
+            schema_version: BOOKING_DATA_VERSION,
+        }
+    }
 }
 
 impl BookingData {
@@ -70,4 +284,286 @@ impl BookingData {
         self.hash(&mut hasher);
         hasher.finish().to_string()
     }
-}
\ No newline at end of file
+}
+
+/// Result of an attempt to book a slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BookingOutcome {
+    Booked,
+    /// The slot was not offered by the portal any more
+    Unavailable,
+    /// The user declined the held slot or did not answer in time
+    Declined,
+    /// The portal asked for payment; the bot stopped and the user has to finish
+    PaymentRequired,
+    /// The slot is not earlier than the existing booking by the configured margin
+    NotAnImprovement,
+    /// A dry run got the slot to the confirmation page and stopped there
+    DryRun,
+    /// Changing the booking would cost a fee and `abort_on_change_fee` is set
+    ChangeFeeApplies,
+    /// The portal allows no more changes to this booking
+    ChangeLimitReached,
+    /// Confirming stopped short of the receipt page, e.g. on an error page or
+    /// a timed out session, so whether the slot was booked is unknown
+    Unconfirmed,
+}
+
+/// What came of booking a slot, with the portal's booking number once it went through.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BookingReceipt {
+    pub outcome: BookingOutcome,
+    pub location: LocationId,
+    /// The date and time on the portal's receipt, or the slot that was tried
+    pub start_time: NaiveDateTime,
+    pub confirmation_number: Option<String>,
+}
+
+impl BookingReceipt {
+    pub fn new(outcome: BookingOutcome, location: LocationId, start_time: NaiveDateTime) -> Self {
+        Self { outcome, location, start_time, confirmation_number: None }
+    }
+
+    /// Whether the slot is the user's now, even if payment is still due.
+    pub fn secured(&self) -> bool {
+        matches!(self.outcome, BookingOutcome::Booked | BookingOutcome::PaymentRequired)
+    }
+}
+
+/// Where an auto-find job is up to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum JobStatus {
+    /// No matching slot found yet
+    #[default]
+    Searching,
+    /// Handed a slot and trying to book it
+    Booking { location: LocationId, start_time: NaiveDateTime },
+    /// The last slot tried wasn't booked; still searching
+    Missed { location: LocationId, start_time: NaiveDateTime, reason: String },
+    Booked { receipt: BookingReceipt },
+    /// The last search round failed before any slot was looked at
+    Failed { reason: String },
+}
+
+impl JobStatus {
+    pub fn label(&self) -> String {
+        match self {
+            JobStatus::Searching => "Searching".to_string(),
+            JobStatus::Booking { location, start_time } => {
+                format!("Booking {} at {}", format_slot_time(start_time), location)
+            }
+            JobStatus::Missed { location, start_time, reason } => {
+                format!("Missed {} at {}: {}", format_slot_time(start_time), location, reason)
+            }
+            JobStatus::Booked { receipt } => {
+                format!("Booked {} at {}", format_slot_time(&receipt.start_time), receipt.location)
+            }
+            JobStatus::Failed { reason } => format!("Search failed: {}", reason),
+        }
+    }
+}
+
+/// The auto-finder as a user sees it: whether it runs, and their own job.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AutoFindStatus {
+    pub running: bool,
+    pub job: Option<AutoFindJobStatus>,
+    /// When the next search round starts
+    pub next_attempt: Option<DateTime<Utc>>,
+}
+
+/// One booking's auto-find job: what it looks for and how it's going.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutoFindJobStatus {
+    pub locations: Vec<LocationId>,
+    pub area: Option<SearchArea>,
+    pub before: chrono::NaiveDate,
+    pub strategy: StrategyConfig,
+    pub preferences: SlotPreferences,
+    /// Search rounds the job has been part of
+    pub attempts: u32,
+    pub last_attempt: Option<DateTime<Utc>>,
+    pub status: JobStatus,
+}
+
+/// What the "Manage booking" page says about changing the booking.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RebookEligibility {
+    /// Fee the portal would charge for a change, e.g. `$27.00`
+    pub change_fee: Option<String>,
+    pub limit_reached: bool,
+}
+
+impl RebookEligibility {
+    /// Why a change shouldn't go ahead, if it shouldn't.
+    pub fn blocks(&self, abort_on_change_fee: bool) -> Option<BookingOutcome> {
+        if self.limit_reached {
+            Some(BookingOutcome::ChangeLimitReached)
+        } else if abort_on_change_fee && self.change_fee.is_some() {
+            Some(BookingOutcome::ChangeFeeApplies)
+        } else {
+            None
+        }
+    }
+}
+
+/// A slot the auto-finder is holding until the user approves booking it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingApproval {
+    /// Answers the hold, same as the token in the notification links
+    pub token: String,
+    pub location: LocationId,
+    pub start_time: NaiveDateTime,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A booking the bot made, kept in `bookings_history.json` as proof of what it did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BookingRecord {
+    pub recorded_at: DateTime<Utc>,
+    pub booking_id: String,
+    pub test_type: TestType,
+    pub receipt: BookingReceipt,
+    /// Screenshot of the receipt, or of the payment page when payment is still due
+    pub screenshot: Option<String>,
+}
+
+/// One try at scraping a centre.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocationAttempt {
+    pub location: LocationId,
+    pub duration_ms: u64,
+    /// Slots found, `None` if the attempt failed
+    pub slots: Option<usize>,
+}
+
+/// What one scrape pass produced: the centres that returned data, and why
+/// each of the others failed after its retries.
+#[derive(Debug, Clone, Default)]
+pub struct ScrapeResults {
+    pub bookings: HashMap<LocationId, LocationBookings>,
+    pub errors: BTreeMap<LocationId, String>,
+    /// Every try at every centre, retries included, for the metrics
+    pub attempts: Vec<LocationAttempt>,
+}
+
+impl ScrapeResults {
+    pub fn succeeded(&mut self, bookings: LocationBookings) {
+        self.errors.remove(&bookings.location);
+        self.bookings.insert(bookings.location, bookings);
+    }
+
+    pub fn failed(&mut self, location: LocationId, reason: impl Into<String>) {
+        if !self.bookings.contains_key(&location) {
+            self.errors.insert(location, reason.into());
+        }
+    }
+
+    pub fn attempted(&mut self, location: LocationId, duration_ms: u64, slots: Option<usize>) {
+        self.attempts.push(LocationAttempt { location, duration_ms, slots });
+    }
+
+    /// Fold in another pass, e.g. another worker's or a later attempt's. A
+    /// success anywhere clears the centre's error.
+    pub fn merge(&mut self, other: ScrapeResults) {
+        self.attempts.extend(other.attempts);
+        for (location, reason) in other.errors {
+            self.failed(location, reason);
+        }
+        for bookings in other.bookings.into_values() {
+            self.succeeded(bookings);
+        }
+    }
+}
+
+/// One run of the scraper over a test type, kept for the admin dashboard.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScrapeRun {
+    pub test_type: TestType,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub requested: usize,
+    pub scraped: usize,
+    pub error: Option<String>,
+    /// Centres that returned data
+    #[serde(default)]
+    pub succeeded: Vec<LocationId>,
+    /// Centres still missing after the last attempt
+    #[serde(default)]
+    pub failed: Vec<LocationId>,
+    /// Scrape attempts made, the first one included
+    #[serde(default)]
+    pub attempts: u64,
+    /// What did the scraping, e.g. `selenium`
+    #[serde(default)]
+    pub backend: String,
+    /// Centres skipped because they failed too many runs in a row
+    #[serde(default)]
+    pub cooling_down: Vec<LocationId>,
+    /// Last error of each failed centre
+    #[serde(default)]
+    pub failure_reasons: BTreeMap<LocationId, String>,
+}
+
+impl ScrapeRun {
+    pub fn retries(&self) -> u64 {
+        self.attempts.saturating_sub(1)
+    }
+
+    pub fn has_failures(&self) -> bool {
+        !self.failed.is_empty() || self.error.is_some()
+    }
+}
+
+/// Health of the background scraper, surfaced to the UI and API consumers.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScraperStatus {
+    pub background_running: bool,
+    /// Set once `degraded_after_failures` updates in a row produced no data, or
+    /// the updater stopped unexpectedly
+    pub degraded: bool,
+    /// Set when the newest data is older than `stale_after_minutes`
+    #[serde(default)]
+    pub stale: bool,
+    /// Failed updates since the last successful one
+    #[serde(default)]
+    pub consecutive_failures: u32,
+    #[serde(default)]
+    pub stale_after_minutes: Option<u64>,
+    pub reason: Option<String>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_failure: Option<DateTime<Utc>>,
+    /// Whether the WebDriver endpoint answered its last probe, `None` when this
+    /// instance does not scrape
+    pub driver_ready: Option<bool>,
+    /// Details of the last WebDriver check
+    #[serde(default)]
+    pub driver_health: Option<DriverHealth>,
+    /// Operator announcement, if one is set
+    #[serde(default)]
+    pub announcement: Option<Announcement>,
+    /// Set while scraping is paused for the portal's maintenance window
+    #[serde(default)]
+    pub maintenance: Option<MaintenanceWindow>,
+    /// Set while scraping is paused after the portal showed a captcha or
+    /// "unusual activity" page
+    #[serde(default)]
+    pub blocked: Option<BlockedWindow>,
+    /// When the background updater will next run, Sydney time
+    #[serde(default)]
+    pub next_run: Option<NaiveDateTime>,
+}
+
+impl ScraperStatus {
+    /// Readiness for container orchestration: not ready while the scraper is
+    /// waiting on an unreachable WebDriver.
+    pub fn is_ready(&self) -> bool {
+        self.driver_ready != Some(false)
+    }
+
+    /// Whether users should be warned not to trust the data.
+    pub fn needs_warning(&self) -> bool {
+        self.degraded || self.stale
+    }
+}
@@ -0,0 +1,127 @@
+//! Availability figures per location for the locations table: how far away
+//! the earliest slot usually is, how many slots are usually free, and how much
+//! the wait moves from day to day. Worked out from the recorded history, raw
+//! entries and compacted daily aggregates alike, by folding both into days.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::history::{daily_aggregates, DailyAggregate, HistoryEntry};
+use super::location::LocationId;
+use super::shared_booking::TestType;
+
+/// Days needed before volatility means anything.
+const MIN_VOLATILITY_DAYS: usize = 2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AvailabilityStats {
+    pub location: LocationId,
+    /// Days with history
+    pub days: usize,
+    pub observations: u32,
+    /// Mean days from a scrape to the earliest slot it saw
+    pub mean_lead_days: Option<f64>,
+    /// Mean free slots seen by one scrape
+    pub mean_available_slots: f64,
+    /// Standard deviation of the daily mean lead time, in days. High when
+    /// the wait swings a lot, e.g. cancellations that come and go
+    pub lead_volatility_days: Option<f64>,
+}
+
+/// Figures for every location of `test_type` with history, by location.
+/// `daily` covers the days compacted out of `entries`.
+pub fn availability_stats(entries: &[HistoryEntry], daily: &[DailyAggregate], test_type: TestType) -> Vec<AvailabilityStats> {
+    let entries: Vec<HistoryEntry> = entries.iter().filter(|entry| entry.test_type == test_type).cloned().collect();
+    let recent = daily_aggregates(&entries);
+
+    let mut by_location: HashMap<LocationId, Vec<&DailyAggregate>> = HashMap::new();
+    for day in daily.iter().filter(|day| day.test_type == test_type).chain(&recent) {
+        by_location.entry(day.location).or_default().push(day);
+    }
+
+    let mut stats: Vec<AvailabilityStats> = by_location
+        .into_iter()
+        .map(|(location, days)| {
+            let observations: u32 = days.iter().map(|day| day.observations).sum();
+            let leads: Vec<(f64, u32)> = days
+                .iter()
+                .filter_map(|day| day.mean_lead_days.map(|lead| (lead, day.observations)))
+                .collect();
+            let lead_weight: u32 = leads.iter().map(|(_, weight)| weight).sum();
+            let available: f64 = days.iter().map(|day| day.mean_available_slots * day.observations as f64).sum();
+
+            AvailabilityStats {
+                location,
+                days: days.len(),
+                observations,
+                mean_lead_days: (lead_weight > 0)
+                    .then(|| leads.iter().map(|(lead, weight)| lead * *weight as f64).sum::<f64>() / lead_weight as f64),
+                mean_available_slots: if observations == 0 { 0.0 } else { available / observations as f64 },
+                lead_volatility_days: standard_deviation(leads.iter().map(|(lead, _)| *lead)),
+            }
+        })
+        .collect();
+    stats.sort_by_key(|stats| stats.location);
+    stats
+}
+
+fn standard_deviation(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let values: Vec<f64> = values.collect();
+    if values.len() < MIN_VOLATILITY_DAYS {
+        return None;
+    }
+
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / values.len() as f64;
+    Some(variance.sqrt())
+}
+
+#[cfg(feature = "server")]
+pub use self::store::cached_availability_stats;
+
+#[cfg(feature = "server")]
+mod store {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::sync::{Arc, OnceLock, RwLock};
+    use std::time::SystemTime;
+
+    use super::{availability_stats, AvailabilityStats};
+    use crate::data::history;
+    use crate::data::shared_booking::TestType;
+    use crate::settings::Settings;
+
+    type StatsCache = HashMap<TestType, ([SystemTime; 2], Arc<Vec<AvailabilityStats>>)>;
+
+    static STATS_CACHE: OnceLock<RwLock<StatsCache>> = OnceLock::new();
+
+    fn get_stats_cache() -> &'static RwLock<StatsCache> {
+        STATS_CACHE.get_or_init(|| RwLock::new(HashMap::new()))
+    }
+
+    /// `availability_stats` over the history files, recomputed only when
+    /// either has changed since the last call.
+    pub fn cached_availability_stats(settings: &Settings, test_type: TestType) -> Result<Arc<Vec<AvailabilityStats>>, String> {
+        let modified = [settings.history_file(), settings.daily_history_file()].map(|path| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH)
+        });
+
+        if let Some((cached_at, stats)) = get_stats_cache().read().unwrap().get(&test_type) {
+            if *cached_at == modified {
+                return Ok(Arc::clone(stats));
+            }
+        }
+
+        let entries = history::load(&settings.history_file(), settings.history_cutoff())?;
+        let daily = history::load_daily(&settings.daily_history_file())?;
+        let stats = Arc::new(availability_stats(&entries, &daily, test_type));
+        get_stats_cache()
+            .write()
+            .unwrap()
+            .insert(test_type, (modified, Arc::clone(&stats)));
+        Ok(stats)
+    }
+}
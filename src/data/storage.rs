@@ -0,0 +1,640 @@
+//! Persistence of the data directory beyond the local disk, for container
+//! platforms (Fly.io, Cloud Run) whose disk is wiped on every restart.
+//!
+//! The data files are still read and written locally. When `object_storage`
+//! is configured, files missing locally are downloaded at startup and changed
+//! files are uploaded every `sync_minutes`. The object store is any
+//! S3-compatible API: AWS S3, Cloudflare R2, MinIO, or Google Cloud Storage
+//! with HMAC keys.
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ObjectStorageSettings {
+    /// e.g. `https://s3.ap-southeast-2.amazonaws.com` or
+    /// `https://storage.googleapis.com`
+    pub endpoint: String,
+    pub bucket: String,
+    /// Prepended to every object name, e.g. `nsw-drivers-test/`
+    #[serde(default)]
+    pub prefix: String,
+    /// Signing region, `auto` for Google Cloud Storage and R2
+    #[serde(default = "default_region")]
+    pub region: String,
+    /// Supports `${VAR}` references like `booking_id`
+    pub access_key_id: String,
+    /// Supports `${VAR}` references like `booking_id`
+    pub secret_access_key: String,
+    /// How often changed files are uploaded
+    #[serde(default = "default_sync_minutes")]
+    pub sync_minutes: u64,
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_sync_minutes() -> u64 {
+    5
+}
+
+#[cfg(feature = "server")]
+pub use self::store::{
+    flush_mirror, leader_files, mirrored_files, pull_changed, restore_missing, shared, sign_v4, start_mirror,
+    upload_changed, DirectoryStorage, ObjectStorage, Storage, StorageFuture, EMPTY_PAYLOAD_SHA256,
+};
+
+#[cfg(feature = "server")]
+mod store {
+    use std::collections::HashMap;
+    use std::future::Future;
+    use std::path::{Path, PathBuf};
+    use std::pin::Pin;
+    use std::sync::{Arc, OnceLock};
+    use std::time::{Duration, SystemTime};
+
+    use chrono::{DateTime, Utc};
+    use hmac::{Hmac, Mac};
+    use sha2::{Digest, Sha256};
+
+    use super::ObjectStorageSettings;
+    use crate::data::shared_booking::TestType;
+    use crate::settings::Settings;
+    use crate::utils::task::spawn_named;
+
+    pub const EMPTY_PAYLOAD_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    pub type StorageFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+    /// Somewhere files from the data directory can be kept by name.
+    pub trait Storage: Send + Sync {
+        /// The stored contents of `key`, `None` if it was never stored.
+        fn get<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Option<Vec<u8>>>;
+        fn put<'a>(&'a self, key: &'a str, contents: Vec<u8>) -> StorageFuture<'a, ()>;
+        /// The stored contents of `key` with a version tag for `put_if`.
+        fn get_versioned<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Option<(Vec<u8>, String)>>;
+        /// Write `key` only if it is still at version `expected`, or with
+        /// `None` only if it doesn't exist. Returns whether it was written,
+        /// so of two writers racing from the same version only one succeeds.
+        fn put_if<'a>(&'a self, key: &'a str, contents: Vec<u8>, expected: Option<&'a str>) -> StorageFuture<'a, bool>;
+        /// Like `get_versioned`, but `None` when `key` is missing or still at
+        /// version `known`.
+        fn get_if_changed<'a>(&'a self, key: &'a str, known: Option<&'a str>) -> StorageFuture<'a, Option<(Vec<u8>, String)>>;
+    }
+
+    /// How old a `DirectoryStorage` lock file has to be before it counts as
+    /// left behind by a crashed writer.
+    const STALE_LOCK: Duration = Duration::from_secs(30);
+
+    /// Another directory, e.g. a network share mounted next to the data
+    /// directory.
+    pub struct DirectoryStorage {
+        pub dir: PathBuf,
+    }
+
+    impl Storage for DirectoryStorage {
+        fn get<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Option<Vec<u8>>> {
+            Box::pin(async move {
+                match tokio::fs::read(self.dir.join(key)).await {
+                    Ok(contents) => Ok(Some(contents)),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                    Err(e) => Err(format!("Failed to read '{}': {}", key, e)),
+                }
+            })
+        }
+
+        fn put<'a>(&'a self, key: &'a str, contents: Vec<u8>) -> StorageFuture<'a, ()> {
+            Box::pin(async move {
+                tokio::fs::create_dir_all(&self.dir)
+                    .await
+                    .map_err(|e| format!("Failed to create '{}': {}", self.dir.display(), e))?;
+                tokio::fs::write(self.dir.join(key), contents)
+                    .await
+                    .map_err(|e| format!("Failed to write '{}': {}", key, e))
+            })
+        }
+
+        /// Versions are the SHA-256 of the contents.
+        fn get_versioned<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Option<(Vec<u8>, String)>> {
+            Box::pin(async move {
+                Ok(self.get(key).await?.map(|contents| {
+                    let version = hex::encode(Sha256::digest(&contents));
+                    (contents, version)
+                }))
+            })
+        }
+
+        /// Serialised through a `<key>.lock` file created exclusively, so it
+        /// holds across processes sharing the directory.
+        fn put_if<'a>(&'a self, key: &'a str, contents: Vec<u8>, expected: Option<&'a str>) -> StorageFuture<'a, bool> {
+            Box::pin(async move {
+                tokio::fs::create_dir_all(&self.dir)
+                    .await
+                    .map_err(|e| format!("Failed to create '{}': {}", self.dir.display(), e))?;
+
+                let lock = self.dir.join(format!("{}.lock", key));
+                let stale = std::fs::metadata(&lock)
+                    .and_then(|metadata| metadata.modified())
+                    .is_ok_and(|modified| modified.elapsed().is_ok_and(|age| age > STALE_LOCK));
+                if stale {
+                    std::fs::remove_file(&lock).ok();
+                }
+                match std::fs::OpenOptions::new().write(true).create_new(true).open(&lock) {
+                    Ok(_) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Ok(false),
+                    Err(e) => return Err(format!("Failed to lock '{}': {}", key, e)),
+                }
+
+                let written = async {
+                    let current = self.get_versioned(key).await?.map(|(_, version)| version);
+                    if current.as_deref() != expected {
+                        return Ok(false);
+                    }
+                    let temp = self.dir.join(format!("{}.tmp", key));
+                    tokio::fs::write(&temp, contents)
+                        .await
+                        .map_err(|e| format!("Failed to write '{}': {}", key, e))?;
+                    tokio::fs::rename(&temp, self.dir.join(key))
+                        .await
+                        .map_err(|e| format!("Failed to write '{}': {}", key, e))?;
+                    Ok(true)
+                }
+                .await;
+
+                std::fs::remove_file(&lock).ok();
+                written
+            })
+        }
+
+        fn get_if_changed<'a>(&'a self, key: &'a str, known: Option<&'a str>) -> StorageFuture<'a, Option<(Vec<u8>, String)>> {
+            Box::pin(async move {
+                Ok(self
+                    .get_versioned(key)
+                    .await?
+                    .filter(|(_, version)| Some(version.as_str()) != known))
+            })
+        }
+    }
+
+    /// An S3-compatible bucket, addressed path-style and authenticated with
+    /// AWS Signature Version 4.
+    pub struct ObjectStorage {
+        settings: ObjectStorageSettings,
+        client: reqwest::Client,
+    }
+
+    impl ObjectStorage {
+        pub fn new(settings: ObjectStorageSettings) -> Self {
+            Self { settings, client: reqwest::Client::new() }
+        }
+
+        fn request(&self, method: reqwest::Method, key: &str, payload_sha256: &str) -> Result<reqwest::RequestBuilder, String> {
+            let endpoint = reqwest::Url::parse(&self.settings.endpoint)
+                .map_err(|e| format!("Invalid object storage endpoint: {}", e))?;
+            let host = match (endpoint.host_str(), endpoint.port()) {
+                (Some(host), Some(port)) => format!("{}:{}", host, port),
+                (Some(host), None) => host.to_string(),
+                (None, _) => return Err("Object storage endpoint has no host".to_string()),
+            };
+
+            let object = format!("{}{}", self.settings.prefix, key);
+            let path = std::iter::once(self.settings.bucket.as_str())
+                .chain(object.split('/'))
+                .map(|segment| urlencoding::encode(segment).into_owned())
+                .collect::<Vec<_>>()
+                .join("/");
+            let path = format!("/{}", path);
+
+            let now = Utc::now();
+            let authorization = sign_v4(
+                method.as_str(),
+                &host,
+                &path,
+                &[],
+                payload_sha256,
+                &self.settings,
+                now,
+            );
+
+            Ok(self
+                .client
+                .request(method, format!("{}://{}{}", endpoint.scheme(), host, path))
+                .header("x-amz-content-sha256", payload_sha256)
+                .header("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string())
+                .header("authorization", authorization))
+        }
+    }
+
+    impl Storage for ObjectStorage {
+        fn get<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Option<Vec<u8>>> {
+            Box::pin(async move {
+                let response = self
+                    .request(reqwest::Method::GET, key, EMPTY_PAYLOAD_SHA256)?
+                    .send()
+                    .await
+                    .map_err(|e| format!("Failed to fetch '{}': {}", key, e))?;
+
+                match response.status() {
+                    reqwest::StatusCode::NOT_FOUND => Ok(None),
+                    status if status.is_success() => response
+                        .bytes()
+                        .await
+                        .map(|bytes| Some(bytes.to_vec()))
+                        .map_err(|e| format!("Failed to fetch '{}': {}", key, e)),
+                    status => Err(format!("Fetching '{}' returned {}", key, status)),
+                }
+            })
+        }
+
+        fn put<'a>(&'a self, key: &'a str, contents: Vec<u8>) -> StorageFuture<'a, ()> {
+            Box::pin(async move {
+                let payload_sha256 = hex::encode(Sha256::digest(&contents));
+                let response = self
+                    .request(reqwest::Method::PUT, key, &payload_sha256)?
+                    .body(contents)
+                    .send()
+                    .await
+                    .map_err(|e| format!("Failed to upload '{}': {}", key, e))?;
+
+                if response.status().is_success() {
+                    Ok(())
+                } else {
+                    Err(format!("Uploading '{}' returned {}", key, response.status()))
+                }
+            })
+        }
+
+        /// Versions are the object's `ETag`.
+        fn get_versioned<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Option<(Vec<u8>, String)>> {
+            self.get_if_changed(key, None)
+        }
+
+        /// A conditional GET with `If-None-Match`, answered with 304 and no
+        /// body while the object is unchanged.
+        fn get_if_changed<'a>(&'a self, key: &'a str, known: Option<&'a str>) -> StorageFuture<'a, Option<(Vec<u8>, String)>> {
+            Box::pin(async move {
+                let mut request = self.request(reqwest::Method::GET, key, EMPTY_PAYLOAD_SHA256)?;
+                if let Some(etag) = known {
+                    request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+                }
+                let response = request
+                    .send()
+                    .await
+                    .map_err(|e| format!("Failed to fetch '{}': {}", key, e))?;
+
+                match response.status() {
+                    reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::NOT_MODIFIED => Ok(None),
+                    status if status.is_success() => {
+                        let etag = response
+                            .headers()
+                            .get(reqwest::header::ETAG)
+                            .and_then(|value| value.to_str().ok())
+                            .map(str::to_string)
+                            .ok_or_else(|| format!("Fetching '{}' returned no ETag", key))?;
+                        let contents = response
+                            .bytes()
+                            .await
+                            .map_err(|e| format!("Failed to fetch '{}': {}", key, e))?;
+                        Ok(Some((contents.to_vec(), etag)))
+                    }
+                    status => Err(format!("Fetching '{}' returned {}", key, status)),
+                }
+            })
+        }
+
+        /// A conditional PUT with `If-Match` or `If-None-Match: *`, which
+        /// S3, R2 and MinIO answer with 412 when another writer got there
+        /// first.
+        fn put_if<'a>(&'a self, key: &'a str, contents: Vec<u8>, expected: Option<&'a str>) -> StorageFuture<'a, bool> {
+            Box::pin(async move {
+                let payload_sha256 = hex::encode(Sha256::digest(&contents));
+                let request = self.request(reqwest::Method::PUT, key, &payload_sha256)?;
+                let request = match expected {
+                    Some(etag) => request.header(reqwest::header::IF_MATCH, etag),
+                    None => request.header(reqwest::header::IF_NONE_MATCH, "*"),
+                };
+                let response = request
+                    .body(contents)
+                    .send()
+                    .await
+                    .map_err(|e| format!("Failed to upload '{}': {}", key, e))?;
+
+                match response.status() {
+                    reqwest::StatusCode::PRECONDITION_FAILED | reqwest::StatusCode::CONFLICT => Ok(false),
+                    status if status.is_success() => Ok(true),
+                    status => Err(format!("Uploading '{}' returned {}", key, status)),
+                }
+            })
+        }
+    }
+
+    fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+        mac.update(data.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    /// `Authorization` header value for an S3 request. `path` must already be
+    /// URI-encoded and `headers` must not repeat `host`, `x-amz-date` or
+    /// `x-amz-content-sha256`, which are always signed.
+    pub fn sign_v4(
+        method: &str,
+        host: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        payload_sha256: &str,
+        settings: &ObjectStorageSettings,
+        now: DateTime<Utc>,
+    ) -> String {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+
+        let mut signed: Vec<(String, String)> = headers
+            .iter()
+            .map(|(name, value)| (name.to_lowercase(), value.trim().to_string()))
+            .chain([
+                ("host".to_string(), host.to_string()),
+                ("x-amz-content-sha256".to_string(), payload_sha256.to_string()),
+                ("x-amz-date".to_string(), amz_date.clone()),
+            ])
+            .collect();
+        signed.sort();
+
+        let canonical_headers: String = signed.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+        let signed_headers = signed.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(";");
+        let canonical_request =
+            format!("{}\n{}\n\n{}\n{}\n{}", method, path, canonical_headers, signed_headers, payload_sha256);
+
+        let scope = format!("{}/{}/s3/aws4_request", date, settings.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let key = hmac_sha256(format!("AWS4{}", settings.secret_access_key).as_bytes(), &date);
+        let key = hmac_sha256(&key, &settings.region);
+        let key = hmac_sha256(&key, "s3");
+        let key = hmac_sha256(&key, "aws4_request");
+        let signature = hex::encode(hmac_sha256(&key, &string_to_sign));
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{},SignedHeaders={},Signature={}",
+            settings.access_key_id, scope, signed_headers, signature
+        )
+    }
+
+    /// Data directory files kept in storage. Backups stay local.
+    pub fn mirrored_files(settings: &Settings) -> Vec<PathBuf> {
+        TestType::ALL
+            .iter()
+            .map(|&test_type| settings.bookings_file(test_type))
+            .chain([
+                settings.history_file(),
+                settings.daily_history_file(),
+                settings.accounts_file(),
+                settings.notification_rules_file(),
+                settings.announcement_file(),
+                settings.scrape_runs_file(),
+                settings.auto_find_jobs_file(),
+                settings.bookings_history_file(),
+                settings.audit_log_file(),
+                settings.database_file(),
+            ])
+            .collect()
+    }
+
+    /// Mirrored files only the leader writes. With `leader_election` the
+    /// followers pull these instead of uploading their own copies.
+    pub fn leader_files(settings: &Settings) -> Vec<PathBuf> {
+        let replica_files = [
+            settings.accounts_file(),
+            settings.notification_rules_file(),
+            settings.announcement_file(),
+            settings.audit_log_file(),
+        ];
+        mirrored_files(settings)
+            .into_iter()
+            .filter(|path| !replica_files.contains(path))
+            .collect()
+    }
+
+    /// Where replicas share files: the `object_storage` bucket, or else the
+    /// data directory itself, e.g. on a network share.
+    pub fn shared(settings: &Settings) -> Arc<dyn Storage> {
+        match settings.object_storage.clone() {
+            Some(object_storage) => Arc::new(ObjectStorage::new(object_storage)),
+            None => Arc::new(DirectoryStorage { dir: settings.data_dir.clone() }),
+        }
+    }
+
+    fn key_for(path: &Path) -> Option<&str> {
+        path.file_name().and_then(|name| name.to_str())
+    }
+
+    /// Download every mirrored file that doesn't exist locally. Returns how
+    /// many were restored.
+    pub async fn restore_missing(storage: &dyn Storage, settings: &Settings) -> Result<usize, String> {
+        let mut restored = 0;
+        for path in mirrored_files(settings) {
+            let Some(key) = key_for(&path) else { continue };
+            if path.exists() {
+                continue;
+            }
+            if let Some(contents) = storage.get(key).await? {
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent)
+                        .await
+                        .map_err(|e| format!("Failed to create '{}': {}", parent.display(), e))?;
+                }
+                replace_local(&path, contents, settings).await?;
+                restored += 1;
+            }
+        }
+        Ok(restored)
+    }
+
+    /// Download the `leader_files` whose stored version differs from the one
+    /// recorded in `pulled`, replacing the local copies. Returns the files
+    /// that changed.
+    pub async fn pull_changed(
+        storage: &dyn Storage,
+        settings: &Settings,
+        pulled: &mut HashMap<PathBuf, String>,
+    ) -> Result<Vec<PathBuf>, String> {
+        let mut changed = Vec::new();
+        for path in leader_files(settings) {
+            let Some(key) = key_for(&path) else { continue };
+            let Some((contents, version)) = storage.get_if_changed(key, pulled.get(&path).map(String::as_str)).await? else {
+                continue;
+            };
+
+            replace_local(&path, contents, settings).await?;
+            pulled.insert(path.clone(), version);
+            changed.push(path);
+        }
+        Ok(changed)
+    }
+
+    /// `path` with a suffix added to its file name.
+    fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(suffix);
+        PathBuf::from(name)
+    }
+
+    /// Swap the local copy of `path` for `contents`. The database's WAL and
+    /// shared memory files belong to the copy being replaced, so they go too.
+    async fn replace_local(path: &Path, contents: Vec<u8>, settings: &Settings) -> Result<(), String> {
+        let temp_path = with_suffix(path, ".pull");
+        tokio::fs::write(&temp_path, contents)
+            .await
+            .map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+        if path == settings.database_file() {
+            for suffix in ["-wal", "-shm"] {
+                tokio::fs::remove_file(with_suffix(path, suffix)).await.ok();
+            }
+        }
+        tokio::fs::rename(&temp_path, path)
+            .await
+            .map_err(|e| format!("Failed to replace '{}': {}", path.display(), e))
+    }
+
+    /// When `path` last changed. Writes to the database land in its WAL and
+    /// only reach the file itself at a checkpoint, so both count.
+    fn last_modified(path: &Path, settings: &Settings) -> Option<SystemTime> {
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+        let file = modified(path)?;
+        if path != settings.database_file() {
+            return Some(file);
+        }
+        Some(modified(&with_suffix(path, "-wal")).map_or(file, |wal| wal.max(file)))
+    }
+
+    /// What to upload for `path`. The database is copied with `VACUUM INTO`
+    /// rather than read directly, which could catch it mid-write and leave
+    /// out the WAL. `None` for a database in a build without SQLite.
+    async fn upload_contents(path: &Path, settings: &Settings) -> Result<Option<Vec<u8>>, String> {
+        if path != settings.database_file() {
+            return tokio::fs::read(path)
+                .await
+                .map(Some)
+                .map_err(|e| format!("Failed to read '{}': {}", path.display(), e));
+        }
+
+        #[cfg(feature = "sqlite")]
+        {
+            let snapshot_path = with_suffix(path, ".snapshot");
+            let database_path = path.to_path_buf();
+            let snapshot = snapshot_path.clone();
+            tokio::task::spawn_blocking(move || crate::data::database::Database::open(&database_path)?.snapshot(&snapshot))
+                .await
+                .map_err(|e| format!("Database snapshot failed: {}", e))??;
+            let contents = tokio::fs::read(&snapshot_path)
+                .await
+                .map_err(|e| format!("Failed to read '{}': {}", snapshot_path.display(), e))?;
+            tokio::fs::remove_file(&snapshot_path).await.ok();
+            return Ok(Some(contents));
+        }
+
+        #[allow(unreachable_code)]
+        Ok(None)
+    }
+
+    /// Upload mirrored files modified since the last upload recorded in
+    /// `uploaded`. Followers skip the `leader_files`, their copies are only
+    /// ever older. Returns how many were uploaded.
+    pub async fn upload_changed(
+        storage: &dyn Storage,
+        settings: &Settings,
+        uploaded: &mut HashMap<PathBuf, SystemTime>,
+    ) -> Result<usize, String> {
+        let skipped = if crate::data::leader::is_leader() { Vec::new() } else { leader_files(settings) };
+        let mut count = 0;
+        for path in mirrored_files(settings) {
+            if skipped.contains(&path) {
+                continue;
+            }
+            let Some(key) = key_for(&path) else { continue };
+            let Some(modified) = last_modified(&path, settings) else {
+                continue;
+            };
+            if uploaded.get(&path) == Some(&modified) {
+                continue;
+            }
+
+            let Some(contents) = upload_contents(&path, settings).await? else {
+                continue;
+            };
+            storage.put(key, contents).await?;
+            uploaded.insert(path, modified);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// The bucket being mirrored to, with the modification times of the last
+    /// uploads. The lock keeps the final upload from overlapping a periodic
+    /// one.
+    struct Mirror {
+        storage: Arc<dyn Storage>,
+        settings: Settings,
+        uploaded: tokio::sync::Mutex<HashMap<PathBuf, SystemTime>>,
+    }
+
+    static MIRROR: OnceLock<Arc<Mirror>> = OnceLock::new();
+
+    /// With `object_storage` configured, restore missing files now and keep
+    /// uploading changes in the background. Call before loading any data,
+    /// and `flush_mirror` before exiting.
+    pub async fn start_mirror(settings: &Settings) {
+        let Some(object_storage) = settings.object_storage.clone() else {
+            return;
+        };
+        let interval = Duration::from_secs(object_storage.sync_minutes.max(1) * 60);
+        let storage: Arc<dyn Storage> = Arc::new(ObjectStorage::new(object_storage));
+
+        match restore_missing(storage.as_ref(), settings).await {
+            Ok(0) => {}
+            Ok(restored) => println!("INFO: Restored {} data files from object storage", restored),
+            Err(e) => eprintln!("ERROR: Failed to restore data from object storage: {}", e),
+        }
+
+        // Whatever is on disk now came from storage or is about to be
+        // written, so start from the current modification times
+        let uploaded: HashMap<PathBuf, SystemTime> = mirrored_files(settings)
+            .into_iter()
+            .filter_map(|path| Some((path.clone(), last_modified(&path, settings)?)))
+            .collect();
+        let mirror = Arc::new(Mirror { storage, settings: settings.clone(), uploaded: tokio::sync::Mutex::new(uploaded) });
+        if MIRROR.set(Arc::clone(&mirror)).is_err() {
+            return;
+        }
+
+        spawn_named("object-storage-mirror", async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let mut uploaded = mirror.uploaded.lock().await;
+                if let Err(e) = upload_changed(mirror.storage.as_ref(), &mirror.settings, &mut uploaded).await {
+                    eprintln!("ERROR: Failed to upload data to object storage: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Upload whatever changed since the last periodic upload, so a restart
+    /// doesn't lose up to `sync_minutes` of data. Does nothing without
+    /// `object_storage`.
+    pub async fn flush_mirror() {
+        let Some(mirror) = MIRROR.get() else {
+            return;
+        };
+        let mut uploaded = mirror.uploaded.lock().await;
+        match upload_changed(mirror.storage.as_ref(), &mirror.settings, &mut uploaded).await {
+            Ok(0) => {}
+            Ok(count) => println!("INFO: Uploaded {} data files to object storage before exiting", count),
+            Err(e) => eprintln!("ERROR: Failed to upload data to object storage before exiting: {}", e),
+        }
+    }
+}
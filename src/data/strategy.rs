@@ -0,0 +1,169 @@
+//! How auto-find chooses between candidate slots. A strategy filters the
+//! candidates and ranks the rest; jobs pick one through `StrategyConfig`, so
+//! adding a behaviour means adding an impl here rather than touching the
+//! search loops.
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+use super::location::{Location, LocationId};
+use super::scoring::SlotScoring;
+use super::shared_booking::TimeSlot;
+use crate::utils::holidays::is_business_day;
+
+pub trait FindStrategy: Send + Sync {
+    /// Whether the slot is a candidate at all.
+    fn accepts(&self, _location: Option<&Location>, _slot: &TimeSlot) -> bool {
+        true
+    }
+
+    /// Rank of an accepted slot. Higher is better.
+    fn score(&self, location: Option<&Location>, slot: &TimeSlot, now: NaiveDateTime) -> f64;
+}
+
+fn earliest(slot: &TimeSlot, now: NaiveDateTime) -> f64 {
+    -((slot.start_time - now).num_minutes() as f64)
+}
+
+pub struct EarliestAnywhere;
+
+impl FindStrategy for EarliestAnywhere {
+    fn score(&self, _location: Option<&Location>, slot: &TimeSlot, now: NaiveDateTime) -> f64 {
+        earliest(slot, now)
+    }
+}
+
+pub struct EarliestWithinRadius {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub radius_km: f64,
+}
+
+impl FindStrategy for EarliestWithinRadius {
+    fn accepts(&self, location: Option<&Location>, _slot: &TimeSlot) -> bool {
+        location.is_some_and(|location| location.distance_from(self.latitude, self.longitude) <= self.radius_km)
+    }
+
+    fn score(&self, _location: Option<&Location>, slot: &TimeSlot, now: NaiveDateTime) -> f64 {
+        earliest(slot, now)
+    }
+}
+
+pub struct BestScore(pub SlotScoring);
+
+impl FindStrategy for BestScore {
+    fn score(&self, location: Option<&Location>, slot: &TimeSlot, now: NaiveDateTime) -> f64 {
+        self.0.score(slot, location, now)
+    }
+}
+
+pub struct SpecificDaysOnly {
+    pub days: Vec<Weekday>,
+}
+
+impl FindStrategy for SpecificDaysOnly {
+    fn accepts(&self, _location: Option<&Location>, slot: &TimeSlot) -> bool {
+        self.days.contains(&slot.start_time.weekday())
+    }
+
+    fn score(&self, _location: Option<&Location>, slot: &TimeSlot, now: NaiveDateTime) -> f64 {
+        earliest(slot, now)
+    }
+}
+
+/// Weekdays that aren't NSW public holidays.
+pub struct BusinessDaysOnly;
+
+impl FindStrategy for BusinessDaysOnly {
+    fn accepts(&self, _location: Option<&Location>, slot: &TimeSlot) -> bool {
+        is_business_day(slot.start_time.date())
+    }
+
+    fn score(&self, _location: Option<&Location>, slot: &TimeSlot, now: NaiveDateTime) -> f64 {
+        earliest(slot, now)
+    }
+}
+
+/// Centres in the user's order of preference: a slot at a higher-ranked centre
+/// always beats one further down, whatever the dates. Centres not ranked come
+/// last. Within a centre the earliest slot wins.
+pub struct PreferredLocations {
+    pub ranking: Vec<LocationId>,
+}
+
+/// Score gap between neighbouring ranks, more minutes than any booking
+/// horizon so date never outweighs rank.
+const RANK_STEP: f64 = 1e8;
+
+impl FindStrategy for PreferredLocations {
+    fn score(&self, location: Option<&Location>, slot: &TimeSlot, now: NaiveDateTime) -> f64 {
+        let rank = location
+            .and_then(|location| self.ranking.iter().position(|&id| id == location.id))
+            .unwrap_or(self.ranking.len());
+        earliest(slot, now) - rank as f64 * RANK_STEP
+    }
+}
+
+/// When the user can attend at all, checked before any strategy sees a slot.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlotPreferences {
+    /// Earliest acceptable start time of day, inclusive
+    pub earliest_time: Option<NaiveTime>,
+    /// Latest acceptable start time of day, inclusive
+    pub latest_time: Option<NaiveTime>,
+    /// Days a slot may fall on, any day when empty
+    pub weekdays: Vec<Weekday>,
+    /// Dates to skip even when they have a slot, e.g. exam days or holidays
+    pub excluded_dates: Vec<NaiveDate>,
+}
+
+impl SlotPreferences {
+    pub fn accepts(&self, slot: &TimeSlot) -> bool {
+        let time = slot.start_time.time();
+        self.earliest_time.map_or(true, |earliest| time >= earliest)
+            && self.latest_time.map_or(true, |latest| time <= latest)
+            && (self.weekdays.is_empty() || self.weekdays.contains(&slot.start_time.weekday()))
+            && !self.excluded_dates.contains(&slot.start_time.date())
+    }
+}
+
+/// Strategy selection as it appears in `settings.yaml` and auto-find requests.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StrategyConfig {
+    EarliestAnywhere,
+    EarliestWithinRadius { latitude: f64, longitude: f64, radius_km: f64 },
+    /// Rank with the `scoring` weights
+    BestScore,
+    SpecificDaysOnly { days: Vec<Weekday> },
+    /// Earliest slot on a weekday that isn't a public holiday
+    BusinessDaysOnly,
+    /// Earliest slot at the best-ranked centre that has one
+    PreferredLocations { ranking: Vec<LocationId> },
+}
+
+impl Default for StrategyConfig {
+    fn default() -> Self {
+        StrategyConfig::BestScore
+    }
+}
+
+impl StrategyConfig {
+    pub fn build(&self, scoring: &SlotScoring) -> Box<dyn FindStrategy> {
+        match self {
+            StrategyConfig::EarliestAnywhere => Box::new(EarliestAnywhere),
+            StrategyConfig::EarliestWithinRadius { latitude, longitude, radius_km } => {
+                Box::new(EarliestWithinRadius {
+                    latitude: *latitude,
+                    longitude: *longitude,
+                    radius_km: *radius_km,
+                })
+            }
+            StrategyConfig::BestScore => Box::new(BestScore(scoring.clone())),
+            StrategyConfig::SpecificDaysOnly { days } => Box::new(SpecificDaysOnly { days: days.clone() }),
+            StrategyConfig::BusinessDaysOnly => Box::new(BusinessDaysOnly),
+            StrategyConfig::PreferredLocations { ranking } => Box::new(PreferredLocations { ranking: ranking.clone() }),
+        }
+    }
+}
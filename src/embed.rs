@@ -0,0 +1,125 @@
+//! A minimal availability card for one centre, small enough to iframe into
+//! driving-school sites and community wikis. `/embed` renders it as a
+//! standalone HTML page without the app shell; `/api/embed` returns the same
+//! data as JSON.
+
+use axum::extract::Query;
+use axum::http::{header, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::data::booking::BookingManager;
+use crate::data::location::{LocationId, LocationManager};
+use crate::data::shared_booking::TestType;
+use crate::utils::date::{format_datetime, format_slot_time, slot_time_format_opt};
+
+/// Embedders get a short-lived copy; the data changes at most once a scrape.
+const CACHE_CONTROL: &str = "public, max-age=60";
+
+#[derive(Debug, Deserialize)]
+pub struct EmbedQuery {
+    pub location: LocationId,
+    #[serde(default)]
+    pub test_type: TestType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbedCard {
+    pub location: LocationId,
+    pub name: String,
+    pub test_type: TestType,
+    #[serde(with = "slot_time_format_opt")]
+    pub earliest_slot: Option<NaiveDateTime>,
+    pub last_updated: Option<DateTime<Utc>>,
+}
+
+pub fn router<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/embed", get(|Query(query): Query<EmbedQuery>| async move {
+            match card(&query) {
+                Some(card) => ([(header::CACHE_CONTROL, CACHE_CONTROL)], Html(render(&card))).into_response(),
+                None => (StatusCode::NOT_FOUND, "Unknown test centre").into_response(),
+            }
+        }))
+        .route("/api/embed", get(|Query(query): Query<EmbedQuery>| async move {
+            match card(&query) {
+                Some(card) => ([(header::CACHE_CONTROL, CACHE_CONTROL)], Json(card)).into_response(),
+                None => StatusCode::NOT_FOUND.into_response(),
+            }
+        }))
+}
+
+/// The card for the requested centre, or `None` for an unknown centre.
+pub fn card(query: &EmbedQuery) -> Option<EmbedCard> {
+    let location = LocationManager::new().get_by_id(query.location)?;
+    let (data, _) = BookingManager::get_data(query.test_type);
+
+    let earliest_slot = data
+        .results
+        .iter()
+        .find(|bookings| bookings.location == query.location)
+        .and_then(|bookings| bookings.earliest_slot())
+        .map(|slot| slot.start_time);
+
+    Some(EmbedCard {
+        location: query.location,
+        name: location.name,
+        test_type: query.test_type,
+        earliest_slot,
+        last_updated: data.last_updated,
+    })
+}
+
+fn render(card: &EmbedCard) -> String {
+    let earliest = card
+        .earliest_slot
+        .map(|time| format_slot_time(&time))
+        .unwrap_or_else(|| "No slots available".to_string());
+    let updated = card
+        .last_updated
+        .map(|time| format_datetime(&time))
+        .unwrap_or_else(|| "never".to_string());
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{name} - earliest {test_type}</title>
+<style>
+body {{ margin: 0; font-family: system-ui, sans-serif; }}
+.card {{ padding: 12px 16px; border: 1px solid #e5e7eb; border-radius: 8px; }}
+.name {{ font-weight: 600; }}
+.slot {{ font-size: 1.25rem; margin: 4px 0; }}
+.updated {{ color: #6b7280; font-size: 0.75rem; }}
+</style>
+</head>
+<body>
+<div class="card">
+<div class="name">{name} &middot; {test_type}</div>
+<div class="slot">{earliest}</div>
+<div class="updated">Updated {updated}</div>
+</div>
+</body>
+</html>"#,
+        name = escape(&card.name),
+        test_type = card.test_type.label(),
+        earliest = earliest,
+        updated = updated,
+    )
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
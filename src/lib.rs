@@ -1,9 +1,22 @@
 #![allow(warnings)]
+#[cfg(feature = "web")]
 pub mod app;
 pub mod data;
 pub mod utils;
 pub mod settings;
-mod pages;
+pub mod paths;
+pub mod notifications;
+pub mod accounts;
+pub mod announcement;
+pub mod selenium;
+#[cfg(feature = "web")]
+pub mod pages;
+#[cfg(feature = "ssr")]
+pub mod api;
+#[cfg(feature = "ssr")]
+pub mod page_cache;
+#[cfg(feature = "ssr")]
+pub mod embed;
 
 #[cfg(feature = "hydrate")]
 #[wasm_bindgen::prelude::wasm_bindgen]
@@ -1,71 +1,114 @@
 #![recursion_limit = "512"]
-use std::fs::File;
-use std::io::Read;
-use std::path::Path;
-
-use axum::Router;
-use leptos::prelude::*;
-use leptos_axum::{generate_route_list, LeptosRoutes};
-use nsw_closest_display::app::{shell, App};
-use nsw_closest_display::data::booking::BookingManager;
-use nsw_closest_display::data::location::Location;
-use nsw_closest_display::settings::Settings;
-use serde::Deserialize;
-
-// FIX: HACKY
-fn get_location_names() -> Vec<String> {
-    fn parse_locations() -> Vec<Location> {
-        let mut file = File::open("data/centres.json").unwrap();
-        let mut contents = String::new();
-        file.read_to_string(&mut contents).unwrap();
-        serde_json::from_str(&contents).unwrap_or_else(|e| {
-            log::error!("Failed to parse locations: {}", e);
-            Vec::new()
-        })
-    }
-
-    parse_locations()
-        .into_iter()
-        .map(|location| location.id.to_string())
-        .collect()
-}
 
+#[cfg(feature = "ssr")]
 #[tokio::main]
 async fn main() {
+    use axum::Router;
+    use leptos::prelude::*;
+    use leptos_axum::{generate_route_list, LeptosRoutes};
+    use nsw_closest_display::{api, embed, page_cache};
+    use nsw_closest_display::app::{shell, App};
+    use nsw_closest_display::data::booking::BookingManager;
+    use nsw_closest_display::data::location::LocationManager;
+    use nsw_closest_display::data::shared_booking::TestType;
+    use nsw_closest_display::settings::Settings;
+    use tracing::{error, info};
+
+    nsw_closest_display::utils::logging::init();
+
     let conf = get_configuration(None).unwrap();
     let leptos_options = conf.leptos_options;
     let addr = leptos_options.site_addr;
     let routes = generate_route_list(App);
 
-    let data_file_path = "data/bookings.json";
-    match BookingManager::init_from_file(data_file_path) {
-        Ok(_) => println!("BookingManager initialized from file"),
-        Err(e) => println!("Failed to initialize BookingManager from file: {}", e),
+    let mut settings = Settings::load().unwrap();
+
+    #[cfg(feature = "scraper")]
+    if settings.manage_driver && settings.remote_api_url.is_none() {
+        if let Err(e) = nsw_closest_display::selenium::driver_manager::start(&mut settings).await {
+            eprintln!("ERROR: Failed to start chromedriver: {}", e);
+        }
+    }
+
+    nsw_closest_display::data::storage::start_mirror(&settings).await;
+    nsw_closest_display::data::leader::start(&settings).await;
+
+    for test_type in TestType::ALL {
+        match BookingManager::load_data(test_type, &settings) {
+            Ok(_) => info!("BookingManager initialized {} data from {:?} store", test_type.label(), settings.data_store),
+            Err(e) => error!("Failed to initialize BookingManager from {:?} store: {}", settings.data_store, e),
+        }
+    }
+
+    if let Err(e) = nsw_closest_display::notifications::load_rules(&settings.notification_rules_file()) {
+        eprintln!("ERROR: Failed to load notification rules: {}", e);
+    }
+
+    if let Err(e) = BookingManager::load_runs(&settings) {
+        eprintln!("ERROR: Failed to load scrape run log: {}", e);
     }
 
-    let settings = Settings::from_yaml("settings.yaml").unwrap();
+    if let Err(e) = BookingManager::load_booking_history(&settings) {
+        error!("Failed to load booking history: {}", e);
+    }
 
-    let location_id = get_location_names();
+    if let Err(e) = nsw_closest_display::announcement::load(&settings) {
+        eprintln!("ERROR: Failed to load announcement: {}", e);
+    }
 
-    BookingManager::start_background_updates(
-        location_id,
-        data_file_path.to_string(),
-        settings,
-    );
+    if settings.accounts_enabled {
+        if let Err(e) = nsw_closest_display::accounts::load(&settings.accounts_file()) {
+            eprintln!("ERROR: Failed to load accounts: {}", e);
+        }
+        nsw_closest_display::accounts::start_notifications();
+    }
+
+    BookingManager::start_change_notifications(&settings);
+    BookingManager::start_compaction(&settings);
+
+    if let Some(api_url) = settings.remote_api_url.clone() {
+        println!("Mirroring booking data from {}", api_url);
+        BookingManager::start_remote_sync(api_url, settings);
+    } else {
+        #[cfg(feature = "scraper")]
+        {
+            let location_ids = LocationManager::new().scrape_list(&settings);
+            if let Some((_, _, radius_km)) = settings.scrape_area() {
+                println!("INFO: Scraping {} centres within {} km of home", location_ids.len(), radius_km);
+            }
+
+            match BookingManager::restore_auto_find(&settings) {
+                Ok(0) => {}
+                Ok(count) => info!("Restored {} auto-find jobs", count),
+                Err(e) => error!("Failed to restore auto-find jobs: {}", e),
+            }
+            BookingManager::start_booking_queue(&settings);
+            BookingManager::start_submission_intake(&settings);
+            BookingManager::start_background_updates(location_ids, settings);
+        }
+
+        #[cfg(not(feature = "scraper"))]
+        println!("Built without the scraper and no remote_api_url set, serving data from file only");
+    }
 
     let app = Router::new()
+        .merge(api::router())
+        .merge(embed::router())
         .leptos_routes(&leptos_options, routes, {
             let leptos_options = leptos_options.clone();
             move || shell(leptos_options.clone())
         })
         .fallback(leptos_axum::file_and_error_handler(shell))
+        .layer(axum::middleware::from_fn(page_cache::cache_home_page))
         .with_state(leptos_options);
 
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
     println!("listening on http://{}", &addr);
     axum::serve(listener, app.into_make_service())
+        .with_graceful_shutdown(nsw_closest_display::utils::task::shutdown_signal())
         .await
         .unwrap();
+    nsw_closest_display::data::storage::flush_mirror().await;
 }
 
 #[cfg(not(feature = "ssr"))]
@@ -0,0 +1,323 @@
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::data::changes::SlotChanges;
+use crate::data::location::LocationManager;
+use crate::data::shared_booking::{BookingOutcome, BookingReceipt};
+use crate::utils::date::format_slot_time_long;
+
+#[cfg(feature = "server")]
+pub use self::delivery::{add_rule, dispatch, dispatch_to, load_rules, remove_rule, rules};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub level: NotificationLevel,
+    pub title: String,
+    pub message: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl Notification {
+    pub fn new(level: NotificationLevel, title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            level,
+            title: title.into(),
+            message: message.into(),
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    /// Tell the user how booking `booking_id` ended, for outcomes that end
+    /// the attempt. `None` for those that don't, and for payment steps, which
+    /// send their own notification.
+    pub fn booking(booking_id: &str, receipt: &BookingReceipt) -> Option<Self> {
+        let location = LocationManager::new()
+            .get_by_id(receipt.location)
+            .map(|location| location.name)
+            .unwrap_or_else(|| receipt.location.to_string());
+        let when = format_slot_time_long(&receipt.start_time);
+
+        let (level, title, message) = match receipt.outcome {
+            BookingOutcome::Booked => {
+                let mut message = format!("Booking {} is now at {} on {}", booking_id, location, when);
+                if let Some(number) = &receipt.confirmation_number {
+                    message.push_str(&format!(", booking number {}", number));
+                }
+                (NotificationLevel::Info, "Test booked", message)
+            }
+            BookingOutcome::ChangeFeeApplies => (
+                NotificationLevel::Warning,
+                "Booking not changed",
+                format!("Booking {} was left alone because changing it would cost a fee", booking_id),
+            ),
+            BookingOutcome::ChangeLimitReached => (
+                NotificationLevel::Warning,
+                "Booking not changed",
+                format!("Booking {} can't be changed any more, the search for it has stopped", booking_id),
+            ),
+            BookingOutcome::Unconfirmed => (
+                NotificationLevel::Warning,
+                "Booking not confirmed",
+                format!(
+                    "Booking {} may or may not have moved to {} on {}, the portal never showed a receipt. Check it on the Service NSW website",
+                    booking_id, location, when
+                ),
+            ),
+            _ => return None,
+        };
+        Some(Self::new(level, title, message))
+    }
+
+    /// Announce the locations that gained an earlier slot in an update.
+    /// `None` when none did.
+    pub fn earlier_slots(changes: &SlotChanges) -> Option<Self> {
+        let locations = LocationManager::new();
+        let lines: Vec<String> = changes
+            .earlier()
+            .filter_map(|change| {
+                let name = locations
+                    .get_by_id(change.location)
+                    .map(|location| location.name)
+                    .unwrap_or_else(|| change.location.to_string());
+                change.earliest_after.map(|earliest| format!("{}: {}", name, format_slot_time_long(&earliest)))
+            })
+            .collect();
+        if lines.is_empty() {
+            return None;
+        }
+
+        Some(Self::new(
+            NotificationLevel::Info,
+            format!("Earlier {} slots", changes.test_type.label()),
+            lines.join("\n"),
+        ))
+    }
+}
+
+/// A webhook added from the admin dashboard. Unlike `notification_webhooks`,
+/// which receive everything, it only receives notifications at or above
+/// `min_level`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationRule {
+    pub id: u64,
+    pub url: String,
+    pub min_level: NotificationLevel,
+}
+
+impl NotificationRule {
+    pub fn matches(&self, notification: &Notification) -> bool {
+        notification.level >= self.min_level
+    }
+}
+
+/// Check a webhook an account wants to add. Accounts are open to anyone, so
+/// only https to a public host is accepted: the server must not be usable to
+/// reach itself or the network it runs in. Names are resolved and checked
+/// again on every delivery.
+pub fn check_account_webhook(url: &str) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| format!("'{}' is not a valid URL", url))?;
+    if parsed.scheme() != "https" {
+        return Err("Webhooks must use https".into());
+    }
+
+    let host = parsed
+        .host_str()
+        .map(|host| host.trim_start_matches('[').trim_end_matches(']').to_lowercase())
+        .ok_or_else(|| format!("'{}' has no host", url))?;
+    match host.parse::<IpAddr>() {
+        Ok(ip) if !is_public_ip(ip) => Err(format!("{} is not a public address", ip)),
+        Ok(_) => Ok(()),
+        // Single-label names resolve through the server's search domains
+        Err(_) if host == "localhost" || host.ends_with(".localhost") || !host.contains('.') => {
+            Err(format!("{} is not a public host", host))
+        }
+        Err(_) => Ok(()),
+    }
+}
+
+/// Whether `ip` is reachable on the public internet, i.e. not loopback,
+/// private, link-local or otherwise reserved.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (64..128).contains(&b))
+                // Reserved, 240.0.0.0/4
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(mapped));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local, fc00::/7
+                || (first & 0xfe00) == 0xfc00
+                // Link-local, fe80::/10
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+mod delivery {
+    use std::fs;
+    use std::net::SocketAddr;
+    use std::path::Path;
+    use std::sync::{Arc, OnceLock, RwLock};
+
+    use super::{Notification, NotificationLevel, NotificationRule};
+    use crate::settings::Settings;
+    use crate::utils::task::spawn_named;
+
+    static NOTIFICATION_RULES: OnceLock<Arc<RwLock<Vec<NotificationRule>>>> = OnceLock::new();
+
+    fn get_rules() -> &'static Arc<RwLock<Vec<NotificationRule>>> {
+        NOTIFICATION_RULES.get_or_init(|| Arc::new(RwLock::new(Vec::new())))
+    }
+
+    /// Load the rules saved by the admin dashboard. A missing file means no rules.
+    pub fn load_rules(path: &Path) -> Result<(), String> {
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+        let loaded: Vec<NotificationRule> =
+            serde_json::from_str(&contents).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+        *get_rules().write().unwrap() = loaded;
+        Ok(())
+    }
+
+    fn save_rules(path: &Path, rules: &[NotificationRule]) -> Result<(), String> {
+        let contents = serde_json::to_string_pretty(rules).map_err(|e| format!("Failed to serialize rules: {}", e))?;
+        fs::write(path, contents).map_err(|e| format!("Failed to write to file: {}", e))
+    }
+
+    pub fn rules() -> Vec<NotificationRule> {
+        get_rules().read().unwrap().clone()
+    }
+
+    pub fn add_rule(settings: &Settings, url: String, min_level: NotificationLevel) -> Result<NotificationRule, String> {
+        let mut rules = get_rules().write().unwrap();
+        let rule = NotificationRule {
+            id: rules.iter().map(|rule| rule.id).max().unwrap_or(0) + 1,
+            url,
+            min_level,
+        };
+        rules.push(rule.clone());
+        save_rules(&settings.notification_rules_file(), &rules)?;
+        Ok(rule)
+    }
+
+    pub fn remove_rule(settings: &Settings, id: u64) -> Result<(), String> {
+        let mut rules = get_rules().write().unwrap();
+        rules.retain(|rule| rule.id != id);
+        save_rules(&settings.notification_rules_file(), &rules)
+    }
+
+    /// Send a notification to every configured channel. Failures are logged and
+    /// never propagated, a broken webhook must not take the scraper down with it.
+    pub async fn dispatch(settings: &Settings, notification: &Notification) {
+        let urls: Vec<String> = settings
+            .notification_webhooks
+            .iter()
+            .cloned()
+            .chain(
+                rules()
+                    .into_iter()
+                    .filter(|rule| rule.matches(notification))
+                    .map(|rule| rule.url),
+            )
+            .collect();
+
+        if urls.is_empty() {
+            println!("INFO: No notification channels configured, dropping '{}'", notification.title);
+            return;
+        }
+        deliver(&reqwest::Client::new(), urls, notification).await;
+    }
+
+    /// Send a notification to those of `rules` that match it, e.g. an
+    /// account's own webhooks. Those are untrusted, so each is checked again
+    /// and only sent to the public address its host resolved to.
+    pub async fn dispatch_to(rules: &[NotificationRule], notification: &Notification) {
+        for rule in rules.iter().filter(|rule| rule.matches(notification)) {
+            match public_client(&rule.url).await {
+                Ok(client) => deliver(&client, vec![rule.url.clone()], notification).await,
+                Err(e) => eprintln!("WARN: Not sending notification to {}: {}", rule.url, e),
+            }
+        }
+    }
+
+    /// A client for `url` pinned to the address its host resolves to, once
+    /// every address it resolves to is known to be public. Pinning stops the
+    /// name from resolving somewhere else between the check and the request.
+    async fn public_client(url: &str) -> Result<reqwest::Client, String> {
+        super::check_account_webhook(url)?;
+        let parsed = reqwest::Url::parse(url).map_err(|e| e.to_string())?;
+        let host = parsed.host_str().unwrap_or_default().trim_start_matches('[').trim_end_matches(']');
+        let port = parsed.port_or_known_default().unwrap_or(443);
+
+        let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| format!("Failed to resolve {}: {}", host, e))?
+            .collect();
+        if let Some(private) = addresses.iter().find(|address| !super::is_public_ip(address.ip())) {
+            return Err(format!("{} resolves to {}, which is not public", host, private.ip()));
+        }
+        let address = addresses.first().ok_or_else(|| format!("{} does not resolve", host))?;
+
+        reqwest::Client::builder()
+            .resolve(host, *address)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| format!("Failed to build client: {}", e))
+    }
+
+    async fn deliver(client: &reqwest::Client, urls: Vec<String>, notification: &Notification) {
+        let deliveries: Vec<_> = urls
+            .into_iter()
+            .map(|url| {
+                let request = client.post(&url).json(notification);
+                spawn_named("notification-dispatch", async move {
+                    match request.send().await {
+                        Ok(response) if response.status().is_success() => {}
+                        Ok(response) => {
+                            eprintln!("WARN: Notification webhook {} responded with {}", url, response.status());
+                        }
+                        Err(e) => {
+                            eprintln!("WARN: Failed to send notification to {}: {}", url, e);
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for delivery in deliveries {
+            if let Err(e) = delivery.await {
+                eprintln!("WARN: Notification task failed: {}", e);
+            }
+        }
+    }
+}
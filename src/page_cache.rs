@@ -0,0 +1,96 @@
+//! Caches the server-rendered HomePage for anonymous visitors. The page only
+//! changes when the booking data does, so the cached copy is keyed by the
+//! booking etag and dropped as soon as a new etag is seen.
+
+use std::sync::{OnceLock, RwLock};
+
+use axum::body::{to_bytes, Body, Bytes};
+use axum::extract::Request;
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::data::booking::BookingManager;
+use crate::data::shared_booking::TestType;
+
+/// Upper bound on a page we are willing to buffer.
+const MAX_PAGE_BYTES: usize = 4 * 1024 * 1024;
+
+struct CachedPage {
+    etag: String,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+static PAGE_CACHE: OnceLock<RwLock<Option<CachedPage>>> = OnceLock::new();
+
+fn get_page_cache() -> &'static RwLock<Option<CachedPage>> {
+    PAGE_CACHE.get_or_init(|| RwLock::new(None))
+}
+
+fn is_cacheable(request: &Request) -> bool {
+    request.method() == Method::GET
+        && request.uri().path() == "/"
+        && request.uri().query().is_none()
+        && !request.headers().contains_key(header::COOKIE)
+        && !request.headers().contains_key(header::AUTHORIZATION)
+}
+
+/// Middleware serving `/` from the cache when the booking etag is unchanged.
+pub async fn cache_home_page(request: Request, next: Next) -> Response {
+    if !is_cacheable(&request) {
+        return next.run(request).await;
+    }
+
+    let etag = TestType::ALL
+        .iter()
+        .map(|&test_type| BookingManager::get_data(test_type).1)
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if let Ok(cache) = get_page_cache().read() {
+        if let Some(page) = cache.as_ref().filter(|page| page.etag == etag) {
+            return cached_response(page);
+        }
+    }
+
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, MAX_PAGE_BYTES).await {
+        Ok(body) => body,
+        Err(e) => {
+            eprintln!("ERROR: Failed to buffer rendered page: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    if let Ok(mut cache) = get_page_cache().write() {
+        *cache = Some(CachedPage {
+            etag,
+            headers: parts.headers.clone(),
+            body: body.clone(),
+        });
+    }
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn cached_response(page: &CachedPage) -> Response {
+    let mut response = Response::new(Body::from(page.body.clone()));
+    *response.headers_mut() = page.headers.clone();
+    response
+        .headers_mut()
+        .insert("x-page-cache", HeaderValue::from_static("hit"));
+    response
+}
+
+/// Drop the cached page. Called whenever the booking data changes.
+pub fn invalidate() {
+    if let Ok(mut cache) = get_page_cache().write() {
+        *cache = None;
+    }
+}
@@ -0,0 +1,413 @@
+use leptos::prelude::*;
+use leptos::server_fn::error::NoCustomError;
+
+use crate::accounts::{AccountState, Preferences, SavedAddress, SyncedPreferences};
+use crate::data::location::{Location, LocationId};
+use crate::data::shared_booking::TestType;
+use crate::notifications::{NotificationLevel, NotificationRule};
+use crate::pages::home::get_locations;
+use crate::utils::geocoding::geocode_address;
+
+/// The signed-in account for the current request, if any.
+#[cfg(feature = "ssr")]
+pub async fn current_account() -> Result<Option<crate::accounts::Account>, ServerFnError> {
+    let headers: axum::http::HeaderMap = leptos_axum::extract().await?;
+    Ok(crate::accounts::session_from_headers(&headers).and_then(|session| crate::accounts::account_for_session(&session)))
+}
+
+#[cfg(feature = "ssr")]
+fn account_error(message: String) -> ServerFnError {
+    ServerFnError::<NoCustomError>::ServerError(message)
+}
+
+#[cfg(feature = "ssr")]
+fn load_settings() -> Result<crate::settings::Settings, ServerFnError> {
+    crate::settings::Settings::load().map_err(|e| account_error(format!("Failed to load settings: {}", e)))
+}
+
+#[server(GetAccountState)]
+pub async fn get_account_state() -> Result<AccountState, ServerFnError> {
+    if !load_settings()?.accounts_enabled {
+        return Ok(AccountState::default());
+    }
+
+    Ok(AccountState {
+        enabled: true,
+        account: current_account().await?,
+    })
+}
+
+#[server(RequestSignIn)]
+pub async fn request_sign_in(email: String) -> Result<(), ServerFnError> {
+    let settings = load_settings()?;
+    if !settings.accounts_enabled {
+        return Err(account_error("Accounts are not enabled on this server".into()));
+    }
+    crate::accounts::request_login(&settings, &email).await.map_err(account_error)
+}
+
+#[server(SavePreferences)]
+pub async fn save_preferences(preferences: Preferences) -> Result<(), ServerFnError> {
+    let headers: axum::http::HeaderMap = leptos_axum::extract().await?;
+    let session = crate::accounts::session_from_headers(&headers).ok_or_else(|| account_error("Not signed in".into()))?;
+    crate::accounts::save_preferences(&session, preferences).map_err(account_error)?;
+    Ok(())
+}
+
+/// Merge this browser's favourites and filters into the signed-in account.
+/// Returns the merged copy, or `None` for anonymous visitors, who stay
+/// local-only.
+#[server(SyncFavourites)]
+pub async fn sync_favourites(local: SyncedPreferences) -> Result<Option<SyncedPreferences>, ServerFnError> {
+    if !load_settings()?.accounts_enabled {
+        return Ok(None);
+    }
+
+    let headers: axum::http::HeaderMap = leptos_axum::extract().await?;
+    let Some(session) = crate::accounts::session_from_headers(&headers) else {
+        return Ok(None);
+    };
+    match crate::accounts::sync(&session, &local) {
+        Ok(merged) => Ok(Some(merged)),
+        Err(_) => Ok(None),
+    }
+}
+
+#[server(SignOut)]
+pub async fn sign_out() -> Result<(), ServerFnError> {
+    use axum::http::{header, HeaderValue};
+
+    let headers: axum::http::HeaderMap = leptos_axum::extract().await?;
+    if let Some(session) = crate::accounts::session_from_headers(&headers) {
+        crate::accounts::logout(&session).map_err(account_error)?;
+    }
+
+    let response = expect_context::<leptos_axum::ResponseOptions>();
+    response.insert_header(
+        header::SET_COOKIE,
+        HeaderValue::from_static("session=; Path=/; HttpOnly; SameSite=Lax; Max-Age=0"),
+    );
+    Ok(())
+}
+
+fn level_label(level: NotificationLevel) -> &'static str {
+    match level {
+        NotificationLevel::Info => "info",
+        NotificationLevel::Warning => "warning",
+        NotificationLevel::Error => "error",
+    }
+}
+
+const SECTION_TITLE: &str = "text-lg font-semibold text-gray-800 mb-2";
+const INPUT: &str = "px-3 py-1 border border-gray-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500";
+const BUTTON: &str = "px-3 py-1 text-sm text-white rounded-md bg-blue-600 hover:bg-blue-700 focus:outline-none focus:ring-2 focus:ring-blue-500 focus:ring-offset-2 transition-colors";
+
+#[component]
+pub fn AccountPage() -> impl IntoView {
+    let (state, set_state) = create_signal::<Option<AccountState>>(None);
+    let (preferences, set_preferences) = create_signal(Preferences::default());
+    let (message, set_message) = create_signal::<Option<String>>(None);
+    let (email, set_email) = create_signal(String::new());
+    let (address_label, set_address_label) = create_signal(String::new());
+    let (address_query, set_address_query) = create_signal(String::new());
+    let (rule_url, set_rule_url) = create_signal(String::new());
+    let (new_location, set_new_location) = create_signal::<Option<LocationId>>(None);
+    let locations = Resource::new(|| (), |_| get_locations());
+
+    let load = move || {
+        leptos::task::spawn_local(async move {
+            match get_account_state().await {
+                Ok(loaded) => {
+                    if let Some(account) = &loaded.account {
+                        set_preferences(account.preferences.clone());
+                    }
+                    set_state(Some(loaded));
+                }
+                Err(e) => set_message(Some(format!("Error: {e}"))),
+            }
+        });
+    };
+
+    #[cfg(not(feature = "ssr"))]
+    load();
+
+    let send_link = move || {
+        let email = email.get_untracked();
+        leptos::task::spawn_local(async move {
+            match request_sign_in(email).await {
+                Ok(()) => set_message(Some("Check your email for a sign-in link.".into())),
+                Err(e) => set_message(Some(format!("Error: {e}"))),
+            }
+        });
+    };
+
+    let save = move || {
+        let preferences = preferences.get_untracked();
+        leptos::task::spawn_local(async move {
+            match save_preferences(preferences).await {
+                Ok(()) => set_message(Some("Preferences saved".into())),
+                Err(e) => set_message(Some(format!("Error: {e}"))),
+            }
+        });
+    };
+
+    let add_address = move || {
+        let label = address_label.get_untracked();
+        let query = address_query.get_untracked();
+        leptos::task::spawn_local(async move {
+            match geocode_address(&query).await {
+                Ok(result) => {
+                    let label = if label.trim().is_empty() { result.display_name } else { label };
+                    set_preferences.update(|preferences| {
+                        preferences.saved_addresses.push(SavedAddress {
+                            label,
+                            latitude: result.latitude,
+                            longitude: result.longitude,
+                        })
+                    });
+                    set_address_label(String::new());
+                    set_address_query(String::new());
+                }
+                Err(e) => set_message(Some(e.to_string())),
+            }
+        });
+    };
+
+    let location_name = move |id: LocationId| {
+        locations
+            .get()
+            .and_then(|result| result.ok())
+            .and_then(|all| all.into_iter().find(|location| location.id == id))
+            .map(|location| location.name)
+            .unwrap_or_else(|| id.to_string())
+    };
+
+    view! {
+        <div class="max-w-4xl mx-auto p-4">
+            <div class="flex justify-between items-center mb-4">
+                <h2 class="text-2xl font-bold text-gray-800">Account</h2>
+                <a href="/" class="text-sm text-blue-600 hover:underline">"Back to availability"</a>
+            </div>
+
+            {move || message.get().map(|message| view! {
+                <p class="mb-4 text-sm text-gray-700">{message}</p>
+            })}
+
+            {move || match state.get() {
+                None => view! { <p class="text-sm text-gray-500">"Loading..."</p> }.into_any(),
+                Some(AccountState { enabled: false, .. }) => view! {
+                    <p class="text-sm text-gray-500">"Accounts are not enabled on this server."</p>
+                }.into_any(),
+                Some(AccountState { account: None, .. }) => view! {
+                    <div class="flex gap-2">
+                        <input
+                            type="email"
+                            class=format!("{INPUT} flex-grow")
+                            placeholder="you@example.com"
+                            prop:value={email}
+                            on:input=move |ev| set_email(event_target_value(&ev))
+                        />
+                        <button class=BUTTON on:click=move |_| send_link()>"Email me a sign-in link"</button>
+                    </div>
+                }.into_any(),
+                Some(AccountState { account: Some(account), .. }) => view! {
+                    <p class="mb-4 text-sm text-gray-600">"Signed in as " {account.email}</p>
+
+                    <section class="mb-6">
+                        <h3 class=SECTION_TITLE>"Filters"</h3>
+                        <div class="flex flex-wrap gap-4 items-center text-sm">
+                            <select
+                                class=INPUT
+                                on:change=move |ev| {
+                                    if let Ok(selected) = event_target_value(&ev).parse::<TestType>() {
+                                        set_preferences.update(|preferences| preferences.filters.test_type = selected);
+                                    }
+                                }
+                            >
+                                {TestType::ALL.into_iter().map(|option| view! {
+                                    <option value=option.as_str() selected=move || preferences.get().filters.test_type == option>
+                                        {option.label()}
+                                    </option>
+                                }).collect::<Vec<_>>()}
+                            </select>
+                            <label>
+                                "Within "
+                                <input
+                                    type="number"
+                                    min="0"
+                                    class=format!("{INPUT} w-24")
+                                    prop:value=move || preferences.get().filters.max_distance_km.map(|km| km.to_string()).unwrap_or_default()
+                                    on:change=move |ev| {
+                                        let km = event_target_value(&ev).parse::<f64>().ok().filter(|km| *km > 0.0);
+                                        set_preferences.update(|preferences| preferences.filters.max_distance_km = km);
+                                    }
+                                />
+                                " km"
+                            </label>
+                            <label>
+                                <input
+                                    type="checkbox"
+                                    class="mr-1"
+                                    prop:checked=move || preferences.get().filters.hide_near_holidays
+                                    on:change=move |ev| {
+                                        let hide = event_target_checked(&ev);
+                                        set_preferences.update(|preferences| preferences.filters.hide_near_holidays = hide);
+                                    }
+                                />
+                                "Hide slots on or next to public holidays"
+                            </label>
+                        </div>
+                    </section>
+
+                    <section class="mb-6">
+                        <h3 class=SECTION_TITLE>"Watched centres"</h3>
+                        <ul class="text-sm text-gray-700 space-y-1 mb-2">
+                            {move || preferences.get().watched_locations.into_iter().map(|id| view! {
+                                <li class="flex gap-2">
+                                    <span>{location_name(id)}</span>
+                                    <button
+                                        class="text-red-600 hover:underline"
+                                        on:click=move |_| set_preferences.update(|preferences| preferences.watched_locations.retain(|watched| *watched != id))
+                                    >
+                                        "Remove"
+                                    </button>
+                                </li>
+                            }).collect::<Vec<_>>()}
+                        </ul>
+                        <div class="flex gap-2">
+                            <select
+                                class=INPUT
+                                on:change=move |ev| set_new_location(event_target_value(&ev).parse::<LocationId>().ok())
+                            >
+                                <option value="">"Choose a centre"</option>
+                                <Suspense>
+                                    {move || locations.get().and_then(|result| result.ok()).map(|all: Vec<Location>| {
+                                        all.into_iter().map(|location| view! {
+                                            <option value=location.id.to_string()>{location.name}</option>
+                                        }).collect::<Vec<_>>()
+                                    })}
+                                </Suspense>
+                            </select>
+                            <button
+                                class=BUTTON
+                                on:click=move |_| {
+                                    if let Some(id) = new_location.get_untracked() {
+                                        set_preferences.update(|preferences| {
+                                            if !preferences.watched_locations.contains(&id) {
+                                                preferences.watched_locations.push(id);
+                                            }
+                                        });
+                                    }
+                                }
+                            >
+                                "Watch"
+                            </button>
+                        </div>
+                    </section>
+
+                    <section class="mb-6">
+                        <h3 class=SECTION_TITLE>"Saved addresses"</h3>
+                        <ul class="text-sm text-gray-700 space-y-1 mb-2">
+                            {move || preferences.get().saved_addresses.into_iter().enumerate().map(|(i, address)| view! {
+                                <li class="flex gap-2">
+                                    <span>{address.label}</span>
+                                    <button
+                                        class="text-red-600 hover:underline"
+                                        on:click=move |_| set_preferences.update(|preferences| { preferences.saved_addresses.remove(i); })
+                                    >
+                                        "Remove"
+                                    </button>
+                                </li>
+                            }).collect::<Vec<_>>()}
+                        </ul>
+                        <div class="flex flex-wrap gap-2">
+                            <input
+                                type="text"
+                                class=INPUT
+                                placeholder="Label, e.g. Home"
+                                prop:value={address_label}
+                                on:input=move |ev| set_address_label(event_target_value(&ev))
+                            />
+                            <input
+                                type="text"
+                                class=format!("{INPUT} flex-grow")
+                                placeholder="Address, suburb or postcode"
+                                prop:value={address_query}
+                                on:input=move |ev| set_address_query(event_target_value(&ev))
+                            />
+                            <button class=BUTTON on:click=move |_| add_address()>"Add"</button>
+                        </div>
+                    </section>
+
+                    <section class="mb-6">
+                        <h3 class=SECTION_TITLE>"Notification webhooks"</h3>
+                        <p class="text-sm text-gray-600 mb-2">"Earlier slots at your watched centres are posted to these."</p>
+                        <ul class="text-sm text-gray-700 space-y-1 mb-2">
+                            {move || preferences.get().notification_rules.into_iter().map(|rule| {
+                                let rule_id = rule.id;
+                                view! {
+                                    <li class="flex gap-2">
+                                        <span class="font-mono break-all">{rule.url}</span>
+                                        <span class="text-gray-500">{format!("({}+)", level_label(rule.min_level))}</span>
+                                        <button
+                                            class="text-red-600 hover:underline"
+                                            on:click=move |_| set_preferences.update(|preferences| preferences.notification_rules.retain(|rule| rule.id != rule_id))
+                                        >
+                                            "Remove"
+                                        </button>
+                                    </li>
+                                }
+                            }).collect::<Vec<_>>()}
+                        </ul>
+                        <div class="flex gap-2">
+                            <input
+                                type="url"
+                                class=format!("{INPUT} flex-grow")
+                                placeholder="https://example.com/webhook"
+                                prop:value={rule_url}
+                                on:input=move |ev| set_rule_url(event_target_value(&ev))
+                            />
+                            <button
+                                class=BUTTON
+                                on:click=move |_| {
+                                    let url = rule_url.get_untracked().trim().to_string();
+                                    if url.is_empty() {
+                                        return;
+                                    }
+                                    set_preferences.update(|preferences| {
+                                        let id = preferences.notification_rules.iter().map(|rule| rule.id).max().unwrap_or(0) + 1;
+                                        preferences.notification_rules.push(NotificationRule {
+                                            id,
+                                            url,
+                                            min_level: NotificationLevel::Info,
+                                        });
+                                    });
+                                    set_rule_url(String::new());
+                                }
+                            >
+                                "Add"
+                            </button>
+                        </div>
+                    </section>
+
+                    <div class="flex gap-4">
+                        <button class=BUTTON on:click=move |_| save()>"Save preferences"</button>
+                        <button
+                            class="text-sm text-gray-600 hover:underline"
+                            on:click=move |_| {
+                                leptos::task::spawn_local(async move {
+                                    let _ = sign_out().await;
+                                    set_state(None);
+                                    set_preferences(Preferences::default());
+                                    load();
+                                });
+                            }
+                        >
+                            "Sign out"
+                        </button>
+                    </div>
+                }.into_any(),
+            }}
+        </div>
+    }
+}
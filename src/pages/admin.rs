@@ -0,0 +1,751 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use leptos::prelude::*;
+use leptos::server_fn::error::NoCustomError;
+use serde::{Deserialize, Serialize};
+
+use crate::data::audit::AuditEntry;
+use crate::data::backup::BackupInfo;
+use crate::data::location::LocationId;
+use crate::data::metrics::LocationMetrics;
+use crate::data::scrape_runs::RunQuery;
+use crate::data::shared_booking::{BookingRecord, JobStatus, ScrapeRun, ScraperStatus, TestType};
+use crate::notifications::{NotificationLevel, NotificationRule};
+use crate::utils::date::{format_datetime, format_slot_time};
+
+/// An auto-find job as shown on the dashboard.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminJob {
+    pub id: u64,
+    pub booking_id: String,
+    pub locations: Vec<LocationId>,
+    pub before: NaiveDate,
+    pub priority: i32,
+    pub created_at: DateTime<Utc>,
+    pub status: JobStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminOverview {
+    /// One per scraped test type, they fail and recover separately
+    pub statuses: Vec<(TestType, ScraperStatus)>,
+    pub runs: Vec<ScrapeRun>,
+    pub jobs: Vec<AdminJob>,
+    pub rules: Vec<NotificationRule>,
+    pub manual_scrape_running: bool,
+    pub backups: Vec<BackupInfo>,
+    pub bookings: Vec<BookingRecord>,
+}
+
+/// Check `token` against `admin_token` and hand back the settings. Compares
+/// in constant time so the token can't be guessed byte by byte.
+#[cfg(feature = "ssr")]
+fn authorize(token: &str) -> Result<crate::settings::Settings, ServerFnError> {
+    use crate::settings::Settings;
+
+    let settings = Settings::load()
+        .map_err(|e| ServerFnError::<NoCustomError>::ServerError(format!("Failed to load settings: {}", e)))?;
+    let Some(expected) = settings.admin_token.as_deref().filter(|expected| !expected.is_empty()) else {
+        return Err(ServerFnError::<NoCustomError>::ServerError("The admin dashboard is disabled".into()));
+    };
+
+    let matches = expected.len() == token.len()
+        && expected
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0;
+    if !matches {
+        eprintln!("WARN: Rejected admin request with an invalid token");
+        return Err(ServerFnError::<NoCustomError>::ServerError("Invalid admin token".into()));
+    }
+
+    Ok(settings)
+}
+
+#[cfg(feature = "ssr")]
+fn admin_error(message: String) -> ServerFnError {
+    ServerFnError::<NoCustomError>::ServerError(message)
+}
+
+#[server(GetAdminOverview)]
+pub async fn get_admin_overview(token: String) -> Result<AdminOverview, ServerFnError> {
+    use crate::data::auto_find;
+    use crate::data::booking::BookingManager;
+    use crate::notifications;
+
+    let settings = authorize(&token)?;
+
+    let jobs = auto_find::jobs()
+        .into_iter()
+        .map(|(job, _)| AdminJob {
+            status: auto_find::status(&job.booking_id),
+            id: job.id,
+            booking_id: job.booking_id,
+            locations: job.search_locations(),
+            before: job.before,
+            priority: job.priority,
+            created_at: job.created_at,
+        })
+        .collect();
+
+    Ok(AdminOverview {
+        statuses: settings
+            .test_types
+            .iter()
+            .map(|&test_type| (test_type, BookingManager::get_status(test_type)))
+            .collect(),
+        runs: BookingManager::recent_runs(),
+        jobs,
+        rules: notifications::rules(),
+        manual_scrape_running: BookingManager::manual_scrape_running(),
+        bookings: BookingManager::booking_history(),
+        backups: crate::data::backup::list(&settings).map_err(admin_error)?,
+    })
+}
+
+/// Query the scrape run log, which goes back further than the recent runs
+/// in the overview.
+#[server(AdminScrapeRuns)]
+pub async fn admin_scrape_runs(token: String, query: RunQuery) -> Result<Vec<ScrapeRun>, ServerFnError> {
+    let settings = authorize(&token)?;
+    crate::data::scrape_runs::load(&settings.scrape_runs_file(), &query).map_err(admin_error)
+}
+
+/// The newest `limit` entries of the audit log, oldest first.
+#[server(AdminAuditLog)]
+pub async fn admin_audit_log(token: String, limit: usize) -> Result<Vec<AuditEntry>, ServerFnError> {
+    let settings = authorize(&token)?;
+    crate::data::audit::recent(&settings.audit_log_file(), limit).map_err(admin_error)
+}
+
+/// Per-centre scrape metrics for `test_type`, worst centres first.
+#[server(AdminScrapeMetrics)]
+pub async fn admin_scrape_metrics(
+    token: String,
+    test_type: TestType,
+) -> Result<Vec<(LocationId, LocationMetrics)>, ServerFnError> {
+    use crate::data::booking::BookingManager;
+
+    authorize(&token)?;
+    Ok(BookingManager::scrape_metrics(test_type).ranked())
+}
+
+#[server(AdminScrapeNow)]
+pub async fn admin_scrape_now(token: String, test_type: TestType) -> Result<(), ServerFnError> {
+    let settings = authorize(&token)?;
+
+    #[cfg(feature = "scraper")]
+    {
+        use crate::data::booking::BookingManager;
+        use crate::data::location::LocationManager;
+
+        let locations = LocationManager::new().scrape_list(&settings);
+        if !BookingManager::start_manual_scrape(test_type, locations, settings) {
+            return Err(admin_error("A manual scrape is already running".into()));
+        }
+        println!("INFO: Manual {} scrape started from the admin dashboard", test_type.label());
+        return Ok(());
+    }
+
+    #[allow(unreachable_code)]
+    Err(admin_error("This server was built without the scraper".into()))
+}
+
+#[server(AdminRollback)]
+pub async fn admin_rollback(token: String, test_type: TestType) -> Result<(), ServerFnError> {
+    use crate::data::booking::BookingManager;
+
+    let settings = authorize(&token)?;
+    BookingManager::rollback(test_type).map_err(admin_error)?;
+
+    BookingManager::save_data(test_type, &settings).map_err(admin_error)
+}
+
+#[server(AdminRestoreBackup)]
+pub async fn admin_restore_backup(token: String, test_type: TestType, file_name: String) -> Result<(), ServerFnError> {
+    let settings = authorize(&token)?;
+    crate::data::backup::restore(&settings, test_type, &file_name).map_err(admin_error)
+}
+
+#[server(AdminStopJob)]
+pub async fn admin_stop_job(token: String, job_id: u64) -> Result<(), ServerFnError> {
+    use crate::data::booking::BookingManager;
+
+    let settings = authorize(&token)?;
+    crate::data::auto_find::remove_job(job_id);
+    BookingManager::save_auto_find_jobs(&settings);
+    println!("INFO: Auto-find job {} stopped from the admin dashboard", job_id);
+    Ok(())
+}
+
+#[server(AdminAddRule)]
+pub async fn admin_add_rule(token: String, url: String, min_level: NotificationLevel) -> Result<(), ServerFnError> {
+    let settings = authorize(&token)?;
+
+    let url = url.trim().to_string();
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        return Err(admin_error("Webhook URL must start with http:// or https://".into()));
+    }
+
+    crate::notifications::add_rule(&settings, url, min_level).map_err(admin_error)?;
+    Ok(())
+}
+
+/// Set the announcement banner, or clear it with an empty message.
+#[server(AdminSetAnnouncement)]
+pub async fn admin_set_announcement(token: String, message: String, level: NotificationLevel) -> Result<(), ServerFnError> {
+    let settings = authorize(&token)?;
+    match crate::announcement::set(&settings, &message, level).map_err(admin_error)? {
+        Some(_) => println!("INFO: Announcement set from the admin dashboard"),
+        None => println!("INFO: Announcement cleared from the admin dashboard"),
+    }
+    Ok(())
+}
+
+#[server(AdminRemoveRule)]
+pub async fn admin_remove_rule(token: String, id: u64) -> Result<(), ServerFnError> {
+    let settings = authorize(&token)?;
+    crate::notifications::remove_rule(&settings, id).map_err(admin_error)
+}
+
+fn level_label(level: NotificationLevel) -> &'static str {
+    match level {
+        NotificationLevel::Info => "info",
+        NotificationLevel::Warning => "warning",
+        NotificationLevel::Error => "error",
+    }
+}
+
+const SECTION_TITLE: &str = "text-lg font-semibold text-gray-800 mb-2";
+const HEADER_CELL: &str = "px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider";
+const CELL: &str = "px-2 py-2 text-gray-700";
+const BUTTON: &str = "px-3 py-1 text-sm text-white rounded-md focus:outline-none focus:ring-2 focus:ring-offset-2 transition-colors";
+
+#[component]
+pub fn AdminPage() -> impl IntoView {
+    let (token_input, set_token_input) = create_signal(String::new());
+    let (token, set_token) = create_signal(String::new());
+    let (overview, set_overview) = create_signal::<Option<AdminOverview>>(None);
+    let (message, set_message) = create_signal::<Option<String>>(None);
+    let (test_type, set_test_type) = create_signal(TestType::default());
+    let (rule_url, set_rule_url) = create_signal(String::new());
+    let (rule_level, set_rule_level) = create_signal(NotificationLevel::Warning);
+    let (announcement_input, set_announcement_input) = create_signal(String::new());
+    let (announcement_level, set_announcement_level) = create_signal(NotificationLevel::Warning);
+    let (failed_runs, set_failed_runs) = create_signal::<Option<Vec<ScrapeRun>>>(None);
+    let (metrics, set_metrics) = create_signal::<Vec<(LocationId, LocationMetrics)>>(Vec::new());
+    let (audit_entries, set_audit_entries) = create_signal::<Vec<AuditEntry>>(Vec::new());
+
+    let refresh = move || {
+        let token = token.get_untracked();
+        leptos::task::spawn_local(async move {
+            match get_admin_overview(token).await {
+                Ok(loaded) => {
+                    if let Some(announcement) = loaded.statuses.first().and_then(|(_, status)| status.announcement.as_ref()) {
+                        set_announcement_input(announcement.message.clone());
+                        set_announcement_level(announcement.level);
+                    }
+                    set_overview(Some(loaded));
+                }
+                Err(e) => {
+                    set_overview(None);
+                    set_message(Some(format!("Error: {e}")));
+                }
+            }
+        });
+    };
+
+    let show_failed_runs = move |show: bool| {
+        if !show {
+            set_failed_runs(None);
+            return;
+        }
+        let query = RunQuery { failed_only: true, limit: Some(50), ..RunQuery::default() };
+        leptos::task::spawn_local(async move {
+            match admin_scrape_runs(token.get_untracked(), query).await {
+                Ok(runs) => set_failed_runs(Some(runs)),
+                Err(e) => set_message(Some(format!("Error: {e}"))),
+            }
+        });
+    };
+
+    let load_metrics = move || {
+        leptos::task::spawn_local(async move {
+            match admin_scrape_metrics(token.get_untracked(), test_type.get_untracked()).await {
+                Ok(loaded) => set_metrics(loaded),
+                Err(e) => set_message(Some(format!("Error: {e}"))),
+            }
+        });
+    };
+
+    let load_audit_log = move || {
+        leptos::task::spawn_local(async move {
+            match admin_audit_log(token.get_untracked(), 100).await {
+                Ok(loaded) => set_audit_entries(loaded),
+                Err(e) => set_message(Some(format!("Error: {e}"))),
+            }
+        });
+    };
+
+    let unlock = move || {
+        set_message(None);
+        set_token(token_input.get_untracked());
+        refresh();
+    };
+
+    // Runs an admin action, then reloads the dashboard so it reflects the result.
+    let run_action = move |done: &'static str, action: std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ServerFnError>>>>| {
+        leptos::task::spawn_local(async move {
+            match action.await {
+                Ok(()) => set_message(Some(done.to_string())),
+                Err(e) => set_message(Some(format!("Error: {e}"))),
+            }
+            refresh();
+        });
+    };
+
+    view! {
+        <div class="max-w-4xl mx-auto p-4">
+            <div class="flex justify-between items-center mb-4">
+                <h2 class="text-2xl font-bold text-gray-800">Admin</h2>
+                <a href="/" class="text-sm text-blue-600 hover:underline">"Back to availability"</a>
+            </div>
+
+            <div class="flex gap-2 mb-4">
+                <input
+                    type="password"
+                    class="flex-grow px-3 py-2 border border-gray-300 rounded-md focus:outline-none focus:ring-2 focus:ring-blue-500"
+                    placeholder="Admin token"
+                    prop:value={token_input}
+                    on:input=move |ev| set_token_input(event_target_value(&ev))
+                    on:keydown=move |ev| {
+                        if ev.key() == "Enter" {
+                            unlock();
+                        }
+                    }
+                />
+                <button class=format!("{BUTTON} bg-blue-600 hover:bg-blue-700 focus:ring-blue-500") on:click=move |_| unlock()>
+                    "Unlock"
+                </button>
+            </div>
+
+            {move || message.get().map(|message| view! {
+                <p class="mb-4 text-sm text-gray-700">{message}</p>
+            })}
+
+            {move || overview.get().map(|overview| {
+                let statuses = overview.statuses;
+                let status = statuses.first().map(|(_, status)| status.clone()).unwrap_or_default();
+                let recent_runs = overview.runs;
+                view! {
+                    <section class="mb-6">
+                        <h3 class=SECTION_TITLE>"Scraper"</h3>
+                        <ul class="text-sm text-gray-700 space-y-1">
+                            <li>"Background updates: " {if status.background_running { "running" } else { "stopped" }}</li>
+                            <li>"WebDriver ready: " {match status.driver_ready {
+                                Some(true) => "yes",
+                                Some(false) => "no",
+                                None => "not scraping",
+                            }}</li>
+                            {status.driver_health.map(|health| view! {
+                                <li>
+                                    "WebDriver checked " {format_datetime(&health.checked_at)}
+                                    {health.message.map(|message| format!(": {}", message))}
+                                    {(health.restarts > 0).then(|| format!(" (restarted {} times)", health.restarts))}
+                                </li>
+                            })}
+                        </ul>
+                        {statuses.into_iter().map(|(test_type, status)| view! {
+                            <h4 class="mt-3 text-sm font-semibold text-gray-800">{test_type.label()}</h4>
+                            <ul class="text-sm text-gray-700 space-y-1">
+                                <li>"Degraded: " {if status.degraded { "yes" } else { "no" }} ", stale: " {if status.stale { "yes" } else { "no" }}</li>
+                                <li>"Failed updates in a row: " {status.consecutive_failures}</li>
+                                <li>"Last success: " {status.last_success.map(|time| format_datetime(&time)).unwrap_or_else(|| "never".into())}</li>
+                                <li>"Last failure: " {status.last_failure.map(|time| format_datetime(&time)).unwrap_or_else(|| "never".into())}</li>
+                                {status.reason.map(|reason| view! { <li>"Reason: " {reason}</li> })}
+                            </ul>
+                        }).collect::<Vec<_>>()}
+
+                        <div class="flex flex-wrap gap-2 items-center mt-3">
+                            <select
+                                class="px-3 py-1 border border-gray-300 rounded-md text-sm"
+                                on:change=move |ev| {
+                                    if let Ok(selected) = event_target_value(&ev).parse::<TestType>() {
+                                        set_test_type(selected);
+                                    }
+                                }
+                            >
+                                {TestType::ALL.into_iter().map(|option| view! {
+                                    <option value=option.as_str() selected=move || test_type.get() == option>
+                                        {option.label()}
+                                    </option>
+                                }).collect::<Vec<_>>()}
+                            </select>
+                            <button
+                                class=format!("{BUTTON} bg-blue-600 hover:bg-blue-700 focus:ring-blue-500")
+                                disabled=overview.manual_scrape_running
+                                on:click=move |_| run_action(
+                                    "Manual scrape started",
+                                    Box::pin(admin_scrape_now(token.get_untracked(), test_type.get_untracked())),
+                                )
+                            >
+                                {if overview.manual_scrape_running { "Scraping..." } else { "Scrape now" }}
+                            </button>
+                            <button
+                                class=format!("{BUTTON} bg-amber-600 hover:bg-amber-700 focus:ring-amber-500")
+                                on:click=move |_| run_action(
+                                    "Rolled back to the previous data",
+                                    Box::pin(admin_rollback(token.get_untracked(), test_type.get_untracked())),
+                                )
+                            >
+                                "Roll back last update"
+                            </button>
+                            <button class="text-sm text-blue-600 hover:underline" on:click=move |_| refresh()>
+                                "Refresh"
+                            </button>
+                        </div>
+                    </section>
+
+                    <section class="mb-6">
+                        <h3 class=SECTION_TITLE>"Announcement"</h3>
+                        <p class="text-sm text-gray-500 mb-2">
+                            "Shown as a banner to every visitor and returned by /api/status. Leave empty and save to clear it."
+                        </p>
+                        <div class="flex flex-wrap gap-2">
+                            <input
+                                type="text"
+                                class="flex-grow px-3 py-1 border border-gray-300 rounded-md text-sm"
+                                placeholder="RTA portal under maintenance tonight; data may be stale"
+                                prop:value={announcement_input}
+                                on:input=move |ev| set_announcement_input(event_target_value(&ev))
+                            />
+                            <select
+                                class="px-3 py-1 border border-gray-300 rounded-md text-sm"
+                                on:change=move |ev| {
+                                    let level = match event_target_value(&ev).as_str() {
+                                        "info" => NotificationLevel::Info,
+                                        "error" => NotificationLevel::Error,
+                                        _ => NotificationLevel::Warning,
+                                    };
+                                    set_announcement_level(level);
+                                }
+                            >
+                                {[NotificationLevel::Info, NotificationLevel::Warning, NotificationLevel::Error].into_iter().map(|level| view! {
+                                    <option value=level_label(level) selected=move || announcement_level.get() == level>
+                                        {level_label(level)}
+                                    </option>
+                                }).collect::<Vec<_>>()}
+                            </select>
+                            <button
+                                class=format!("{BUTTON} bg-blue-600 hover:bg-blue-700 focus:ring-blue-500")
+                                on:click=move |_| run_action(
+                                    "Announcement saved",
+                                    Box::pin(admin_set_announcement(
+                                        token.get_untracked(),
+                                        announcement_input.get_untracked(),
+                                        announcement_level.get_untracked(),
+                                    )),
+                                )
+                            >
+                                "Save"
+                            </button>
+                        </div>
+                    </section>
+
+                    <section class="mb-6">
+                        <h3 class=SECTION_TITLE>"Recent runs"</h3>
+                        <label class="flex items-center gap-1 mb-2 text-sm text-gray-700">
+                            <input
+                                type="checkbox"
+                                prop:checked=move || failed_runs.get().is_some()
+                                on:change=move |ev| show_failed_runs(event_target_checked(&ev))
+                            />
+                            "Only runs with failures"
+                        </label>
+                        <table class="min-w-full divide-y divide-gray-200 text-sm">
+                            <thead class="bg-gray-50">
+                                <tr>
+                                    <th class=HEADER_CELL>"Finished"</th>
+                                    <th class=HEADER_CELL>"Test"</th>
+                                    <th class=HEADER_CELL>"Scraped"</th>
+                                    <th class=HEADER_CELL>"Failed centres"</th>
+                                    <th class=HEADER_CELL>"On cooldown"</th>
+                                    <th class=HEADER_CELL>"Retries"</th>
+                                    <th class=HEADER_CELL>"Took"</th>
+                                    <th class=HEADER_CELL>"Backend"</th>
+                                    <th class=HEADER_CELL>"Error"</th>
+                                </tr>
+                            </thead>
+                            <tbody class="bg-white divide-y divide-gray-200">
+                                {move || failed_runs.get().unwrap_or_else(|| recent_runs.clone()).into_iter().rev().map(|run| view! {
+                                    <tr>
+                                        <td class=CELL>{format_datetime(&run.finished_at)}</td>
+                                        <td class=CELL>{run.test_type.label()}</td>
+                                        <td class=CELL>{format!("{}/{}", run.scraped, run.requested)}</td>
+                                        <td class=CELL>
+                                            {run.failed.iter().map(|location| {
+                                                let reason = run.failure_reasons.get(location).cloned().unwrap_or_default();
+                                                view! { <span class="mr-1" title=reason>{location.to_string()}</span> }
+                                            }).collect::<Vec<_>>()}
+                                        </td>
+                                        <td class=CELL>
+                                            {run.cooling_down.iter().map(|location| location.to_string()).collect::<Vec<_>>().join(", ")}
+                                        </td>
+                                        <td class=CELL>{run.retries()}</td>
+                                        <td class=CELL>{format!("{}s", (run.finished_at - run.started_at).num_seconds())}</td>
+                                        <td class=CELL>{run.backend}</td>
+                                        <td class=CELL>{run.error.unwrap_or_default()}</td>
+                                    </tr>
+                                }).collect::<Vec<_>>()}
+                            </tbody>
+                        </table>
+                    </section>
+
+                    <section class="mb-6">
+                        <h3 class=SECTION_TITLE>"Centre metrics"</h3>
+                        <button
+                            class=format!("{BUTTON} mb-2 bg-blue-600 hover:bg-blue-700 focus:ring-blue-500")
+                            on:click=move |_| load_metrics()
+                        >
+                            "Load"
+                        </button>
+                        <table class="min-w-full divide-y divide-gray-200 text-sm">
+                            <thead class="bg-gray-50">
+                                <tr>
+                                    <th class=HEADER_CELL>"Centre"</th>
+                                    <th class=HEADER_CELL>"Success"</th>
+                                    <th class=HEADER_CELL>"Avg time"</th>
+                                    <th class=HEADER_CELL>"Slowest"</th>
+                                    <th class=HEADER_CELL>"Avg slots"</th>
+                                    <th class=HEADER_CELL>"Last error"</th>
+                                </tr>
+                            </thead>
+                            <tbody class="bg-white divide-y divide-gray-200">
+                                {move || metrics.get().into_iter().map(|(location, metrics)| view! {
+                                    <tr>
+                                        <td class=CELL>{location.to_string()}</td>
+                                        <td class=CELL>
+                                            {format!("{:.0}% of {}", metrics.success_rate() * 100.0, metrics.attempts)}
+                                        </td>
+                                        <td class=CELL>{format!("{:.1}s", metrics.average_duration_ms() as f64 / 1000.0)}</td>
+                                        <td class=CELL>{format!("{:.1}s", metrics.slowest_ms as f64 / 1000.0)}</td>
+                                        <td class=CELL>{format!("{:.1}", metrics.average_slots())}</td>
+                                        <td class=CELL>{metrics.last_error.unwrap_or_default()}</td>
+                                    </tr>
+                                }).collect::<Vec<_>>()}
+                            </tbody>
+                        </table>
+                    </section>
+
+                    <section class="mb-6">
+                        <h3 class=SECTION_TITLE>"Audit log"</h3>
+                        <button
+                            class=format!("{BUTTON} mb-2 bg-blue-600 hover:bg-blue-700 focus:ring-blue-500")
+                            on:click=move |_| load_audit_log()
+                        >
+                            "Load"
+                        </button>
+                        <table class="min-w-full divide-y divide-gray-200 text-sm">
+                            <thead class="bg-gray-50">
+                                <tr>
+                                    <th class=HEADER_CELL>"When"</th>
+                                    <th class=HEADER_CELL>"Action"</th>
+                                    <th class=HEADER_CELL>"Parameters"</th>
+                                    <th class=HEADER_CELL>"Outcome"</th>
+                                </tr>
+                            </thead>
+                            <tbody class="bg-white divide-y divide-gray-200">
+                                {move || audit_entries.get().into_iter().rev().map(|entry| view! {
+                                    <tr class={if entry.success { "" } else { "bg-red-50" }}>
+                                        <td class=CELL>{format_datetime(&entry.timestamp)}</td>
+                                        <td class=CELL>{entry.action.label()}</td>
+                                        <td class=CELL><code class="text-xs">{entry.parameters.to_string()}</code></td>
+                                        <td class=CELL>{entry.outcome}</td>
+                                    </tr>
+                                }).collect::<Vec<_>>()}
+                            </tbody>
+                        </table>
+                    </section>
+
+                    <section class="mb-6">
+                        <h3 class=SECTION_TITLE>"Bookings made"</h3>
+                        <table class="min-w-full divide-y divide-gray-200 text-sm">
+                            <thead class="bg-gray-50">
+                                <tr>
+                                    <th class=HEADER_CELL>"When"</th>
+                                    <th class=HEADER_CELL>"Booking"</th>
+                                    <th class=HEADER_CELL>"Centre"</th>
+                                    <th class=HEADER_CELL>"Slot"</th>
+                                    <th class=HEADER_CELL>"Number"</th>
+                                    <th class=HEADER_CELL>"Screenshot"</th>
+                                </tr>
+                            </thead>
+                            <tbody class="bg-white divide-y divide-gray-200">
+                                {overview.bookings.into_iter().rev().map(|record| view! {
+                                    <tr>
+                                        <td class=CELL>{format_datetime(&record.recorded_at)}</td>
+                                        <td class=CELL>{format!("{} ({})", record.booking_id, record.test_type.label())}</td>
+                                        <td class=CELL>{record.receipt.location.to_string()}</td>
+                                        <td class=CELL>{format_slot_time(&record.receipt.start_time)}</td>
+                                        <td class=CELL>
+                                            {record.receipt.confirmation_number.unwrap_or_else(|| format!("{:?}", record.receipt.outcome))}
+                                        </td>
+                                        <td class=CELL>{record.screenshot.unwrap_or_default()}</td>
+                                    </tr>
+                                }).collect::<Vec<_>>()}
+                            </tbody>
+                        </table>
+                    </section>
+
+                    <section class="mb-6">
+                        <h3 class=SECTION_TITLE>"Backups"</h3>
+                        <p class="text-sm text-gray-500 mb-2">
+                            "Restoring replaces the current data; \"Roll back last update\" undoes a restore."
+                        </p>
+                        <table class="min-w-full divide-y divide-gray-200 text-sm">
+                            <thead class="bg-gray-50">
+                                <tr>
+                                    <th class=HEADER_CELL>"Taken"</th>
+                                    <th class=HEADER_CELL>"Test"</th>
+                                    <th class=HEADER_CELL>"Size"</th>
+                                    <th class=HEADER_CELL></th>
+                                </tr>
+                            </thead>
+                            <tbody class="bg-white divide-y divide-gray-200">
+                                {overview.backups.into_iter().map(|backup| {
+                                    let test_type = backup.test_type;
+                                    let file_name = backup.file_name.clone();
+                                    view! {
+                                        <tr>
+                                            <td class=CELL>{format_datetime(&backup.created_at)}</td>
+                                            <td class=CELL>{test_type.label()}</td>
+                                            <td class=CELL>{format!("{} KB", backup.size_bytes.div_ceil(1024))}</td>
+                                            <td class=CELL>
+                                                <button
+                                                    class="text-sm text-amber-700 hover:underline"
+                                                    on:click=move |_| run_action(
+                                                        "Backup restored",
+                                                        Box::pin(admin_restore_backup(token.get_untracked(), test_type, file_name.clone())),
+                                                    )
+                                                >
+                                                    "Restore"
+                                                </button>
+                                            </td>
+                                        </tr>
+                                    }
+                                }).collect::<Vec<_>>()}
+                            </tbody>
+                        </table>
+                    </section>
+
+                    <section class="mb-6">
+                        <h3 class=SECTION_TITLE>"Auto-find jobs"</h3>
+                        <table class="min-w-full divide-y divide-gray-200 text-sm">
+                            <thead class="bg-gray-50">
+                                <tr>
+                                    <th class=HEADER_CELL>"Booking"</th>
+                                    <th class=HEADER_CELL>"Before"</th>
+                                    <th class=HEADER_CELL>"Centres"</th>
+                                    <th class=HEADER_CELL>"Priority"</th>
+                                    <th class=HEADER_CELL>"Status"</th>
+                                    <th class=HEADER_CELL></th>
+                                </tr>
+                            </thead>
+                            <tbody class="bg-white divide-y divide-gray-200">
+                                {overview.jobs.into_iter().map(|job| {
+                                    let job_id = job.id;
+                                    view! {
+                                        <tr>
+                                            <td class=CELL>{job.booking_id}</td>
+                                            <td class=CELL>{job.before.to_string()}</td>
+                                            <td class=CELL>{job.locations.len()}</td>
+                                            <td class=CELL>{job.priority}</td>
+                                            <td class=CELL>{job.status.label()}</td>
+                                            <td class=CELL>
+                                                <button
+                                                    class="text-sm text-red-600 hover:underline"
+                                                    on:click=move |_| run_action(
+                                                        "Job stopped",
+                                                        Box::pin(admin_stop_job(token.get_untracked(), job_id)),
+                                                    )
+                                                >
+                                                    "Stop"
+                                                </button>
+                                            </td>
+                                        </tr>
+                                    }
+                                }).collect::<Vec<_>>()}
+                            </tbody>
+                        </table>
+                    </section>
+
+                    <section class="mb-6">
+                        <h3 class=SECTION_TITLE>"Notification rules"</h3>
+                        <p class="text-sm text-gray-500 mb-2">
+                            "Webhooks in settings.yaml receive every notification; these receive those at or above their level."
+                        </p>
+                        <ul class="text-sm text-gray-700 space-y-1 mb-3">
+                            {overview.rules.into_iter().map(|rule| {
+                                let rule_id = rule.id;
+                                view! {
+                                    <li class="flex gap-2 items-center">
+                                        <span class="font-mono break-all">{rule.url}</span>
+                                        <span class="text-gray-500">{format!("({}+)", level_label(rule.min_level))}</span>
+                                        <button
+                                            class="text-red-600 hover:underline"
+                                            on:click=move |_| run_action(
+                                                "Rule removed",
+                                                Box::pin(admin_remove_rule(token.get_untracked(), rule_id)),
+                                            )
+                                        >
+                                            "Remove"
+                                        </button>
+                                    </li>
+                                }
+                            }).collect::<Vec<_>>()}
+                        </ul>
+                        <div class="flex flex-wrap gap-2">
+                            <input
+                                type="url"
+                                class="flex-grow px-3 py-1 border border-gray-300 rounded-md text-sm"
+                                placeholder="https://example.com/webhook"
+                                prop:value={rule_url}
+                                on:input=move |ev| set_rule_url(event_target_value(&ev))
+                            />
+                            <select
+                                class="px-3 py-1 border border-gray-300 rounded-md text-sm"
+                                on:change=move |ev| {
+                                    let level = match event_target_value(&ev).as_str() {
+                                        "info" => NotificationLevel::Info,
+                                        "error" => NotificationLevel::Error,
+                                        _ => NotificationLevel::Warning,
+                                    };
+                                    set_rule_level(level);
+                                }
+                            >
+                                {[NotificationLevel::Info, NotificationLevel::Warning, NotificationLevel::Error].into_iter().map(|level| view! {
+                                    <option value=level_label(level) selected=move || rule_level.get() == level>
+                                        {level_label(level)}
+                                    </option>
+                                }).collect::<Vec<_>>()}
+                            </select>
+                            <button
+                                class=format!("{BUTTON} bg-green-600 hover:bg-green-700 focus:ring-green-500")
+                                on:click=move |_| {
+                                    let url = rule_url.get_untracked();
+                                    set_rule_url(String::new());
+                                    run_action(
+                                        "Rule added",
+                                        Box::pin(admin_add_rule(token.get_untracked(), url, rule_level.get_untracked())),
+                                    )
+                                }
+                            >
+                                "Add rule"
+                            </button>
+                        </div>
+                    </section>
+                }
+            })}
+        </div>
+    }
+}
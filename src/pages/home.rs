@@ -1,97 +1,83 @@
 use std::collections::HashMap;
 use std::time::Duration;
 
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
 use leptos::prelude::*;
 use leptos::server_fn::error::NoCustomError;
 use reqwest::header;
 use serde::{Deserialize, Serialize};
 use web_sys::wasm_bindgen::prelude::Closure;
 
-use crate::data::location::LocationManager;
-use crate::data::shared_booking::TimeSlot;
-use crate::utils::date::TimeDisplay;
+use crate::accounts::SyncedPreferences;
+use crate::data::location::{Location, LocationId, LocationManager, SearchArea};
+use crate::data::strategy::{SlotPreferences, StrategyConfig};
+use crate::notifications::NotificationLevel;
+use crate::data::shared_booking::{AutoFindJobStatus, AutoFindStatus, BookingOutcome, BookingReceipt, LocationBookingViewModel, PendingApproval, RebookEligibility, ScraperStatus, TestType, TimeSlot};
+use crate::utils::date::{format_age, format_slot_time_long, TimeDisplay};
 use crate::utils::geocoding::geocode_address;
+use crate::utils::local_preferences;
+use crate::pages::account::sync_favourites;
 use crate::pages::location_table::LocationsTable;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LocationBookingViewModel {
-    pub location: String,
-    pub earliest_slot: Option<TimeSlot>,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BookingResponse {
     pub bookings: Vec<LocationBookingViewModel>,
-    pub last_updated: Option<String>,
+    pub last_updated: Option<DateTime<Utc>>,
     pub etag: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocationDetailBookingResponse {
-    pub location: String,
+    pub location: LocationId,
     pub slots: Vec<TimeSlot>,
     pub etag: String,
 }
 
+/// Error returned by the booking automation server functions when the server
+/// was built without the `scraper` feature.
+#[cfg(feature = "ssr")]
+fn automation_unavailable() -> ServerFnError {
+    ServerFnError::<NoCustomError>::ServerError("Booking automation is not available on this server".into())
+}
+
 #[server(GetBookings)]
 pub async fn get_location_bookings(
+    test_type: TestType,
     client_etag: String,
 ) -> Result<Option<BookingResponse>, ServerFnError> {
     use crate::data::booking::BookingManager;
-    use axum::http::HeaderValue;
-    use axum::http::StatusCode;
-
-    let response = expect_context::<leptos_axum::ResponseOptions>();
 
-    let (booking_data, server_etag) = BookingManager::get_data();
+    let (summaries, server_etag) = BookingManager::get_summaries(test_type);
     if client_etag == server_etag {
         // WARN: for some reason this makes it open in hte browser
         // response.set_status(StatusCode::NOT_MODIFIED);
         return Ok(None);
     }
 
-    let view_models: Vec<_> = booking_data
-        .results
-        .iter()
-        .map(|location_booking| {
-            let earliest_slot = location_booking
-                .slots
-                .iter()
-                .filter(|slot| slot.availability)
-                .min_by(|a, b| a.start_time.cmp(&b.start_time))
-                .cloned();
-
-            LocationBookingViewModel {
-                location: location_booking.location.clone(),
-                earliest_slot,
-            }
-        })
-        .collect();
-
     Ok(Some(BookingResponse {
-        bookings: view_models,
-        last_updated: booking_data.last_updated.clone(),
+        bookings: summaries.as_ref().clone(),
+        last_updated: BookingManager::get_data(test_type).0.last_updated,
         etag: server_etag,
     }))
 }
 
 #[server(GetLocationDetails)]
 pub async fn get_location_details(
-    location_id: String,
+    test_type: TestType,
+    location_id: LocationId,
     client_etag: String,
 ) -> Result<Option<LocationDetailBookingResponse>, ServerFnError> {
     use crate::data::booking::BookingManager;
 
-    let (location_booking, server_etag) = BookingManager::get_location_data(location_id).ok_or(
-        ServerFnError::<NoCustomError>::ServerError("Location not found".into()),
-    )?;
-
-    if client_etag == server_etag {
+    let not_found = || ServerFnError::<NoCustomError>::ServerError("Location not found".into());
+    if BookingManager::location_etag(test_type, location_id).ok_or_else(not_found)? == client_etag {
         // WARN: for some reason this makes it open in hte browser
         // response.set_status(StatusCode::NOT_MODIFIED);
         return Ok(None);
     }
 
+    let (location_booking, server_etag) = BookingManager::get_location_data(test_type, location_id).ok_or_else(not_found)?;
+
     Ok(Some(LocationDetailBookingResponse {
         location: location_booking.location,
         slots: location_booking.slots,
@@ -101,69 +87,310 @@ pub async fn get_location_details(
 
 #[server(FindFirstSlot)]
 pub async fn find_first_slot(
+    test_type: TestType,
     before: String,
     booking_id: String,
     last_name: String,
-) -> Result<Option<(String, String)>, ServerFnError> {
-    use crate::data::booking::BookingManager;
-    use crate::data::rta::book_first_available;
-    use crate::settings::Settings;
-
-    let date = chrono::NaiveDate::parse_from_str(&before, "%Y-%m-%d")
-        .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()))?;
+    dry_run: bool,
+    preferences: Option<SlotPreferences>,
+) -> Result<Option<BookingReceipt>, ServerFnError> {
+    #[cfg(feature = "scraper")]
+    {
+        use crate::data::booking::BookingManager;
+        use crate::data::rta::book_first_available;
+        use crate::settings::Settings;
+
+        let date = chrono::NaiveDate::parse_from_str(&before, "%Y-%m-%d")
+            .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()))?;
+
+        let mut settings = Settings::load()
+            .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()))?;
+        settings.booking_id = booking_id;
+        settings.last_name = last_name;
+        settings.booking_test_type = test_type;
+        if let Some(preferences) = preferences {
+            settings.slot_preferences = preferences;
+        }
+        // The user is waiting on this request, book directly
+        settings.confirm_bookings = false;
+
+        let locations: Vec<LocationId> = BookingManager::get_data(settings.booking_test_type)
+            .0
+            .results
+            .iter()
+            .map(|l| l.location)
+            .collect();
+
+        return match book_first_available(locations, date, dry_run, &settings).await {
+            Ok(res) => Ok(res),
+            Err(e) => Err(ServerFnError::<NoCustomError>::ServerError(e.to_string())),
+        };
+    }
 
-    let mut settings = Settings::from_yaml("settings.yaml")
-        .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()))?;
-    settings.booking_id = booking_id;
-    settings.last_name = last_name;
+    #[allow(unreachable_code)]
+    Err(automation_unavailable())
+}
 
-    let locations: Vec<String> = BookingManager::get_data()
-        .0
-        .results
-        .iter()
-        .map(|l| l.location.clone())
-        .collect();
 
-    match book_first_available(locations, date, &settings).await {
-        Ok(res) => Ok(res),
-        Err(e) => Err(ServerFnError::<NoCustomError>::ServerError(e.to_string())),
+/// Book one specific slot from the expanded slot list. The slot is looked up
+/// in the current data first so a stale page can't book a slot that is gone.
+#[server(BookSlot)]
+pub async fn book_slot(
+    test_type: TestType,
+    location_id: LocationId,
+    start_time: NaiveDateTime,
+    booking_id: String,
+    last_name: String,
+) -> Result<BookingOutcome, ServerFnError> {
+    #[cfg(feature = "scraper")]
+    {
+        use crate::data::booking::BookingManager;
+        use crate::data::rta::try_book_slot;
+        use crate::settings::Settings;
+
+        let slot = BookingManager::get_location_slots(test_type, location_id)
+            .and_then(|slots| slots.into_iter().find(|slot| slot.start_time == start_time && slot.availability))
+            .ok_or(ServerFnError::<NoCustomError>::ServerError("Slot is no longer available".into()))?;
+
+        let mut settings = Settings::load()
+            .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()))?;
+        settings.booking_id = booking_id;
+        settings.last_name = last_name;
+        settings.booking_test_type = test_type;
+
+        return try_book_slot(location_id, &slot, &settings)
+            .await
+            .map(|receipt| receipt.outcome)
+            .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()));
     }
-}
 
+    #[allow(unreachable_code)]
+    Err(automation_unavailable())
+}
 
 #[server(StartAutoFind)]
 pub async fn start_auto_find(
     before: String,
     booking_id: String,
     last_name: String,
-    locations: Vec<String>,
-) -> Result<(), ServerFnError> {
-    use crate::data::booking::BookingManager;
-    use crate::settings::Settings;
+    locations: Vec<LocationId>,
+    priority: Option<i32>,
+    strategy: Option<StrategyConfig>,
+    preferences: Option<SlotPreferences>,
+    area: Option<SearchArea>,
+    needs_approval: bool,
+) -> Result<u64, ServerFnError> {
+    #[cfg(feature = "scraper")]
+    {
+        use crate::data::booking::BookingManager;
+        use crate::settings::Settings;
+
+        let date = chrono::NaiveDate::parse_from_str(&before, "%Y-%m-%d")
+            .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()))?;
+
+        let mut settings = Settings::load()
+            .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()))?;
+        if settings.accounts_enabled && crate::pages::account::current_account().await?.is_none() {
+            return Err(ServerFnError::<NoCustomError>::ServerError(
+                "Sign in to use the auto test finder".into(),
+            ));
+        }
+        settings.booking_id = booking_id;
+        settings.last_name = last_name;
+        if let Some(strategy) = strategy {
+            settings.find_strategy = strategy;
+        }
+        if let Some(preferences) = preferences {
+            settings.slot_preferences = preferences;
+        }
+        if needs_approval {
+            settings.confirm_bookings = true;
+        }
+
+        if locations.is_empty() && area.is_none() {
+            return Err(ServerFnError::<NoCustomError>::ServerError(
+                "Pick some centres or a search radius".into(),
+            ));
+        }
+
+        if !crate::data::leader::is_leader() {
+            // Jobs only run on the leader, hand this one over
+            return BookingManager::submit_auto_find(locations, area, date, priority.unwrap_or_default(), &settings)
+                .await
+                .map_err(ServerFnError::<NoCustomError>::ServerError);
+        }
+        let job_id = BookingManager::start_auto_find(locations, area, date, priority.unwrap_or_default(), settings);
+        return Ok(job_id);
+    }
+
+    #[allow(unreachable_code)]
+    Err(automation_unavailable())
+}
+
+/// The slot held for this booking's auto-find job, waiting for the user.
+#[server(GetPendingApproval)]
+pub async fn get_pending_approval(booking_id: String, last_name: String) -> Result<Option<PendingApproval>, ServerFnError> {
+    #[cfg(feature = "scraper")]
+    {
+        use crate::data::{auto_find, confirmation};
+
+        // The hold's token books the slot, only hand it to the job's owner
+        let owns_job = auto_find::owned_by(&booking_id, &last_name);
+        return Ok(owns_job.then(|| confirmation::pending_for(&booking_id)).flatten());
+    }
+
+    #[allow(unreachable_code)]
+    Err(automation_unavailable())
+}
+
+/// Approve or decline a held slot.
+#[server(AnswerPendingApproval)]
+pub async fn answer_pending_approval(token: String, approve: bool) -> Result<String, ServerFnError> {
+    #[cfg(feature = "scraper")]
+    {
+        use crate::data::confirmation;
+        use crate::utils::date::format_slot_time;
+
+        return match confirmation::resolve(&token, approve) {
+            Some((location, start_time)) if approve => {
+                Ok(format!("Booking the slot at {} on {}", location, format_slot_time(&start_time)))
+            }
+            Some((location, start_time)) => {
+                Ok(format!("Released the slot at {} on {}", location, format_slot_time(&start_time)))
+            }
+            None => Err(ServerFnError::<NoCustomError>::ServerError(
+                "The held slot has already been released".into(),
+            )),
+        };
+    }
+
+    #[allow(unreachable_code)]
+    Err(automation_unavailable())
+}
+
+/// Cancel the booking on the portal.
+#[server(CancelBooking)]
+pub async fn cancel_booking(booking_id: String, last_name: String) -> Result<Option<NaiveDateTime>, ServerFnError> {
+    #[cfg(feature = "scraper")]
+    {
+        use crate::settings::Settings;
+
+        let mut settings = Settings::load()
+            .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()))?;
+        settings.booking_id = booking_id;
+        settings.last_name = last_name;
+
+        return crate::data::rta::cancel_booking(&settings)
+            .await
+            .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()));
+    }
 
-    let date = chrono::NaiveDate::parse_from_str(&before, "%Y-%m-%d")
-        .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()))?;
+    #[allow(unreachable_code)]
+    Err(automation_unavailable())
+}
+
+/// Whether the booking can still be changed, and the fee for doing so.
+#[server(CheckRebooking)]
+pub async fn check_rebooking(booking_id: String, last_name: String) -> Result<RebookEligibility, ServerFnError> {
+    #[cfg(feature = "scraper")]
+    {
+        use crate::settings::Settings;
+
+        let mut settings = Settings::load()
+            .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()))?;
+        settings.booking_id = booking_id;
+        settings.last_name = last_name;
+
+        return crate::data::rta::check_rebook_eligibility(&settings)
+            .await
+            .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()));
+    }
+
+    #[allow(unreachable_code)]
+    Err(automation_unavailable())
+}
 
-    let mut settings = Settings::from_yaml("settings.yaml")
-        .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()))?;
-    settings.booking_id = booking_id;
-    settings.last_name = last_name;
+/// Move the booking to the best slot at `location` on or before `before`.
+#[server(ChangeBookingLocation)]
+pub async fn change_booking_location(
+    booking_id: String,
+    last_name: String,
+    location: LocationId,
+    before: String,
+) -> Result<Option<BookingReceipt>, ServerFnError> {
+    #[cfg(feature = "scraper")]
+    {
+        use crate::settings::Settings;
+
+        let date = chrono::NaiveDate::parse_from_str(&before, "%Y-%m-%d")
+            .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()))?;
+        let mut settings = Settings::load()
+            .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()))?;
+        settings.booking_id = booking_id;
+        settings.last_name = last_name;
+
+        return crate::data::rta::change_booking_location(location, date, &settings)
+            .await
+            .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()));
+    }
 
-    BookingManager::start_auto_find(locations, date, settings);
-    Ok(())
+    #[allow(unreachable_code)]
+    Err(automation_unavailable())
 }
 
 #[server(StopAutoFind)]
-pub async fn stop_auto_find() -> Result<(), ServerFnError> {
+pub async fn stop_auto_find(booking_id: String, last_name: String) -> Result<(), ServerFnError> {
+    use crate::data::booking::BookingManager;
+    use crate::settings::Settings;
+
+    let settings = Settings::load().map_err(|e| ServerFnError::<NoCustomError>::ServerError(e.to_string()))?;
+    BookingManager::request_stop_auto_find(&booking_id, &last_name, &settings)
+        .await
+        .map_err(ServerFnError::<NoCustomError>::ServerError)
+}
+
+#[server(GetScraperStatus)]
+pub async fn get_scraper_status(test_type: TestType) -> Result<ScraperStatus, ServerFnError> {
     use crate::data::booking::BookingManager;
-    BookingManager::stop_auto_find();
-    Ok(())
+    Ok(BookingManager::get_status(test_type))
 }
 
+/// Centre list for the client. Kept out of the WASM bundle and fetched once
+/// the page is up.
+#[server(GetLocations)]
+pub async fn get_locations() -> Result<Vec<Location>, ServerFnError> {
+    Ok(LocationManager::new().get_all())
+}
+
+/// Whether the auto-finder runs and, given a booking and its last name, that
+/// booking's job.
 #[server(GetAutoFindStatus)]
-pub async fn get_auto_find_status() -> Result<bool, ServerFnError> {
+pub async fn get_auto_find_status(booking_id: Option<String>, last_name: String) -> Result<AutoFindStatus, ServerFnError> {
+    use crate::data::auto_find;
     use crate::data::booking::BookingManager;
-    Ok(BookingManager::auto_find_running())
+
+    // The job holds the home area and any receipt, only show it to its owner,
+    // and answer anyone else as if there were no job
+    let job = booking_id
+        .as_deref()
+        .filter(|booking_id| auto_find::owned_by(booking_id, &last_name))
+        .and_then(auto_find::job_for)
+        .map(|job| AutoFindJobStatus {
+            status: auto_find::status(&job.booking_id),
+            locations: job.locations,
+            area: job.area,
+            before: job.before,
+            strategy: job.strategy,
+            preferences: job.preferences,
+            attempts: job.attempts,
+            last_attempt: job.last_attempt,
+        });
+
+    Ok(AutoFindStatus {
+        running: BookingManager::auto_find_running(),
+        job,
+        next_attempt: auto_find::next_round(),
+    })
 }
 
 
@@ -176,40 +403,86 @@ pub fn HomePage() -> impl IntoView {
     let (geocoding_status, set_geocoding_status) = create_signal::<Option<String>>(None);
     let (is_loading, set_is_loading) = create_signal(false);
 
-    let (last_updated, set_last_updated) = create_signal::<Option<String>>(None);
+    let (last_updated, set_last_updated) = create_signal::<Option<DateTime<Utc>>>(None);
 
-    let (bookings, set_bookings) = create_signal(Vec::<LocationBookingViewModel>::new());
     let (is_fetching_bookings, set_is_fetching_bookings) = create_signal(false);
 
     let (booking_etag, set_booking_etag) = create_signal(String::new());
+    let (test_type, set_test_type) = create_signal(TestType::default());
+    let (scraper_status, set_scraper_status) = create_signal::<Option<ScraperStatus>>(None);
+    let (preferences, set_preferences) = create_signal(SyncedPreferences::default());
+    let (dismissed_announcement, set_dismissed_announcement) = create_signal::<Option<String>>(None);
 
     // inputs for booking search
     let (booking_id_input, set_booking_id_input) = create_signal(String::new());
     let (last_name_input, set_last_name_input) = create_signal(String::new());
     let (latest_date_input, set_latest_date_input) = create_signal(String::new());
     let (find_slot_msg, set_find_slot_msg) = create_signal::<Option<String>>(None);
+    let (move_to_location, set_move_to_location) = create_signal::<Option<LocationId>>(None);
+    let (earliest_time_input, set_earliest_time_input) = create_signal(String::new());
+    let (latest_time_input, set_latest_time_input) = create_signal(String::new());
+    let (weekdays_input, set_weekdays_input) = create_signal(Vec::<Weekday>::new());
+    let (excluded_date_input, set_excluded_date_input) = create_signal(String::new());
+    let (excluded_dates, set_excluded_dates) = create_signal(Vec::<NaiveDate>::new());
 
 
     // auto finder state
     let (show_auto_panel, set_show_auto_panel) = create_signal(false);
     let (auto_active, set_auto_active) = create_signal(false);
-    let (selected_locations, set_selected_locations) = create_signal(Vec::<String>::new());
+    let (selected_locations, set_selected_locations) = create_signal(Vec::<LocationId>::new());
     let (auto_msg, set_auto_msg) = create_signal::<Option<String>>(None);
+    let (rank_locations, set_rank_locations) = create_signal(false);
+    let (auto_radius_input, set_auto_radius_input) = create_signal(String::new());
+    let (auto_status, set_auto_status) = create_signal::<Option<AutoFindStatus>>(None);
+    let (ask_approval, set_ask_approval) = create_signal(false);
+    let (pending_approval, set_pending_approval) = create_signal::<Option<PendingApproval>>(None);
+    let (clock, set_clock) = create_signal(Utc::now());
 
 
     let (reset_sort_trigger, set_reset_sort_trigger) = create_signal(());
 
     let location_manager = LocationManager::new();
+    let (centres, set_centres) = create_signal::<Option<Result<Vec<Location>, String>>>(None);
+    // Everything that names a centre reads it through `centres`, so it
+    // updates once the list has arrived
+    let centre_list = move || centres.get().and_then(Result::ok).unwrap_or_default();
+    let centre_name = move |id: LocationId| {
+        centres
+            .with(|centres| {
+                centres
+                    .as_ref()
+                    .and_then(|centres| centres.as_ref().ok())
+                    .and_then(|centres| centres.iter().find(|centre| centre.id == id))
+                    .map(|centre| centre.name.clone())
+            })
+            .unwrap_or_else(|| id.to_string())
+    };
+
+    // Fetched in the browser after first paint instead of with the page, and
+    // only once: the store keeps it for the rest of the visit
+    Effect::new(move |_| {
+        leptos::task::spawn_local(async move {
+            let cached = location_manager.get_all();
+            let result = if cached.is_empty() {
+                get_locations().await.map_err(|e| e.to_string())
+            } else {
+                Ok(cached)
+            };
+            if let Ok(locations) = &result {
+                location_manager.load(locations.clone());
+            }
+            set_centres(Some(result));
+        });
+    });
 
     let fetch_bookings = move || {
         set_is_fetching_bookings(true);
 
         leptos::task::spawn_local(async move {
-            match get_location_bookings(booking_etag.get_untracked()).await {
+            match get_location_bookings(test_type.get_untracked(), booking_etag.get_untracked()).await {
                 Ok(data) => {
                     match data {
                         Some(data) => {
-                            set_bookings(data.bookings);
                             set_last_updated(data.last_updated);
                             set_booking_etag(data.etag);
                         }
@@ -221,6 +494,10 @@ pub fn HomePage() -> impl IntoView {
                 }
             }
             set_is_fetching_bookings(false);
+
+            if let Ok(status) = get_scraper_status(test_type.get_untracked()).await {
+                set_scraper_status(Some(status));
+            }
         });
     };
 
@@ -229,8 +506,8 @@ fetch_bookings();
 
 #[cfg(not(feature = "ssr"))]
 leptos::task::spawn_local(async move {
-    if let Ok(active) = get_auto_find_status().await {
-        set_auto_active(active);
+    if let Ok(status) = get_auto_find_status(None, String::new()).await {
+        set_auto_active(status.running);
     }
 });
 
@@ -254,6 +531,64 @@ leptos::task::spawn_local(async move {
         || {}
     });
 
+    let select_test_type = move |selected: TestType| {
+        if selected == test_type.get_untracked() {
+            return;
+        }
+        set_test_type(selected);
+        set_booking_etag(String::new());
+        set_last_updated(None);
+        fetch_bookings();
+    };
+
+    // Favourites and filters are always kept in localStorage. For signed-in
+    // users they are also merged with the account, anonymous users stay
+    // local-only.
+    let sync_preferences = move || {
+        let local = preferences.get_untracked();
+        local_preferences::save(&local);
+        leptos::task::spawn_local(async move {
+            match sync_favourites(local).await {
+                Ok(Some(merged)) => {
+                    local_preferences::save(&merged);
+                    select_test_type(merged.filters.value.test_type);
+                    set_preferences(merged);
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    leptos::logging::log!("Error syncing preferences: {:?}", err);
+                }
+            }
+        });
+    };
+
+    #[cfg(not(feature = "ssr"))]
+    {
+        set_dismissed_announcement(local_preferences::dismissed_announcement());
+        let local = local_preferences::load();
+        select_test_type(local.filters.value.test_type);
+        set_preferences(local);
+        sync_preferences();
+    }
+
+    let change_test_type = move |selected: TestType| {
+        select_test_type(selected);
+        set_preferences.update(|preferences| {
+            let mut filters = preferences.filters.value.clone();
+            filters.test_type = selected;
+            preferences.set_filters(filters, Utc::now());
+        });
+        sync_preferences();
+    };
+
+    let toggle_favourite = Callback::new(move |id: LocationId| {
+        set_preferences.update(|preferences| {
+            let favourite = !preferences.is_favourite(id);
+            preferences.set_favourite(id, favourite, Utc::now());
+        });
+        sync_preferences();
+    });
+
     let handle_geocode = move |_| {
         let address = address_input.get();
         if address.is_empty() {
@@ -275,14 +610,45 @@ leptos::task::spawn_local(async move {
                     set_reset_sort_trigger(());
                 }
                 Err(err) => {
-                    set_geocoding_status(Some(format!("Error: {}", err)));
+                    set_geocoding_status(Some(err.to_string()));
                     set_is_loading(false);
                 }
             }
         });
     };
 
-    let handle_find_slot = move |_| {
+    // When the user can attend, used by both the one-off search and the auto finder
+    let slot_preferences = move || SlotPreferences {
+        earliest_time: NaiveTime::parse_from_str(&earliest_time_input.get_untracked(), "%H:%M").ok(),
+        latest_time: NaiveTime::parse_from_str(&latest_time_input.get_untracked(), "%H:%M").ok(),
+        weekdays: weekdays_input.get_untracked(),
+        excluded_dates: excluded_dates.get_untracked(),
+    };
+
+    let exclude_date = move || {
+        let Ok(date) = NaiveDate::parse_from_str(&excluded_date_input.get_untracked(), "%Y-%m-%d") else {
+            return;
+        };
+        let mut current = excluded_dates.get_untracked();
+        if !current.contains(&date) {
+            current.push(date);
+            current.sort();
+            set_excluded_dates(current);
+        }
+        set_excluded_date_input(String::new());
+    };
+
+    let toggle_weekday = move |day: Weekday| {
+        let mut current = weekdays_input.get();
+        if let Some(pos) = current.iter().position(|d| d == &day) {
+            current.remove(pos);
+        } else {
+            current.push(day);
+        }
+        set_weekdays_input(current);
+    };
+
+    let handle_find_slot = move |dry_run: bool| {
         let booking = booking_id_input.get();
         let last = last_name_input.get();
         let date = latest_date_input.get();
@@ -293,10 +659,30 @@ leptos::task::spawn_local(async move {
         }
 
         set_find_slot_msg(Some("Searching...".to_string()));
+        let preferences = slot_preferences();
+        let test_type = test_type.get_untracked();
         leptos::task::spawn_local(async move {
-            match find_first_slot(date.clone(), booking, last).await {
-                Ok(Some((loc, time))) => {
-                    set_find_slot_msg(Some(format!("Found slot at {} on {}", loc, time)));
+            match find_first_slot(test_type, date.clone(), booking, last, dry_run, Some(preferences)).await {
+                Ok(Some(receipt)) => {
+                    let name = LocationManager::new()
+                        .get_by_id(receipt.location)
+                        .map(|l| l.name)
+                        .unwrap_or_else(|| receipt.location.to_string());
+                    let when = format_slot_time_long(&receipt.start_time);
+                    let msg = match (receipt.outcome, receipt.confirmation_number) {
+                        (BookingOutcome::DryRun, _) => format!("Would book {} on {}", name, when),
+                        (BookingOutcome::ChangeFeeApplies, _) => "Not booked, changing your booking would cost a fee".to_string(),
+                        (BookingOutcome::ChangeLimitReached, _) => "Not booked, your booking can't be changed any more".to_string(),
+                        (BookingOutcome::Unconfirmed, _) => {
+                            format!("Could not confirm {} on {}, check your booking on the Service NSW website", name, when)
+                        }
+                        (BookingOutcome::PaymentRequired, _) => {
+                            format!("{} on {} is reserved but needs payment, complete it on the Service NSW website", name, when)
+                        }
+                        (_, Some(number)) => format!("Booked {} on {}, booking number {}", name, when, number),
+                        (_, None) => format!("Booked {} on {}", name, when),
+                    };
+                    set_find_slot_msg(Some(msg));
                 }
                 Ok(None) => {
                     set_find_slot_msg(Some("No slot found".to_string()));
@@ -309,7 +695,91 @@ leptos::task::spawn_local(async move {
     };
 
 
-    let toggle_location = move |loc: String| {
+    let handle_cancel_booking = move |_| {
+        let booking = booking_id_input.get();
+        let last = last_name_input.get();
+        if booking.is_empty() || last.is_empty() {
+            set_find_slot_msg(Some("Please fill in your booking ID and last name".to_string()));
+            return;
+        }
+        let sure = web_sys::window()
+            .and_then(|window| window.confirm_with_message("Cancel your test booking? This can't be undone.").ok())
+            .unwrap_or(false);
+        if !sure {
+            return;
+        }
+
+        set_find_slot_msg(Some("Cancelling...".to_string()));
+        leptos::task::spawn_local(async move {
+            let msg = match cancel_booking(booking, last).await {
+                Ok(Some(start_time)) => format!("Cancelled your booking on {}", format_slot_time_long(&start_time)),
+                Ok(None) => "Cancelled your booking".to_string(),
+                Err(e) => format!("Error: {e}"),
+            };
+            set_find_slot_msg(Some(msg));
+        });
+    };
+
+    let handle_check_rebooking = move |_| {
+        let booking = booking_id_input.get();
+        let last = last_name_input.get();
+        if booking.is_empty() || last.is_empty() {
+            set_find_slot_msg(Some("Please fill in your booking ID and last name".to_string()));
+            return;
+        }
+
+        set_find_slot_msg(Some("Checking...".to_string()));
+        leptos::task::spawn_local(async move {
+            let msg = match check_rebooking(booking, last).await {
+                Ok(eligibility) if eligibility.limit_reached => "Your booking can't be changed any more".to_string(),
+                Ok(RebookEligibility { change_fee: Some(fee), .. }) => format!("Changing your booking costs {}", fee),
+                Ok(_) => "Your booking can be changed free of charge".to_string(),
+                Err(e) => format!("Error: {e}"),
+            };
+            set_find_slot_msg(Some(msg));
+        });
+    };
+
+    let handle_change_location = move |_| {
+        let booking = booking_id_input.get();
+        let last = last_name_input.get();
+        let date = latest_date_input.get();
+        let Some(location) = move_to_location.get() else {
+            set_find_slot_msg(Some("Pick a centre to move to".to_string()));
+            return;
+        };
+        if booking.is_empty() || last.is_empty() || date.is_empty() {
+            set_find_slot_msg(Some("Please fill in all fields".to_string()));
+            return;
+        }
+
+        set_find_slot_msg(Some("Moving...".to_string()));
+        leptos::task::spawn_local(async move {
+            let msg = match change_booking_location(booking, last, location, date).await {
+                Ok(Some(receipt)) if receipt.secured() => {
+                    let name = LocationManager::new()
+                        .get_by_id(receipt.location)
+                        .map(|l| l.name)
+                        .unwrap_or_else(|| receipt.location.to_string());
+                    format!("Moved your booking to {} on {}", name, format_slot_time_long(&receipt.start_time))
+                }
+                Ok(Some(receipt)) if receipt.outcome == BookingOutcome::ChangeFeeApplies => {
+                    "Not moved, changing your booking would cost a fee".to_string()
+                }
+                Ok(Some(receipt)) if receipt.outcome == BookingOutcome::ChangeLimitReached => {
+                    "Not moved, your booking can't be changed any more".to_string()
+                }
+                Ok(Some(receipt)) if receipt.outcome == BookingOutcome::Unconfirmed => {
+                    "Could not confirm the move, check your booking on the Service NSW website".to_string()
+                }
+                Ok(_) => "No earlier slot at that centre, your booking is unchanged".to_string(),
+                Err(e) => format!("Error: {e}"),
+            };
+            set_find_slot_msg(Some(msg));
+        });
+    };
+
+    let toggle_location = move |loc: LocationId| {
         let mut current = selected_locations.get();
         if let Some(pos) = current.iter().position(|l| l == &loc) {
             current.remove(pos);
@@ -319,6 +789,28 @@ leptos::task::spawn_local(async move {
         set_selected_locations(current);
     };
 
+    // Selected centres double as the ranking, earliest picked first
+    let move_location_up = move |loc: LocationId| {
+        set_selected_locations.update(|current| {
+            if let Some(pos) = current.iter().position(|l| l == &loc).filter(|&pos| pos > 0) {
+                current.swap(pos, pos - 1);
+            }
+        });
+    };
+
+    // Status of the job for the booking in the form
+    let refresh_auto_status = move || {
+        let booking = booking_id_input.get_untracked();
+        let booking = (!booking.is_empty()).then_some(booking);
+        let last = last_name_input.get_untracked();
+        leptos::task::spawn_local(async move {
+            match get_auto_find_status(booking, last).await {
+                Ok(status) => set_auto_status(Some(status)),
+                Err(e) => set_auto_msg(Some(format!("Error: {e}"))),
+            }
+        });
+    };
+
     let toggle_auto_panel = move |_| {
         set_show_auto_panel(!show_auto_panel.get());
     };
@@ -328,34 +820,88 @@ leptos::task::spawn_local(async move {
         let last = last_name_input.get();
         let date = latest_date_input.get();
         let locs = selected_locations.get();
+        let area = auto_radius_input.get().trim().parse::<f64>().ok().filter(|km| *km > 0.0).map(|radius_km| SearchArea {
+            latitude: latitude.get_untracked(),
+            longitude: longitude.get_untracked(),
+            radius_km,
+        });
 
-        if booking.is_empty() || last.is_empty() || date.is_empty() || locs.is_empty() {
-            set_auto_msg(Some("Please fill in details and pick locations".into()));
+        if booking.is_empty() || last.is_empty() || date.is_empty() || (locs.is_empty() && area.is_none()) {
+            set_auto_msg(Some("Please fill in details and pick locations or a radius".into()));
             return;
         }
 
         set_auto_msg(Some("Processing...".into()));
         if auto_active.get() {
             leptos::task::spawn_local(async move {
-                if let Err(e) = stop_auto_find().await {
+                if let Err(e) = stop_auto_find(booking, last).await {
                     set_auto_msg(Some(format!("Error: {e}")));
                 } else {
                     set_auto_msg(Some("Auto finder stopped".into()));
                     set_auto_active(false);
                 }
+                refresh_auto_status();
             });
         } else {
+            let preferences = slot_preferences();
+            let strategy = rank_locations
+                .get_untracked()
+                .then(|| StrategyConfig::PreferredLocations { ranking: locs.clone() });
             leptos::task::spawn_local(async move {
-                if let Err(e) = start_auto_find(date.clone(), booking, last, locs).await {
+                let needs_approval = ask_approval.get_untracked();
+                if let Err(e) = start_auto_find(date.clone(), booking, last, locs, None, strategy, Some(preferences), area, needs_approval).await {
                     set_auto_msg(Some(format!("Error: {e}")));
                 } else {
                     set_auto_msg(Some("Auto finder started".into()));
                     set_auto_active(true);
                 }
+                refresh_auto_status();
             });
         }
     };
 
+    let answer_approval = move |token: String, approve: bool| {
+        set_pending_approval(None);
+        leptos::task::spawn_local(async move {
+            match answer_pending_approval(token, approve).await {
+                Ok(message) => set_auto_msg(Some(message)),
+                Err(e) => set_auto_msg(Some(format!("Error: {e}"))),
+            }
+            refresh_auto_status();
+        });
+    };
+
+    // Look for a held slot while the auto finder runs, and tick its countdown
+    #[cfg(not(feature = "ssr"))]
+    Effect::new(move |_| {
+        let poll = set_interval_with_handle(
+            move || {
+                let booking = booking_id_input.get_untracked();
+                let last = last_name_input.get_untracked();
+                if !auto_active.get_untracked() || booking.is_empty() || last.is_empty() {
+                    set_pending_approval(None);
+                    return;
+                }
+                leptos::task::spawn_local(async move {
+                    if let Ok(pending) = get_pending_approval(booking, last).await {
+                        set_pending_approval(pending);
+                    }
+                });
+            },
+            Duration::from_secs(15),
+        )
+        .expect("failed to set interval");
+        let tick = set_interval_with_handle(move || set_clock(Utc::now()), Duration::from_secs(1))
+            .expect("failed to set interval");
+
+        on_cleanup(move || {
+            poll.clear();
+            tick.clear();
+        });
+
+        || {}
+    });
+
     use leptos::wasm_bindgen::JsCast;
     use web_sys::Geolocation;
 
@@ -390,8 +936,75 @@ leptos::task::spawn_local(async move {
         <div class="max-w-4xl mx-auto p-4">
             <div class="flex justify-between items-center mb-6">
                 <h2 class="text-2xl font-bold text-gray-800">NSW Available Drivers Tests</h2>
+                <a href="/stats" class="ml-auto mr-4 text-sm text-blue-600 hover:underline">Statistics</a>
+                <a href="/account" class="mr-4 text-sm text-blue-600 hover:underline">Account</a>
+                <select
+                    class="px-3 py-1 border border-gray-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500"
+                    on:change=move |ev| {
+                        if let Ok(selected) = event_target_value(&ev).parse::<TestType>() {
+                            change_test_type(selected);
+                        }
+                    }
+                >
+                    {TestType::ALL.into_iter().map(|option| view! {
+                        <option value=option.as_str() selected=move || test_type.get() == option>
+                            {option.label()}
+                        </option>
+                    }).collect::<Vec<_>>()}
+                </select>
             </div>
 
+            {move || {
+                let announcement = scraper_status.get().and_then(|status| status.announcement)?;
+                let key = announcement.key();
+                if dismissed_announcement.get().as_deref() == Some(key.as_str()) {
+                    return None;
+                }
+                let colours = match announcement.level {
+                    NotificationLevel::Info => "border-blue-300 bg-blue-50 text-blue-900",
+                    NotificationLevel::Warning => "border-amber-400 bg-amber-50 text-amber-900",
+                    NotificationLevel::Error => "border-red-400 bg-red-50 text-red-900",
+                };
+                Some(view! {
+                    <div class=format!("mb-4 p-4 rounded-md border flex items-start gap-4 {}", colours) role="status">
+                        <p class="flex-grow">{announcement.message}</p>
+                        <button
+                            class="text-sm underline"
+                            on:click=move |_| {
+                                local_preferences::dismiss_announcement(&key);
+                                set_dismissed_announcement(Some(key.clone()));
+                            }
+                        >
+                            "Dismiss"
+                        </button>
+                    </div>
+                })
+            }}
+
+            {move || match scraper_status.get() {
+                Some(status) if status.needs_warning() => {
+                    let headline = match status.last_success.or(last_updated.get_untracked()) {
+                        Some(time) => format!(
+                            "Data may be stale \u{2014} last successful update {}",
+                            format_age(&time, Utc::now()),
+                        ),
+                        None => "Data may be stale \u{2014} no successful update yet".to_string(),
+                    };
+                    view! {
+                        <div class="mb-4 p-4 rounded-md border-2 border-amber-400 bg-amber-50 text-amber-900" role="alert">
+                            <p class="font-semibold">{headline}</p>
+                            {status.degraded.then(|| view! {
+                                <p class="text-sm">{status.reason.unwrap_or_default()}</p>
+                            })}
+                            {status.last_success.map(|time| view! {
+                                <p class="text-sm">"Last successful update: " <TimeDisplay time={time} /></p>
+                            })}
+                        </div>
+                    }.into_any()
+                }
+                _ => view! { <div class="hidden"></div> }.into_any(),
+            }}
+
             <div class="mb-6">
                 <div class="flex flex-wrap gap-4 items-end">
                     <div class="flex flex-col flex-grow">
@@ -432,7 +1045,7 @@ leptos::task::spawn_local(async move {
                     <div class="ml-auto text-sm text-gray-500">
                         {move || match last_updated.get() {
                             Some(time) => view! {
-                                <span>"Data last updated: " <TimeDisplay iso_time={time} /></span>
+                                <span>"Data last updated: " <TimeDisplay time={time} /></span>
                             }.into_any(),
                             None => view! { <span>"Data last updated: unknown"</span> }.into_any(),
                         }}
@@ -501,8 +1114,104 @@ leptos::task::spawn_local(async move {
                     />
                     <button
                         class="px-4 py-2 bg-green-600 text-white rounded-md hover:bg-green-700"
-                        on:click=move |_| handle_find_slot(())
+                        on:click=move |_| handle_find_slot(false)
                     >"Go"</button>
+                    <button
+                        class="px-4 py-2 border border-green-600 text-green-700 rounded-md hover:bg-green-50"
+                        title="Run the search and stop before confirming, to check what would be booked"
+                        on:click=move |_| handle_find_slot(true)
+                    >"Preview"</button>
+                </div>
+                <div class="mt-2 flex flex-wrap gap-2 items-center text-sm text-gray-700">
+                    "Existing booking:"
+                    <select
+                        class="px-2 py-1 border border-gray-300 rounded-md"
+                        on:change=move |ev| set_move_to_location(event_target_value(&ev).parse::<u32>().ok().map(LocationId))
+                    >
+                        <option value="">"Move to centre..."</option>
+                        {move || {
+                            let mut centres = centre_list();
+                            centres.sort_by(|a, b| a.name.cmp(&b.name));
+                            centres
+                                .into_iter()
+                                .map(|centre| view! { <option value={centre.id.to_string()}>{centre.name}</option> })
+                                .collect::<Vec<_>>()
+                        }}
+                    </select>
+                    <button
+                        class="px-3 py-1 border border-blue-600 text-blue-700 rounded-md hover:bg-blue-50"
+                        title="Move your booking to the best slot at this centre before the date above"
+                        on:click=handle_change_location
+                    >"Move"</button>
+                    <button
+                        class="px-3 py-1 border border-gray-400 text-gray-700 rounded-md hover:bg-gray-50"
+                        title="Ask the portal whether a change would cost a fee, without changing anything"
+                        on:click=handle_check_rebooking
+                    >"Check fee"</button>
+                    <button
+                        class="px-3 py-1 border border-red-600 text-red-700 rounded-md hover:bg-red-50"
+                        on:click=handle_cancel_booking
+                    >"Cancel booking"</button>
+                </div>
+                <div class="mt-2 flex flex-wrap gap-4 items-center text-sm text-gray-700">
+                    <label class="flex items-center gap-1">
+                        "Between"
+                        <input
+                            type="time"
+                            class="px-2 py-1 border border-gray-300 rounded-md"
+                            prop:value={earliest_time_input}
+                            on:input=move |ev| set_earliest_time_input(event_target_value(&ev))
+                        />
+                    </label>
+                    <label class="flex items-center gap-1">
+                        "and"
+                        <input
+                            type="time"
+                            class="px-2 py-1 border border-gray-300 rounded-md"
+                            prop:value={latest_time_input}
+                            on:input=move |ev| set_latest_time_input(event_target_value(&ev))
+                        />
+                    </label>
+                    <div class="flex flex-wrap gap-2" title="Leave all unticked for any day">
+                        {[Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun]
+                            .into_iter()
+                            .map(|day| view! {
+                                <label class="flex items-center gap-1">
+                                    <input
+                                        type="checkbox"
+                                        prop:checked=move || weekdays_input.get().contains(&day)
+                                        on:change=move |_| toggle_weekday(day)
+                                    />
+                                    {day.to_string()}
+                                </label>
+                            })
+                            .collect::<Vec<_>>()}
+                    </div>
+                </div>
+                <div class="mt-2 flex flex-wrap gap-2 items-center text-sm text-gray-700">
+                    <label class="flex items-center gap-1">
+                        "Skip"
+                        <input
+                            type="date"
+                            class="px-2 py-1 border border-gray-300 rounded-md"
+                            prop:value={excluded_date_input}
+                            on:input=move |ev| set_excluded_date_input(event_target_value(&ev))
+                        />
+                    </label>
+                    <button
+                        class="px-2 py-1 border border-gray-300 rounded-md hover:bg-gray-50"
+                        on:click=move |_| exclude_date()
+                    >"Add"</button>
+                    {move || excluded_dates.get().into_iter().map(|date| view! {
+                        <span class="inline-flex items-center gap-1 px-2 py-0.5 bg-gray-100 rounded-full">
+                            {date.format("%a %-d %b").to_string()}
+                            <button
+                                class="text-gray-500 hover:text-red-600"
+                                title="Don't skip this date"
+                                on:click=move |_| set_excluded_dates.update(|dates| dates.retain(|d| d != &date))
+                            >"×"</button>
+                        </span>
+                    }).collect::<Vec<_>>()}
                 </div>
                 <div class="mt-2 text-sm text-emerald-600">
                     {move || match find_slot_msg.get() { Some(ref m) => m.clone(), None => String::new() }}
@@ -513,16 +1222,62 @@ leptos::task::spawn_local(async move {
                     view! {
                         <div class="mt-4 p-4 border rounded-md w-full">
                             <div class="flex flex-wrap gap-2 max-h-32 overflow-y-auto">
-                                {location_manager.get_all().into_iter().map(|loc| {
-                                    let name = loc.name.clone();
+                                {centre_list().into_iter().map(|loc| {
+                                    let id = loc.id;
                                     view! {
                                         <label class="flex items-center gap-1 text-sm">
-                                            <input type="checkbox" checked={selected_locations.get().contains(&name)} on:change=move |_| toggle_location(name.clone()) />
-                                            {name.clone()}
+                                            <input type="checkbox" checked={selected_locations.get().contains(&id)} on:change=move |_| toggle_location(id) />
+                                            {loc.name.clone()}
                                         </label>
                                     }
                                 }).collect::<Vec<_>>()}
                             </div>
+                            <label class="mt-2 flex items-center gap-1 text-sm text-gray-700">
+                                "Also any centre within"
+                                <input
+                                    type="number"
+                                    min="0"
+                                    class="w-20 px-2 py-1 border border-gray-300 rounded-md"
+                                    prop:value={auto_radius_input}
+                                    on:input=move |ev| set_auto_radius_input(event_target_value(&ev))
+                                />
+                                "km of the location above"
+                            </label>
+                            <label class="mt-2 flex items-center gap-1 text-sm text-gray-700">
+                                <input
+                                    type="checkbox"
+                                    prop:checked=move || rank_locations.get()
+                                    on:change=move |ev| set_rank_locations(event_target_checked(&ev))
+                                />
+                                "Prefer centres in this order"
+                            </label>
+                            {move || rank_locations.get().then(|| view! {
+                                <ol class="mt-1 ml-5 list-decimal text-sm">
+                                    {selected_locations.get().into_iter().enumerate().map(|(index, id)| {
+                                        let name = centre_name(id);
+                                        view! {
+                                            <li>
+                                                {name}
+                                                {(index > 0).then(|| view! {
+                                                    <button
+                                                        class="ml-2 text-blue-600 hover:underline"
+                                                        title="Move up"
+                                                        on:click=move |_| move_location_up(id)
+                                                    >"↑"</button>
+                                                })}
+                                            </li>
+                                        }
+                                    }).collect::<Vec<_>>()}
+                                </ol>
+                            })}
+                            <label class="mt-2 flex items-center gap-1 text-sm text-gray-700">
+                                <input
+                                    type="checkbox"
+                                    prop:checked=move || ask_approval.get()
+                                    on:change=move |ev| set_ask_approval(event_target_checked(&ev))
+                                />
+                                "Ask me before booking"
+                            </label>
                             <div class="mt-2 flex items-center gap-4">
                                 <button class="px-4 py-2 bg-purple-600 text-white rounded-md" on:click=move |_| handle_auto_action(())>
                                     {move || if auto_active.get() { "Deactivate" } else { "Activate" }}
@@ -532,6 +1287,85 @@ leptos::task::spawn_local(async move {
                                 </span>
                             </div>
                             <div class="mt-2 text-sm text-emerald-600">{move || auto_msg.get().unwrap_or_default()}</div>
+                            {move || pending_approval.get().map(|pending| {
+                                let name = centre_name(pending.location);
+                                let expires_at = pending.expires_at;
+                                let approve_token = pending.token.clone();
+                                let decline_token = pending.token;
+                                view! {
+                                    <div class="mt-2 p-3 border border-amber-300 bg-amber-50 rounded-md text-sm">
+                                        <p class="font-medium">
+                                            {format!("Approve booking of {} at {}?", name, format_slot_time_long(&pending.start_time))}
+                                        </p>
+                                        <p class="text-gray-600">
+                                            {move || {
+                                                let left = (expires_at - clock.get()).num_seconds().max(0);
+                                                format!("Held for another {}:{:02}", left / 60, left % 60)
+                                            }}
+                                        </p>
+                                        <div class="mt-2 flex gap-2">
+                                            <button
+                                                class="px-3 py-1 bg-green-600 text-white rounded-md"
+                                                on:click=move |_| answer_approval(approve_token.clone(), true)
+                                            >"Approve"</button>
+                                            <button
+                                                class="px-3 py-1 bg-gray-200 rounded-md"
+                                                on:click=move |_| answer_approval(decline_token.clone(), false)
+                                            >"Decline"</button>
+                                        </div>
+                                    </div>
+                                }
+                            })}
+                            <div class="mt-2 text-sm text-gray-700">
+                                <button class="text-blue-600 hover:underline" on:click=move |_| refresh_auto_status()>
+                                    "Check status"
+                                </button>
+                                {move || auto_status.get().map(|status| match status.job {
+                                    Some(job) => {
+                                        let mut criteria = format!("Before {}", job.before.format("%a %-d %b"));
+                                        if !job.locations.is_empty() {
+                                            criteria.push_str(&format!(", {} centres", job.locations.len()));
+                                        }
+                                        if let Some(area) = &job.area {
+                                            criteria.push_str(&format!(", within {} km", area.radius_km));
+                                        }
+                                        if let (Some(from), Some(to)) = (job.preferences.earliest_time, job.preferences.latest_time) {
+                                            criteria.push_str(&format!(", {}-{}", from.format("%H:%M"), to.format("%H:%M")));
+                                        }
+                                        if !job.preferences.excluded_dates.is_empty() {
+                                            criteria.push_str(&format!(", skipping {} dates", job.preferences.excluded_dates.len()));
+                                        }
+                                        view! {
+                                            <dl class="mt-1 grid grid-cols-[auto_1fr] gap-x-3">
+                                                <dt class="text-gray-500">"Looking for"</dt>
+                                                <dd>{criteria}</dd>
+                                                <dt class="text-gray-500">"Attempts"</dt>
+                                                <dd>{job.attempts}</dd>
+                                                <dt class="text-gray-500">"Last attempt"</dt>
+                                                <dd>
+                                                    {match job.last_attempt {
+                                                        Some(time) => view! { <TimeDisplay time={time} /> }.into_any(),
+                                                        None => view! { <span>"Not yet"</span> }.into_any(),
+                                                    }}
+                                                    {format!(" ({})", job.status.label())}
+                                                </dd>
+                                                <dt class="text-gray-500">"Next attempt"</dt>
+                                                <dd>
+                                                    {match status.next_attempt {
+                                                        Some(next) => format!("in {} min", (next - Utc::now()).num_minutes().max(0)),
+                                                        None => "Not scheduled".to_string(),
+                                                    }}
+                                                </dd>
+                                            </dl>
+                                        }.into_any()
+                                    }
+                                    None => view! {
+                                        <p class="mt-1">
+                                            {if status.running { "No job for this booking" } else { "The auto finder isn't running" }}
+                                        </p>
+                                    }.into_any(),
+                                })}
+                            </div>
                         </div>
                     }
                 } else { view!{ <div class="hidden"></div> } }
@@ -539,14 +1373,26 @@ leptos::task::spawn_local(async move {
 
             </div>
 
-            <LocationsTable
-                bookings=bookings
-                is_loading=is_fetching_bookings
-                latitude=latitude
-                longitude=longitude
-                location_manager=location_manager.clone()
-                reset_sort_trigger=reset_sort_trigger
-            />
+            {move || match centres.get() {
+                None => view! {
+                    <div class="text-center py-8 text-gray-500">"Loading locations..."</div>
+                }.into_any(),
+                Some(Ok(_)) => view! {
+                    <LocationsTable
+                        booking_etag=booking_etag
+                        is_loading=is_fetching_bookings
+                        latitude=latitude
+                        longitude=longitude
+                        test_type=test_type
+                        reset_sort_trigger=reset_sort_trigger
+                        preferences=preferences
+                        on_favourite=toggle_favourite
+                    />
+                }.into_any(),
+                Some(Err(e)) => view! {
+                    <div class="text-center py-8 text-red-600">{format!("Failed to load locations: {}", e)}</div>
+                }.into_any(),
+            }}
 
             <div class="mt-6 flex justify-between items-center">
                 <div class="text-sm text-gray-500">
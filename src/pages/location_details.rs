@@ -1,75 +1,116 @@
 use std::collections::HashMap;
 use std::time::Duration;
 
+use chrono::{NaiveDate, NaiveDateTime};
 use leptos::prelude::*;
 use leptos::server_fn::error::NoCustomError;
 use reqwest::header;
 use serde::{Deserialize, Serialize};
 use web_sys::wasm_bindgen::prelude::Closure;
 
-use crate::data::location::LocationManager;
-use crate::data::shared_booking::TimeSlot;
-use crate::utils::date::format_iso_date;
+use crate::accounts::SyncedPreferences;
+use crate::data::location::{LocationId, LocationManager};
+use crate::data::shared_booking::{BookingOutcome, TestType, TimeSlot};
+use crate::utils::date::{format_datetime, format_slot_time, format_slot_time_long};
 use crate::utils::geocoding::geocode_address;
+use crate::utils::holidays::is_near_public_holiday;
 
-use crate::pages::home::get_location_details;
+use crate::pages::home::{book_slot, get_location_details};
 
 #[component]
-pub fn ExpandedLocationDetails(location_id: String, expanded: ReadSignal<bool>) -> impl IntoView {
+pub fn ExpandedLocationDetails(
+    location_id: LocationId,
+    test_type: ReadSignal<TestType>,
+    expanded: ReadSignal<bool>,
+    preferences: ReadSignal<SyncedPreferences>,
+) -> impl IntoView {
     let (slots, set_slots) = create_signal(Vec::<TimeSlot>::new());
     let (is_loading, set_is_loading) = create_signal(false);
     let (error, set_error) = create_signal::<Option<String>>(None);
 
     let (location_etag, set_location_etag) = create_signal(String::new());
 
+    // booking a specific slot
+    let (selected_slot, set_selected_slot) = create_signal::<Option<NaiveDateTime>>(None);
+    let (booking_id_input, set_booking_id_input) = create_signal(String::new());
+    let (last_name_input, set_last_name_input) = create_signal(String::new());
+    let (is_booking, set_is_booking) = create_signal(false);
+    let (booking_msg, set_booking_msg) = create_signal::<Option<String>>(None);
+
+    let handle_book_slot = move |_| {
+        let Some(start_time) = selected_slot.get() else {
+            return;
+        };
+        let booking_id = booking_id_input.get();
+        let last_name = last_name_input.get();
+        if booking_id.is_empty() || last_name.is_empty() {
+            set_booking_msg(Some("Enter your booking number and last name".to_string()));
+            return;
+        }
+
+        set_is_booking(true);
+        set_booking_msg(Some("Booking, this can take a minute...".to_string()));
+
+        leptos::task::spawn_local(async move {
+            let msg = match book_slot(test_type.get_untracked(), location_id, start_time, booking_id, last_name).await {
+                Ok(BookingOutcome::Booked) => format!("Booked {}", format_slot_time_long(&start_time)),
+                Ok(BookingOutcome::PaymentRequired) => format!(
+                    "{} is reserved but needs payment, complete it on the Service NSW website",
+                    format_slot_time_long(&start_time)
+                ),
+                Ok(BookingOutcome::NotAnImprovement) => format!(
+                    "Not booked, {} is not earlier than your current booking",
+                    format_slot_time_long(&start_time)
+                ),
+                Ok(BookingOutcome::ChangeFeeApplies) => "Not booked, changing your booking would cost a fee".to_string(),
+                Ok(BookingOutcome::ChangeLimitReached) => "Not booked, your booking can't be changed any more".to_string(),
+                Ok(BookingOutcome::Unconfirmed) => format!(
+                    "Could not confirm {}, check your booking on the Service NSW website",
+                    format_slot_time_long(&start_time)
+                ),
+                Ok(_) => format!("{} is no longer available", format_slot_time_long(&start_time)),
+                Err(e) => format!("Booking failed: {}", e),
+            };
+            set_booking_msg(Some(msg));
+            set_is_booking(false);
+        });
+    };
+
     let slots_by_date = create_memo(move |_| {
-        let mut grouped: HashMap<String, Vec<TimeSlot>> = HashMap::new();
+        let mut grouped: HashMap<NaiveDate, Vec<TimeSlot>> = HashMap::new();
 
+        let hide_near_holidays = preferences.with(|preferences| preferences.filters.value.hide_near_holidays);
         for slot in slots.get().iter() {
-            if slot.availability {
-                if let Some(date_part) = slot.start_time.split_whitespace().next() {
-                    let entry = grouped
-                        .entry(date_part.to_string())
-                        .or_insert_with(Vec::new);
-                    entry.push(slot.clone());
-                }
+            if slot.availability && !(hide_near_holidays && is_near_public_holiday(slot.start_time.date())) {
+                grouped
+                    .entry(slot.start_time.date())
+                    .or_insert_with(Vec::new)
+                    .push(slot.clone());
             }
         }
 
         let mut dates: Vec<_> = grouped.into_iter().collect();
-        dates.sort_by(|(date_a, _), (date_b, _)| {
-            let parts_a: Vec<&str> = date_a.split('/').collect();
-            let parts_b: Vec<&str> = date_b.split('/').collect();
-
-            if parts_a.len() == 3 && parts_b.len() == 3 {
-                let year_compare = parts_a[2].cmp(parts_b[2]);
-                if year_compare != std::cmp::Ordering::Equal {
-                    return year_compare;
-                }
-
-                let month_compare = parts_a[1].cmp(parts_b[1]);
-                if month_compare != std::cmp::Ordering::Equal {
-                    return month_compare;
-                }
-
-                return parts_a[0].cmp(parts_b[0]);
-            }
-
-            date_a.cmp(date_b)
-        });
+        dates.sort_by_key(|(date, _)| *date);
+        for (_, slots) in dates.iter_mut() {
+            slots.sort();
+        }
 
         dates
     });
 
     create_effect(move |_| {
-        if expanded.get() {
-            let location_id_clone = location_id.clone();
+        // A different dataset, the cached slots and etag no longer apply
+        test_type.get();
+        set_location_etag(String::new());
+    });
 
+    create_effect(move |_| {
+        if expanded.get() {
             set_is_loading(true);
             set_error(None);
 
             leptos::task::spawn_local(async move {
-                match get_location_details(location_id_clone, location_etag.get_untracked()).await {
+                match get_location_details(test_type.get_untracked(), location_id, location_etag.get_untracked()).await {
                     Ok(response) => match response {
                         Some(response) => {
                             set_slots(response.slots);
@@ -89,7 +130,7 @@ pub fn ExpandedLocationDetails(location_id: String, expanded: ReadSignal<bool>)
     view! {
         <Show when=move || expanded.get()>
             <tr>
-                <td colspan="5" class="px-6 py-4 bg-gray-50">
+                <td colspan="6" class="px-6 py-4 bg-gray-50">
                     {move || {
                         if is_loading.get() {
                             view! {
@@ -111,24 +152,31 @@ pub fn ExpandedLocationDetails(location_id: String, expanded: ReadSignal<bool>)
                             } else {
                                 view! {
                                     <div class="max-h-80 overflow-y-auto">
-                                        <h3 class="text-lg font-medium mb-2">Available Times</h3>
+                                        <h3 class="text-lg font-medium mb-2">{move || format!("Available Times: {}", test_type.get().label())}</h3>
                                         <div class="space-y-4">
                                             {dates.into_iter().map(|(date, slots)| {
                                                 view! {
                                                     <div class="border-b border-gray-200 pb-2">
-                                                        <h4 class="font-medium text-gray-700 mb-1">{date}</h4>
+                                                        <h4 class="font-medium text-gray-700 mb-1">{date.format("%d/%m/%Y").to_string()}</h4>
                                                         <div class="flex flex-wrap gap-2">
                                                             {slots.into_iter().map(|slot| {
-                                                                let time_only = slot.start_time
-                                                                    .split_whitespace()
-                                                                    .nth(1)
-                                                                    .unwrap_or(&slot.start_time)
-                                                                    .to_string();
+                                                                let start_time = slot.start_time;
+                                                                let time_only = start_time.format("%H:%M").to_string();
 
                                                                 view! {
-                                                                    <span class="inline-block bg-green-100 text-green-800 px-2 py-1 text-sm rounded">
+                                                                    <button
+                                                                        class=move || if selected_slot.get() == Some(start_time) {
+                                                                            "inline-block bg-green-600 text-white px-2 py-1 text-sm rounded"
+                                                                        } else {
+                                                                            "inline-block bg-green-100 text-green-800 px-2 py-1 text-sm rounded hover:bg-green-200"
+                                                                        }
+                                                                        on:click=move |_| {
+                                                                            set_selected_slot(Some(start_time));
+                                                                            set_booking_msg(None);
+                                                                        }
+                                                                    >
                                                                         {time_only}
-                                                                    </span>
+                                                                    </button>
                                                                 }
                                                             }).collect::<Vec<_>>()}
                                                         </div>
@@ -136,6 +184,37 @@ pub fn ExpandedLocationDetails(location_id: String, expanded: ReadSignal<bool>)
                                                 }
                                             }).collect::<Vec<_>>()}
                                         </div>
+                                        <Show when=move || selected_slot.get().is_some()>
+                                            <div class="mt-4 p-3 border border-gray-200 rounded-md bg-white">
+                                                <p class="text-sm font-medium text-gray-700 mb-2">
+                                                    {move || selected_slot.get().map(|time| format!("Book {}", format_slot_time(&time))).unwrap_or_default()}
+                                                </p>
+                                                <div class="flex flex-wrap gap-2 items-center">
+                                                    <input
+                                                        type="text"
+                                                        placeholder="Booking number"
+                                                        class="px-2 py-1 border border-gray-300 rounded-md text-sm"
+                                                        prop:value=booking_id_input
+                                                        on:input=move |ev| set_booking_id_input(event_target_value(&ev))
+                                                    />
+                                                    <input
+                                                        type="text"
+                                                        placeholder="Last name"
+                                                        class="px-2 py-1 border border-gray-300 rounded-md text-sm"
+                                                        prop:value=last_name_input
+                                                        on:input=move |ev| set_last_name_input(event_target_value(&ev))
+                                                    />
+                                                    <button
+                                                        class="px-3 py-1 bg-green-600 text-white rounded-md text-sm hover:bg-green-700 disabled:opacity-50"
+                                                        disabled=is_booking
+                                                        on:click=handle_book_slot
+                                                    >
+                                                        "Book this slot"
+                                                    </button>
+                                                </div>
+                                                <div class="mt-2 text-sm text-emerald-600">{move || booking_msg.get().unwrap_or_default()}</div>
+                                            </div>
+                                        </Show>
                                     </div>
                                 }.into_any()
                             }
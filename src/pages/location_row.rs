@@ -7,9 +7,11 @@ use reqwest::header;
 use serde::{Deserialize, Serialize};
 use web_sys::wasm_bindgen::prelude::Closure;
 
-use crate::data::location::LocationManager;
-use crate::data::shared_booking::TimeSlot;
-use crate::utils::date::format_iso_date;
+use crate::accounts::SyncedPreferences;
+use crate::data::location::{LocationId, LocationManager};
+use crate::data::shared_booking::{TestType, TimeSlot};
+use crate::data::stats::AvailabilityStats;
+use crate::utils::date::{format_slot_time, format_slot_time_long};
 use crate::utils::geocoding::geocode_address;
 
 use crate::pages::location_details::ExpandedLocationDetails;
@@ -19,9 +21,15 @@ pub fn LocationRow(
     loc: crate::data::location::Location,
     distance: f64,
     earliest_slot: Option<TimeSlot>,
+    stats: Option<AvailabilityStats>,
     is_loading: ReadSignal<bool>,
+    test_type: ReadSignal<TestType>,
+    preferences: ReadSignal<SyncedPreferences>,
+    on_favourite: Callback<LocationId>,
 ) -> impl IntoView {
     let (expanded, set_expanded) = create_signal(false);
+    let id = loc.id;
+    let is_favourite = move || preferences.with(|preferences| preferences.is_favourite(id));
 
     let toggle_expand = move |_| {
         set_expanded.update(|val| *val = !*val);
@@ -36,6 +44,16 @@ pub fn LocationRow(
                 on:click=toggle_expand>
 
                 <td class="px-2 py-3 md:px-4 md:py-3 whitespace-nowrap text-sm font-medium text-gray-900 truncate">
+                    <button
+                        class=move || if is_favourite() { "mr-1 text-amber-500" } else { "mr-1 text-gray-300 hover:text-amber-400" }
+                        title=move || if is_favourite() { "Remove from favourites" } else { "Add to favourites" }
+                        on:click=move |ev| {
+                            ev.stop_propagation();
+                            on_favourite.run(id);
+                        }
+                    >
+                        {move || if is_favourite() { "\u{2605}" } else { "\u{2606}" }}
+                    </button>
                     {loc.name}
                 </td>
 
@@ -46,7 +64,7 @@ pub fn LocationRow(
                 <td class="px-1 py-3 md:px-3 md:py-3 whitespace-nowrap text-sm text-gray-500">
                     {match earliest_slot {
                         Some(slot) => view! {
-                            <span class="text-green-600 font-medium">{slot.start_time}</span>
+                            <span class="text-green-600 font-medium" title=format_slot_time_long(&slot.start_time)>{format_slot_time(&slot.start_time)}</span>
                         }.into_any(),
                         None => {
                             if is_loading.get_untracked() {
@@ -116,6 +134,25 @@ pub fn LocationRow(
                     }}
                 </td>
 
+                <td class="hidden md:table-cell px-1 py-3 md:px-3 md:py-3 whitespace-nowrap text-sm text-gray-500">
+                    {match stats.as_ref().and_then(|stats| stats.mean_lead_days.map(|lead| (stats, lead))) {
+                        Some((stats, lead)) => {
+                            let mut tooltip = format!(
+                                "Earliest slot {:.1} days away on average, {:.1} free slots per scrape",
+                                lead, stats.mean_available_slots
+                            );
+                            if let Some(volatility) = stats.lead_volatility_days {
+                                tooltip.push_str(&format!(", varies by \u{b1}{:.1} days", volatility));
+                            }
+                            tooltip.push_str(&format!(" (over {} days)", stats.days));
+                            view! {
+                                <span class="cursor-help" title=tooltip>{format!("{:.0} days", lead)}</span>
+                            }.into_any()
+                        }
+                        None => view! { <span class="text-gray-400">"-"</span> }.into_any(),
+                    }}
+                </td>
+
                 <td class="px-6 py-4 whitespace-nowrap text-sm text-center">
                     <span class={move || {
                         if expanded.get() {
@@ -132,8 +169,10 @@ pub fn LocationRow(
             </tr>
 
             <ExpandedLocationDetails
-                location_id=loc.id.to_string()
+                location_id=loc.id
+                test_type=test_type
                 expanded=expanded
+                preferences=preferences
             />
         </>
     }
@@ -1,4 +1,3 @@
-use std::collections::HashMap;
 use std::time::Duration;
 
 use leptos::prelude::*;
@@ -7,27 +6,36 @@ use reqwest::header;
 use serde::{Deserialize, Serialize};
 use web_sys::wasm_bindgen::prelude::Closure;
 
-use crate::data::location::LocationManager;
-use crate::data::shared_booking::TimeSlot;
-use crate::utils::date::format_iso_date;
+use crate::accounts::SyncedPreferences;
+use crate::data::location::LocationId;
+use crate::data::shared_booking::TestType;
+use crate::data::stats::AvailabilityStats;
+use crate::utils::date::format_datetime;
 use crate::utils::geocoding::geocode_address;
-
-use crate::pages::home::LocationBookingViewModel;
+use crate::utils::sort::{LocationsQuery, SortColumn, SortDirection, SortedLocation};
 
 use crate::pages::location_row::LocationRow;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum SortColumn {
-    Name,
-    Distance,
-    EarliestSlot,
-    PassRate,
+/// The locations table rows, distances and earliest slots joined and sorted
+/// on the server.
+#[server(GetSortedLocations)]
+pub async fn get_sorted_locations(query: LocationsQuery) -> Result<Vec<SortedLocation>, ServerFnError> {
+    use crate::data::booking::BookingManager;
+    Ok(BookingManager::sorted_locations(&query))
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum SortDirection {
-    Ascending,
-    Descending,
+/// Per-location lead time, free slots and volatility from the recorded
+/// availability history.
+#[server(GetAvailabilityStats)]
+pub async fn get_availability_stats(test_type: TestType) -> Result<Vec<AvailabilityStats>, ServerFnError> {
+    use crate::data::stats;
+    use crate::settings::Settings;
+
+    let settings = Settings::load()
+        .map_err(|e| ServerFnError::<NoCustomError>::ServerError(format!("Failed to load settings: {}", e)))?;
+    let stats = stats::cached_availability_stats(&settings, test_type)
+        .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e))?;
+    Ok(stats.as_ref().clone())
 }
 
 #[component]
@@ -78,21 +86,15 @@ fn SortableHeader(
 
 #[component]
 pub fn LocationsTable(
-    bookings: ReadSignal<Vec<LocationBookingViewModel>>,
+    booking_etag: ReadSignal<String>,
     is_loading: ReadSignal<bool>,
     latitude: ReadSignal<f64>,
     longitude: ReadSignal<f64>,
-    location_manager: LocationManager,
     reset_sort_trigger: ReadSignal<()>,
+    test_type: ReadSignal<TestType>,
+    preferences: ReadSignal<SyncedPreferences>,
+    on_favourite: Callback<LocationId>,
 ) -> impl IntoView {
-    let booking_map = create_memo(move |_| {
-        bookings
-            .get()
-            .into_iter()
-            .map(|booking| (booking.location.clone(), booking.earliest_slot))
-            .collect::<HashMap<String, Option<TimeSlot>>>()
-    });
-
     let (sort_column, set_sort_column) = create_signal(SortColumn::Distance);
     let (sort_direction, set_sort_direction) = create_signal(SortDirection::Ascending);
 
@@ -117,45 +119,23 @@ pub fn LocationsTable(
         }
     };
 
-    let sorted_locations = create_memo(move |_| {
-        let mut locations_by_distance =
-            location_manager.get_by_distance(latitude.get(), longitude.get());
-        let booking_data = booking_map.get();
-        let column = sort_column.get();
-        let direction = sort_direction.get();
-
-        let mut locations_with_data: Vec<_> = locations_by_distance
-            .into_iter()
-            .map(|(loc, distance)| {
-                let location_id = loc.id.to_string();
-                let earliest_slot = booking_data.get(&location_id).cloned().flatten();
-                (loc, distance, earliest_slot)
-            })
-            .collect();
-
-        locations_with_data.sort_by(|a, b| {
-            let ordering = match column {
-                SortColumn::Name => a.0.name.cmp(&b.0.name),
-                SortColumn::Distance => a.1.total_cmp(&b.1),
-                SortColumn::EarliestSlot => {
-                    match (&a.2, &b.2) {
-                        (Some(slot_a), Some(slot_b)) => slot_a.cmp(&slot_b),
-                        (Some(_), None) => std::cmp::Ordering::Less,
-                        (None, Some(_)) => std::cmp::Ordering::Greater,
-                        (None, None) => std::cmp::Ordering::Equal,
-                    }
-                },
-                SortColumn::PassRate => b.0.pass_rate.partial_cmp(&a.0.pass_rate).unwrap_or(std::cmp::Ordering::Equal),
+    // Refetched whenever the search, the sort or the data (its ETag) changes
+    let sorted_locations = Resource::new(
+        move || {
+            let query = LocationsQuery {
+                test_type: test_type.get(),
+                lat: latitude.get(),
+                lng: longitude.get(),
+                sort: sort_column.get(),
+                direction: sort_direction.get(),
+                max_distance_km: preferences.with(|preferences| preferences.filters.value.max_distance_km),
+                hide_near_holidays: preferences.with(|preferences| preferences.filters.value.hide_near_holidays),
             };
-
-            match direction {
-                SortDirection::Ascending => ordering,
-                SortDirection::Descending => ordering.reverse(),
-            }
-        });
-
-        locations_with_data
-    });
+            (query, booking_etag.get())
+        },
+        |(query, _)| get_sorted_locations(query),
+    );
+    let availability_stats = Resource::new(move || test_type.get(), get_availability_stats);
 
     view! {
         <div>
@@ -181,6 +161,7 @@ pub fn LocationsTable(
                         <col style="width: 12%;" />
                         <col style="width: 28%;" />
                         <col style="width: 15%;" />
+                        <col class="hidden md:table-column" style="width: 12%;" />
                         <col style="width: 10%;" />
                     </colgroup>
                     <thead class="bg-gray-50">
@@ -217,26 +198,40 @@ pub fn LocationsTable(
                                 title="Pass Rate"
                                 mobile_title=Some("Pass %")
                             />
+                            <th
+                                class="hidden md:table-cell px-1 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider"
+                                title="Average days to the earliest slot over the recorded history"
+                            >
+                                "Usual Wait"
+                            </th>
                             <th class="px-1 py-2 text-center text-xs font-medium text-gray-500 uppercase tracking-wider">
                                 <span class="sr-only">Details</span>
                             </th>
                         </tr>
                     </thead>
                     <tbody class="divide-y divide-gray-200">
+                        <Transition>
                         {move || {
-                            let locations_data = sorted_locations.get();
+                            let locations_data = sorted_locations.get().and_then(Result::ok).unwrap_or_default();
+                            let stats = availability_stats.get().and_then(Result::ok).unwrap_or_default();
 
-                            locations_data.into_iter().map(|(loc, distance, earliest_slot)| {
+                            locations_data.into_iter().map(|row| {
+                                let stats = stats.iter().find(|stats| stats.location == row.location.id).cloned();
                                 view! {
                                     <LocationRow
-                                        loc=loc
-                                        distance=distance
-                                        earliest_slot=earliest_slot
+                                        loc=row.location
+                                        distance=row.distance_km
+                                        earliest_slot=row.earliest_slot
+                                        stats=stats
                                         is_loading=is_loading
+                                        test_type=test_type
+                                        preferences=preferences
+                                        on_favourite=on_favourite
                                     />
                                 }
                             }).collect::<Vec<_>>()
                         }}
+                        </Transition>
                     </tbody>
                 </table>
             </div>
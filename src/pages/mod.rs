@@ -1,4 +1,7 @@
+pub mod account;
+pub mod admin;
 pub mod home;
+pub mod stats;
 mod location_details;
 mod location_row;
 mod location_table;
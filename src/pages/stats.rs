@@ -0,0 +1,114 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::data::history::CentreStats;
+use crate::data::shared_booking::TestType;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CentreStatsRow {
+    pub name: String,
+    pub stats: CentreStats,
+}
+
+/// Per-centre statistics from the recorded availability history, sorted by
+/// centre name.
+#[server(GetStatistics)]
+pub async fn get_statistics(test_type: TestType) -> Result<Vec<CentreStatsRow>, ServerFnError> {
+    use leptos::server_fn::error::NoCustomError;
+    use crate::data::history;
+    use crate::data::location::LocationManager;
+    use crate::settings::Settings;
+
+    let settings = Settings::load()
+        .map_err(|e| ServerFnError::<NoCustomError>::ServerError(format!("Failed to load settings: {}", e)))?;
+    let stats = history::cached_stats(&settings.history_file(), settings.history_cutoff(), test_type)
+        .map_err(|e| ServerFnError::<NoCustomError>::ServerError(e))?;
+
+    let location_manager = LocationManager::new();
+    let mut rows: Vec<CentreStatsRow> = stats
+        .centres
+        .iter()
+        .filter_map(|stats| {
+            let location = location_manager.get_by_id(stats.location)?;
+            Some(CentreStatsRow { name: location.name, stats: stats.clone() })
+        })
+        .collect();
+    rows.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(rows)
+}
+
+fn format_days(days: Option<f64>) -> String {
+    days.map(|days| format!("{:.1} days", days)).unwrap_or_else(|| "-".to_string())
+}
+
+fn format_rate(rate: Option<f64>) -> String {
+    rate.map(|rate| format!("{:.1} / week", rate)).unwrap_or_else(|| "-".to_string())
+}
+
+#[component]
+pub fn StatsPage() -> impl IntoView {
+    let (test_type, set_test_type) = create_signal(TestType::default());
+    let statistics = Resource::new(move || test_type.get(), get_statistics);
+
+    view! {
+        <div class="max-w-4xl mx-auto p-4">
+            <div class="flex justify-between items-center mb-2">
+                <h2 class="text-2xl font-bold text-gray-800">Test Centre Statistics</h2>
+                <select
+                    class="px-3 py-1 border border-gray-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500"
+                    on:change=move |ev| {
+                        if let Ok(selected) = event_target_value(&ev).parse::<TestType>() {
+                            set_test_type(selected);
+                        }
+                    }
+                >
+                    {TestType::ALL.into_iter().map(|option| view! {
+                        <option value=option.as_str() selected=move || test_type.get() == option>
+                            {option.label()}
+                        </option>
+                    }).collect::<Vec<_>>()}
+                </select>
+            </div>
+            <p class="mb-4 text-sm text-gray-500">
+                "Built from the availability seen by each scrape. Lead time is how far away the earliest slot was; same-week cancellations are slots within a week that appeared between scrapes; the busiest weekdays have the fewest free slots. "
+                <a href="/" class="text-blue-600 hover:underline">"Back to availability"</a>
+            </p>
+
+            <Suspense fallback=|| view! { <p class="text-sm text-gray-500">"Loading statistics..."</p> }>
+                {move || statistics.get().map(|result| match result {
+                    Ok(rows) if rows.is_empty() => view! {
+                        <p class="text-sm text-gray-500">"No history has been recorded yet."</p>
+                    }.into_any(),
+                    Ok(rows) => view! {
+                        <table class="min-w-full divide-y divide-gray-200 text-sm">
+                            <thead class="bg-gray-50">
+                                <tr>
+                                    <th class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">Centre</th>
+                                    <th class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">Median lead time</th>
+                                    <th class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">Same-week cancellations</th>
+                                    <th class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">Busiest weekdays</th>
+                                </tr>
+                            </thead>
+                            <tbody class="bg-white divide-y divide-gray-200">
+                                {rows.into_iter().map(|row| view! {
+                                    <tr>
+                                        <td class="px-2 py-2 text-gray-800">{row.name}</td>
+                                        <td class="px-2 py-2 text-gray-600">{format_days(row.stats.median_lead_days)}</td>
+                                        <td class="px-2 py-2 text-gray-600">{format_rate(row.stats.same_week_cancellations_per_week)}</td>
+                                        <td class="px-2 py-2 text-gray-600">
+                                            {row.stats.busiest_weekdays.iter().take(2).map(|day| day.to_string()).collect::<Vec<_>>().join(", ")}
+                                        </td>
+                                    </tr>
+                                }).collect::<Vec<_>>()}
+                            </tbody>
+                        </table>
+                    }.into_any(),
+                    Err(e) => view! {
+                        <p class="text-sm text-red-600">{format!("Failed to load statistics: {}", e)}</p>
+                    }.into_any(),
+                })}
+            </Suspense>
+        </div>
+    }
+}
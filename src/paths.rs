@@ -0,0 +1,88 @@
+//! Resolution of the configuration and data directories.
+//!
+//! Lookup order for the config directory (holding `settings.yaml` and `.env`):
+//! 1. `--config-dir <path>` on the command line
+//! 2. the `NSW_DRIVERS_TEST_CONFIG_DIR` environment variable
+//! 3. the current directory, if it contains `settings.yaml` (the historical layout)
+//! 4. the platform config directory, e.g. `~/.config/nsw-drivers-test` or
+//!    `%APPDATA%\nsw-drivers-test`
+//!
+//! The data directory follows the same pattern with `--data-dir`,
+//! `NSW_DRIVERS_TEST_DATA_DIR`, `./data` and the platform data directory, and
+//! can also be set with `data_dir` in `settings.yaml`.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const APP_DIR_NAME: &str = "nsw-drivers-test";
+const CONFIG_DIR_ENV: &str = "NSW_DRIVERS_TEST_CONFIG_DIR";
+const DATA_DIR_ENV: &str = "NSW_DRIVERS_TEST_DATA_DIR";
+
+static APP_PATHS: OnceLock<AppPaths> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+pub struct AppPaths {
+    pub config_dir: PathBuf,
+    pub data_dir: PathBuf,
+}
+
+/// Paths for this process, resolved once from the command line and environment.
+pub fn app_paths() -> &'static AppPaths {
+    APP_PATHS.get_or_init(|| AppPaths::resolve(env::args().skip(1)))
+}
+
+impl AppPaths {
+    pub fn resolve<I: IntoIterator<Item = String>>(args: I) -> Self {
+        let args: Vec<String> = args.into_iter().collect();
+
+        let config_dir = arg_value(&args, "--config-dir")
+            .or_else(|| env::var_os(CONFIG_DIR_ENV).map(PathBuf::from))
+            .or_else(|| {
+                let cwd = env::current_dir().ok()?;
+                cwd.join("settings.yaml").exists().then_some(cwd)
+            })
+            .or_else(|| dirs::config_dir().map(|dir| dir.join(APP_DIR_NAME)))
+            .unwrap_or_else(|| PathBuf::from("."));
+
+        let data_dir = arg_value(&args, "--data-dir")
+            .or_else(|| env::var_os(DATA_DIR_ENV).map(PathBuf::from))
+            .or_else(|| {
+                let legacy = config_dir.join("data");
+                legacy.is_dir().then_some(legacy)
+            })
+            .or_else(|| dirs::data_dir().map(|dir| dir.join(APP_DIR_NAME)))
+            .unwrap_or_else(|| PathBuf::from("data"));
+
+        Self { config_dir, data_dir }
+    }
+
+    pub fn settings_file(&self) -> PathBuf {
+        self.config_dir.join("settings.yaml")
+    }
+
+    pub fn env_file(&self) -> PathBuf {
+        self.config_dir.join(".env")
+    }
+
+    /// Resolve a path from the settings file: absolute paths are kept,
+    /// relative ones are taken relative to the config directory.
+    pub fn resolve_relative(&self, path: &Path) -> PathBuf {
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.config_dir.join(path)
+        }
+    }
+}
+
+fn arg_value(args: &[String], flag: &str) -> Option<PathBuf> {
+    let prefix = format!("{}=", flag);
+    args.iter().enumerate().find_map(|(idx, arg)| {
+        if arg == flag {
+            args.get(idx + 1).map(PathBuf::from)
+        } else {
+            arg.strip_prefix(&prefix).map(PathBuf::from)
+        }
+    })
+}
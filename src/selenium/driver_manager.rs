@@ -0,0 +1,187 @@
+//! With `manage_driver`, setting up Selenium is no longer the user's job: the
+//! chromedriver build matching the installed Chrome is downloaded from Chrome
+//! for Testing into `data_dir/drivers`, started on a free local port, and
+//! `selenium_driver_url`/`driver_command` are pointed at it so the health
+//! check restarts it like any other managed driver.
+
+use serde_json::Value;
+
+/// Every Chrome for Testing release with its download links, oldest first.
+pub const VERSIONS_URL: &str =
+    "https://googlechromelabs.github.io/chrome-for-testing/known-good-versions-with-downloads.json";
+
+/// The version in `chrome --version` output, e.g. `126.0.6478.126` from
+/// `Google Chrome 126.0.6478.126`.
+pub fn parse_chrome_version(output: &str) -> Option<String> {
+    output
+        .split_whitespace()
+        .find(|word| {
+            let parts: Vec<&str> = word.split('.').collect();
+            parts.len() == 4 && parts.iter().all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+        })
+        .map(str::to_string)
+}
+
+/// Chrome for Testing's name for the platform this was built for.
+pub fn platform() -> Option<&'static str> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => Some("linux64"),
+        ("macos", "aarch64") => Some("mac-arm64"),
+        ("macos", "x86_64") => Some("mac-x64"),
+        ("windows", "x86_64") => Some("win64"),
+        ("windows", "x86") => Some("win32"),
+        _ => None,
+    }
+}
+
+/// Download link of the chromedriver for `chrome_version`: the exact release
+/// if listed, otherwise the newest of the same build (the first three parts),
+/// which Chrome guarantees to be compatible.
+pub fn chromedriver_url(versions: &Value, chrome_version: &str, platform: &str) -> Option<String> {
+    let build = chrome_version.rsplit_once('.').map_or(chrome_version, |(build, _)| build);
+    let releases = versions.get("versions")?.as_array()?;
+
+    let url_of = |release: &Value| -> Option<String> {
+        release
+            .get("downloads")?
+            .get("chromedriver")?
+            .as_array()?
+            .iter()
+            .find(|download| download.get("platform").and_then(Value::as_str) == Some(platform))?
+            .get("url")?
+            .as_str()
+            .map(str::to_string)
+    };
+    let version_of = |release: &Value| release.get("version").and_then(Value::as_str).unwrap_or_default().to_string();
+
+    releases
+        .iter()
+        .find(|release| version_of(release) == chrome_version)
+        .and_then(url_of)
+        .or_else(|| {
+            releases
+                .iter()
+                .rev()
+                .filter(|release| version_of(release).starts_with(&format!("{}.", build)))
+                .find_map(url_of)
+        })
+}
+
+#[cfg(feature = "scraper")]
+pub use self::install::start;
+
+#[cfg(feature = "scraper")]
+mod install {
+    use std::io::{Cursor, Read};
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    use serde_json::Value;
+    use tracing::info;
+
+    use super::{chromedriver_url, parse_chrome_version, platform, VERSIONS_URL};
+    use crate::selenium::health;
+    use crate::settings::Settings;
+
+    const CHROME_BINARIES: &[&str] = &[
+        "google-chrome",
+        "google-chrome-stable",
+        "chromium",
+        "chromium-browser",
+        "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+        "/Applications/Chromium.app/Contents/MacOS/Chromium",
+    ];
+
+    fn chrome_version(settings: &Settings) -> Result<String, String> {
+        let candidates: Vec<&str> = match &settings.chrome_binary {
+            Some(binary) => vec![binary.as_str()],
+            None => CHROME_BINARIES.to_vec(),
+        };
+
+        candidates
+            .into_iter()
+            .filter_map(|binary| Command::new(binary).arg("--version").output().ok())
+            .find_map(|output| parse_chrome_version(&String::from_utf8_lossy(&output.stdout)))
+            .ok_or_else(|| "Chrome not found, install it or set chrome_binary".to_string())
+    }
+
+    fn driver_file_name() -> &'static str {
+        if cfg!(windows) { "chromedriver.exe" } else { "chromedriver" }
+    }
+
+    /// Unpack the chromedriver executable from the release zip into `dir`.
+    fn unpack(archive: &[u8], dir: &Path) -> Result<PathBuf, String> {
+        let mut zip = zip::ZipArchive::new(Cursor::new(archive)).map_err(|e| format!("Invalid chromedriver archive: {}", e))?;
+        for index in 0..zip.len() {
+            let mut entry = zip.by_index(index).map_err(|e| format!("Invalid chromedriver archive: {}", e))?;
+            if !entry.name().ends_with(&format!("/{}", driver_file_name())) {
+                continue;
+            }
+
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents).map_err(|e| format!("Failed to unpack chromedriver: {}", e))?;
+            std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create '{}': {}", dir.display(), e))?;
+            let path = dir.join(driver_file_name());
+            std::fs::write(&path, contents).map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+                    .map_err(|e| format!("Failed to make '{}' executable: {}", path.display(), e))?;
+            }
+            return Ok(path);
+        }
+        Err("No chromedriver in the downloaded archive".to_string())
+    }
+
+    /// The chromedriver for the installed Chrome, downloading it on first use.
+    async fn install(settings: &Settings) -> Result<PathBuf, String> {
+        let version = chrome_version(settings)?;
+        let dir = settings.data_dir.join("drivers").join(&version);
+        let path = dir.join(driver_file_name());
+        if path.exists() {
+            return Ok(path);
+        }
+
+        let platform = platform().ok_or("No chromedriver builds for this platform")?;
+        let client = reqwest::Client::new();
+        let versions: Value = client
+            .get(VERSIONS_URL)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Failed to fetch chromedriver versions: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse chromedriver versions: {}", e))?;
+        let url = chromedriver_url(&versions, &version, platform)
+            .ok_or_else(|| format!("No chromedriver release for Chrome {} on {}", version, platform))?;
+
+        info!("Downloading chromedriver for Chrome {} from {}", version, url);
+        let archive = client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Failed to download chromedriver: {}", e))?
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to download chromedriver: {}", e))?;
+        unpack(&archive, &dir)
+    }
+
+    /// Install and start chromedriver, pointing `settings` at it.
+    pub async fn start(settings: &mut Settings) -> Result<(), String> {
+        let path = install(settings).await?;
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .map_err(|e| format!("No free port for chromedriver: {}", e))?
+            .port();
+
+        settings.selenium_driver_url = format!("http://127.0.0.1:{}", port);
+        settings.driver_command = Some(format!("\"{}\" --port={}", path.display(), port));
+        health::restart(settings)?;
+        info!("Started {} on {}", path.display(), settings.selenium_driver_url);
+        Ok(())
+    }
+}
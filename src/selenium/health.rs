@@ -0,0 +1,184 @@
+//! Whether the WebDriver at `selenium_driver_url` can take sessions, checked
+//! through its W3C `/status` endpoint before every scrape attempt. With
+//! `driver_command` set, the app runs the driver itself and restarts it when
+//! it stops answering, instead of failing the whole update.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DriverHealth {
+    pub ready: bool,
+    /// The driver's own status message, or why it couldn't be reached
+    pub message: Option<String>,
+    pub checked_at: DateTime<Utc>,
+    /// Restarts of the managed driver since the app started
+    #[serde(default)]
+    pub restarts: u32,
+}
+
+/// Readiness and message from a `/status` response body. A body without
+/// `value.ready` counts as not ready.
+pub fn parse_status(body: &Value) -> (bool, Option<String>) {
+    let value = body.get("value");
+    let ready = value
+        .and_then(|value| value.get("ready"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let message = value
+        .and_then(|value| value.get("message"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    (ready, message)
+}
+
+/// Split `driver_command` into the program and its arguments on whitespace,
+/// keeping double-quoted parts together so paths with spaces work.
+pub fn split_command(command: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut in_part = false;
+
+    for c in command.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_part = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_part {
+                    parts.push(std::mem::take(&mut current));
+                    in_part = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_part = true;
+            }
+        }
+    }
+    if in_part {
+        parts.push(current);
+    }
+    parts
+}
+
+#[cfg(feature = "server")]
+pub use self::probe::{check, ensure_ready, restart, wait_until_ready};
+
+#[cfg(feature = "server")]
+mod probe {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Mutex, OnceLock};
+    use std::time::{Duration, Instant};
+
+    use serde_json::Value;
+    use tokio::process::{Child, Command};
+    use tracing::{error, info, warn};
+
+    use super::{parse_status, split_command, DriverHealth};
+    use crate::settings::Settings;
+
+    static MANAGED_DRIVER: OnceLock<Mutex<Option<Child>>> = OnceLock::new();
+    static RESTARTS: AtomicU32 = AtomicU32::new(0);
+
+    fn get_managed_driver() -> &'static Mutex<Option<Child>> {
+        MANAGED_DRIVER.get_or_init(|| Mutex::new(None))
+    }
+
+    /// Ask the driver once whether it can start a session.
+    pub async fn check(driver_url: &str) -> DriverHealth {
+        let url = format!("{}/status", driver_url.trim_end_matches('/'));
+        let response = reqwest::Client::new()
+            .get(&url)
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await;
+
+        let (ready, message) = match response {
+            Ok(response) => match response.json::<Value>().await {
+                Ok(body) => parse_status(&body),
+                Err(e) => (false, Some(format!("Unreadable status response: {}", e))),
+            },
+            Err(e) => (false, Some(format!("Not reachable: {}", e))),
+        };
+
+        DriverHealth {
+            ready,
+            message,
+            checked_at: chrono::Utc::now(),
+            restarts: RESTARTS.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Poll the driver with exponential backoff until it is ready or `max_wait`
+    /// has elapsed. Used to ride out a chromedriver sidecar that is still
+    /// starting or restarting instead of burning scrape attempts against it.
+    pub async fn wait_until_ready(driver_url: &str, max_wait: Duration) -> DriverHealth {
+        let start = Instant::now();
+        let mut delay = Duration::from_secs(1);
+
+        loop {
+            let health = check(driver_url).await;
+            if health.ready {
+                return health;
+            }
+
+            let elapsed = start.elapsed();
+            if elapsed >= max_wait {
+                return health;
+            }
+
+            info!("Waiting for WebDriver at {} to become ready...", driver_url);
+            tokio::time::sleep(delay.min(max_wait - elapsed)).await;
+            delay = (delay * 2).min(Duration::from_secs(30));
+        }
+    }
+
+    /// Stop the driver started by an earlier call, if any, and start
+    /// `driver_command` again.
+    pub fn restart(settings: &Settings) -> Result<(), String> {
+        let command = settings.driver_command.as_deref().ok_or("No driver_command configured")?;
+        let parts = split_command(command);
+        let (program, args) = parts.split_first().ok_or("driver_command is empty")?;
+
+        let mut managed = get_managed_driver().lock().unwrap();
+        if let Some(mut child) = managed.take() {
+            if let Err(e) = child.start_kill() {
+                warn!("Failed to stop the old WebDriver process: {}", e);
+            }
+        }
+
+        let child = Command::new(program)
+            .args(args)
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to start '{}': {}", command, e))?;
+        *managed = Some(child);
+        RESTARTS.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Wait for the driver as `wait_until_ready` does and, if it never became
+    /// ready and the app manages it, restart it and wait once more.
+    pub async fn ensure_ready(settings: &Settings) -> DriverHealth {
+        let max_wait = Duration::from_secs(settings.driver_wait_seconds);
+        let health = wait_until_ready(&settings.selenium_driver_url, max_wait).await;
+        if health.ready || settings.driver_command.is_none() {
+            return health;
+        }
+
+        warn!(
+            "WebDriver at {} is unresponsive ({}), restarting it.",
+            settings.selenium_driver_url,
+            health.message.as_deref().unwrap_or("no message")
+        );
+        if let Err(e) = restart(settings) {
+            error!("Failed to restart the WebDriver: {}", e);
+            return health;
+        }
+        wait_until_ready(&settings.selenium_driver_url, max_wait).await
+    }
+}
@@ -0,0 +1,4 @@
+//! The WebDriver the browser scraper drives.
+
+pub mod driver_manager;
+pub mod health;
@@ -2,9 +2,70 @@ use serde::Deserialize;
 use std::env;
 use std::fs::File;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use dotenv::dotenv;
 
+use crate::data::backup::BackupSchedule;
+use crate::data::database::DataStore;
+use crate::data::fingerprint::{default_user_agents, default_window_sizes, parse_window_size};
+use crate::data::location::LocationId;
+use crate::data::rta_http::ScrapeBackend;
+use crate::data::schedule::ScrapeWindow;
+use crate::data::scoring::SlotScoring;
+use crate::data::selectors::Selectors;
+use crate::data::shared_booking::{TestType, UnavailableSlots};
+use crate::data::storage::ObjectStorageSettings;
+use crate::data::strategy::{SlotPreferences, StrategyConfig};
+use crate::paths::app_paths;
+
+/// How the scraper signs in to the portal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoginMode {
+    /// Booking number and last name, which only reaches that one booking
+    #[default]
+    Booking,
+    /// Licence number and myRTA password, which reaches the whole account
+    Licence,
+}
+
+/// Steps of the portal flow that can get their own wait limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// The sign-in form
+    Login,
+    /// Buttons and links between pages
+    Navigation,
+    /// The location dropdown, which the portal fills in slowly
+    Dropdown,
+    /// The page's `timeslots` data after picking a location
+    Timeslots,
+}
+
+/// Per-step wait limits in milliseconds. Unset steps use
+/// `selenium_element_timout`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct StepTimeouts {
+    pub login: Option<u64>,
+    pub navigation: Option<u64>,
+    pub dropdown: Option<u64>,
+    pub timeslots: Option<u64>,
+}
+
+/// One person's booking for the auto-finder to work on, see `booking_queue`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BookingQueueEntry {
+    pub booking_id: String,
+    pub last_name: String,
+    /// Only book slots on or before this date
+    pub target_date: chrono::NaiveDate,
+    /// Centres to search, every scraped centre when empty
+    #[serde(default)]
+    pub locations: Vec<LocationId>,
+}
+
 #[derive(Deserialize, Clone)]
 pub struct Settings {
     pub headless: bool,
@@ -12,40 +73,753 @@ pub struct Settings {
     pub booking_id: String,
     /// Last name associated with the booking
     pub last_name: String,
+    /// Sign in with `booking_id`/`last_name` (`booking`) or with
+    /// `licence_number`/`password` (`licence`)
+    #[serde(default)]
+    pub login_mode: LoginMode,
+    /// Licence (card) number for `login_mode: licence`
+    #[serde(default)]
+    pub licence_number: String,
+    /// myRTA password for `login_mode: licence`. Supports `${VAR}` references
+    #[serde(default)]
+    pub password: String,
     pub have_booking: bool,
+    /// Test type of the existing booking when `have_booking` is set
+    #[serde(default)]
+    pub booking_test_type: TestType,
     pub selenium_driver_url: String,
     pub selenium_element_timout: u64,
     pub selenium_element_polling: u64,
     pub retries: u64,
+    /// How long to wait for the WebDriver endpoint to become ready before a scrape attempt
+    #[serde(default = "default_driver_wait_seconds")]
+    pub driver_wait_seconds: u64,
+    /// Command that starts a local WebDriver listening on
+    /// `selenium_driver_url`, e.g. `chromedriver --port=4444`. When set, the
+    /// app starts the driver itself and restarts it if it stops answering.
+    /// Quote paths containing spaces
+    #[serde(default)]
+    pub driver_command: Option<String>,
+    /// Download the chromedriver matching the installed Chrome into the data
+    /// directory and run it, instead of using `selenium_driver_url`
+    #[serde(default)]
+    pub manage_driver: bool,
+    /// Chrome executable whose version `manage_driver` matches, when it isn't
+    /// one of the usual names or install locations
+    #[serde(default)]
+    pub chrome_binary: Option<String>,
+    /// Scrape through Chrome (`browser`), with plain HTTP requests (`http`),
+    /// which needs no WebDriver but only supports `have_booking`, or over the
+    /// Chrome DevTools Protocol (`cdp`) in builds with the `cdp` feature.
+    /// `mock` replays recorded data from `mock_fixtures_dir` instead, for
+    /// development. Also accepted as `backend` or `scrape_mode`
+    #[serde(default, alias = "backend", alias = "scrape_mode")]
+    pub scrape_backend: ScrapeBackend,
+    /// Recorded data files `scrape_backend: mock` answers from
+    #[serde(default = "default_mock_fixtures_dir")]
+    pub mock_fixtures_dir: PathBuf,
+    /// Simulated time to scrape each location in mock mode
+    #[serde(default = "default_mock_latency_ms")]
+    pub mock_latency_ms: u64,
+    /// Origin of the booking portal, changed to point the scraper at a
+    /// stand-in such as the integration tests' mock portal
+    #[serde(default = "default_portal_url")]
+    pub portal_url: String,
     /// How often to refresh scraping in minutes
     pub scrape_refresh_minutes: u64,
+    /// Failed updates in a row before the data is flagged degraded and an
+    /// alert is sent
+    #[serde(default = "default_degraded_after_failures")]
+    pub degraded_after_failures: u32,
+    /// Data older than this is flagged stale in the UI and API
+    #[serde(default = "default_stale_after_minutes")]
+    pub stale_after_minutes: u64,
+    /// Webhook URLs that receive a JSON POST for alerts and booking outcomes
+    #[serde(default)]
+    pub notification_webhooks: Vec<String>,
+    /// Also notify whenever an update finds a location with an earlier slot
+    /// than before
+    #[serde(default)]
+    pub notify_earlier_slots: bool,
+    /// Base URL of another instance to mirror booking data from instead of scraping
+    #[serde(default)]
+    pub remote_api_url: Option<String>,
+    /// Directory for booking data and other state. Relative paths are resolved
+    /// against the config directory; defaults to the platform data directory
+    #[serde(default)]
+    pub data_dir: PathBuf,
+    /// Store the booking data gzip-compressed, as `bookings.json.gz`. A file
+    /// saved with the other setting is picked up and renamed on startup
+    #[serde(default)]
+    pub compress_data: bool,
+    /// Keep the booking data and scrape run log in JSON files (`json`) or in
+    /// `bookings.sqlite3` (`sqlite`) in builds with the `sqlite` feature
+    #[serde(default)]
+    pub data_store: DataStore,
+    /// Which tests to scrape availability for, each kept as its own dataset
+    #[serde(default = "default_test_types")]
+    pub test_types: Vec<TestType>,
+    /// Hold slots found by the auto-finder at the confirmation page and only
+    /// book them once the user approves via the notification link
+    #[serde(default)]
+    pub confirm_bookings: bool,
+    /// Hold found slots and alert with the portal's remaining reservation time
+    /// and the saved session, so the user can take over or approve completion
+    #[serde(default)]
+    pub hold_slots: bool,
+    /// Only move an existing booking if the new slot is at least this many
+    /// hours earlier. Any change must be strictly earlier regardless
+    #[serde(default)]
+    pub min_improvement_hours: u64,
+    /// Leave an existing booking alone when the portal says changing it
+    /// would cost a fee, including when the fee's amount can't be read
+    #[serde(default)]
+    pub abort_on_change_fee: bool,
+    /// Weights used by auto-find to rank candidate slots
+    #[serde(default)]
+    pub scoring: SlotScoring,
+    /// How auto-find picks between candidate slots, unless a job chooses its own
+    #[serde(default)]
+    pub find_strategy: StrategyConfig,
+    /// Times of day and weekdays the user can attend, unless a job sets its own
+    #[serde(default)]
+    pub slot_preferences: SlotPreferences,
+    /// Minutes between auto-find rounds, 1 to 1440
+    #[serde(default = "default_auto_find_interval_minutes")]
+    pub auto_find_interval_minutes: u64,
+    /// Add a random delay up to this many minutes to every auto-find round,
+    /// no more than `auto_find_interval_minutes`
+    #[serde(default = "default_auto_find_jitter_minutes")]
+    pub auto_find_jitter_minutes: u64,
+    /// Bookings the auto-finder works through from startup, each signed in
+    /// with its own `booking_id`/`last_name` instead of the ones above
+    #[serde(default)]
+    pub booking_queue: Vec<BookingQueueEntry>,
+    /// How long a held slot waits for approval before it is released
+    #[serde(default = "default_confirmation_timeout_minutes")]
+    pub confirmation_timeout_minutes: u64,
+    /// Public base URL of this instance, used to build links in notifications
+    #[serde(default)]
+    pub public_url: Option<String>,
+    /// S3-compatible bucket the data directory is mirrored to, for hosts
+    /// without persistent disks
+    #[serde(default)]
+    pub object_storage: Option<ObjectStorageSettings>,
+    /// With `home_lng` and `scrape_radius_km`, only scrape centres within the
+    /// radius of this point instead of every centre in NSW
+    #[serde(default)]
+    pub home_lat: Option<f64>,
+    #[serde(default)]
+    pub home_lng: Option<f64>,
+    #[serde(default)]
+    pub scrape_radius_km: Option<f64>,
+    /// Failed runs in a row after which a centre is put on cooldown, 0
+    /// disables cooldowns
+    #[serde(default = "default_location_failure_threshold")]
+    pub location_failure_threshold: u32,
+    /// Runs a centre on cooldown is skipped for
+    #[serde(default = "default_location_cooldown_runs")]
+    pub location_cooldown_runs: u32,
+    /// Centres scraped before all others in every run
+    #[serde(default)]
+    pub priority_locations: Vec<LocationId>,
+    /// Visit centres in a different random order each run, after
+    /// `priority_locations`
+    #[serde(default = "default_shuffle_locations")]
+    pub shuffle_locations: bool,
+    /// Delay each background run by a random amount up to this many minutes
+    #[serde(default = "default_start_jitter_minutes")]
+    pub start_jitter_minutes: u64,
+    /// Skip background runs on weekends and NSW public holidays (Sydney
+    /// time), when few cancellations are released
+    #[serde(default)]
+    pub quiet_on_non_business_days: bool,
+    /// Only run background scrapes between these times of day (`HH:MM`,
+    /// Sydney time), e.g. `06:00` and `23:00` to skip the night. An end
+    /// before the start wraps past midnight. Set both or neither
+    #[serde(default)]
+    pub scrape_window_start: Option<String>,
+    #[serde(default)]
+    pub scrape_window_end: Option<String>,
+    /// Wait for the portal's requests to finish instead of sleeping a fixed
+    /// time after each action in the location loop, where the driver
+    /// supports it
+    #[serde(default = "default_event_driven_waits")]
+    pub event_driven_waits: bool,
+    /// Keep the browser signed in on the location picker between background
+    /// runs, signing in again only once the portal has expired the session
+    #[serde(default)]
+    pub keep_browser_session: bool,
+    /// Save the portal cookies after signing in and restore them in the next
+    /// browser, so a restart doesn't cost a fresh login. Only used with a
+    /// single scrape worker
+    #[serde(default)]
+    pub persist_session_cookies: bool,
+    /// Browser sessions scraping at once, each with its share of the
+    /// locations. Needs a WebDriver that allows that many sessions
+    #[serde(default = "default_scrape_workers")]
+    pub scrape_workers: usize,
+    /// Proxies such as `http://host:port` or `socks5://host:port`, a
+    /// different one for each browser session in turn. Chrome ignores
+    /// credentials in the URL, use proxies that authorise by IP
+    #[serde(default)]
+    pub proxies: Vec<String>,
+    /// Ceiling on page requests to the portal per minute, shared by the
+    /// background updater, booking and the auto-finder. 0 for no limit
+    #[serde(default = "default_portal_requests_per_minute")]
+    pub portal_requests_per_minute: u32,
+    /// Requests allowed back to back before the per-minute rate applies
+    #[serde(default = "default_portal_request_burst")]
+    pub portal_request_burst: u32,
+    /// User agents to pick from for each browser session. Keep them Chrome,
+    /// which the browser really is
+    #[serde(default = "default_user_agents")]
+    pub user_agents: Vec<String>,
+    /// Window sizes (`WIDTHxHEIGHT`) to pick from for each browser session
+    #[serde(default = "default_window_sizes")]
+    pub window_sizes: Vec<String>,
+    /// Run the browser visibly and slowed down, show the current step in a
+    /// banner on the page and pause on errors, for fixing broken selectors
+    #[serde(default)]
+    pub debug_browser: bool,
+    /// Further calendar weeks the browser scraper pages through after the
+    /// first, so slots cover more than a week from the earliest
+    #[serde(default)]
+    pub weeks_ahead: u32,
+    /// Tries per centre within one scrape before it is given up on until the
+    /// next attempt
+    #[serde(default = "default_location_attempts")]
+    pub location_attempts: u32,
+    /// Wait before retrying a centre, doubled after every further failure
+    #[serde(default = "default_location_retry_delay_ms")]
+    pub location_retry_delay_ms: u64,
+    /// Where the browser scraper finds each element of the portal, for
+    /// patching a markup change without a rebuild
+    #[serde(default)]
+    pub selectors: Selectors,
+    /// Longer or shorter waits for individual steps than
+    /// `selenium_element_timout`
+    #[serde(default)]
+    pub timeouts: StepTimeouts,
+    /// Resume an interrupted scrape run from its checkpoint if it made
+    /// progress within this many minutes, instead of starting over. 0 turns
+    /// checkpoints off
+    #[serde(default = "default_checkpoint_max_age_minutes")]
+    pub checkpoint_max_age_minutes: u64,
+    /// Record every XHR and fetch response from the portal to
+    /// `diagnostics.jsonl`, for adapting the parser after site changes
+    #[serde(default)]
+    pub diagnostics: bool,
+    /// Responses kept in `diagnostics.jsonl`, oldest dropped first
+    #[serde(default = "default_diagnostics_max_entries")]
+    pub diagnostics_max_entries: usize,
+    /// Save a screenshot and the page source to `debug/` whenever a location
+    /// fails to scrape
+    #[serde(default = "default_failure_captures")]
+    pub failure_captures: bool,
+    /// Failure captures kept in `debug/`, oldest deleted first
+    #[serde(default = "default_failure_captures_max")]
+    pub failure_captures_max: usize,
+    /// How long to stop scraping after hitting the portal's maintenance page
+    /// when it doesn't say when it will be back
+    #[serde(default = "default_maintenance_backoff_minutes")]
+    pub maintenance_backoff_minutes: u64,
+    /// How long to stop scraping after the portal shows a captcha or
+    /// "unusual activity" page
+    #[serde(default = "default_blocked_cooldown_minutes")]
+    pub blocked_cooldown_minutes: u64,
+    /// Contend for a lease in the data directory (or `object_storage`) so only
+    /// one of several replicas scrapes and runs auto-find jobs
+    #[serde(default)]
+    pub leader_election: bool,
+    /// How long the leader's lease lasts without renewal before another
+    /// replica takes over
+    #[serde(default = "default_leader_lease_seconds")]
+    pub leader_lease_seconds: u64,
+    /// Timestamped copies of each data file kept in `data_dir/backups`, 0
+    /// disables backups
+    #[serde(default = "default_backup_count")]
+    pub backup_count: usize,
+    /// `daily` or `every_run`: how often a successful scrape takes a backup
+    #[serde(default)]
+    pub backup_schedule: BackupSchedule,
+    /// `keep` stores slots the portal lists as unavailable, flagged, for
+    /// capacity analysis at `/api/capacity/{test_type}`
+    #[serde(default)]
+    pub unavailable_slots: UnavailableSlots,
+    /// How many days of availability history to keep for the statistics page,
+    /// also used for the scrape run log. Older days are compacted into daily
+    /// aggregates
+    #[serde(default = "default_history_retention_days")]
+    pub history_retention_days: u64,
+    /// How many days of daily aggregates to keep, 0 to keep them forever
+    #[serde(default)]
+    pub daily_history_retention_days: u64,
+    /// How often old history and scrape runs are compacted and pruned
+    #[serde(default = "default_compaction_interval_hours")]
+    pub compaction_interval_hours: u64,
+    /// Let visitors sign in by email to keep their preferences server-side.
+    /// The auto test finder then requires an account
+    #[serde(default)]
+    pub accounts_enabled: bool,
+    /// URL that receives sign-in emails as a JSON POST of `to`, `subject` and
+    /// `text`, for relaying through the operator's mail provider
+    #[serde(default)]
+    pub login_mail_webhook: Option<String>,
+    /// Shared token that unlocks the `/admin` dashboard, which stays disabled
+    /// when unset. Supports `${VAR}` references like `booking_id`
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    /// Banner shown to every visitor and returned by `/api/status`. Once the
+    /// announcement is changed from the admin dashboard, that takes precedence
+    #[serde(default)]
+    pub announcement: Option<String>,
 }
 
 impl Settings {
+    /// Load `settings.yaml` from the resolved config directory.
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let paths = app_paths();
+        dotenv::from_path(paths.env_file()).ok();
+
+        let mut settings = Self::from_yaml(paths.settings_file())?;
+        settings.data_dir = if settings.data_dir.as_os_str().is_empty() {
+            paths.data_dir.clone()
+        } else {
+            paths.resolve_relative(&settings.data_dir)
+        };
+        std::fs::create_dir_all(&settings.data_dir)?;
+
+        Ok(settings)
+    }
+
+    /// Data file for one test type. Car tests keep the original
+    /// `bookings.json` name so existing deployments pick up their data.
+    pub fn bookings_file(&self, test_type: TestType) -> PathBuf {
+        self.bookings_file_compressed(test_type, self.compress_data)
+    }
+
+    fn bookings_file_compressed(&self, test_type: TestType, compressed: bool) -> PathBuf {
+        let stem = match test_type {
+            TestType::Car => "bookings".to_string(),
+            other => format!("bookings-{}", other.as_str()),
+        };
+        let extension = if compressed { "json.gz" } else { "json" };
+        self.data_dir.join(format!("{}.{}", stem, extension))
+    }
+
+    /// The data file to load: `bookings_file`, or the file saved with the
+    /// other `compress_data` setting when only that one exists, so toggling
+    /// it doesn't start with no data.
+    pub fn existing_bookings_file(&self, test_type: TestType) -> PathBuf {
+        let preferred = self.bookings_file(test_type);
+        let other = self.bookings_file_compressed(test_type, !self.compress_data);
+        if !preferred.exists() && other.exists() {
+            other
+        } else {
+            preferred
+        }
+    }
+
+    /// Booking data and scrape runs of every test type with
+    /// `data_store: sqlite`.
+    pub fn database_file(&self) -> PathBuf {
+        self.data_dir.join("bookings.sqlite3")
+    }
+
+    /// Progress of the scrape run in progress for a test type.
+    pub fn checkpoint_file(&self, test_type: TestType) -> PathBuf {
+        self.data_dir.join(format!("checkpoint-{}.json", test_type.as_str()))
+    }
+
+    /// Cookies of the last signed-in browser session for a test type.
+    pub fn session_cookies_file(&self, test_type: TestType) -> PathBuf {
+        self.data_dir.join(format!("session-{}.json", test_type.as_str()))
+    }
+
+    /// Availability history behind the statistics page, shared by all test types.
+    pub fn history_file(&self) -> PathBuf {
+        self.data_dir.join("history.jsonl")
+    }
+
+    /// Daily aggregates of availability history compacted out of
+    /// `history_file`.
+    pub fn daily_history_file(&self) -> PathBuf {
+        self.data_dir.join("history-daily.jsonl")
+    }
+
+    /// Accounts, their preferences and sign-in sessions.
+    pub fn accounts_file(&self) -> PathBuf {
+        self.data_dir.join("accounts.json")
+    }
+
+    /// Notification rules managed from the admin dashboard.
+    pub fn notification_rules_file(&self) -> PathBuf {
+        self.data_dir.join("notification-rules.json")
+    }
+
+    /// Rotating backups of the data files.
+    pub fn backups_dir(&self) -> PathBuf {
+        self.data_dir.join("backups")
+    }
+
+    /// The portal's login page, where every session starts.
+    pub fn portal_login_url(&self) -> String {
+        format!("{}/wps/portal/extvp/myrta/login/", self.portal_url.trim_end_matches('/'))
+    }
+
+    /// Bookings the bot made, with their receipts.
+    pub fn bookings_history_file(&self) -> PathBuf {
+        self.data_dir.join("bookings_history.json")
+    }
+
+    /// Auto-find jobs still searching, restored on startup.
+    pub fn auto_find_jobs_file(&self) -> PathBuf {
+        self.data_dir.join("auto_find_jobs.json")
+    }
+
+    /// Automated actions, one JSON line each, never rewritten.
+    pub fn audit_log_file(&self) -> PathBuf {
+        self.data_dir.join("audit.jsonl")
+    }
+
+    /// Log of scrape runs, one JSON line per run.
+    pub fn scrape_runs_file(&self) -> PathBuf {
+        self.data_dir.join("scrape_runs.jsonl")
+    }
+
+    /// Portal responses captured in diagnostics mode.
+    pub fn diagnostics_file(&self) -> PathBuf {
+        self.data_dir.join("diagnostics.jsonl")
+    }
+
+    /// Screenshots and page sources of failed scrape steps.
+    pub fn debug_dir(&self) -> PathBuf {
+        self.data_dir.join("debug")
+    }
+
+    /// Announcement set from the admin dashboard.
+    pub fn announcement_file(&self) -> PathBuf {
+        self.data_dir.join("announcement.json")
+    }
+
+    /// Home point and radius limiting which centres are scraped, when all
+    /// three are set.
+    pub fn scrape_area(&self) -> Option<(f64, f64, f64)> {
+        Some((self.home_lat?, self.home_lng?, self.scrape_radius_km?))
+    }
+
+    /// How long to wait for an element at `step`.
+    pub fn step_timeout(&self, step: Step) -> Duration {
+        let millis = match step {
+            Step::Login => self.timeouts.login,
+            Step::Navigation => self.timeouts.navigation,
+            Step::Dropdown => self.timeouts.dropdown,
+            Step::Timeslots => self.timeouts.timeslots,
+        };
+        Duration::from_millis(millis.unwrap_or(self.selenium_element_timout))
+    }
+
+    /// Hours background scrapes may run in, when both bounds are set.
+    /// Validated on load, so an unparsable window reads as none.
+    pub fn scrape_window(&self) -> Option<ScrapeWindow> {
+        ScrapeWindow::parse(self.scrape_window_start.as_ref()?, self.scrape_window_end.as_ref()?).ok()
+    }
+
+    /// Oldest history entry still counted.
+    pub fn history_cutoff(&self) -> chrono::NaiveDateTime {
+        crate::utils::date::sydney_now() - chrono::Duration::days(self.history_retention_days as i64)
+    }
+
     pub fn from_yaml<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
         dotenv().ok();
-        
-        let mut file = File::open(path)?;
+
+        let path = path.as_ref();
+        let mut file = File::open(path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
-        
-        let mut settings: Settings = serde_yaml::from_str(&contents)?;
 
-        settings.booking_id = parse_env_var(&settings.booking_id)?;
-        settings.last_name = parse_env_var(&settings.last_name)?;
-        
+        Self::from_yaml_str(&contents).map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    /// Parse settings from YAML and interpolate `${VAR}` references from the environment.
+    pub fn from_yaml_str(contents: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut settings: Settings = serde_yaml::from_str(contents)?;
+
+        settings.booking_id = interpolate_field("booking_id", &settings.booking_id)?;
+        settings.last_name = interpolate_field("last_name", &settings.last_name)?;
+        settings.licence_number = interpolate_field("licence_number", &settings.licence_number)?;
+        settings.password = interpolate_field("password", &settings.password)?;
+        if let Some(token) = &settings.admin_token {
+            settings.admin_token = Some(interpolate_field("admin_token", token)?);
+        }
+        if let Some(storage) = &mut settings.object_storage {
+            storage.access_key_id = interpolate_field("object_storage.access_key_id", &storage.access_key_id)?;
+            storage.secret_access_key =
+                interpolate_field("object_storage.secret_access_key", &storage.secret_access_key)?;
+        }
+
+        if settings.login_mode == LoginMode::Licence && (settings.licence_number.is_empty() || settings.password.is_empty()) {
+            return Err("login_mode: licence needs licence_number and password".into());
+        }
+
+        if let Some(size) = settings.window_sizes.iter().find(|size| parse_window_size(size).is_none()) {
+            return Err(format!("window_sizes entry \"{}\" is not WIDTHxHEIGHT", size).into());
+        }
+
+        match (&settings.scrape_window_start, &settings.scrape_window_end) {
+            (Some(start), Some(end)) => {
+                ScrapeWindow::parse(start, end)?;
+            }
+            (None, None) => {}
+            _ => return Err("scrape_window_start and scrape_window_end must be set together".into()),
+        }
+
+        if !(1..=1440).contains(&settings.auto_find_interval_minutes) {
+            return Err("auto_find_interval_minutes must be between 1 and 1440".into());
+        }
+        if settings.auto_find_jitter_minutes > settings.auto_find_interval_minutes {
+            return Err("auto_find_jitter_minutes can't be more than auto_find_interval_minutes".into());
+        }
+
+        if settings.login_mail_webhook.is_some() && settings.public_url.is_none() {
+            return Err("login_mail_webhook needs public_url for the sign-in links".into());
+        }
+        if (settings.confirm_bookings || settings.hold_slots) && settings.public_url.is_none() {
+            return Err("confirm_bookings and hold_slots need public_url for the links they send".into());
+        }
+        if settings.compaction_interval_hours == 0 {
+            return Err("compaction_interval_hours must be at least 1".into());
+        }
+
+        if settings.data_store == DataStore::Sqlite && !cfg!(feature = "sqlite") {
+            return Err("data_store is sqlite but this build has no sqlite feature".into());
+        }
+
+        let area = [settings.home_lat, settings.home_lng, settings.scrape_radius_km];
+        if area.iter().any(Option::is_some) && area.iter().any(Option::is_none) {
+            return Err("home_lat, home_lng and scrape_radius_km must be set together".into());
+        }
+
         Ok(settings)
     }
 }
 
-fn parse_env_var(value: &str) -> Result<String, Box<dyn std::error::Error>> {
-    if value.starts_with("${") && value.ends_with("}") {
-        let env_name = &value[2..value.len() - 1];
-        match env::var(env_name) {
-            Ok(val) => Ok(val),
-            Err(_) => Err(format!("Environment variable '{}' not found", env_name).into()),
+fn default_driver_wait_seconds() -> u64 {
+    120
+}
+
+fn default_degraded_after_failures() -> u32 {
+    1
+}
+
+fn default_stale_after_minutes() -> u64 {
+    180
+}
+
+fn default_confirmation_timeout_minutes() -> u64 {
+    10
+}
+
+fn default_compaction_interval_hours() -> u64 {
+    24
+}
+
+fn default_history_retention_days() -> u64 {
+    90
+}
+
+fn default_location_failure_threshold() -> u32 {
+    3
+}
+
+fn default_location_cooldown_runs() -> u32 {
+    5
+}
+
+fn default_shuffle_locations() -> bool {
+    true
+}
+
+fn default_portal_url() -> String {
+    "https://www.myrta.com".to_string()
+}
+
+fn default_start_jitter_minutes() -> u64 {
+    3
+}
+
+fn default_auto_find_interval_minutes() -> u64 {
+    10
+}
+
+fn default_auto_find_jitter_minutes() -> u64 {
+    2
+}
+
+fn default_scrape_workers() -> usize {
+    1
+}
+
+fn default_event_driven_waits() -> bool {
+    true
+}
+
+fn default_diagnostics_max_entries() -> usize {
+    500
+}
+
+fn default_location_attempts() -> u32 {
+    3
+}
+
+fn default_location_retry_delay_ms() -> u64 {
+    2000
+}
+
+fn default_failure_captures() -> bool {
+    true
+}
+
+fn default_failure_captures_max() -> usize {
+    50
+}
+
+fn default_mock_fixtures_dir() -> PathBuf {
+    PathBuf::from("tests/fixtures")
+}
+
+fn default_mock_latency_ms() -> u64 {
+    500
+}
+
+fn default_portal_requests_per_minute() -> u32 {
+    30
+}
+
+fn default_portal_request_burst() -> u32 {
+    5
+}
+
+fn default_checkpoint_max_age_minutes() -> u64 {
+    60
+}
+
+fn default_maintenance_backoff_minutes() -> u64 {
+    60
+}
+
+fn default_blocked_cooldown_minutes() -> u64 {
+    120
+}
+
+fn default_leader_lease_seconds() -> u64 {
+    60
+}
+
+fn default_backup_count() -> usize {
+    7
+}
+
+fn default_test_types() -> Vec<TestType> {
+    vec![TestType::Car]
+}
+
+/// Error from `${VAR}` interpolation. Positions are byte offsets into the
+/// value of the `${` that started the offending reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvVarError {
+    Unterminated { position: usize },
+    EmptyName { position: usize },
+    Nested { position: usize },
+    InvalidName { position: usize, name: String },
+    Missing { position: usize, name: String },
+    EmptyValue { position: usize, name: String },
+}
+
+impl std::fmt::Display for EnvVarError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnvVarError::Unterminated { position } => {
+                write!(f, "unterminated '${{' at position {}", position)
+            }
+            EnvVarError::EmptyName { position } => {
+                write!(f, "empty variable name at position {}", position)
+            }
+            EnvVarError::Nested { position } => {
+                write!(f, "nested '${{' inside variable reference at position {}", position)
+            }
+            EnvVarError::InvalidName { position, name } => {
+                write!(f, "invalid variable name '{}' at position {}", name, position)
+            }
+            EnvVarError::Missing { position, name } => {
+                write!(f, "environment variable '{}' not found (position {})", name, position)
+            }
+            EnvVarError::EmptyValue { position, name } => {
+                write!(f, "environment variable '{}' is empty (position {})", name, position)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EnvVarError {}
+
+fn interpolate_field(field: &str, value: &str) -> Result<String, String> {
+    interpolate_env(value).map_err(|e| format!("{}: {}", field, e))
+}
+
+/// Replace every `${NAME}` in `value` with the environment variable `NAME`.
+/// `$` not followed by `{` is kept as is.
+pub fn interpolate_env(value: &str) -> Result<String, EnvVarError> {
+    interpolate_with(value, |name| env::var(name).ok())
+}
+
+/// `interpolate_env` with an injectable lookup, so the parser can be tested
+/// without touching the process environment.
+pub fn interpolate_with<F>(value: &str, lookup: F) -> Result<String, EnvVarError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut output = String::with_capacity(value.len());
+    let mut rest = value;
+    let mut offset = 0;
+
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+        let position = offset + start;
+        let after_open = &rest[start + 2..];
+
+        let end = after_open
+            .find('}')
+            .ok_or(EnvVarError::Unterminated { position })?;
+        let name = &after_open[..end];
+
+        if let Some(inner) = name.find("${") {
+            return Err(EnvVarError::Nested { position: position + 2 + inner });
+        }
+        if name.is_empty() {
+            return Err(EnvVarError::EmptyName { position });
         }
-    } else {
-        Ok(value.to_string())
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            || name.starts_with(|c: char| c.is_ascii_digit())
+        {
+            return Err(EnvVarError::InvalidName { position, name: name.to_string() });
+        }
+
+        match lookup(name) {
+            Some(val) if val.is_empty() => {
+                return Err(EnvVarError::EmptyValue { position, name: name.to_string() })
+            }
+            Some(val) => output.push_str(&val),
+            None => return Err(EnvVarError::Missing { position, name: name.to_string() }),
+        }
+
+        let consumed = start + 2 + end + 1;
+        rest = &rest[consumed..];
+        offset += consumed;
     }
+
+    output.push_str(rest);
+    Ok(output)
 }
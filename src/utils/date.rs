@@ -1,52 +1,172 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+#[cfg(feature = "web")]
 use leptos::prelude::*;
 
-pub fn format_iso_date(iso_string: &str) -> String {
-    if let Ok(datetime) = DateTime::parse_from_rfc3339(iso_string) {
-        return datetime.format("%d %b %Y, %H:%M UTC").to_string();
-    } else {
-        iso_string.to_string()
+/// Format the RTA portal uses for slot times, e.g. `29/05/2025 14:35`.
+pub const SLOT_TIME_FORMAT: &str = "%d/%m/%Y %H:%M";
+
+pub fn parse_slot_time(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value.trim(), SLOT_TIME_FORMAT).ok()
+}
+
+pub fn format_slot_time(time: &NaiveDateTime) -> String {
+    time.format(SLOT_TIME_FORMAT).to_string()
+}
+
+/// Timezone of the portal's slot times, which carry no offset.
+pub const PORTAL_TIMEZONE: Tz = chrono_tz::Australia::Sydney;
+
+/// Sydney wall-clock time now, comparable with slot times wherever the server
+/// runs (containers usually run in UTC).
+pub fn sydney_now() -> NaiveDateTime {
+    Utc::now().with_timezone(&PORTAL_TIMEZONE).naive_local()
+}
+
+/// Attach Sydney's offset to a slot time. In the repeated hour when daylight
+/// saving ends the earlier (AEDT) instant is used; a time in the hour skipped
+/// when it starts is moved to the same wall-clock minute an hour later.
+pub fn to_sydney(time: NaiveDateTime) -> DateTime<Tz> {
+    PORTAL_TIMEZONE
+        .from_local_datetime(&time)
+        .earliest()
+        .or_else(|| PORTAL_TIMEZONE.from_local_datetime(&(time + Duration::hours(1))).earliest())
+        .expect("Sydney has no gaps longer than an hour")
+}
+
+/// `parse_slot_time` with the timezone attached, e.g. `05/10/2025 02:30`
+/// (inside the daylight saving gap) becomes `2025-10-05 03:30 AEDT`.
+pub fn parse_slot_time_sydney(value: &str) -> Option<DateTime<Tz>> {
+    parse_slot_time(value).map(to_sydney)
+}
+
+/// Whether a slot starts before the first minute of `date`.
+pub fn is_before_date(time: NaiveDateTime, date: NaiveDate) -> bool {
+    time.date() < date
+}
+
+/// Whether a slot starts on `date` or earlier.
+pub fn is_on_or_before_date(time: NaiveDateTime, date: NaiveDate) -> bool {
+    time.date() <= date
+}
+
+/// Whether a slot starts within `[start, end)`. Compared as instants, so an
+/// hour-long window across a daylight saving change is still an hour long.
+pub fn is_within_window(time: NaiveDateTime, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+    let time = to_sydney(time).with_timezone(&Utc);
+    start <= time && time < end
+}
+
+/// Slot time for people rather than the portal, e.g. `Thu 29 May 2025, 2:35 pm AEST`.
+pub fn format_slot_time_long(time: &NaiveDateTime) -> String {
+    to_sydney(*time).format("%a %-d %b %Y, %-I:%M %P %Z").to_string()
+}
+
+/// Serde helpers for slot times, kept in the portal's format so the data file
+/// and the scraped payloads share a representation.
+pub mod slot_time_format {
+    use chrono::NaiveDateTime;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(time: &NaiveDateTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::format_slot_time(time))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDateTime, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        super::parse_slot_time(&value)
+            .ok_or_else(|| de::Error::custom(format!("invalid slot time '{}'", value)))
+    }
+}
+
+pub mod slot_time_format_opt {
+    use chrono::NaiveDateTime;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(time: &Option<NaiveDateTime>, serializer: S) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => serializer.serialize_some(&super::format_slot_time(time)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(value) => super::parse_slot_time(&value)
+                .map(Some)
+                .ok_or_else(|| de::Error::custom(format!("invalid slot time '{}'", value))),
+            None => Ok(None),
+        }
+    }
+}
+
+pub fn format_datetime(time: &DateTime<Utc>) -> String {
+    time.format("%d %b %Y, %H:%M UTC").to_string()
+}
+
+/// Coarse age of `time`, e.g. `6 hours ago` or `just now`.
+pub fn format_age(time: &DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = (now - *time).num_minutes().max(0);
+    if minutes == 0 {
+        return "just now".to_string();
     }
+    let (value, unit) = match minutes {
+        0..=59 => (minutes, "minute"),
+        60..=2879 => (minutes / 60, "hour"),
+        _ => (minutes / (24 * 60), "day"),
+    };
+    format!("{} {}{} ago", value, unit, if value == 1 { "" } else { "s" })
 }
 
-#[cfg(not(feature = "ssr"))]
-pub fn format_iso_date_local(iso_string: &str) -> String {
+#[cfg(all(feature = "web", not(feature = "ssr")))]
+pub fn format_datetime_local(time: &DateTime<Utc>) -> String {
     use wasm_bindgen::prelude::*;
     use web_sys::js_sys;
-    
-    if let Ok(_) = DateTime::parse_from_rfc3339(iso_string) {
-        let date = js_sys::Date::new(&JsValue::from_str(iso_string));
-
-        if !date.get_time().is_nan() {
-            let options = js_sys::Object::new();
-            js_sys::Reflect::set(&options, &"year".into(), &"numeric".into()).unwrap();
-            js_sys::Reflect::set(&options, &"month".into(), &"short".into()).unwrap();
-            js_sys::Reflect::set(&options, &"day".into(), &"2-digit".into()).unwrap();
-            js_sys::Reflect::set(&options, &"hour".into(), &"2-digit".into()).unwrap();
-            js_sys::Reflect::set(&options, &"minute".into(), &"2-digit".into()).unwrap();
-            js_sys::Reflect::set(&options, &"hour12".into(), &false.into()).unwrap();
-            
-            return date.to_locale_time_string_with_options("en-AU", &options).into();
-        }
+
+    let date = js_sys::Date::new(&JsValue::from_f64(time.timestamp_millis() as f64));
+
+    if !date.get_time().is_nan() {
+        let options = js_sys::Object::new();
+        js_sys::Reflect::set(&options, &"year".into(), &"numeric".into()).unwrap();
+        js_sys::Reflect::set(&options, &"month".into(), &"short".into()).unwrap();
+        js_sys::Reflect::set(&options, &"day".into(), &"2-digit".into()).unwrap();
+        js_sys::Reflect::set(&options, &"hour".into(), &"2-digit".into()).unwrap();
+        js_sys::Reflect::set(&options, &"minute".into(), &"2-digit".into()).unwrap();
+        js_sys::Reflect::set(&options, &"hour12".into(), &false.into()).unwrap();
+
+        return date.to_locale_time_string_with_options("en-AU", &options).into();
     }
-    iso_string.to_string()
+    format_datetime(time)
 }
 
+/// How often `TimeDisplay` moves its relative time forward.
+#[cfg(feature = "web")]
+const AGE_TICK: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Relative time such as `4 minutes ago` that keeps itself current, with the
+/// absolute time in the viewer's timezone as a tooltip.
+#[cfg(feature = "web")]
 #[component]
 pub fn TimeDisplay(
-    #[prop(into)] iso_time: String,
+    time: DateTime<Utc>,
     #[prop(optional)] class: Option<String>,
 ) -> impl IntoView {
-    let (display_time, set_display_time) = create_signal(format_iso_date(&iso_time));
-    
+    let (now, set_now) = create_signal(Utc::now());
+    let (absolute_time, set_absolute_time) = create_signal(format_datetime(&time));
+
     #[cfg(not(feature = "ssr"))]
     create_effect(move |_| {
-        set_display_time(format_iso_date_local(&iso_time));
+        set_absolute_time(format_datetime_local(&time));
+        set_now(Utc::now());
+
+        let handle = set_interval_with_handle(move || set_now(Utc::now()), AGE_TICK)
+            .expect("failed to set interval");
+        on_cleanup(move || handle.clear());
     });
-    
+
     view! {
-        <span class={class.unwrap_or_default()}>
-            {display_time}
-        </span>
+        <time class={class.unwrap_or_default()} datetime={time.to_rfc3339()} title={absolute_time}>
+            {move || format_age(&time, now.get())}
+        </time>
     }
 }
@@ -1,6 +1,22 @@
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, sync::{Mutex, OnceLock}};
+use std::{collections::HashMap, fmt, sync::{Mutex, OnceLock}, time::Duration};
 use gloo_net::http::Request;
+use gloo_timers::future::sleep;
+
+/// Nominatim's usage policy allows at most one request per second.
+const MIN_REQUEST_INTERVAL_MS: f64 = 1000.0;
+
+/// Requests per search, the first one included.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Browsers may drop this header, in which case Nominatim identifies the app
+/// by the `Referer` they send instead.
+const USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/teehee567/nsw-drivers-test)"
+);
 
 #[derive(Debug, Serialize, Deserialize)]
 struct NominatimResponse {
@@ -16,7 +32,47 @@ pub struct GeocodingResult {
     pub display_name: String,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeocodingError {
+    /// Nominatim refused the request for exceeding its rate limit
+    RateLimited,
+    /// The search matched nothing in Australia
+    NotFound,
+    /// The request failed or Nominatim had a server error
+    Network(String),
+    /// Nominatim answered with something that isn't a search result
+    InvalidResponse(String),
+}
+
+impl GeocodingError {
+    /// Whether trying the same search again later might succeed.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, GeocodingError::RateLimited | GeocodingError::Network(_))
+    }
+
+    fn from_status(status: u16) -> Option<Self> {
+        match status {
+            200..=299 => None,
+            429 => Some(GeocodingError::RateLimited),
+            500..=599 => Some(GeocodingError::Network(format!("server error {}", status))),
+            _ => Some(GeocodingError::InvalidResponse(format!("status {}", status))),
+        }
+    }
+}
+
+impl fmt::Display for GeocodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeocodingError::RateLimited => write!(f, "Too many searches, please wait a few seconds and try again"),
+            GeocodingError::NotFound => write!(f, "No matching place found, try a suburb or postcode"),
+            GeocodingError::Network(e) => write!(f, "Couldn't reach the location search: {}", e),
+            GeocodingError::InvalidResponse(e) => write!(f, "Unexpected answer from the location search: {}", e),
+        }
+    }
+}
+
 static GEOCODING_CACHE: OnceLock<Mutex<HashMap<String, GeocodingResult>>> = OnceLock::new();
+static NEXT_REQUEST_AT: OnceLock<Mutex<f64>> = OnceLock::new();
 
 fn get_geocoding_cache() -> &'static Mutex<HashMap<String, GeocodingResult>> {
     GEOCODING_CACHE.get_or_init(|| {
@@ -24,43 +80,88 @@ fn get_geocoding_cache() -> &'static Mutex<HashMap<String, GeocodingResult>> {
     })
 }
 
-pub async fn geocode_address(address: &str) -> Result<GeocodingResult, String> {
-    {
-        let cache = get_geocoding_cache().lock().unwrap();
-        if let Some(result) = cache.get(address) {
-            return Ok(result.clone());
-        }
+/// Wait for this tab's next free request slot. Slots are reserved before
+/// waiting, so searches started together still go out a second apart.
+async fn wait_for_request_slot() {
+    let now = web_sys::js_sys::Date::now();
+    let wait_ms = {
+        let mut next_request_at = NEXT_REQUEST_AT.get_or_init(|| Mutex::new(0.0)).lock().unwrap();
+        let request_at = next_request_at.max(now);
+        *next_request_at = request_at + MIN_REQUEST_INTERVAL_MS;
+        request_at - now
+    };
+
+    if wait_ms > 0.0 {
+        sleep(Duration::from_millis(wait_ms as u64)).await;
     }
-    
+}
+
+/// Backoff before retry number `attempt` (1-based): 1s, 2s, 4s...
+fn retry_delay(attempt: u32) -> Duration {
+    Duration::from_secs(1 << (attempt - 1).min(5))
+}
+
+async fn search(address: &str) -> Result<GeocodingResult, GeocodingError> {
     let encoded_address = urlencoding::encode(address);
     let url = format!(
         "https://nominatim.openstreetmap.org/search?q={}&format=json&limit=1&addressdetails=1&countrycodes=au",
         encoded_address
     );
-    
+
+    wait_for_request_slot().await;
     let response = Request::get(&url)
-        .header("User-Agent", "NSW Drivers Test Nearest Date - teegee567/1.0")
+        .header("User-Agent", USER_AGENT)
         .send()
         .await
-        .map_err(|e| format!("Request error: {}", e))?;
-    
+        .map_err(|e| GeocodingError::Network(e.to_string()))?;
+
+    if let Some(error) = GeocodingError::from_status(response.status()) {
+        return Err(error);
+    }
+
     let results: Vec<NominatimResponse> = response.json()
         .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
-    
-    let result = results.first()
-        .ok_or_else(|| "No results found".to_string())?;
-    
-    let geocoding_result = GeocodingResult {
-        latitude: result.lat.parse().unwrap_or(0.0),
-        longitude: result.lon.parse().unwrap_or(0.0),
-        display_name: result.display_name.clone(),
+        .map_err(|e| GeocodingError::InvalidResponse(e.to_string()))?;
+
+    let result = results.first().ok_or(GeocodingError::NotFound)?;
+    match (result.lat.parse(), result.lon.parse()) {
+        (Ok(latitude), Ok(longitude)) => Ok(GeocodingResult {
+            latitude,
+            longitude,
+            display_name: result.display_name.clone(),
+        }),
+        _ => Err(GeocodingError::InvalidResponse(format!("bad coordinates {}, {}", result.lat, result.lon))),
+    }
+}
+
+/// Look up an Australian address on Nominatim, at most one request a second,
+/// retrying rate limits and network failures with backoff. Results are cached
+/// for the lifetime of the page.
+pub async fn geocode_address(address: &str) -> Result<GeocodingResult, GeocodingError> {
+    {
+        let cache = get_geocoding_cache().lock().unwrap();
+        if let Some(result) = cache.get(address) {
+            return Ok(result.clone());
+        }
+    }
+
+    let mut attempt = 1;
+    let geocoding_result = loop {
+        match search(address).await {
+            Ok(result) => break result,
+            Err(e) if e.is_transient() && attempt < MAX_ATTEMPTS => {
+                leptos::logging::warn!("Geocoding attempt {} failed: {}", attempt, e);
+                sleep(retry_delay(attempt)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
     };
-    
+
     {
         let mut cache = get_geocoding_cache().lock().unwrap();
         cache.insert(address.to_string(), geocoding_result.clone());
     }
-    
+
     Ok(geocoding_result)
 }
@@ -0,0 +1,122 @@
+//! The NSW public-holiday calendar, as set by the Public Holidays Act 2010,
+//! and business-day helpers built on it. Bank Holiday is left out, it only
+//! applies to banks and test centres stay open.
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicHoliday {
+    pub date: NaiveDate,
+    pub name: &'static str,
+}
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+fn is_weekend(date: NaiveDate) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// The first Monday on or after `date`.
+fn monday_on_or_after(date: NaiveDate) -> NaiveDate {
+    let days = (7 - date.weekday().num_days_from_monday()) % 7;
+    date + Duration::days(days as i64)
+}
+
+/// Easter Sunday in the Gregorian calendar (anonymous Gregorian algorithm).
+pub fn easter_sunday(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    date(year, month as u32, day as u32)
+}
+
+/// Every NSW public holiday in `year`, in date order. Holidays falling on a
+/// weekend get their additional weekday as well.
+pub fn public_holidays(year: i32) -> Vec<PublicHoliday> {
+    let holiday = |date, name| PublicHoliday { date, name };
+    let easter = easter_sunday(year);
+    let mut holidays = Vec::new();
+
+    let new_year = date(year, 1, 1);
+    holidays.push(holiday(new_year, "New Year's Day"));
+    if is_weekend(new_year) {
+        holidays.push(holiday(monday_on_or_after(new_year), "New Year's Day (additional day)"));
+    }
+
+    // Australia Day moves to the Monday rather than gaining one
+    let australia_day = date(year, 1, 26);
+    holidays.push(holiday(
+        if is_weekend(australia_day) { monday_on_or_after(australia_day) } else { australia_day },
+        "Australia Day",
+    ));
+
+    holidays.push(holiday(easter - Duration::days(2), "Good Friday"));
+    holidays.push(holiday(easter - Duration::days(1), "Easter Saturday"));
+    holidays.push(holiday(easter, "Easter Sunday"));
+    holidays.push(holiday(easter + Duration::days(1), "Easter Monday"));
+
+    // Anzac Day is never moved in NSW
+    holidays.push(holiday(date(year, 4, 25), "Anzac Day"));
+
+    holidays.push(holiday(monday_on_or_after(date(year, 6, 8)), "King's Birthday"));
+    holidays.push(holiday(monday_on_or_after(date(year, 10, 1)), "Labour Day"));
+
+    let christmas = date(year, 12, 25);
+    let boxing_day = date(year, 12, 26);
+    holidays.push(holiday(christmas, "Christmas Day"));
+    holidays.push(holiday(boxing_day, "Boxing Day"));
+    match christmas.weekday() {
+        // Both additional days land on the Monday and Tuesday after
+        Weekday::Sat => {
+            holidays.push(holiday(date(year, 12, 27), "Christmas Day (additional day)"));
+            holidays.push(holiday(date(year, 12, 28), "Boxing Day (additional day)"));
+        }
+        // Boxing Day is already the Monday
+        Weekday::Sun => holidays.push(holiday(date(year, 12, 27), "Christmas Day (additional day)")),
+        Weekday::Fri => holidays.push(holiday(date(year, 12, 28), "Boxing Day (additional day)")),
+        _ => {}
+    }
+
+    holidays.sort_by_key(|holiday| holiday.date);
+    holidays
+}
+
+/// The NSW public holiday on `date`, if there is one.
+pub fn public_holiday(date: NaiveDate) -> Option<PublicHoliday> {
+    public_holidays(date.year()).into_iter().find(|holiday| holiday.date == date)
+}
+
+/// A weekday that isn't a NSW public holiday.
+pub fn is_business_day(date: NaiveDate) -> bool {
+    !is_weekend(date) && public_holiday(date).is_none()
+}
+
+/// The first business day after `date`.
+pub fn next_business_day(date: NaiveDate) -> NaiveDate {
+    let mut next = date + Duration::days(1);
+    while !is_business_day(next) {
+        next += Duration::days(1);
+    }
+    next
+}
+
+/// Whether `date` is a public holiday or the day either side of one, days
+/// many people are away or travelling.
+pub fn is_near_public_holiday(date: NaiveDate) -> bool {
+    [date - Duration::days(1), date, date + Duration::days(1)]
+        .into_iter()
+        .any(|day| public_holiday(day).is_some())
+}
@@ -0,0 +1,42 @@
+use crate::accounts::SyncedPreferences;
+
+const STORAGE_KEY: &str = "preferences";
+const DISMISSED_ANNOUNCEMENT_KEY: &str = "dismissed_announcement";
+
+fn storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+/// Favourites and filters saved in this browser. Anything missing or
+/// unreadable comes back as empty preferences.
+pub fn load() -> SyncedPreferences {
+    storage()
+        .and_then(|storage| storage.get_item(STORAGE_KEY).ok().flatten())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(preferences: &SyncedPreferences) {
+    let Some(storage) = storage() else {
+        return;
+    };
+    match serde_json::to_string(preferences) {
+        Ok(contents) => {
+            if storage.set_item(STORAGE_KEY, &contents).is_err() {
+                leptos::logging::log!("Failed to save preferences to localStorage");
+            }
+        }
+        Err(e) => leptos::logging::log!("Failed to serialize preferences: {:?}", e),
+    }
+}
+
+/// Key of the announcement this browser last dismissed.
+pub fn dismissed_announcement() -> Option<String> {
+    storage()?.get_item(DISMISSED_ANNOUNCEMENT_KEY).ok().flatten()
+}
+
+pub fn dismiss_announcement(key: &str) {
+    if let Some(storage) = storage() {
+        let _ = storage.set_item(DISMISSED_ANNOUNCEMENT_KEY, key);
+    }
+}
@@ -0,0 +1,25 @@
+//! Log output for the server and scraper binaries.
+//!
+//! Scraper and `BookingManager` logs go through `tracing`, with a span per
+//! scrape run and per location, so interleaved background and auto-find runs
+//! can be told apart. `RUST_LOG` filters them (default `info`), e.g.
+//! `RUST_LOG=nsw_closest_display::data::rta=debug`. With the `console`
+//! feature the tokio-console layer is installed alongside.
+
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let registry = tracing_subscriber::registry().with(fmt::layer().with_filter(filter));
+
+    #[cfg(feature = "console")]
+    {
+        registry.with(console_subscriber::spawn()).init();
+        tracing::info!("tokio-console subscriber listening on 127.0.0.1:6669");
+    }
+
+    #[cfg(not(feature = "console"))]
+    registry.init();
+}
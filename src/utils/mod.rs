@@ -1,2 +1,11 @@
+#[cfg(feature = "web")]
 pub mod geocoding;
+#[cfg(feature = "web")]
+pub mod local_preferences;
 pub mod date;
+pub mod holidays;
+pub mod sort;
+#[cfg(feature = "server")]
+pub mod logging;
+#[cfg(feature = "server")]
+pub mod task;
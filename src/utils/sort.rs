@@ -0,0 +1,113 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::data::location::{Location, LocationId};
+use crate::data::shared_booking::{LocationBookingViewModel, TestType, TimeSlot};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortColumn {
+    Name,
+    #[default]
+    Distance,
+    #[serde(rename = "earliest", alias = "earliest_slot")]
+    EarliestSlot,
+    PassRate,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortDirection {
+    #[default]
+    #[serde(rename = "asc", alias = "ascending")]
+    Ascending,
+    #[serde(rename = "desc", alias = "descending")]
+    Descending,
+}
+
+/// A request for the locations table, as taken by the `GetSortedLocations`
+/// server function and the `/api/locations` endpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocationsQuery {
+    #[serde(default)]
+    pub test_type: TestType,
+    pub lat: f64,
+    pub lng: f64,
+    #[serde(default)]
+    pub sort: SortColumn,
+    #[serde(default)]
+    pub direction: SortDirection,
+    /// Leave out centres further than this from the search point
+    #[serde(default)]
+    pub max_distance_km: Option<f64>,
+    /// Ignore slots on or next to a NSW public holiday
+    #[serde(default)]
+    pub hide_near_holidays: bool,
+}
+
+/// One row of the locations table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SortedLocation {
+    pub location: Location,
+    pub distance_km: f64,
+    pub earliest_slot: Option<TimeSlot>,
+}
+
+/// A location joined with its distance from the search point and its earliest slot.
+pub type LocationRowData = (Location, f64, Option<TimeSlot>);
+
+/// Comparator used by the locations table for the selected column and direction.
+pub fn compare_locations(
+    a: &LocationRowData,
+    b: &LocationRowData,
+    column: SortColumn,
+    direction: SortDirection,
+) -> Ordering {
+    let ordering = match column {
+        SortColumn::Name => a.0.name.cmp(&b.0.name),
+        SortColumn::Distance => a.1.total_cmp(&b.1),
+        SortColumn::EarliestSlot => {
+            match (&a.2, &b.2) {
+                (Some(slot_a), Some(slot_b)) => slot_a.cmp(slot_b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        },
+        SortColumn::PassRate => b.0.pass_rate.partial_cmp(&a.0.pass_rate).unwrap_or(Ordering::Equal),
+    };
+
+    match direction {
+        SortDirection::Ascending => ordering,
+        SortDirection::Descending => ordering.reverse(),
+    }
+}
+
+/// Join centres (with their distance from the search point) to their earliest
+/// slots, drop those beyond `max_distance_km` and sort the rest as requested.
+pub fn sort_locations(
+    locations_by_distance: Vec<(Location, f64)>,
+    summaries: &[LocationBookingViewModel],
+    query: &LocationsQuery,
+) -> Vec<SortedLocation> {
+    let earliest_slots: HashMap<LocationId, &TimeSlot> = summaries
+        .iter()
+        .filter_map(|summary| Some((summary.location, summary.earliest_slot.as_ref()?)))
+        .collect();
+
+    let mut rows: Vec<LocationRowData> = locations_by_distance
+        .into_iter()
+        .filter(|(_, distance)| query.max_distance_km.map_or(true, |max| *distance <= max))
+        .map(|(location, distance)| {
+            let earliest_slot = earliest_slots.get(&location.id).map(|slot| (*slot).clone());
+            (location, distance, earliest_slot)
+        })
+        .collect();
+
+    rows.sort_by(|a, b| compare_locations(a, b, query.sort, query.direction));
+
+    rows.into_iter()
+        .map(|(location, distance_km, earliest_slot)| SortedLocation { location, distance_km, earliest_slot })
+        .collect()
+}
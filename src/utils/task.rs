@@ -0,0 +1,46 @@
+//! Task spawning with names visible in tokio-console, and waiting for the
+//! process to be told to stop.
+//!
+//! Names are only attached when built with the `console` feature and
+//! `RUSTFLAGS="--cfg tokio_unstable"`; otherwise this is plain `tokio::spawn`.
+
+use std::future::Future;
+
+use tokio::task::JoinHandle;
+
+pub fn spawn_named<F>(name: &str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(all(feature = "console", tokio_unstable))]
+    {
+        return tokio::task::Builder::new()
+            .name(name)
+            .spawn(future)
+            .expect("failed to spawn task");
+    }
+
+    #[allow(unreachable_code)]
+    {
+        let _ = name;
+        tokio::spawn(future)
+    }
+}
+
+/// Resolves on Ctrl-C, or on SIGTERM, which container platforms send before
+/// stopping the process.
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await.ok();
+}
@@ -0,0 +1,155 @@
+//! Email validation, session cookie parsing for sign-in, account webhook
+//! checks and merging of synced favourites.
+
+use axum::http::{header, HeaderMap, HeaderValue};
+use chrono::{DateTime, TimeZone, Utc};
+use proptest::prelude::*;
+
+use nsw_closest_display::accounts::{is_plausible_email, session_from_headers, Filters, Preferences, SyncedPreferences};
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::shared_booking::TestType;
+use nsw_closest_display::notifications::check_account_webhook;
+
+#[test]
+fn plausible_emails() {
+    assert!(is_plausible_email("learner@example.com.au"));
+    assert!(!is_plausible_email("learner"));
+    assert!(!is_plausible_email("@example.com"));
+    assert!(!is_plausible_email("learner@localhost"));
+    assert!(!is_plausible_email("learner@example.com."));
+    assert!(!is_plausible_email("learner @example.com"));
+}
+
+#[test]
+fn account_webhooks_must_be_public_https() {
+    assert!(check_account_webhook("https://hooks.example.com/notify").is_ok());
+    assert!(check_account_webhook("https://203.0.113.0.example.net/").is_ok());
+
+    for url in [
+        "http://hooks.example.com/notify",
+        "https://localhost/notify",
+        "https://127.0.0.1/",
+        "https://169.254.169.254/latest/meta-data",
+        "https://10.0.0.5/",
+        "https://192.168.1.1/",
+        "https://172.16.0.1/",
+        "https://[::1]/",
+        "https://[fd00::1]/",
+        "https://[::ffff:127.0.0.1]/",
+        "https://intranet/",
+        "not a url",
+    ] {
+        assert!(check_account_webhook(url).is_err(), "{}", url);
+    }
+}
+
+#[test]
+fn session_is_read_from_cookie_header() {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::COOKIE, HeaderValue::from_static("theme=dark; session=abc123; other=1"));
+    assert_eq!(session_from_headers(&headers).as_deref(), Some("abc123"));
+}
+
+#[test]
+fn missing_session_cookie() {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::COOKIE, HeaderValue::from_static("sessionid=nope"));
+    assert_eq!(session_from_headers(&headers), None);
+    assert_eq!(session_from_headers(&HeaderMap::new()), None);
+}
+
+fn at(minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 6, 10, 10, minute, 0).unwrap()
+}
+
+#[test]
+fn merge_keeps_latest_change_per_favourite() {
+    let mut phone = SyncedPreferences::default();
+    phone.set_favourite(LocationId(18), true, at(0));
+    phone.set_favourite(LocationId(18), false, at(5));
+    phone.set_favourite(LocationId(20), true, at(1));
+
+    let mut laptop = SyncedPreferences::default();
+    laptop.set_favourite(LocationId(18), true, at(2));
+    laptop.set_favourite(LocationId(30), true, at(3));
+
+    let mut merged = phone.clone();
+    merged.merge(&laptop);
+    assert_eq!(merged.favourites(), vec![LocationId(20), LocationId(30)]);
+
+    let mut other_way = laptop.clone();
+    other_way.merge(&phone);
+    assert_eq!(merged, other_way);
+
+    let mut again = merged.clone();
+    again.merge(&laptop);
+    assert_eq!(again, merged);
+}
+
+#[test]
+fn merge_keeps_newest_filters() {
+    let mut phone = SyncedPreferences::default();
+    phone.set_filters(Filters { test_type: TestType::Rider, max_distance_km: None, hide_near_holidays: false }, at(1));
+
+    let mut laptop = SyncedPreferences::default();
+    laptop.set_filters(Filters { test_type: TestType::Car, max_distance_km: Some(25.0), hide_near_holidays: false }, at(4));
+
+    phone.merge(&laptop);
+    assert_eq!(phone.filters.value.max_distance_km, Some(25.0));
+    assert_eq!(phone.filters.value.test_type, TestType::Car);
+}
+
+#[test]
+fn direct_edits_are_stamped_for_sync() {
+    let mut preferences = Preferences::default();
+    preferences.synced.set_favourite(LocationId(18), true, at(0));
+    preferences.watched_locations = vec![LocationId(20)];
+    preferences.record_edits(at(10));
+
+    assert_eq!(preferences.synced.favourites(), vec![LocationId(20)]);
+
+    let mut device = SyncedPreferences::default();
+    device.set_favourite(LocationId(18), true, at(5));
+    preferences.merge_synced(&device);
+    assert_eq!(preferences.watched_locations, vec![LocationId(20)]);
+}
+
+/// Copies stamped within a few minutes of each other, so many changes tie.
+fn synced_preferences() -> impl Strategy<Value = SyncedPreferences> {
+    let favourites = prop::collection::vec((0..4u32, any::<bool>(), 0..3u32), 0..6);
+    let filters = (0..TestType::ALL.len(), prop::option::of(prop::sample::select(vec![10.0, 25.0])), any::<bool>(), 0..3u32);
+    (favourites, prop::option::of(filters)).prop_map(|(favourites, filters)| {
+        let mut preferences = SyncedPreferences::default();
+        for (location, favourite, minute) in favourites {
+            preferences.set_favourite(LocationId(location), favourite, at(minute));
+        }
+        if let Some((test_type, max_distance_km, hide_near_holidays, minute)) = filters {
+            let filters = Filters { test_type: TestType::ALL[test_type], max_distance_km, hide_near_holidays };
+            preferences.set_filters(filters, at(minute));
+        }
+        preferences
+    })
+}
+
+proptest! {
+    #[test]
+    fn merge_is_commutative(a in synced_preferences(), b in synced_preferences()) {
+        let mut ab = a.clone();
+        ab.merge(&b);
+        let mut ba = b.clone();
+        ba.merge(&a);
+        prop_assert_eq!(ab, ba);
+    }
+
+    #[test]
+    fn merge_is_associative(a in synced_preferences(), b in synced_preferences(), c in synced_preferences()) {
+        let mut left = a.clone();
+        left.merge(&b);
+        left.merge(&c);
+        let mut bc = b.clone();
+        bc.merge(&c);
+        let mut right = a.clone();
+        right.merge(&bc);
+        prop_assert_eq!(left, right);
+    }
+}
@@ -0,0 +1,47 @@
+//! Appending to and reading back the audit log.
+
+mod common;
+
+use serde_json::json;
+
+use nsw_closest_display::data::audit::{self, AuditAction, AuditEntry};
+
+use common::temp_path;
+
+#[test]
+fn missing_log_is_empty() {
+    let path = temp_path("audit-missing.jsonl");
+    assert!(audit::recent(&path, 10).unwrap().is_empty());
+}
+
+#[test]
+fn appends_and_keeps_the_newest() {
+    let path = temp_path("audit-append.jsonl");
+    for hour in 0..5 {
+        let entry = AuditEntry::new(AuditAction::Scrape, json!({ "hour": hour }), hour != 3, format!("run {}", hour));
+        audit::append(&path, &entry).unwrap();
+    }
+    let booking = AuditEntry::new(AuditAction::Booking, json!({ "mode": "dry_run" }), true, "DryRun");
+    audit::append(&path, &booking).unwrap();
+
+    let recent = audit::recent(&path, 3).unwrap();
+    let outcomes: Vec<_> = recent.iter().map(|entry| entry.outcome.as_str()).collect();
+    assert_eq!(outcomes, vec!["run 3", "run 4", "DryRun"]);
+    assert!(!recent[0].success);
+    assert_eq!(recent[2], booking);
+}
+
+#[test]
+fn skips_unparseable_lines() {
+    let path = temp_path("audit-corrupt.jsonl");
+    audit::append(&path, &AuditEntry::new(AuditAction::AutoFind, json!({}), true, "first")).unwrap();
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| std::io::Write::write_all(&mut file, b"{not json\n"))
+        .unwrap();
+    audit::append(&path, &AuditEntry::new(AuditAction::AutoFind, json!({}), true, "second")).unwrap();
+
+    let outcomes: Vec<_> = audit::recent(&path, 10).unwrap().into_iter().map(|entry| entry.outcome).collect();
+    assert_eq!(outcomes, vec!["first", "second"]);
+}
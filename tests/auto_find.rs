@@ -0,0 +1,203 @@
+//! Slot allocation between competing auto-find jobs.
+
+mod common;
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+
+use nsw_closest_display::data::auto_find::{allocate, AutoFindJob};
+use nsw_closest_display::data::location::{LocationId, SearchArea};
+use nsw_closest_display::data::scoring::SlotScoring;
+use nsw_closest_display::data::shared_booking::TimeSlot;
+use nsw_closest_display::data::strategy::{SlotPreferences, StrategyConfig};
+
+use common::at;
+
+const BLACKTOWN: LocationId = LocationId(18);
+const PENRITH: LocationId = LocationId(37);
+
+// For the tests that depend on where the centres are
+const ALBURY: LocationId = LocationId(17);
+const ARMIDALE: LocationId = LocationId(18);
+
+fn now() -> NaiveDateTime {
+    at(1, 12)
+}
+
+fn slot(location: LocationId, start_time: NaiveDateTime) -> (LocationId, TimeSlot) {
+    (location, TimeSlot { availability: true, slot_number: None, start_time })
+}
+
+fn job(id: u64, locations: Vec<LocationId>, priority: i32, age_minutes: i64) -> AutoFindJob {
+    AutoFindJob {
+        id,
+        booking_id: format!("booking-{}", id),
+        locations,
+        area: None,
+        before: NaiveDate::from_ymd_opt(2025, 6, 30).unwrap(),
+        priority,
+        created_at: Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap() - Duration::minutes(age_minutes),
+        scoring: SlotScoring::default(),
+        strategy: StrategyConfig::default(),
+        preferences: SlotPreferences::default(),
+        needs_approval: false,
+        last_served: None,
+        attempts: 0,
+        last_attempt: None,
+    }
+}
+
+#[test]
+fn contested_slot_goes_to_higher_priority() {
+    let jobs = vec![job(1, vec![BLACKTOWN], 0, 60), job(2, vec![BLACKTOWN], 5, 0)];
+    let allocations = allocate(&jobs, &[slot(BLACKTOWN, at(10, 9))], now());
+
+    assert_eq!(allocations.len(), 1);
+    assert_eq!(allocations[0].job_id, 2);
+    assert_eq!(allocations[0].contenders, vec![2, 1]);
+}
+
+#[test]
+fn equal_priority_goes_to_older_job() {
+    let jobs = vec![job(1, vec![BLACKTOWN], 0, 0), job(2, vec![BLACKTOWN], 0, 30)];
+    let allocations = allocate(&jobs, &[slot(BLACKTOWN, at(10, 9))], now());
+
+    assert_eq!(allocations[0].job_id, 2);
+}
+
+#[test]
+fn equal_priority_takes_turns() {
+    let mut served = job(1, vec![BLACKTOWN], 0, 30);
+    served.last_served = Some(Utc.with_ymd_and_hms(2025, 6, 1, 11, 0, 0).unwrap());
+    let jobs = vec![served, job(2, vec![BLACKTOWN], 0, 0)];
+    let allocations = allocate(&jobs, &[slot(BLACKTOWN, at(10, 9))], now());
+
+    assert_eq!(allocations[0].job_id, 2);
+}
+
+#[test]
+fn each_job_gets_at_most_one_slot() {
+    let jobs = vec![job(1, vec![BLACKTOWN], 0, 30), job(2, vec![BLACKTOWN], 0, 0)];
+    let slots = [slot(BLACKTOWN, at(12, 9)), slot(BLACKTOWN, at(10, 9)), slot(BLACKTOWN, at(11, 9))];
+    let allocations = allocate(&jobs, &slots, now());
+
+    assert_eq!(allocations.len(), 2);
+    assert_eq!((allocations[0].job_id, allocations[0].start_time), (1, at(10, 9)));
+    assert_eq!((allocations[1].job_id, allocations[1].start_time), (2, at(11, 9)));
+}
+
+#[test]
+fn unwanted_slots_are_not_allocated() {
+    let mut late = job(1, vec![BLACKTOWN], 0, 0);
+    late.before = NaiveDate::from_ymd_opt(2025, 6, 5).unwrap();
+    let slots = [
+        slot(BLACKTOWN, at(10, 9)),
+        slot(PENRITH, at(2, 9)),
+        (BLACKTOWN, TimeSlot { availability: false, slot_number: None, start_time: at(3, 9) }),
+    ];
+
+    assert!(allocate(&[late], &slots, now()).is_empty());
+}
+
+#[test]
+fn default_scoring_picks_the_earliest_slot() {
+    let jobs = vec![job(1, vec![BLACKTOWN, PENRITH], 0, 0)];
+    let slots = [slot(BLACKTOWN, at(12, 9)), slot(PENRITH, at(10, 9))];
+
+    let allocations = allocate(&jobs, &slots, now());
+    assert_eq!((allocations[0].location, allocations[0].start_time), (PENRITH, at(10, 9)));
+}
+
+#[test]
+fn time_of_day_preference_outweighs_a_day_earlier() {
+    let mut picky = job(1, vec![BLACKTOWN], 0, 0);
+    picky.scoring = SlotScoring {
+        time_of_day_weight: 1.0,
+        preferred_start_hour: 9,
+        preferred_end_hour: 12,
+        ..SlotScoring::default()
+    };
+    let slots = [slot(BLACKTOWN, at(10, 17)), slot(BLACKTOWN, at(11, 10))];
+
+    let allocations = allocate(&[picky], &slots, now());
+    assert_eq!(allocations[0].start_time, at(11, 10));
+}
+
+#[test]
+fn specific_days_strategy_skips_other_days() {
+    let mut weekends = job(1, vec![ARMIDALE], 0, 0);
+    weekends.strategy = StrategyConfig::SpecificDaysOnly { days: vec![Weekday::Sat] };
+    // 2025-06-10 is a Tuesday, 2025-06-14 a Saturday
+    let slots = [slot(ARMIDALE, at(10, 9)), slot(ARMIDALE, at(14, 9))];
+
+    let allocations = allocate(&[weekends], &slots, now());
+    assert_eq!(allocations[0].start_time, at(14, 9));
+}
+
+#[test]
+fn preferences_skip_slots_the_user_cannot_attend() {
+    let mut afternoons = job(1, vec![ARMIDALE], 0, 0);
+    afternoons.preferences = SlotPreferences {
+        earliest_time: NaiveTime::from_hms_opt(12, 0, 0),
+        latest_time: NaiveTime::from_hms_opt(15, 0, 0),
+        weekdays: vec![Weekday::Tue, Weekday::Wed],
+        ..SlotPreferences::default()
+    };
+    // 2025-06-09 is a Monday
+    let slots = [
+        slot(ARMIDALE, at(9, 13)),
+        slot(ARMIDALE, at(10, 7)),
+        slot(ARMIDALE, at(10, 16)),
+        slot(ARMIDALE, at(11, 15)),
+    ];
+
+    let allocations = allocate(&[afternoons], &slots, now());
+    assert_eq!(allocations[0].start_time, at(11, 15));
+}
+
+#[test]
+fn excluded_dates_are_skipped() {
+    let mut busy = job(1, vec![ARMIDALE, ALBURY], 0, 0);
+    busy.preferences.excluded_dates = vec![NaiveDate::from_ymd_opt(2025, 6, 3).unwrap()];
+    let slots = [slot(ARMIDALE, at(3, 9)), slot(ALBURY, at(3, 14)), slot(ARMIDALE, at(4, 9))];
+
+    let allocations = allocate(&[busy], &slots, now());
+    assert_eq!(allocations[0].start_time, at(4, 9));
+}
+
+#[test]
+fn preferred_locations_beat_earlier_slots_elsewhere() {
+    let mut ranked = job(1, vec![ARMIDALE, ALBURY], 0, 0);
+    ranked.strategy = StrategyConfig::PreferredLocations { ranking: vec![ALBURY, ARMIDALE] };
+    let slots = [slot(ARMIDALE, at(3, 9)), slot(ALBURY, at(25, 9)), slot(ALBURY, at(20, 9))];
+
+    let allocations = allocate(&[ranked], &slots, now());
+    assert_eq!((allocations[0].location, allocations[0].start_time), (ALBURY, at(20, 9)));
+}
+
+#[test]
+fn area_covers_centres_without_listing_them() {
+    let mut nearby = job(1, vec![], 0, 0);
+    // Albury itself, Armidale is several hundred km away
+    nearby.area = Some(SearchArea { latitude: -36.0751193, longitude: 146.9094852, radius_km: 50.0 });
+    assert!(nearby.search_locations().contains(&ALBURY));
+    assert!(!nearby.search_locations().contains(&ARMIDALE));
+
+    let slots = [slot(ARMIDALE, at(3, 9)), slot(ALBURY, at(20, 9))];
+    let allocations = allocate(&[nearby], &slots, now());
+    assert_eq!(allocations[0].location, ALBURY);
+}
+
+#[test]
+fn radius_strategy_skips_distant_centres() {
+    let mut local = job(1, vec![ARMIDALE, ALBURY], 0, 0);
+    // Albury itself, Armidale is several hundred km away
+    local.strategy = StrategyConfig::EarliestWithinRadius {
+        latitude: -36.0751193,
+        longitude: 146.9094852,
+        radius_km: 50.0,
+    };
+    let slots = [slot(ARMIDALE, at(3, 9)), slot(ALBURY, at(20, 9))];
+
+    let allocations = allocate(&[local], &slots, now());
+    assert_eq!(allocations[0].location, ALBURY);
+}
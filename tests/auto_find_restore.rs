@@ -0,0 +1,78 @@
+//! Auto-find jobs written to disk and brought back after a restart.
+
+mod common;
+
+use chrono::{NaiveDate, TimeZone, Utc};
+
+use nsw_closest_display::data::auto_find::{self, Submission};
+use nsw_closest_display::data::booking::BookingManager;
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::shared_booking::JobStatus;
+use nsw_closest_display::data::storage::DirectoryStorage;
+
+use common::settings;
+
+#[test]
+fn saved_jobs_survive_a_restart() {
+    let settings = settings("auto-find-restore");
+    assert!(auto_find::load(&settings.auto_find_jobs_file()).unwrap().is_empty());
+
+    let mut job_settings = settings.clone();
+    job_settings.booking_id = "87654321".to_string();
+    job_settings.last_name = "Smith".to_string();
+    let before = NaiveDate::from_ymd_opt(2025, 6, 30).unwrap();
+    let job_id = auto_find::add_job(vec![LocationId(18)], None, before, 3, job_settings);
+    auto_find::record_round(Utc.with_ymd_and_hms(2025, 6, 1, 2, 0, 0).unwrap());
+    let missed = JobStatus::Missed {
+        location: LocationId(18),
+        start_time: before.and_hms_opt(9, 30, 0).unwrap(),
+        reason: "Taken".to_string(),
+    };
+    auto_find::set_status("87654321", missed.clone());
+    BookingManager::save_auto_find_jobs(&settings);
+
+    // A restart starts with no jobs and no statuses
+    auto_find::clear_jobs();
+    auto_find::set_status("87654321", JobStatus::Searching);
+    assert!(auto_find::job_for("87654321").is_none());
+
+    let saved = auto_find::load(&settings.auto_find_jobs_file()).unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(auto_find::restore(saved[0].clone(), &settings), job_id);
+
+    let job = auto_find::job_for("87654321").unwrap();
+    assert_eq!((job.locations, job.before, job.priority, job.attempts), (vec![LocationId(18)], before, 3, 1));
+    assert_eq!(auto_find::status("87654321"), missed);
+    let (_, restored_settings) = auto_find::jobs().into_iter().find(|(job, _)| job.id == job_id).unwrap();
+    assert_eq!((restored_settings.booking_id.as_str(), restored_settings.last_name.as_str()), ("87654321", "Smith"));
+
+    // Only the booking's owner may stop it
+    assert!(auto_find::owned_by("87654321", " smith "));
+    assert!(!auto_find::owned_by("87654321", "Doe"));
+
+    BookingManager::stop_auto_find_for("87654321", &settings);
+    assert!(auto_find::load(&settings.auto_find_jobs_file()).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn submitted_jobs_are_taken_once() {
+    let settings = settings("auto-find-submissions");
+    let storage = DirectoryStorage { dir: settings.data_dir.clone() };
+    let before = NaiveDate::from_ymd_opt(2025, 6, 30).unwrap();
+    let saved = auto_find::SavedJob {
+        job: auto_find::new_job(vec![LocationId(18)], None, before, 0, &settings),
+        last_name: "Doe".to_string(),
+        status: JobStatus::Searching,
+    };
+
+    auto_find::submit(&storage, Submission::Start(saved)).await.unwrap();
+    let stop = Submission::Stop { booking_id: "12345678".to_string(), last_name: "Doe".to_string() };
+    auto_find::submit(&storage, stop).await.unwrap();
+
+    let submissions = auto_find::take_submissions(&storage).await.unwrap();
+    assert!(matches!(
+        submissions.as_slice(),
+        [Submission::Start(saved), Submission::Stop { booking_id, .. }] if saved.job.before == before && booking_id == "12345678"
+    ));
+    assert!(auto_find::take_submissions(&storage).await.unwrap().is_empty());
+}
@@ -0,0 +1,156 @@
+//! Naming, rotation and restoring of data file backups.
+
+mod common;
+
+use chrono::Duration;
+
+use nsw_closest_display::data::backup::{self, backup_name, parse_backup_name, BackupSchedule};
+use nsw_closest_display::data::booking::BookingManager;
+use nsw_closest_display::data::shared_booking::TestType;
+use nsw_closest_display::settings::Settings;
+
+use common::{settings, utc_at};
+
+fn write_data(settings: &Settings, test_type: TestType, contents: &str) {
+    std::fs::write(settings.bookings_file(test_type), contents).unwrap();
+}
+
+#[test]
+fn names_round_trip() {
+    let name = backup_name("bookings.json.gz", utc_at(10, 9));
+    assert_eq!(name, "bookings.20250610T090000Z.json.gz");
+    assert_eq!(parse_backup_name("bookings.json.gz", &name), Some(utc_at(10, 9)));
+
+    // Other test types and stray files are not backups of car data
+    assert_eq!(parse_backup_name("bookings.json", "bookings-rider.20250610T090000Z.json"), None);
+    assert_eq!(parse_backup_name("bookings.json", "bookings.json"), None);
+    assert_eq!(parse_backup_name("bookings.json", "bookings.notatime.json"), None);
+}
+
+#[test]
+fn daily_backups_rotate() {
+    let mut settings = settings("backup-rotate");
+    settings.backup_count = 2;
+    settings.backup_schedule = BackupSchedule::Daily;
+    write_data(&settings, TestType::Car, r#"{"results":[],"last_updated":null,"schema_version":2}"#);
+
+    backup::take(&settings, TestType::Car, utc_at(10, 9)).unwrap();
+    // Same day, not due yet
+    backup::take(&settings, TestType::Car, utc_at(10, 21)).unwrap();
+    assert_eq!(backup::list(&settings).unwrap().len(), 1);
+
+    backup::take(&settings, TestType::Car, utc_at(11, 9)).unwrap();
+    backup::take(&settings, TestType::Car, utc_at(12, 9)).unwrap();
+    let taken: Vec<_> = backup::list(&settings).unwrap().iter().map(|backup| backup.created_at).collect();
+    assert_eq!(taken, vec![utc_at(12, 9), utc_at(11, 9)]);
+
+    std::fs::remove_dir_all(&settings.data_dir).ok();
+}
+
+#[test]
+fn every_run_schedule_and_disabled_backups() {
+    let mut settings = settings("backup-schedule");
+    settings.backup_schedule = BackupSchedule::EveryRun;
+    write_data(&settings, TestType::Rider, r#"{"results":[],"last_updated":null,"schema_version":2}"#);
+
+    backup::take(&settings, TestType::Rider, utc_at(10, 9)).unwrap();
+    backup::take(&settings, TestType::Rider, utc_at(10, 9) + Duration::minutes(20)).unwrap();
+    assert_eq!(backup::list(&settings).unwrap().len(), 2);
+
+    settings.backup_count = 0;
+    backup::take(&settings, TestType::Rider, utc_at(11, 9)).unwrap();
+    assert_eq!(backup::list(&settings).unwrap().len(), 2);
+
+    std::fs::remove_dir_all(&settings.data_dir).ok();
+}
+
+#[test]
+fn restore_replaces_current_data() {
+    let settings = settings("backup-restore");
+    write_data(
+        &settings,
+        TestType::Dkt,
+        r#"{"results":[{"location":"18","slots":[],"next_available_date":null}],"last_updated":"2025-06-10T09:00:00Z","schema_version":2}"#,
+    );
+    backup::take(&settings, TestType::Dkt, utc_at(10, 9)).unwrap();
+    let file_name = backup::list(&settings).unwrap()[0].file_name.clone();
+
+    write_data(&settings, TestType::Dkt, r#"{"results":[],"last_updated":"2025-06-11T09:00:00Z","schema_version":2}"#);
+    BookingManager::init_from_file(TestType::Dkt, &settings.bookings_file(TestType::Dkt)).unwrap();
+    assert!(BookingManager::get_data(TestType::Dkt).0.results.is_empty());
+
+    assert!(backup::restore(&settings, TestType::Dkt, "../bookings-dkt.json").is_err());
+    backup::restore(&settings, TestType::Dkt, &file_name).unwrap();
+    assert_eq!(BookingManager::get_data(TestType::Dkt).0.results.len(), 1);
+    let saved = std::fs::read_to_string(settings.bookings_file(TestType::Dkt)).unwrap();
+    assert!(saved.contains("\"18\""));
+
+    std::fs::remove_dir_all(&settings.data_dir).ok();
+}
+
+#[test]
+fn saving_keeps_the_last_good_file() {
+    let settings = settings("backup-last-good");
+    let file = settings.bookings_file(TestType::Hpt);
+    write_data(
+        &settings,
+        TestType::Hpt,
+        r#"{"results":[{"location":"18","slots":[],"next_available_date":null}],"last_updated":"2025-06-10T09:00:00Z","schema_version":2}"#,
+    );
+    BookingManager::init_from_file(TestType::Hpt, &file).unwrap();
+    BookingManager::save_to_file(TestType::Hpt, &file).unwrap();
+
+    let last_good = BookingManager::last_good_path(&file);
+    assert!(last_good.exists());
+    assert!(!settings.data_dir.join("bookings-hpt.json.tmp").exists());
+
+    // A write cut short leaves a file that no longer parses
+    std::fs::write(&file, r#"{"results":[{"loca"#).unwrap();
+    BookingManager::init_from_file(TestType::Hpt, &file).unwrap();
+    assert_eq!(BookingManager::get_data(TestType::Hpt).0.results.len(), 1);
+
+    std::fs::remove_dir_all(&settings.data_dir).ok();
+}
+
+#[test]
+fn toggling_compression_keeps_the_data() {
+    let mut settings = settings("backup-toggle-compression");
+    write_data(
+        &settings,
+        TestType::Car,
+        r#"{"results":[{"location":"18","slots":[],"next_available_date":null}],"last_updated":"2025-06-10T09:00:00Z","schema_version":2}"#,
+    );
+    let plain = settings.bookings_file(TestType::Car);
+
+    settings.compress_data = true;
+    BookingManager::load_data(TestType::Car, &settings).unwrap();
+    assert_eq!(BookingManager::get_data(TestType::Car).0.results.len(), 1);
+    assert!(settings.bookings_file(TestType::Car).exists());
+    assert!(!plain.exists());
+
+    std::fs::remove_dir_all(&settings.data_dir).ok();
+}
+
+#[test]
+fn fallback_to_an_old_last_good_file_migrates_it() {
+    let settings = settings("backup-fallback-migrate");
+    let file = settings.bookings_file(TestType::HeavyVehicle);
+    let last_good = BookingManager::last_good_path(&file);
+    std::fs::write(
+        &last_good,
+        r#"{"results":[{"location":18,"test_type":"driving_test","slots":[{"availability":true,"slot_number":null,"startTime":"29/05/2025 14:35"}]}],"last_updated":"2025-05-20T10:00:00+00:00"}"#,
+    )
+    .unwrap();
+    std::fs::write(&file, r#"{"results":[{"loca"#).unwrap();
+
+    BookingManager::init_from_file(TestType::HeavyVehicle, &file).unwrap();
+    assert_eq!(BookingManager::get_data(TestType::HeavyVehicle).0.results.len(), 1);
+    assert!(settings.data_dir.join("bookings-heavy_vehicle.json.v1.bak").exists());
+    BookingManager::read_data_file(&file).unwrap();
+
+    // The broken file was moved aside instead of becoming the last good copy
+    assert!(settings.data_dir.join("bookings-heavy_vehicle.json.corrupt").exists());
+    assert_eq!(BookingManager::read_data_file(&last_good).unwrap().1, 1);
+
+    std::fs::remove_dir_all(&settings.data_dir).ok();
+}
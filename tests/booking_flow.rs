@@ -0,0 +1,218 @@
+//! The browser scraper and booking flow driven end to end against a mock
+//! portal: a local axum server serving trimmed recordings of the portal's
+//! pages from `tests/fixtures/portal`. Needs a WebDriver, by default at
+//! `http://localhost:4444` or wherever `WEBDRIVER_URL` points, so the tests
+//! are ignored unless run with `cargo test --features ssr,scraper -- --ignored`.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use axum::extract::{Path as UrlPath, State};
+use axum::response::Html;
+use axum::routing::get;
+use axum::Router;
+use chrono::NaiveDate;
+
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::rta::{book_first_available, cancel_booking, scrape_rta_timeslots};
+use nsw_closest_display::data::shared_booking::{BookingOutcome, TestType, TimeSlot};
+use nsw_closest_display::settings::Settings;
+
+use common::temp_dir;
+
+const CURRENT_BOOKING: &str = "30/12/2030 09:00";
+
+/// What the mock portal was asked to do.
+#[derive(Clone, Default)]
+struct Portal {
+    booked: Arc<Mutex<Vec<(u32, u32)>>>,
+    cancelled: Arc<Mutex<bool>>,
+}
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/portal")
+}
+
+fn page(name: &str, fields: &[(&str, String)]) -> Html<String> {
+    let mut html = std::fs::read_to_string(fixture_dir().join(name)).unwrap();
+    for (field, value) in fields {
+        html = html.replace(&format!("{{{{{}}}}}", field), value);
+    }
+    Html(html)
+}
+
+fn recorded_timeslots(location: u32) -> serde_json::Value {
+    std::fs::read_to_string(fixture_dir().join(format!("timeslots-{}.json", location)))
+        .map(|contents| serde_json::from_str(&contents).unwrap())
+        .unwrap_or_else(|_| serde_json::json!({ "ajaxresult": { "slots": { "listTimeSlot": [] } } }))
+}
+
+fn recorded_slots(location: u32) -> Vec<TimeSlot> {
+    serde_json::from_value(recorded_timeslots(location)["ajaxresult"]["slots"]["listTimeSlot"].clone()).unwrap()
+}
+
+fn slot_time(location: u32, slot: u32) -> String {
+    recorded_timeslots(location)["ajaxresult"]["slots"]["listTimeSlot"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["slot_number"] == slot)
+        .map(|entry| entry["startTime"].as_str().unwrap().to_string())
+        .unwrap_or_default()
+}
+
+async fn timeslots(UrlPath(location): UrlPath<u32>) -> Html<String> {
+    let buttons: String = recorded_slots(location)
+        .iter()
+        .filter(|slot| slot.availability)
+        .filter_map(|slot| slot.slot_number)
+        .map(|number| {
+            format!(
+                "<button id=\"slot-{number}\" type=\"button\" onclick=\"location.href='/confirm/{location}/{number}'\">{}</button>",
+                slot_time(location, number),
+            )
+        })
+        .collect();
+    page(
+        "timeslots.html",
+        &[
+            ("location", location.to_string()),
+            ("timeslots", recorded_timeslots(location).to_string()),
+            ("slots", buttons),
+        ],
+    )
+}
+
+async fn confirm(UrlPath((location, slot)): UrlPath<(u32, u32)>) -> Html<String> {
+    page(
+        "confirm.html",
+        &[("location", location.to_string()), ("slot", slot.to_string()), ("start_time", slot_time(location, slot))],
+    )
+}
+
+async fn receipt(State(portal): State<Portal>, UrlPath((location, slot)): UrlPath<(u32, u32)>) -> Html<String> {
+    portal.booked.lock().unwrap().push((location, slot));
+    page("receipt.html", &[("start_time", slot_time(location, slot))])
+}
+
+async fn cancelled(State(portal): State<Portal>) -> Html<String> {
+    *portal.cancelled.lock().unwrap() = true;
+    page("cancelled.html", &[])
+}
+
+/// Serve the mock portal on a free local port. Returns its origin.
+async fn start_portal() -> (String, Portal) {
+    let portal = Portal::default();
+    let app = Router::new()
+        .route("/wps/portal/extvp/myrta/login/", get(|| async { page("login.html", &[]) }))
+        .route("/manage", get(|| async { page("manage.html", &[]) }))
+        .route(
+            "/booking",
+            get(|| async { page("booking.html", &[("current_booking", CURRENT_BOOKING.to_string())]) }),
+        )
+        .route("/locations", get(|| async { page("locations.html", &[]) }))
+        .route("/timeslots/:location", get(timeslots))
+        .route("/confirm/:location/:slot", get(confirm))
+        .route("/receipt/:location/:slot", get(receipt))
+        .route("/cancel", get(|| async { page("cancel.html", &[]) }))
+        .route("/cancelled", get(cancelled))
+        .with_state(portal.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (origin, portal)
+}
+
+fn settings(portal_url: &str, name: &str) -> Settings {
+    let driver_url = std::env::var("WEBDRIVER_URL").unwrap_or_else(|_| "http://localhost:4444".to_string());
+    let mut settings = Settings::from_yaml_str(&format!(
+        r#"
+headless: true
+booking_id: "12345678"
+last_name: "Doe"
+have_booking: true
+selenium_driver_url: "{driver_url}"
+selenium_element_timout: 5000
+selenium_element_polling: 100
+retries: 1
+scrape_refresh_minutes: 20
+portal_url: "{portal_url}"
+portal_requests_per_minute: 0
+event_driven_waits: false
+failure_captures: false
+shuffle_locations: false
+weeks_ahead: 0
+location_attempts: 1
+"#
+    ))
+    .unwrap();
+    settings.data_dir = temp_dir(&format!("booking-flow-{}", name));
+    settings
+}
+
+fn before() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2030, 12, 1).unwrap()
+}
+
+#[tokio::test]
+#[ignore = "needs a WebDriver"]
+async fn scrape_reads_every_location() {
+    let (origin, _portal) = start_portal().await;
+    let settings = settings(&origin, "scrape");
+
+    let results = scrape_rta_timeslots(vec![LocationId(17), LocationId(18)], TestType::Car, &settings)
+        .await
+        .unwrap();
+
+    assert_eq!(results.bookings.len(), 2);
+    assert_eq!(results.bookings[&LocationId(17)].slots, recorded_slots(17));
+    assert_eq!(results.bookings[&LocationId(18)].slots, recorded_slots(18));
+}
+
+#[tokio::test]
+#[ignore = "needs a WebDriver"]
+async fn books_the_earliest_available_slot() {
+    let (origin, portal) = start_portal().await;
+    let settings = settings(&origin, "book");
+
+    let receipt = book_first_available(vec![LocationId(17), LocationId(18)], before(), false, &settings)
+        .await
+        .unwrap()
+        .expect("a slot should be booked");
+
+    assert_eq!(receipt.outcome, BookingOutcome::Booked);
+    assert_eq!(receipt.location, LocationId(18));
+    assert_eq!(receipt.start_time, NaiveDate::from_ymd_opt(2030, 6, 10).unwrap().and_hms_opt(9, 0, 0).unwrap());
+    assert_eq!(receipt.confirmation_number.as_deref(), Some("123456789"));
+    assert_eq!(*portal.booked.lock().unwrap(), vec![(18, 2)]);
+}
+
+#[tokio::test]
+#[ignore = "needs a WebDriver"]
+async fn dry_run_stops_before_confirming() {
+    let (origin, portal) = start_portal().await;
+    let settings = settings(&origin, "dry-run");
+
+    let receipt = book_first_available(vec![LocationId(18)], before(), true, &settings)
+        .await
+        .unwrap()
+        .expect("a slot should be previewed");
+
+    assert_eq!(receipt.outcome, BookingOutcome::DryRun);
+    assert!(portal.booked.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+#[ignore = "needs a WebDriver"]
+async fn cancels_the_current_booking() {
+    let (origin, portal) = start_portal().await;
+    let settings = settings(&origin, "cancel");
+
+    let cancelled = cancel_booking(&settings).await.unwrap();
+
+    assert_eq!(cancelled, NaiveDate::from_ymd_opt(2030, 12, 30).unwrap().and_hms_opt(9, 0, 0));
+    assert!(*portal.cancelled.lock().unwrap());
+}
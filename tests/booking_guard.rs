@@ -0,0 +1,31 @@
+//! The guard that stops a reschedule from making an existing booking worse.
+
+mod common;
+
+use chrono::{Duration, NaiveDateTime};
+
+use nsw_closest_display::data::shared_booking::TimeSlot;
+
+use common::at;
+
+fn slot(start_time: NaiveDateTime) -> TimeSlot {
+    TimeSlot { availability: true, slot_number: None, start_time }
+}
+
+#[test]
+fn earlier_slot_is_an_improvement() {
+    assert!(slot(at(10, 9)).improves_on(at(20, 9), Duration::zero()));
+}
+
+#[test]
+fn same_or_later_slot_is_rejected() {
+    assert!(!slot(at(20, 9)).improves_on(at(20, 9), Duration::zero()));
+    assert!(!slot(at(21, 9)).improves_on(at(20, 9), Duration::zero()));
+}
+
+#[test]
+fn minimum_improvement_is_enforced() {
+    let current = at(20, 9);
+    assert!(!slot(at(19, 12)).improves_on(current, Duration::hours(24)));
+    assert!(slot(at(19, 9)).improves_on(current, Duration::hours(24)));
+}
@@ -0,0 +1,37 @@
+//! The record of bookings the bot made, kept across restarts.
+
+mod common;
+
+use chrono::{NaiveDate, TimeZone, Utc};
+
+use nsw_closest_display::data::booking::BookingManager;
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::shared_booking::{BookingOutcome, BookingReceipt, BookingRecord, TestType};
+
+use common::settings;
+
+#[test]
+fn recorded_bookings_survive_a_restart() {
+    let settings = settings("booking-history");
+    BookingManager::load_booking_history(&settings).unwrap();
+    assert!(BookingManager::booking_history().is_empty());
+
+    let mut receipt = BookingReceipt::new(
+        BookingOutcome::Booked,
+        LocationId(18),
+        NaiveDate::from_ymd_opt(2025, 6, 10).unwrap().and_hms_opt(9, 30, 0).unwrap(),
+    );
+    receipt.confirmation_number = Some("123456789".to_string());
+    let record = BookingRecord {
+        recorded_at: Utc.with_ymd_and_hms(2025, 6, 1, 2, 0, 0).unwrap(),
+        booking_id: settings.booking_id.clone(),
+        test_type: TestType::Car,
+        receipt,
+        screenshot: Some("receipts/20250601-020000.png".to_string()),
+    };
+    BookingManager::record_booking(record.clone(), &settings);
+    assert!(settings.bookings_history_file().exists());
+
+    BookingManager::load_booking_history(&settings).unwrap();
+    assert_eq!(BookingManager::booking_history(), vec![record]);
+}
@@ -0,0 +1,54 @@
+//! Notifications sent when a booking attempt ends.
+
+use chrono::NaiveDate;
+
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::shared_booking::{BookingOutcome, BookingReceipt};
+use nsw_closest_display::notifications::{Notification, NotificationLevel};
+
+fn receipt(outcome: BookingOutcome) -> BookingReceipt {
+    let start_time = NaiveDate::from_ymd_opt(2025, 6, 10).unwrap().and_hms_opt(9, 30, 0).unwrap();
+    BookingReceipt::new(outcome, LocationId(18), start_time)
+}
+
+#[test]
+fn booked_slot_names_the_centre_and_number() {
+    let mut booked = receipt(BookingOutcome::Booked);
+    booked.confirmation_number = Some("123456789".to_string());
+
+    let notification = Notification::booking("12345678", &booked).unwrap();
+    assert_eq!(notification.level, NotificationLevel::Info);
+    assert_eq!(notification.title, "Test booked");
+    assert!(notification.message.contains("Armidale"), "{}", notification.message);
+    assert!(notification.message.contains("123456789"), "{}", notification.message);
+}
+
+#[test]
+fn blocked_changes_are_warnings() {
+    for outcome in [BookingOutcome::ChangeFeeApplies, BookingOutcome::ChangeLimitReached] {
+        let notification = Notification::booking("12345678", &receipt(outcome)).unwrap();
+        assert_eq!(notification.level, NotificationLevel::Warning);
+        assert!(notification.message.contains("12345678"), "{}", notification.message);
+    }
+}
+
+#[test]
+fn unconfirmed_booking_asks_the_user_to_check() {
+    let notification = Notification::booking("12345678", &receipt(BookingOutcome::Unconfirmed)).unwrap();
+    assert_eq!(notification.level, NotificationLevel::Warning);
+    assert_ne!(notification.title, "Test booked");
+    assert!(notification.message.contains("Check it"), "{}", notification.message);
+}
+
+#[test]
+fn attempts_that_carry_on_are_not_announced() {
+    for outcome in [
+        BookingOutcome::Unavailable,
+        BookingOutcome::Declined,
+        BookingOutcome::NotAnImprovement,
+        BookingOutcome::DryRun,
+        BookingOutcome::PaymentRequired,
+    ] {
+        assert!(Notification::booking("12345678", &receipt(outcome)).is_none());
+    }
+}
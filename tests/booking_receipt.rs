@@ -0,0 +1,80 @@
+//! Reading the booking number off the portal's receipt, re-checking a slot
+//! on the location page before booking it, and the change fee pre-check.
+
+use chrono::NaiveDate;
+
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::rta::{parse_change_fee, parse_confirmation_number, slot_still_offered, UNKNOWN_CHANGE_FEE};
+use nsw_closest_display::data::shared_booking::{BookingOutcome, RebookEligibility, TimeSlot};
+
+#[test]
+fn finds_the_booking_number() {
+    assert_eq!(parse_confirmation_number("Booking number: 123456789").as_deref(), Some("123456789"));
+    assert_eq!(parse_confirmation_number("  Receipt no. BK20931X\n").as_deref(), Some("BK20931X"));
+}
+
+#[test]
+fn skips_dates_and_times() {
+    assert_eq!(
+        parse_confirmation_number("Monday 12/05/2025 10:30 AM, reference 4471902").as_deref(),
+        Some("4471902"),
+    );
+}
+
+#[test]
+fn no_number_on_the_page() {
+    assert_eq!(parse_confirmation_number("Your booking is confirmed"), None);
+    assert_eq!(parse_confirmation_number(""), None);
+}
+
+fn slot(day: u32, hour: u32, availability: bool) -> TimeSlot {
+    let start_time = NaiveDate::from_ymd_opt(2025, 6, day).unwrap().and_hms_opt(hour, 0, 0).unwrap();
+    TimeSlot { availability, slot_number: None, start_time }
+}
+
+fn timeslots(slots: &[TimeSlot]) -> serde_json::Value {
+    serde_json::json!({ "ajaxresult": { "slots": { "listTimeSlot": slots } } })
+}
+
+#[test]
+fn slot_taken_since_the_scrape() {
+    let page = timeslots(&[slot(10, 9, false), slot(10, 10, true)]);
+    assert!(!slot_still_offered(&page, LocationId(18), &slot(10, 9, true)));
+    assert!(slot_still_offered(&page, LocationId(18), &slot(10, 10, true)));
+}
+
+#[test]
+fn slot_on_a_day_the_page_does_not_show() {
+    let page = timeslots(&[slot(10, 9, true)]);
+    assert!(slot_still_offered(&page, LocationId(18), &slot(17, 9, true)));
+}
+
+#[test]
+fn change_fee_amounts() {
+    assert_eq!(parse_change_fee("A change fee of $27.00 applies.").as_deref(), Some("$27.00"));
+    assert_eq!(parse_change_fee("Change fee: $1,027.50").as_deref(), Some("$1,027.50"));
+    assert_eq!(parse_change_fee("No change fee ($0.00) applies"), None);
+}
+
+#[test]
+fn change_fee_without_a_readable_amount_still_counts() {
+    for notice in ["A change fee applies", "A change fee may apply", "Change fee: $ see below", "Change fee: AUD 27"] {
+        assert_eq!(parse_change_fee(notice).as_deref(), Some(UNKNOWN_CHANGE_FEE), "{}", notice);
+    }
+
+    let unknown = RebookEligibility { change_fee: parse_change_fee("A change fee applies"), limit_reached: false };
+    assert_eq!(unknown.blocks(true), Some(BookingOutcome::ChangeFeeApplies));
+}
+
+#[test]
+fn rebooking_is_blocked_by_the_limit_or_an_unwanted_fee() {
+    let free = RebookEligibility::default();
+    assert_eq!(free.blocks(true), None);
+
+    let fee = RebookEligibility { change_fee: Some("$27.00".to_string()), limit_reached: false };
+    assert_eq!(fee.blocks(false), None);
+    assert_eq!(fee.blocks(true), Some(BookingOutcome::ChangeFeeApplies));
+
+    let limit = RebookEligibility { change_fee: None, limit_reached: true };
+    assert_eq!(limit.blocks(false), Some(BookingOutcome::ChangeLimitReached));
+}
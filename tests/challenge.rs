@@ -0,0 +1,32 @@
+//! Recognising captcha and "unusual activity" pages.
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+use nsw_closest_display::data::challenge::BlockedWindow;
+
+fn at(hour: u32, minute: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2025, 6, 10).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+}
+
+#[test]
+fn captcha_frames_are_detected() {
+    let page = r#"<html><body><div class="g-recaptcha" data-sitekey="abc"></div></body></html>"#;
+
+    let window = BlockedWindow::detect(page, at(9, 0), Duration::minutes(120)).unwrap();
+    assert_eq!(window.marker, "g-recaptcha");
+    assert_eq!(window.until, at(11, 0));
+    assert!(window.is_active(at(10, 59)));
+    assert!(!window.is_active(at(11, 0)));
+}
+
+#[test]
+fn interstitial_text_is_detected_in_any_case() {
+    let page = "<h1>We've noticed Unusual Activity from your network</h1>";
+    assert_eq!(BlockedWindow::detect(page, at(9, 0), Duration::minutes(5)).unwrap().marker, "unusual activity");
+}
+
+#[test]
+fn ordinary_pages_are_not_challenges() {
+    let page = r#"<form id="loginForm"><input id="bookingId"></form><p>Manage booking</p>"#;
+    assert_eq!(BlockedWindow::detect(page, at(9, 0), Duration::minutes(5)), None);
+}
@@ -0,0 +1,99 @@
+//! Diffing the slots of one update against the next.
+
+mod common;
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+
+use nsw_closest_display::data::changes::diff;
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::shared_booking::{LocationBookings, TestType, TimeSlot};
+use nsw_closest_display::notifications::Notification;
+
+use common::future_at;
+
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 6, 10, 9, 0, 0).unwrap()
+}
+
+fn bookings(location: u32, slots: &[(NaiveDateTime, bool)]) -> LocationBookings {
+    LocationBookings {
+        location: LocationId(location),
+        test_type: TestType::Car,
+        slots: slots
+            .iter()
+            .map(|&(start_time, availability)| TimeSlot { availability, slot_number: None, start_time })
+            .collect(),
+        next_available_date: None,
+    }
+}
+
+#[test]
+fn reports_added_removed_and_earliest() {
+    let previous = vec![
+        bookings(18, &[(future_at(12, 9), true), (future_at(14, 9), true)]),
+        bookings(20, &[(future_at(15, 9), true)]),
+        bookings(22, &[(future_at(16, 9), true)]),
+    ];
+    let current = vec![
+        bookings(22, &[(future_at(16, 9), true)]),
+        bookings(18, &[(future_at(11, 9), true), (future_at(14, 9), true)]),
+        bookings(20, &[]),
+    ];
+
+    let changes = diff(TestType::Car, &previous, &current, now());
+    assert_eq!(changes.detected_at, now());
+    assert_eq!(
+        changes.locations.iter().map(|change| change.location).collect::<Vec<_>>(),
+        vec![LocationId(18), LocationId(20)]
+    );
+
+    let armidale = changes.location(LocationId(18)).unwrap();
+    assert_eq!(armidale.added, vec![future_at(11, 9)]);
+    assert_eq!(armidale.removed, vec![future_at(12, 9)]);
+    assert_eq!((armidale.earliest_before, armidale.earliest_after), (Some(future_at(12, 9)), Some(future_at(11, 9))));
+    assert!(armidale.earlier());
+
+    let emptied = changes.location(LocationId(20)).unwrap();
+    assert_eq!(emptied.removed, vec![future_at(15, 9)]);
+    assert!(emptied.earliest_changed());
+    assert!(!emptied.earlier());
+
+    assert_eq!(changes.earlier().count(), 1);
+}
+
+#[test]
+fn only_bookable_slots_of_scraped_locations_count() {
+    let previous = vec![bookings(18, &[(future_at(12, 9), true)]), bookings(20, &[(future_at(15, 9), true)])];
+    // 20 wasn't scraped this time, and 18 only gained a taken slot
+    let current = vec![bookings(18, &[(future_at(11, 9), false), (future_at(12, 9), true)])];
+
+    assert!(diff(TestType::Car, &previous, &current, now()).is_empty());
+}
+
+#[test]
+fn changes_narrow_to_watched_locations() {
+    let previous = vec![bookings(18, &[(future_at(12, 9), true)]), bookings(20, &[(future_at(15, 9), true)])];
+    let current = vec![bookings(18, &[(future_at(11, 9), true)]), bookings(20, &[(future_at(14, 9), true)])];
+    let changes = diff(TestType::Car, &previous, &current, now());
+
+    let watched = changes.for_locations(&[LocationId(20)]);
+    assert_eq!(watched.locations.len(), 1);
+    assert!(watched.location(LocationId(18)).is_none());
+    assert!(watched.location(LocationId(20)).is_some());
+    assert!(changes.for_locations(&[]).is_empty());
+}
+
+#[test]
+fn new_locations_are_earlier() {
+    let changes = diff(TestType::Car, &[], &[bookings(18, &[(future_at(12, 9), true)])], now());
+    let change = changes.location(LocationId(18)).unwrap();
+    assert_eq!(change.added, vec![future_at(12, 9)]);
+    assert!(change.earlier());
+
+    let notification = Notification::earlier_slots(&changes).unwrap();
+    assert!(notification.message.contains("Armidale"), "{}", notification.message);
+
+    let later = diff(TestType::Car, &[bookings(18, &[(future_at(12, 9), true)])], &[bookings(18, &[(future_at(13, 9), true)])], now());
+    assert!(!later.is_empty());
+    assert!(Notification::earlier_slots(&later).is_none());
+}
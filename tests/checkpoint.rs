@@ -0,0 +1,50 @@
+//! Resuming interrupted scrape runs.
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+use nsw_closest_display::data::checkpoint::{ScrapeCheckpoint, ScrapeStage};
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::shared_booking::{LocationBookings, TestType};
+
+fn at(minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 6, 10, 9, minute, 0).unwrap()
+}
+
+fn bookings(location: u32) -> LocationBookings {
+    LocationBookings { location: LocationId(location), test_type: TestType::Car, slots: Vec::new(), next_available_date: None }
+}
+
+#[test]
+fn finished_locations_leave_the_pending_list() {
+    let mut checkpoint = ScrapeCheckpoint::new(TestType::Car, vec![LocationId(1), LocationId(2)], at(0));
+    checkpoint.enter(ScrapeStage::Locations, at(1));
+    checkpoint.location_failed(LocationId(1), "timed out".to_string(), at(2));
+    checkpoint.location_done(bookings(2), at(3));
+
+    assert_eq!(checkpoint.pending, vec![LocationId(1)]);
+    assert_eq!(checkpoint.scraped.len(), 1);
+    assert_eq!(checkpoint.errors.get(&LocationId(1)).map(String::as_str), Some("timed out"));
+    assert_eq!(checkpoint.stage, ScrapeStage::Locations);
+
+    checkpoint.location_done(bookings(1), at(4));
+    assert!(checkpoint.errors.is_empty());
+    assert_eq!(checkpoint.stage, ScrapeStage::Done);
+
+    // A lost session can't reopen a finished run
+    checkpoint.enter(ScrapeStage::SignIn, at(5));
+    assert_eq!(checkpoint.stage, ScrapeStage::Done);
+}
+
+#[test]
+fn only_recent_unfinished_runs_resume() {
+    let mut checkpoint = ScrapeCheckpoint::new(TestType::Car, vec![LocationId(1), LocationId(2)], at(0));
+    checkpoint.location_done(bookings(2), at(10));
+
+    let max_age = Duration::minutes(30);
+    assert!(checkpoint.resumable(TestType::Car, at(40), max_age));
+    assert!(!checkpoint.resumable(TestType::Car, at(41), max_age));
+    assert!(!checkpoint.resumable(TestType::Rider, at(20), max_age));
+
+    checkpoint.location_done(bookings(1), at(20));
+    assert!(!checkpoint.resumable(TestType::Car, at(21), max_age));
+}
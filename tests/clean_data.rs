@@ -0,0 +1,102 @@
+//! Cleaning of scraped results before they are stored and hashed.
+
+mod common;
+
+use chrono::NaiveDateTime;
+
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::shared_booking::{
+    clean_results, CleanReport, LocationBookings, TestType, TimeSlot, UnavailableSlots,
+};
+
+use common::at;
+
+fn slot(start_time: NaiveDateTime, availability: bool) -> TimeSlot {
+    TimeSlot { availability, slot_number: None, start_time }
+}
+
+fn bookings(location: u32, slots: Vec<TimeSlot>) -> LocationBookings {
+    LocationBookings { location: LocationId(location), test_type: TestType::Car, slots, next_available_date: None }
+}
+
+#[test]
+fn drops_unavailable_past_and_duplicate_slots() {
+    let now = at(10, 12);
+    let results = vec![bookings(
+        18,
+        vec![
+            slot(at(12, 9), true),
+            slot(at(11, 9), true),
+            slot(at(12, 9), true),
+            slot(at(13, 9), false),
+            slot(at(9, 9), true),
+        ],
+    )];
+
+    let (cleaned, report) = clean_results(results, now, UnavailableSlots::Drop);
+    let times: Vec<_> = cleaned[0].slots.iter().map(|slot| slot.start_time).collect();
+    assert_eq!(times, vec![at(11, 9), at(12, 9)]);
+    assert_eq!(report, CleanReport { unavailable: 1, past: 1, duplicates: 1 });
+    assert_eq!(report.dropped(), 3);
+}
+
+#[test]
+fn repeated_locations_are_merged_and_sorted() {
+    let now = at(10, 12);
+    let mut later = bookings(18, vec![slot(at(14, 9), true), slot(at(12, 9), true)]);
+    later.next_available_date = Some(at(14, 9));
+    let mut earlier = bookings(18, vec![slot(at(12, 9), true)]);
+    earlier.next_available_date = Some(at(12, 9));
+    let results = vec![bookings(30, vec![slot(at(11, 9), true)]), later, earlier];
+
+    let (cleaned, report) = clean_results(results, now, UnavailableSlots::Drop);
+    let locations: Vec<_> = cleaned.iter().map(|location| location.location).collect();
+    assert_eq!(locations, vec![LocationId(18), LocationId(30)]);
+    assert_eq!(cleaned[0].slots.len(), 2);
+    assert_eq!(cleaned[0].next_available_date, Some(at(12, 9)));
+    assert_eq!(report.duplicates, 1);
+}
+
+#[test]
+fn same_availability_hashes_the_same_in_any_order() {
+    let now = at(10, 12);
+    let a = vec![bookings(18, vec![slot(at(12, 9), true)]), bookings(20, vec![slot(at(13, 9), true), slot(at(11, 9), true)])];
+    let b = vec![bookings(20, vec![slot(at(11, 9), true), slot(at(13, 9), true)]), bookings(18, vec![slot(at(12, 9), true)])];
+
+    let hash = |results: Vec<LocationBookings>| {
+        clean_results(results, now, UnavailableSlots::Drop).0.iter().map(|location| location.calculate_hash()).collect::<Vec<_>>()
+    };
+    assert_eq!(hash(a), hash(b));
+}
+
+#[test]
+fn unavailable_slots_can_be_kept_for_capacity() {
+    let now = at(10, 12);
+    let results = vec![bookings(
+        18,
+        vec![slot(at(12, 9), false), slot(at(11, 9), true), slot(at(12, 9), true), slot(at(13, 9), false)],
+    )];
+
+    let (cleaned, report) = clean_results(results, now, UnavailableSlots::Keep);
+    assert_eq!(report, CleanReport { unavailable: 0, past: 0, duplicates: 1 });
+
+    // The duplicate keeps its bookable copy
+    let slots: Vec<_> = cleaned[0].slots.iter().map(|slot| (slot.start_time, slot.availability)).collect();
+    assert_eq!(slots, vec![(at(11, 9), true), (at(12, 9), true), (at(13, 9), false)]);
+
+    let capacity = cleaned[0].capacity();
+    assert_eq!((capacity.total_slots, capacity.available_slots), (3, 2));
+    assert_eq!(cleaned[0].earliest_slot().map(|slot| slot.start_time), Some(at(11, 9)));
+}
+
+#[test]
+fn later_weeks_add_only_new_slots_in_order() {
+    let mut location = bookings(18, vec![slot(at(11, 9), true), slot(at(12, 9), true)]);
+
+    let added = location.add_slots(vec![slot(at(12, 9), true), slot(at(19, 9), true), slot(at(18, 14), false)]);
+
+    assert_eq!(added, 2);
+    let starts: Vec<_> = location.slots.iter().map(|slot| slot.start_time).collect();
+    assert_eq!(starts, [at(11, 9), at(12, 9), at(18, 14), at(19, 9)]);
+    assert_eq!(location.add_slots(vec![slot(at(19, 9), true)]), 0);
+}
@@ -0,0 +1,72 @@
+//! Fixtures shared by the integration tests. Every test binary compiles its
+//! own copy and uses only some of it.
+
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use nsw_closest_display::settings::Settings;
+
+/// The settings every instance needs, nothing optional.
+pub const MINIMAL_YAML: &str = r#"
+headless: true
+booking_id: "12345678"
+last_name: "Doe"
+have_booking: false
+selenium_driver_url: "http://localhost:4444"
+selenium_element_timout: 20000
+selenium_element_polling: 100
+retries: 3
+scrape_refresh_minutes: 20
+"#;
+
+/// `MINIMAL_YAML` with `extra` YAML appended.
+pub fn settings_with(extra: &str) -> Settings {
+    Settings::from_yaml_str(&format!("{}{}", MINIMAL_YAML, extra)).unwrap()
+}
+
+/// Minimal settings with an empty data directory of their own.
+pub fn settings(name: &str) -> Settings {
+    let mut settings = settings_with("");
+    settings.data_dir = temp_dir(name);
+    settings
+}
+
+/// An empty directory for one test, named after it and this run.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A file in the temp directory for one test, named after it and this run,
+/// e.g. `audit-append.jsonl` becomes `audit-append-<pid>.jsonl`. Nothing is
+/// created, and whatever was left there is removed.
+pub fn temp_path(name: &str) -> PathBuf {
+    let (stem, extension) = match name.split_once('.') {
+        Some((stem, extension)) => (stem, format!(".{}", extension)),
+        None => (name, String::new()),
+    };
+    let path = std::env::temp_dir().join(format!("{}-{}{}", stem, std::process::id(), extension));
+    std::fs::remove_file(&path).ok();
+    path
+}
+
+/// A time in June 2025.
+pub fn at(day: u32, hour: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2025, 6, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+}
+
+/// A time in June 2030, for slots that have to still be ahead when the test
+/// runs.
+pub fn future_at(day: u32, hour: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2030, 6, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+}
+
+/// `at` in UTC.
+pub fn utc_at(day: u32, hour: u32) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&at(day, hour))
+}
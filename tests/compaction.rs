@@ -0,0 +1,63 @@
+//! Compacting old availability history into daily aggregates.
+
+mod common;
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use nsw_closest_display::data::history::{self, Compaction};
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::shared_booking::{LocationBookings, TestType, TimeSlot};
+
+use common::{at, temp_dir};
+
+fn record(path: &std::path::Path, recorded_at: NaiveDateTime) {
+    let bookings = LocationBookings {
+        location: LocationId(18),
+        test_type: TestType::Car,
+        slots: vec![TimeSlot { availability: true, slot_number: None, start_time: at(20, 9) }],
+        next_available_date: None,
+    };
+    history::append(path, &[bookings], recorded_at).unwrap();
+}
+
+#[test]
+fn whole_days_before_the_cutoff_are_compacted_once() {
+    let dir = temp_dir("compaction-days");
+    let (raw, daily) = (dir.join("history.jsonl"), dir.join("history-daily.jsonl"));
+    for recorded_at in [at(2, 9), at(2, 15), at(3, 9), at(4, 9), at(4, 15)] {
+        record(&raw, recorded_at);
+    }
+
+    // The cutoff falls mid-day, that day stays raw
+    let compaction = history::compact(&raw, &daily, at(4, 12), None).unwrap();
+    assert_eq!(compaction, Compaction { compacted_entries: 3, aggregates_written: 2, aggregates_dropped: 0 });
+
+    let remaining = history::load(&raw, at(1, 0)).unwrap();
+    assert_eq!(remaining.iter().map(|entry| entry.recorded_at).collect::<Vec<_>>(), vec![at(4, 9), at(4, 15)]);
+    let aggregates = history::load_daily(&daily).unwrap();
+    assert_eq!(aggregates.iter().map(|aggregate| aggregate.observations).collect::<Vec<_>>(), vec![2, 1]);
+
+    // A day already aggregated is not counted again
+    record(&raw, at(3, 20));
+    let compaction = history::compact(&raw, &daily, at(4, 12), None).unwrap();
+    assert_eq!(compaction.aggregates_written, 0);
+    assert_eq!(history::load_daily(&daily).unwrap().len(), 2);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn old_aggregates_are_dropped() {
+    let dir = temp_dir("compaction-retention");
+    let (raw, daily) = (dir.join("history.jsonl"), dir.join("history-daily.jsonl"));
+    for recorded_at in [at(2, 9), at(3, 9), at(5, 9)] {
+        record(&raw, recorded_at);
+    }
+
+    let compaction = history::compact(&raw, &daily, at(5, 0), NaiveDate::from_ymd_opt(2025, 6, 3)).unwrap();
+    assert_eq!(compaction, Compaction { compacted_entries: 2, aggregates_written: 2, aggregates_dropped: 1 });
+    let aggregates = history::load_daily(&daily).unwrap();
+    assert_eq!(aggregates.iter().map(|aggregate| aggregate.date.format("%d").to_string()).collect::<Vec<_>>(), vec!["03"]);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
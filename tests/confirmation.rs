@@ -0,0 +1,31 @@
+//! Held slots waiting for the user to approve them.
+
+use chrono::{Duration, NaiveDate, Utc};
+
+use nsw_closest_display::data::confirmation;
+use nsw_closest_display::data::location::LocationId;
+
+#[test]
+fn held_slot_is_found_by_booking_and_approved() {
+    let start_time = NaiveDate::from_ymd_opt(2025, 6, 10).unwrap().and_hms_opt(9, 30, 0).unwrap();
+    let (token, mut decision) =
+        confirmation::register("11112222", LocationId(18), start_time, Utc::now() + Duration::minutes(10));
+
+    assert!(confirmation::pending_for("33334444").is_none());
+    let pending = confirmation::pending_for("11112222").unwrap();
+    assert_eq!((pending.token.as_str(), pending.location, pending.start_time), (token.as_str(), LocationId(18), start_time));
+
+    assert_eq!(confirmation::resolve(&token, true), Some((LocationId(18), start_time)));
+    assert_eq!(decision.try_recv(), Ok(true));
+    assert!(confirmation::pending_for("11112222").is_none());
+}
+
+#[test]
+fn expired_hold_is_not_offered() {
+    let start_time = NaiveDate::from_ymd_opt(2025, 6, 11).unwrap().and_hms_opt(9, 30, 0).unwrap();
+    let (token, _decision) =
+        confirmation::register("55556666", LocationId(17), start_time, Utc::now() - Duration::seconds(1));
+
+    assert!(confirmation::pending_for("55556666").is_none());
+    assert_eq!(confirmation::resolve(&token, true), None);
+}
@@ -0,0 +1,52 @@
+//! Putting repeatedly failing centres on cooldown.
+
+use nsw_closest_display::data::cooldown::LocationCooldowns;
+use nsw_closest_display::data::location::LocationId;
+
+const GOOD: LocationId = LocationId(18);
+const BAD: LocationId = LocationId(30);
+
+fn run(cooldowns: &mut LocationCooldowns, bad_fails: bool) -> (Vec<LocationId>, Vec<LocationId>) {
+    let (scrape, cooling) = cooldowns.start_run(vec![GOOD, BAD]);
+    let (failed, succeeded): (Vec<_>, Vec<_>) = scrape.iter().partition(|&&location| bad_fails && location == BAD);
+    let started = cooldowns.record(&succeeded, &failed, 2, 3);
+    assert!(started.iter().all(|&location| location == BAD));
+    (scrape, cooling)
+}
+
+#[test]
+fn failing_centre_sits_out_then_is_retried() {
+    let mut cooldowns = LocationCooldowns::default();
+
+    assert_eq!(run(&mut cooldowns, true), (vec![GOOD, BAD], vec![]));
+    assert_eq!(run(&mut cooldowns, true), (vec![GOOD, BAD], vec![]));
+    assert_eq!(cooldowns.get(BAD).unwrap().remaining_runs, 3);
+
+    for _ in 0..3 {
+        assert_eq!(run(&mut cooldowns, true), (vec![GOOD], vec![BAD]));
+    }
+    assert_eq!(run(&mut cooldowns, false), (vec![GOOD, BAD], vec![]));
+    assert_eq!(cooldowns.get(BAD), None);
+    assert_eq!(cooldowns.get(GOOD), None);
+}
+
+#[test]
+fn success_resets_the_count() {
+    let mut cooldowns = LocationCooldowns::default();
+
+    run(&mut cooldowns, true);
+    run(&mut cooldowns, false);
+    run(&mut cooldowns, true);
+    assert_eq!(cooldowns.get(BAD).unwrap().consecutive_failures, 1);
+    assert_eq!(cooldowns.get(BAD).unwrap().remaining_runs, 0);
+}
+
+#[test]
+fn zero_threshold_disables_cooldowns() {
+    let mut cooldowns = LocationCooldowns::default();
+
+    for _ in 0..5 {
+        assert!(cooldowns.record(&[], &[BAD], 0, 3).is_empty());
+    }
+    assert_eq!(cooldowns.start_run(vec![BAD]), (vec![BAD], vec![]));
+}
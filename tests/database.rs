@@ -0,0 +1,152 @@
+//! The SQLite data store: datasets, partial saves and the scrape run log.
+
+mod common;
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+
+use nsw_closest_display::data::booking::BookingManager;
+use nsw_closest_display::data::database::{DataStore, Database};
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::scrape_runs::RunQuery;
+use nsw_closest_display::data::shared_booking::{
+    BookingData, LocationBookings, ScrapeRun, TestType, TimeSlot, BOOKING_DATA_VERSION,
+};
+
+use common::{future_at, settings_with, temp_dir};
+
+fn updated(hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 6, 10, hour, 0, 0).unwrap()
+}
+
+fn bookings(location: u32, slots: &[(u32, u32)]) -> LocationBookings {
+    LocationBookings {
+        location: LocationId(location),
+        test_type: TestType::Car,
+        slots: slots
+            .iter()
+            .enumerate()
+            .map(|(number, &(day, hour))| TimeSlot {
+                availability: true,
+                slot_number: Some(number as u32 + 1),
+                start_time: future_at(day, hour),
+            })
+            .collect(),
+        next_available_date: slots.first().map(|&(day, hour)| future_at(day, hour)),
+    }
+}
+
+fn data(results: Vec<LocationBookings>, hour: u32) -> BookingData {
+    BookingData { results, last_updated: Some(updated(hour)), schema_version: BOOKING_DATA_VERSION }
+}
+
+type SlotRow = (NaiveDateTime, Option<u32>, bool);
+type LocationRow = (LocationId, Option<NaiveDateTime>, Vec<SlotRow>);
+
+/// Locations and their slots as plain tuples, since slots compare by time only.
+fn contents(data: &BookingData) -> Vec<LocationRow> {
+    data.results
+        .iter()
+        .map(|bookings| {
+            let slots = bookings
+                .slots
+                .iter()
+                .map(|slot| (slot.start_time, slot.slot_number, slot.availability))
+                .collect();
+            (bookings.location, bookings.next_available_date, slots)
+        })
+        .collect()
+}
+
+#[test]
+fn datasets_round_trip() {
+    let path = temp_dir("database-round-trip").join("bookings.sqlite3");
+    let mut database = Database::open(&path).unwrap();
+    assert!(database.load(TestType::Car).unwrap().is_none());
+
+    let saved = data(vec![bookings(18, &[(10, 9), (11, 14)]), bookings(20, &[])], 9);
+    database.save(TestType::Car, &saved).unwrap();
+
+    let loaded = Database::open(&path).unwrap().load(TestType::Car).unwrap().unwrap();
+    assert_eq!(contents(&loaded), contents(&saved));
+    assert_eq!(loaded.last_updated, saved.last_updated);
+    assert_eq!(loaded.schema_version, BOOKING_DATA_VERSION);
+
+    // Each test type is its own dataset
+    assert!(database.load(TestType::Rider).unwrap().is_none());
+}
+
+#[test]
+fn saving_replaces_changed_and_removed_locations() {
+    let path = temp_dir("database-partial").join("bookings.sqlite3");
+    let mut database = Database::open(&path).unwrap();
+    database
+        .save(TestType::Car, &data(vec![bookings(18, &[(10, 9)]), bookings(20, &[(12, 8)]), bookings(22, &[])], 9))
+        .unwrap();
+
+    let next = data(vec![bookings(18, &[(10, 9)]), bookings(20, &[(11, 10), (12, 8)])], 10);
+    database.save(TestType::Car, &next).unwrap();
+
+    let loaded = database.load(TestType::Car).unwrap().unwrap();
+    assert_eq!(contents(&loaded), contents(&next));
+    assert_eq!(loaded.last_updated, Some(updated(10)));
+}
+
+#[test]
+fn snapshots_include_writes_not_yet_checkpointed() {
+    let dir = temp_dir("database-snapshot");
+    let mut database = Database::open(&dir.join("bookings.sqlite3")).unwrap();
+    let saved = data(vec![bookings(18, &[(10, 9)])], 9);
+    database.save(TestType::Car, &saved).unwrap();
+
+    // The write is still in the WAL while the connection is open
+    let snapshot = dir.join("snapshot.sqlite3");
+    database.snapshot(&snapshot).unwrap();
+    let loaded = Database::open(&snapshot).unwrap().load(TestType::Car).unwrap().unwrap();
+    assert_eq!(contents(&loaded), contents(&saved));
+}
+
+#[test]
+fn scrape_runs_are_queryable() {
+    let path = temp_dir("database-runs").join("bookings.sqlite3");
+    let database = Database::open(&path).unwrap();
+    for (hour, test_type) in [(1, TestType::Car), (2, TestType::Rider), (3, TestType::Car)] {
+        database
+            .append_run(&ScrapeRun {
+                test_type,
+                started_at: updated(hour),
+                finished_at: updated(hour) + chrono::Duration::minutes(5),
+                requested: 2,
+                scraped: 2,
+                error: None,
+                succeeded: vec![LocationId(18), LocationId(20)],
+                failed: Vec::new(),
+                attempts: 1,
+                backend: "selenium".to_string(),
+                cooling_down: Vec::new(),
+                failure_reasons: Default::default(),
+            })
+            .unwrap();
+    }
+
+    let all = database.load_runs(&RunQuery::default()).unwrap();
+    assert_eq!(all.iter().map(|run| run.started_at).collect::<Vec<_>>(), vec![updated(1), updated(2), updated(3)]);
+
+    let query = RunQuery { test_type: Some(TestType::Car), since: Some(updated(2)), ..RunQuery::default() };
+    let car = database.load_runs(&query).unwrap();
+    assert_eq!(car.iter().map(|run| run.started_at).collect::<Vec<_>>(), vec![updated(3)]);
+}
+
+#[test]
+fn switching_to_sqlite_imports_the_json_file() {
+    let mut settings = settings_with("data_store: sqlite\n");
+    assert_eq!(settings.data_store, DataStore::Sqlite);
+    settings.data_dir = temp_dir("database-import");
+
+    let json = data(vec![bookings(18, &[(10, 9)])], 9);
+    std::fs::write(settings.bookings_file(TestType::Car), serde_json::to_string(&json).unwrap()).unwrap();
+
+    BookingManager::load_data(TestType::Car, &settings).unwrap();
+
+    let imported = Database::open(&settings.database_file()).unwrap().load(TestType::Car).unwrap().unwrap();
+    assert_eq!(contents(&imported), contents(&json));
+}
@@ -0,0 +1,78 @@
+//! Sydney timezone handling of the portal's slot times, around daylight
+//! saving changes.
+
+use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use nsw_closest_display::utils::date::{
+    format_slot_time_long, is_before_date, is_on_or_before_date, is_within_window, parse_slot_time, parse_slot_time_sydney,
+};
+
+fn slot(value: &str) -> NaiveDateTime {
+    parse_slot_time(value).unwrap()
+}
+
+#[test]
+fn offsets_follow_daylight_saving() {
+    let winter = parse_slot_time_sydney("29/05/2025 14:35").unwrap();
+    assert_eq!(winter.to_rfc3339(), "2025-05-29T14:35:00+10:00");
+
+    let summer = parse_slot_time_sydney("15/01/2025 09:00").unwrap();
+    assert_eq!(summer.to_rfc3339(), "2025-01-15T09:00:00+11:00");
+}
+
+#[test]
+fn skipped_hour_moves_forward() {
+    // Clocks went from 02:00 AEST to 03:00 AEDT on 5 October 2025
+    let skipped = parse_slot_time_sydney("05/10/2025 02:30").unwrap();
+    assert_eq!(skipped.to_rfc3339(), "2025-10-05T03:30:00+11:00");
+}
+
+#[test]
+fn repeated_hour_uses_daylight_time() {
+    // Clocks went from 03:00 AEDT back to 02:00 AEST on 6 April 2025
+    let repeated = parse_slot_time_sydney("06/04/2025 02:30").unwrap();
+    assert_eq!(repeated.to_rfc3339(), "2025-04-06T02:30:00+11:00");
+    assert_eq!(parse_slot_time_sydney("06/04/2025 03:30").unwrap().to_rfc3339(), "2025-04-06T03:30:00+10:00");
+}
+
+#[test]
+fn window_is_compared_as_instants() {
+    // 01:00 to 05:00 on the night daylight saving ends is five hours long
+    let start = Utc.with_ymd_and_hms(2025, 4, 5, 14, 0, 0).unwrap();
+    let end = start + chrono::Duration::hours(5);
+
+    assert!(is_within_window(slot("06/04/2025 01:00"), start, end));
+    assert!(is_within_window(slot("06/04/2025 04:59"), start, end));
+    assert!(!is_within_window(slot("06/04/2025 05:00"), start, end));
+    assert!(!is_within_window(slot("06/04/2025 00:59"), start, end));
+}
+
+#[test]
+fn date_comparisons() {
+    let date = NaiveDate::from_ymd_opt(2025, 6, 10).unwrap();
+
+    assert!(is_before_date(slot("09/06/2025 23:59"), date));
+    assert!(!is_before_date(slot("10/06/2025 00:00"), date));
+    assert!(is_on_or_before_date(slot("10/06/2025 23:59"), date));
+    assert!(!is_on_or_before_date(slot("11/06/2025 00:00"), date));
+}
+
+#[test]
+fn long_format_names_the_timezone() {
+    assert_eq!(format_slot_time_long(&slot("29/05/2025 14:35")), "Thu 29 May 2025, 2:35 pm AEST");
+    assert_eq!(format_slot_time_long(&slot("15/01/2025 09:05")), "Wed 15 Jan 2025, 9:05 am AEDT");
+}
+
+#[test]
+fn ages_are_coarse() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 10, 12, 0, 0).unwrap();
+    let ago = |minutes| nsw_closest_display::utils::date::format_age(&(now - chrono::Duration::minutes(minutes)), now);
+
+    assert_eq!(ago(0), "just now");
+    assert_eq!(ago(1), "1 minute ago");
+    assert_eq!(ago(4), "4 minutes ago");
+    assert_eq!(ago(61), "1 hour ago");
+    assert_eq!(ago(3 * 24 * 60), "3 days ago");
+    // Clock skew between browser and server never shows a future time
+    assert_eq!(ago(-5), "just now");
+}
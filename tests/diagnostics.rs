@@ -0,0 +1,75 @@
+//! Bounding the file of captured portal responses and the failure captures.
+
+mod common;
+
+use chrono::{TimeZone, Utc};
+
+use nsw_closest_display::data::diagnostics::{self, CapturedResponse, MAX_BODY_CHARS};
+use nsw_closest_display::data::location::LocationId;
+
+use common::{temp_dir, temp_path};
+
+fn response(n: u32, body: &str) -> CapturedResponse {
+    CapturedResponse {
+        captured_at: Utc.with_ymd_and_hms(2025, 6, 10, 9, 0, n).unwrap(),
+        method: "POST".to_string(),
+        url: format!("https://www.myrta.com/wps/portal/extvp/myrta/timeslots/{}", n),
+        status: 200,
+        body: body.to_string(),
+    }
+}
+
+#[test]
+fn oldest_responses_are_dropped() {
+    let path = temp_path("diagnostics-ring.jsonl");
+
+    diagnostics::append(&path, (0..3).map(|n| response(n, "{}")).collect(), 4).unwrap();
+    diagnostics::append(&path, (3..6).map(|n| response(n, "{}")).collect(), 4).unwrap();
+
+    let urls: Vec<_> = diagnostics::load(&path).unwrap().into_iter().map(|response| response.url).collect();
+    assert_eq!(urls.len(), 4);
+    assert!(urls[0].ends_with("/2") && urls[3].ends_with("/5"), "{:?}", urls);
+
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn long_bodies_are_truncated() {
+    let path = temp_path("diagnostics-truncate.jsonl");
+
+    diagnostics::append(&path, vec![response(0, &"x".repeat(MAX_BODY_CHARS + 10))], 10).unwrap();
+    assert_eq!(diagnostics::load(&path).unwrap()[0].body.len(), MAX_BODY_CHARS);
+
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn browser_entries_parse() {
+    let json = r#"[{"captured_at":"2025-06-10T09:00:00.123Z","method":"GET","url":"/x","status":200,"body":"ok"}]"#;
+    let parsed: Vec<CapturedResponse> = serde_json::from_str(json).unwrap();
+    assert_eq!(parsed[0].status, 200);
+}
+
+#[test]
+fn failure_captures_keep_the_newest_pairs() {
+    let dir = temp_dir("failure-captures");
+
+    for n in 0..3 {
+        let stem = diagnostics::failure_capture_stem(Utc.with_ymd_and_hms(2025, 6, 10, 9, 0, n).unwrap(), LocationId(138));
+        std::fs::write(dir.join(format!("{}.png", stem)), "").unwrap();
+        std::fs::write(dir.join(format!("{}.html", stem)), "").unwrap();
+    }
+    diagnostics::prune_failure_captures(&dir, 2).unwrap();
+
+    let mut names: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        ["20250610-090001-138.html", "20250610-090001-138.png", "20250610-090002-138.html", "20250610-090002-138.png"]
+    );
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,56 @@
+//! Picking the chromedriver download for the installed Chrome.
+
+use serde_json::json;
+
+use nsw_closest_display::selenium::driver_manager::{chromedriver_url, parse_chrome_version};
+use nsw_closest_display::selenium::health::split_command;
+
+fn versions() -> serde_json::Value {
+    let release = |version: &str| {
+        json!({
+            "version": version,
+            "downloads": {
+                "chrome": [{ "platform": "linux64", "url": format!("https://example.test/{}/chrome-linux64.zip", version) }],
+                "chromedriver": [
+                    { "platform": "linux64", "url": format!("https://example.test/{}/chromedriver-linux64.zip", version) },
+                    { "platform": "mac-arm64", "url": format!("https://example.test/{}/chromedriver-mac-arm64.zip", version) },
+                ],
+            },
+        })
+    };
+    json!({ "versions": [release("126.0.6478.55"), release("126.0.6478.61"), release("126.0.6478.126"), release("127.0.6533.72")] })
+}
+
+#[test]
+fn chrome_versions_are_read_from_version_output() {
+    assert_eq!(parse_chrome_version("Google Chrome 126.0.6478.126 \n"), Some("126.0.6478.126".to_string()));
+    assert_eq!(parse_chrome_version("Chromium 127.0.6533.72 built on Debian"), Some("127.0.6533.72".to_string()));
+    assert_eq!(parse_chrome_version("command not found"), None);
+}
+
+#[test]
+fn exact_release_is_preferred() {
+    assert_eq!(
+        chromedriver_url(&versions(), "126.0.6478.61", "mac-arm64").as_deref(),
+        Some("https://example.test/126.0.6478.61/chromedriver-mac-arm64.zip")
+    );
+}
+
+#[test]
+fn unlisted_release_falls_back_to_newest_of_the_build() {
+    assert_eq!(
+        chromedriver_url(&versions(), "126.0.6478.114", "linux64").as_deref(),
+        Some("https://example.test/126.0.6478.126/chromedriver-linux64.zip")
+    );
+    assert_eq!(chromedriver_url(&versions(), "125.0.6422.60", "linux64"), None);
+    assert_eq!(chromedriver_url(&versions(), "126.0.6478.61", "win64"), None);
+}
+
+#[test]
+fn driver_commands_keep_quoted_paths_together() {
+    assert_eq!(
+        split_command(r#""/Users/me/Library/Application Support/nsw/drivers/chromedriver" --port=4444"#),
+        vec!["/Users/me/Library/Application Support/nsw/drivers/chromedriver", "--port=4444"]
+    );
+    assert_eq!(split_command("  chromedriver   --port=9515 "), vec!["chromedriver", "--port=9515"]);
+}
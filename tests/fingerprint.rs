@@ -0,0 +1,29 @@
+//! User agent and window size rotation.
+
+use nsw_closest_display::data::fingerprint::{parse_window_size, Fingerprint, DEFAULT_WINDOW_SIZES};
+
+#[test]
+fn window_sizes_parse() {
+    assert_eq!(parse_window_size("1920x1080"), Some((1920, 1080)));
+    assert_eq!(parse_window_size(" 1366 X 768 "), Some((1366, 768)));
+    assert_eq!(parse_window_size("1920"), None);
+    assert_eq!(parse_window_size("0x768"), None);
+    assert!(DEFAULT_WINDOW_SIZES.iter().all(|size| parse_window_size(size).is_some()));
+}
+
+#[test]
+fn picks_follow_the_index() {
+    let agents = vec!["agent a".to_string(), "agent b".to_string()];
+    let sizes = vec!["800x600".to_string(), "1024x768".to_string()];
+
+    let mut calls = vec![1, 0].into_iter();
+    let fingerprint = Fingerprint::pick(&agents, &sizes, |_| calls.next().unwrap());
+    assert_eq!(fingerprint, Fingerprint { user_agent: "agent b".to_string(), width: 800, height: 600 });
+}
+
+#[test]
+fn empty_pools_use_the_defaults() {
+    let fingerprint = Fingerprint::pick(&[], &[], |_| 0);
+    assert!(fingerprint.user_agent.contains("Chrome/"));
+    assert_eq!((fingerprint.width, fingerprint.height), (1920, 1080));
+}
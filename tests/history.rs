@@ -0,0 +1,135 @@
+//! Aggregation of recorded availability history into the public statistics.
+
+mod common;
+
+use chrono::{NaiveDate, NaiveDateTime, Weekday};
+
+use nsw_closest_display::data::history::{centre_stats, daily_aggregates, HistoryEntry, HistoryStats};
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::shared_booking::{LocationBookings, TestType, TimeSlot};
+
+use common::at;
+
+const ARMIDALE: LocationId = LocationId(18);
+
+fn entry(recorded_at: NaiveDateTime, slots: &[NaiveDateTime]) -> HistoryEntry {
+    let bookings = LocationBookings {
+        location: ARMIDALE,
+        test_type: TestType::Car,
+        slots: slots
+            .iter()
+            .map(|&start_time| TimeSlot { availability: true, slot_number: None, start_time })
+            .collect(),
+        next_available_date: None,
+    };
+    HistoryEntry::from_bookings(&bookings, recorded_at)
+}
+
+#[test]
+fn median_lead_time_in_days() {
+    // 2025-06-02 is a Monday
+    let entries = [
+        entry(at(2, 9), &[at(4, 9)]),
+        entry(at(3, 9), &[at(13, 9)]),
+        entry(at(4, 9), &[at(10, 9)]),
+    ];
+
+    let stats = centre_stats(&entries, TestType::Car);
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0].observations, 3);
+    assert_eq!(stats[0].median_lead_days, Some(6.0));
+}
+
+#[test]
+fn slots_rolling_into_the_week_are_not_cancellations() {
+    let entries = [
+        entry(at(2, 9), &[at(20, 9)]),
+        // Freed up inside the previous week: a cancellation
+        entry(at(3, 9), &[at(5, 9), at(20, 9)]),
+        // 10 June 10:00 was outside the previous scrape's week
+        entry(at(9, 9), &[at(10, 10), at(20, 9)]),
+    ];
+
+    let stats = centre_stats(&entries, TestType::Car);
+    let per_week = stats[0].same_week_cancellations_per_week.unwrap();
+    assert!((per_week - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn busiest_weekdays_have_fewest_free_slots() {
+    let entries = [entry(
+        at(2, 9),
+        &[at(3, 9), at(3, 10), at(3, 11), at(4, 9), at(4, 10), at(5, 9)],
+    )];
+
+    // Days without a single free slot are the busiest of all
+    let stats = centre_stats(&entries, TestType::Car);
+    assert_eq!(
+        stats[0].busiest_weekdays,
+        vec![
+            Weekday::Mon,
+            Weekday::Fri,
+            Weekday::Sat,
+            Weekday::Sun,
+            Weekday::Thu,
+            Weekday::Wed,
+            Weekday::Tue
+        ]
+    );
+}
+
+#[test]
+fn other_test_types_are_ignored() {
+    let entries = [entry(at(2, 9), &[at(4, 9)])];
+    assert!(centre_stats(&entries, TestType::Rider).is_empty());
+}
+
+#[test]
+fn single_scrape_has_no_cancellation_rate() {
+    let entries = [entry(at(2, 9), &[at(4, 9)]), entry(at(2, 9), &[at(4, 9)])];
+    assert_eq!(centre_stats(&entries, TestType::Car)[0].same_week_cancellations_per_week, None);
+}
+
+#[test]
+fn weekday_breakdown_per_centre_and_overall() {
+    let entries = [
+        entry(at(2, 9), &[at(20, 9)]),
+        entry(at(3, 9), &[at(5, 9), at(6, 9), at(20, 9)]),
+        entry(at(9, 9), &[at(20, 9)]),
+    ];
+
+    let stats = HistoryStats::from_entries(&entries, TestType::Car);
+    let thursday = &stats.weekdays[Weekday::Thu.num_days_from_monday() as usize];
+    assert_eq!(thursday.weekday, Weekday::Thu);
+    assert_eq!(thursday.median_lead_days, Some(2.0));
+    assert!((thursday.same_week_cancellations_per_week.unwrap() - 1.0).abs() < 1e-9);
+
+    // 20 June is a Friday, seen by every scrape
+    let friday = &stats.weekdays_by_location[&ARMIDALE][Weekday::Fri.num_days_from_monday() as usize];
+    assert!((friday.free_slots_per_observation - 4.0 / 3.0).abs() < 1e-9);
+}
+
+#[test]
+fn daily_aggregates_per_day_and_centre() {
+    let entries = [
+        entry(at(2, 9), &[at(4, 9), at(5, 9)]),
+        entry(at(2, 15), &[at(6, 15)]),
+        entry(at(3, 9), &[]),
+    ];
+
+    let aggregates = daily_aggregates(&entries);
+    assert_eq!(aggregates.len(), 2);
+
+    let monday = &aggregates[0];
+    assert_eq!(monday.date, NaiveDate::from_ymd_opt(2025, 6, 2).unwrap());
+    assert_eq!(monday.location, ARMIDALE);
+    assert_eq!(monday.observations, 2);
+    assert_eq!(monday.mean_lead_days, Some(3.0));
+    assert_eq!((monday.min_lead_days, monday.max_lead_days), (Some(2.0), Some(4.0)));
+    assert_eq!(monday.mean_available_slots, 1.5);
+
+    let tuesday = &aggregates[1];
+    assert_eq!(tuesday.observations, 1);
+    assert_eq!(tuesday.mean_lead_days, None);
+    assert_eq!(tuesday.mean_available_slots, 0.0);
+}
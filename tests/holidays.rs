@@ -0,0 +1,67 @@
+//! The NSW public-holiday calendar and business-day helpers.
+
+use chrono::NaiveDate;
+
+use nsw_closest_display::utils::holidays::{
+    easter_sunday, is_business_day, is_near_public_holiday, next_business_day, public_holiday, public_holidays,
+};
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+#[test]
+fn easter_dates() {
+    assert_eq!(easter_sunday(2024), date(2024, 3, 31));
+    assert_eq!(easter_sunday(2025), date(2025, 4, 20));
+    assert_eq!(easter_sunday(2026), date(2026, 4, 5));
+}
+
+#[test]
+fn calendar_for_2025() {
+    let dates: Vec<NaiveDate> = public_holidays(2025).into_iter().map(|holiday| holiday.date).collect();
+    assert_eq!(
+        dates,
+        vec![
+            date(2025, 1, 1),
+            // Australia Day was a Sunday
+            date(2025, 1, 27),
+            date(2025, 4, 18),
+            date(2025, 4, 19),
+            date(2025, 4, 20),
+            date(2025, 4, 21),
+            date(2025, 4, 25),
+            date(2025, 6, 9),
+            date(2025, 10, 6),
+            date(2025, 12, 25),
+            date(2025, 12, 26),
+        ]
+    );
+}
+
+#[test]
+fn weekend_christmas_gets_additional_days() {
+    // Christmas 2021 was a Saturday
+    assert_eq!(public_holiday(date(2021, 12, 27)).unwrap().name, "Christmas Day (additional day)");
+    assert_eq!(public_holiday(date(2021, 12, 28)).unwrap().name, "Boxing Day (additional day)");
+    // Christmas 2022 was a Sunday, Boxing Day already the Monday
+    assert_eq!(public_holiday(date(2022, 12, 27)).unwrap().name, "Christmas Day (additional day)");
+    assert!(public_holiday(date(2022, 12, 28)).is_none());
+}
+
+#[test]
+fn business_days_skip_weekends_and_holidays() {
+    // Thursday before Good Friday 2025
+    assert!(is_business_day(date(2025, 4, 17)));
+    assert!(!is_business_day(date(2025, 4, 18)));
+    assert_eq!(next_business_day(date(2025, 4, 17)), date(2025, 4, 22));
+}
+
+#[test]
+fn holiday_neighbours() {
+    // Labour Day 2025 was Monday 6 October
+    assert!(is_near_public_holiday(date(2025, 10, 5)));
+    assert!(is_near_public_holiday(date(2025, 10, 6)));
+    assert!(is_near_public_holiday(date(2025, 10, 7)));
+    assert!(!is_near_public_holiday(date(2025, 10, 8)));
+}
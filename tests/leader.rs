@@ -0,0 +1,60 @@
+//! Taking over and renewing the leader lease.
+
+mod common;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+use nsw_closest_display::data::leader::{try_acquire, Lease, LEASE_KEY};
+use nsw_closest_display::data::storage::{DirectoryStorage, Storage};
+
+use common::temp_dir;
+
+fn at(minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 6, 10, 9, minute, 0).unwrap()
+}
+
+fn storage(name: &str) -> DirectoryStorage {
+    DirectoryStorage { dir: temp_dir(name) }
+}
+
+#[test]
+fn lease_can_be_renewed_by_holder_or_taken_once_expired() {
+    let lease = Lease { holder: "a".to_string(), expires_at: at(1) };
+
+    assert!(lease.can_acquire("a", at(0)));
+    assert!(!lease.can_acquire("b", at(0)));
+    assert!(lease.can_acquire("b", at(1)));
+    assert!(lease.can_acquire("b", at(2)));
+}
+
+#[tokio::test]
+async fn second_replica_takes_over_when_leader_stops_renewing() {
+    let storage = storage("leader-takeover");
+    let lease = Duration::minutes(1);
+
+    assert!(try_acquire(&storage, "a", lease, at(0)).await.unwrap());
+    assert!(!try_acquire(&storage, "b", lease, at(0)).await.unwrap());
+
+    // Renewal pushes the expiry out
+    assert!(try_acquire(&storage, "a", lease, at(0) + Duration::seconds(30)).await.unwrap());
+    assert!(!try_acquire(&storage, "b", lease, at(1)).await.unwrap());
+
+    // "a" died, its lease ran out
+    assert!(try_acquire(&storage, "b", lease, at(2)).await.unwrap());
+    assert!(!try_acquire(&storage, "a", lease, at(2)).await.unwrap());
+}
+
+#[tokio::test]
+async fn racing_writes_from_the_same_version_only_let_one_through() {
+    let storage = storage("leader-race");
+
+    // Both replicas saw no lease
+    assert!(storage.put_if(LEASE_KEY, b"a".to_vec(), None).await.unwrap());
+    assert!(!storage.put_if(LEASE_KEY, b"b".to_vec(), None).await.unwrap());
+
+    // Both replicas read the same expired lease
+    let (_, version) = storage.get_versioned(LEASE_KEY).await.unwrap().unwrap();
+    assert!(storage.put_if(LEASE_KEY, b"b".to_vec(), Some(&version)).await.unwrap());
+    assert!(!storage.put_if(LEASE_KEY, b"a".to_vec(), Some(&version)).await.unwrap());
+    assert_eq!(storage.get(LEASE_KEY).await.unwrap().unwrap(), b"b".to_vec());
+}
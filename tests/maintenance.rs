@@ -0,0 +1,52 @@
+//! Recognising the RTA portal's maintenance page and when it ends.
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+
+use nsw_closest_display::data::maintenance::{parse_end_time, MaintenanceWindow};
+
+fn at(hour: u32, minute: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2025, 6, 10).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+}
+
+fn time(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+#[test]
+fn parses_advertised_end_times() {
+    assert_eq!(parse_end_time("Unavailable until 6am"), Some(time(6, 0)));
+    assert_eq!(parse_end_time("unavailable UNTIL 5:30 a.m. Sunday"), Some(time(5, 30)));
+    assert_eq!(parse_end_time("back until 12 pm"), Some(time(12, 0)));
+    assert_eq!(parse_end_time("until 12am"), Some(time(0, 0)));
+    assert_eq!(parse_end_time("until 06:15"), Some(time(6, 15)));
+    assert_eq!(parse_end_time("closed until 2025"), None);
+    assert_eq!(parse_end_time("until further notice, back at 6am"), None);
+}
+
+#[test]
+fn window_ends_at_next_advertised_time() {
+    let page = "Service NSW\nmyRTA is undergoing scheduled maintenance until 6:00am.\nSorry for any inconvenience.";
+
+    let window = MaintenanceWindow::detect(page, at(23, 10), Duration::minutes(60)).unwrap();
+    assert_eq!(window.message, "myRTA is undergoing scheduled maintenance until 6:00am.");
+    assert_eq!(window.until, at(6, 0) + Duration::days(1));
+    assert!(window.is_active(at(23, 30)));
+
+    let window = MaintenanceWindow::detect(page, at(2, 0), Duration::minutes(60)).unwrap();
+    assert_eq!(window.until, at(6, 0));
+    assert!(!window.is_active(at(6, 0)));
+}
+
+#[test]
+fn window_falls_back_to_backoff_without_end_time() {
+    let page = "This service is temporarily unavailable. Please try again later.";
+
+    let window = MaintenanceWindow::detect(page, at(21, 0), Duration::minutes(45)).unwrap();
+    assert_eq!(window.until, at(21, 45));
+}
+
+#[test]
+fn ordinary_pages_are_not_maintenance() {
+    let page = "Book a test\nBooking number\nFamily name\nNext";
+    assert_eq!(MaintenanceWindow::detect(page, at(9, 0), Duration::minutes(60)), None);
+}
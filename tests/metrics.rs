@@ -0,0 +1,61 @@
+//! Per-centre scrape metrics.
+
+use chrono::{TimeZone, Utc};
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::metrics::ScrapeMetrics;
+use nsw_closest_display::data::shared_booking::LocationAttempt;
+
+const FAST: LocationId = LocationId(18);
+const SLOW: LocationId = LocationId(30);
+const FLAKY: LocationId = LocationId(42);
+
+fn attempt(location: LocationId, duration_ms: u64, slots: Option<usize>) -> LocationAttempt {
+    LocationAttempt { location, duration_ms, slots }
+}
+
+#[test]
+fn records_attempts_per_centre() {
+    let at = Utc.with_ymd_and_hms(2025, 3, 1, 9, 0, 0).unwrap();
+    let mut metrics = ScrapeMetrics::default();
+
+    metrics.record(&attempt(FLAKY, 4_000, None), Some("timed out"), at);
+    metrics.record(&attempt(FLAKY, 2_000, Some(6)), None, at);
+    metrics.record(&attempt(FLAKY, 3_000, Some(2)), None, at);
+
+    let flaky = &metrics.locations[&FLAKY];
+    assert_eq!(flaky.attempts, 3);
+    assert_eq!(flaky.failures(), 1);
+    assert!((flaky.success_rate() - 2.0 / 3.0).abs() < 1e-9);
+    assert_eq!(flaky.average_duration_ms(), 3_000);
+    assert_eq!(flaky.slowest_ms, 4_000);
+    assert_eq!(flaky.average_slots(), 4.0);
+    assert_eq!(flaky.last_slots, Some(2));
+    assert_eq!(flaky.last_error.as_deref(), Some("timed out"));
+    assert_eq!(flaky.last_attempt, Some(at));
+}
+
+#[test]
+fn ranks_worst_centres_first() {
+    let at = Utc::now();
+    let mut metrics = ScrapeMetrics::default();
+
+    metrics.record(&attempt(FAST, 1_000, Some(3)), None, at);
+    metrics.record(&attempt(SLOW, 9_000, Some(1)), None, at);
+    metrics.record(&attempt(FLAKY, 2_000, None), Some("no dropdown"), at);
+
+    let order: Vec<_> = metrics.ranked().into_iter().map(|(location, _)| location).collect();
+    assert_eq!(order, vec![FLAKY, SLOW, FAST]);
+}
+
+#[test]
+fn empty_metrics_do_not_divide_by_zero() {
+    let metrics = ScrapeMetrics::default();
+    assert!(metrics.ranked().is_empty());
+
+    let mut metrics = ScrapeMetrics::default();
+    metrics.record(&attempt(FAST, 500, None), None, Utc::now());
+    let fast = &metrics.locations[&FAST];
+    assert_eq!(fast.success_rate(), 0.0);
+    assert_eq!(fast.average_slots(), 0.0);
+    assert_eq!(fast.last_error, None);
+}
@@ -0,0 +1,46 @@
+//! Upgrading booking data files written before the current format.
+
+use serde_json::json;
+
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::migration::{migrate, schema_version};
+use nsw_closest_display::data::shared_booking::{TestType, BOOKING_DATA_VERSION};
+
+#[test]
+fn unversioned_file_is_version_one() {
+    let legacy = json!({
+        "results": [{
+            "location": 18,
+            "test_type": "driving_test",
+            "slots": [{ "availability": true, "slot_number": null, "startTime": "29/05/2025 14:35" }]
+        }],
+        "last_updated": "2025-05-20T10:00:00+00:00"
+    });
+    assert_eq!(schema_version(&legacy), Ok(1));
+
+    let (data, from) = migrate(legacy).unwrap();
+    assert_eq!(from, 1);
+    assert_eq!(data.schema_version, BOOKING_DATA_VERSION);
+    assert_eq!(data.results[0].location, LocationId(18));
+    assert_eq!(data.results[0].test_type, TestType::Car);
+    assert_eq!(data.results[0].slots.len(), 1);
+}
+
+#[test]
+fn current_file_is_unchanged() {
+    let current = json!({ "results": [], "last_updated": null, "schema_version": BOOKING_DATA_VERSION });
+    let (data, from) = migrate(current).unwrap();
+    assert_eq!(from, BOOKING_DATA_VERSION);
+    assert!(data.results.is_empty());
+}
+
+#[test]
+fn newer_file_is_refused() {
+    let newer = json!({ "results": [], "last_updated": null, "schema_version": BOOKING_DATA_VERSION + 1 });
+    assert!(migrate(newer).unwrap_err().contains("newer"));
+}
+
+#[test]
+fn invalid_version_is_an_error() {
+    assert!(schema_version(&json!({ "schema_version": "two" })).is_err());
+}
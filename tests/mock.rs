@@ -0,0 +1,32 @@
+//! Replaying recorded data in mock scrape mode.
+
+use std::path::Path;
+
+use nsw_closest_display::data::booking::BookingManager;
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::mock::{fixture_file, replay};
+use nsw_closest_display::data::shared_booking::TestType;
+
+fn fixtures() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"))
+}
+
+#[test]
+fn test_types_without_a_fixture_use_bookings_json() {
+    let path = fixture_file(fixtures(), TestType::Rider).unwrap();
+    assert!(path.ends_with("bookings.json"));
+    assert_eq!(fixture_file(&fixtures().join("missing"), TestType::Car), None);
+}
+
+#[test]
+fn replays_requested_locations_only() {
+    let path = fixture_file(fixtures(), TestType::Car).unwrap();
+    let (recorded, _) = BookingManager::read_data_file(&path).unwrap();
+
+    let results = replay(&recorded.results, &[LocationId(18), LocationId(999)], TestType::Rider);
+    let bookings = &results.bookings[&LocationId(18)];
+    assert_eq!(bookings.slots.len(), 2);
+    assert_eq!(bookings.test_type, TestType::Rider);
+    assert!(!results.bookings.contains_key(&LocationId(37)));
+    assert!(results.errors[&LocationId(999)].contains("999"));
+}
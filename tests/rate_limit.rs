@@ -0,0 +1,30 @@
+//! The shared token bucket for portal requests.
+
+use std::time::{Duration, Instant};
+
+use nsw_closest_display::data::rate_limit::TokenBucket;
+
+#[test]
+fn burst_then_steady_rate() {
+    let start = Instant::now();
+    let mut bucket = TokenBucket::new(30, 3, start);
+
+    for _ in 0..3 {
+        assert!(bucket.try_take(start).is_ok());
+    }
+    // 30 a minute is one every two seconds
+    assert_eq!(bucket.try_take(start), Err(Duration::from_secs(2)));
+    assert!(bucket.try_take(start + Duration::from_secs(1)).is_err());
+    assert!(bucket.try_take(start + Duration::from_secs(2)).is_ok());
+}
+
+#[test]
+fn idle_time_only_refills_to_the_burst() {
+    let start = Instant::now();
+    let mut bucket = TokenBucket::new(60, 2, start);
+    let later = start + Duration::from_secs(600);
+
+    assert!(bucket.try_take(later).is_ok());
+    assert!(bucket.try_take(later).is_ok());
+    assert!(bucket.try_take(later).is_err());
+}
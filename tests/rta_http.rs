@@ -0,0 +1,85 @@
+//! Form, link and `timeslots` extraction for the HTTP scraper, against
+//! trimmed-down portal markup.
+
+use reqwest::Url;
+
+use nsw_closest_display::data::rta_http::{extract_timeslots, find_form, find_link, page_text};
+
+const LOGIN_PAGE: &str = r#"
+<html><body>
+<form id="search" action="/search"><input name="q" value=""></form>
+<form id="loginForm" method="POST" action="/wps/portal/extvp/myrta/login/!ut/p/z1/abc?x=1&amp;y=2">
+    <div id="widget_bookingId"><input type="text" id="bookingId" name="ns_Z7_bookingId" value=""></div>
+    <input type="text" name="ns_Z7_lastName" value=''>
+    <input type="hidden" name="ns_Z7_token" value="s3cret">
+    <input type="checkbox" name="remember" value="on">
+    <input type="radio" name="mode" value="manage" checked>
+    <input type="radio" name="mode" value="book">
+    <select name="ns_Z7_rms_batLocationSelect2"><option value="1">A</option><option value="2" selected>B</option></select>
+    <button id="nextButton" type="submit" name="next">Next</button>
+</form>
+<a href="/manage" class="link">Manage booking</a>
+<a id="anotherLocationLink" href="another?loc=1">Choose another location</a>
+</body></html>
+"#;
+
+fn base() -> Url {
+    Url::parse("https://www.myrta.com/wps/portal/extvp/myrta/login/").unwrap()
+}
+
+#[test]
+fn finds_the_form_holding_a_control() {
+    let mut form = find_form(LOGIN_PAGE, &base(), "bookingId").unwrap();
+
+    assert_eq!(form.action, "https://www.myrta.com/wps/portal/extvp/myrta/login/!ut/p/z1/abc?x=1&y=2");
+    assert_eq!(form.method, "post");
+    assert_eq!(
+        form.fields,
+        vec![
+            ("ns_Z7_bookingId".to_string(), String::new()),
+            ("ns_Z7_lastName".to_string(), String::new()),
+            ("ns_Z7_token".to_string(), "s3cret".to_string()),
+            ("mode".to_string(), "manage".to_string()),
+            ("ns_Z7_rms_batLocationSelect2".to_string(), "2".to_string()),
+        ]
+    );
+
+    assert!(form.set("lastName", "Citizen"));
+    assert!(!form.set("missing", "value"));
+    assert_eq!(form.fields[1].1, "Citizen");
+
+    assert!(find_form(LOGIN_PAGE, &base(), "nothing").is_none());
+}
+
+#[test]
+fn finds_links_by_text_and_id() {
+    assert_eq!(
+        find_link(LOGIN_PAGE, &base(), None, Some("Manage booking")).unwrap().as_str(),
+        "https://www.myrta.com/manage"
+    );
+    assert_eq!(
+        find_link(LOGIN_PAGE, &base(), Some("anotherLocationLink"), None).unwrap().as_str(),
+        "https://www.myrta.com/wps/portal/extvp/myrta/login/another?loc=1"
+    );
+    assert!(find_link(LOGIN_PAGE, &base(), None, Some("Book test")).is_none());
+}
+
+#[test]
+fn extracts_the_timeslots_object() {
+    let page = r#"<script>
+        var timeslotsLoaded = true;
+        var timeslots = {"ajaxresult":{"slots":{"nextAvailableDate":"29/05/2025 14:35","listTimeSlot":[]}}};
+        renderSlots(timeslots);
+    </script>"#;
+
+    let timeslots = extract_timeslots(page).unwrap();
+    assert_eq!(timeslots["ajaxresult"]["slots"]["nextAvailableDate"], "29/05/2025 14:35");
+    assert!(extract_timeslots("<p>No slots</p>").is_none());
+}
+
+#[test]
+fn page_text_drops_markup() {
+    assert_eq!(page_text("<p>Down for <b>maintenance</b></p>").split_whitespace().collect::<Vec<_>>(), vec![
+        "Down", "for", "maintenance"
+    ]);
+}
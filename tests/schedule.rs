@@ -0,0 +1,49 @@
+//! The daily window background scrapes run in.
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+use nsw_closest_display::data::schedule::ScrapeWindow;
+
+fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2025, 6, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+}
+
+fn time(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+#[test]
+fn daytime_window() {
+    let window = ScrapeWindow::parse("06:00", "23:00").unwrap();
+    assert!(window.contains(time(6, 0)));
+    assert!(window.contains(time(22, 59)));
+    assert!(!window.contains(time(23, 0)));
+    assert!(!window.contains(time(3, 0)));
+
+    assert_eq!(window.next_open(at(10, 12, 0)), at(10, 12, 0));
+    assert_eq!(window.next_open(at(10, 3, 0)), at(10, 6, 0));
+    assert_eq!(window.next_open(at(10, 23, 30)), at(11, 6, 0));
+}
+
+#[test]
+fn window_across_midnight() {
+    let window = ScrapeWindow::parse("22:00", "02:00").unwrap();
+    assert!(window.contains(time(23, 0)));
+    assert!(window.contains(time(1, 59)));
+    assert!(!window.contains(time(2, 0)));
+    assert_eq!(window.next_open(at(10, 12, 0)), at(10, 22, 0));
+}
+
+#[test]
+fn equal_bounds_allow_the_whole_day() {
+    let window = ScrapeWindow::parse("06:00", "06:00").unwrap();
+    assert!(window.contains(time(5, 59)));
+    assert_eq!(window.next_open(at(10, 5, 59)), at(10, 5, 59));
+}
+
+#[test]
+fn bad_times_name_the_setting() {
+    let err = ScrapeWindow::parse("6am", "23:00").unwrap_err();
+    assert!(err.contains("scrape_window_start"), "{}", err);
+    assert!(ScrapeWindow::parse("06:00", "24:30").is_err());
+}
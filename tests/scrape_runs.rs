@@ -0,0 +1,114 @@
+//! Appending to, querying and pruning the scrape run log.
+
+mod common;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::scrape_runs::{self, RunQuery};
+use nsw_closest_display::data::shared_booking::{LocationBookings, ScrapeResults, ScrapeRun, TestType};
+
+use common::temp_path;
+
+fn at(hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 6, 10, hour, 0, 0).unwrap()
+}
+
+fn run(test_type: TestType, hour: u32, failed: &[u32]) -> ScrapeRun {
+    ScrapeRun {
+        test_type,
+        started_at: at(hour),
+        finished_at: at(hour) + chrono::Duration::minutes(5),
+        requested: 2 + failed.len(),
+        scraped: 2,
+        error: None,
+        succeeded: vec![LocationId(18), LocationId(20)],
+        failed: failed.iter().map(|&id| LocationId(id)).collect(),
+        attempts: 1 + failed.len() as u64,
+        backend: "selenium".to_string(),
+        cooling_down: Vec::new(),
+        failure_reasons: failed.iter().map(|&id| (LocationId(id), "Element not found".to_string())).collect(),
+    }
+}
+
+#[test]
+fn query_filters_and_limits() {
+    let runs = vec![
+        run(TestType::Car, 1, &[]),
+        run(TestType::Rider, 2, &[30]),
+        run(TestType::Car, 3, &[30, 31]),
+        run(TestType::Car, 4, &[]),
+    ];
+
+    let car = RunQuery { test_type: Some(TestType::Car), ..RunQuery::default() };
+    assert_eq!(car.apply(runs.clone()).len(), 3);
+
+    let failed = RunQuery { failed_only: true, ..RunQuery::default() };
+    let hours: Vec<_> = failed.apply(runs.clone()).iter().map(|run| run.started_at).collect();
+    assert_eq!(hours, vec![at(2), at(3)]);
+
+    let newest = RunQuery { since: Some(at(2)), limit: Some(2), ..RunQuery::default() };
+    let hours: Vec<_> = newest.apply(runs).iter().map(|run| run.started_at).collect();
+    assert_eq!(hours, vec![at(3), at(4)]);
+}
+
+#[test]
+fn runs_round_trip_through_the_log() {
+    let path = temp_path("scrape-runs-round-trip.jsonl");
+    let first = run(TestType::Car, 1, &[30]);
+    let second = run(TestType::Car, 2, &[]);
+    scrape_runs::append(&path, &first).unwrap();
+    scrape_runs::append(&path, &second).unwrap();
+
+    let loaded = scrape_runs::load(&path, &RunQuery::default()).unwrap();
+    assert_eq!(loaded, vec![first.clone(), second.clone()]);
+    assert_eq!(loaded[0].retries(), 1);
+
+    scrape_runs::prune(&path, at(2)).unwrap();
+    assert_eq!(scrape_runs::load(&path, &RunQuery::default()).unwrap(), vec![second]);
+
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn runs_logged_before_the_metadata_still_load() {
+    let path = temp_path("scrape-runs-legacy.jsonl");
+    std::fs::write(
+        &path,
+        r#"{"test_type":"car","started_at":"2025-06-10T01:00:00Z","finished_at":"2025-06-10T01:05:00Z","requested":3,"scraped":3,"error":null}
+not json
+"#,
+    )
+    .unwrap();
+
+    let loaded = scrape_runs::load(&path, &RunQuery::default()).unwrap();
+    assert_eq!(loaded.len(), 1);
+    assert!(loaded[0].failed.is_empty());
+    assert_eq!(loaded[0].retries(), 0);
+
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn missing_log_is_empty() {
+    let path = temp_path("scrape-runs-missing.jsonl");
+    assert!(scrape_runs::load(&path, &RunQuery::default()).unwrap().is_empty());
+}
+
+#[test]
+fn a_later_success_clears_a_centres_error() {
+    let bookings = |id| LocationBookings { location: LocationId(id), test_type: TestType::Car, slots: Vec::new(), next_available_date: None };
+
+    let mut first = ScrapeResults::default();
+    first.succeeded(bookings(18));
+    first.failed(LocationId(30), "Element not found");
+    first.failed(LocationId(31), "Timed out");
+
+    let mut second = ScrapeResults::default();
+    second.succeeded(bookings(30));
+    second.failed(LocationId(18), "Timed out");
+    first.merge(second);
+
+    assert_eq!(first.bookings.len(), 2);
+    assert_eq!(first.errors.keys().copied().collect::<Vec<_>>(), vec![LocationId(31)]);
+}
@@ -0,0 +1,23 @@
+//! Reading the WebDriver's `/status` response.
+
+use serde_json::json;
+
+use nsw_closest_display::selenium::health::parse_status;
+
+#[test]
+fn ready_driver_with_message() {
+    let body = json!({ "value": { "ready": true, "message": "ChromeDriver ready for new sessions." } });
+    assert_eq!(parse_status(&body), (true, Some("ChromeDriver ready for new sessions.".to_string())));
+}
+
+#[test]
+fn busy_grid_is_not_ready() {
+    let body = json!({ "value": { "ready": false, "message": "Selenium Grid not ready.", "nodes": [] } });
+    assert_eq!(parse_status(&body), (false, Some("Selenium Grid not ready.".to_string())));
+}
+
+#[test]
+fn unexpected_bodies_are_not_ready() {
+    assert_eq!(parse_status(&json!({ "status": 0 })), (false, None));
+    assert_eq!(parse_status(&json!({ "value": { "ready": "yes" } })), (false, None));
+}
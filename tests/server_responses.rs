@@ -0,0 +1,325 @@
+//! Snapshot tests pinning the shapes the WASM client and external API
+//! consumers rely on. Run `cargo insta review` after an intentional change.
+
+mod common;
+
+use std::path::Path;
+use std::sync::Once;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use http_body_util::BodyExt;
+use insta::assert_json_snapshot;
+use tower::ServiceExt;
+
+use nsw_closest_display::{announcement, api, embed};
+use nsw_closest_display::data::booking::BookingManager;
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::shared_booking::{TestType, UnavailableSlots};
+use nsw_closest_display::notifications::NotificationLevel;
+use nsw_closest_display::pages::home::{get_location_bookings, get_location_details};
+
+use common::{settings, temp_dir};
+
+static LOAD_FIXTURE: Once = Once::new();
+
+fn load_fixture() {
+    LOAD_FIXTURE.call_once(|| {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bookings.json");
+        BookingManager::init_from_file(TestType::Car, &path).expect("fixture should load");
+    });
+}
+
+#[tokio::test]
+async fn location_bookings_response() {
+    load_fixture();
+
+    let response = get_location_bookings(TestType::Car, String::new()).await.unwrap();
+    assert_json_snapshot!(response, { ".etag" => "[etag]" });
+}
+
+#[tokio::test]
+async fn location_bookings_not_modified() {
+    load_fixture();
+
+    let etag = get_location_bookings(TestType::Car, String::new()).await.unwrap().unwrap().etag;
+    let response = get_location_bookings(TestType::Car, etag).await.unwrap();
+    assert!(response.is_none());
+}
+
+#[tokio::test]
+async fn location_details_response() {
+    load_fixture();
+
+    let response = get_location_details(TestType::Car, LocationId(18), String::new()).await.unwrap();
+    assert_json_snapshot!(response, { ".etag" => "[etag]" });
+}
+
+#[tokio::test]
+async fn location_details_not_modified() {
+    load_fixture();
+
+    let etag = BookingManager::location_etag(TestType::Car, LocationId(18)).unwrap();
+    assert_eq!(etag, BookingManager::get_location_data(TestType::Car, LocationId(18)).unwrap().1);
+    assert!(get_location_details(TestType::Car, LocationId(18), etag).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn rest_location_endpoint_honours_etag() {
+    load_fixture();
+
+    let response = api::router::<()>()
+        .oneshot(Request::get("/api/data/car/locations/18").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = response.headers()["etag"].clone();
+
+    let response = api::router::<()>()
+        .oneshot(Request::get("/api/data/car/locations/18").header("if-none-match", etag).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    let response = api::router::<()>()
+        .oneshot(Request::get("/api/data/car/locations/999999").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn sign_in_link_only_opens_a_page() {
+    let response = api::router::<()>()
+        .oneshot(Request::get("/login/0123456789abcdef").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("set-cookie").is_none());
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let html = String::from_utf8(body.to_vec()).unwrap();
+    assert!(html.contains(r#"<form method="post">"#), "{}", html);
+}
+
+#[tokio::test]
+async fn location_details_unknown_location() {
+    load_fixture();
+
+    assert!(get_location_details(TestType::Car, LocationId(999_999), String::new()).await.is_err());
+}
+
+#[tokio::test]
+async fn datasets_are_separate_per_test_type() {
+    load_fixture();
+
+    let response = get_location_bookings(TestType::Dkt, String::new()).await.unwrap().unwrap();
+    assert!(response.bookings.is_empty());
+    assert!(get_location_details(TestType::Dkt, LocationId(18), String::new()).await.is_err());
+}
+
+#[tokio::test]
+async fn rest_data_endpoint() {
+    load_fixture();
+
+    let response = api::router::<()>()
+        .oneshot(Request::get("/api/data").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_json_snapshot!(json);
+}
+
+#[tokio::test]
+async fn rest_data_reports_health_headers() {
+    load_fixture();
+
+    let response = api::router::<()>()
+        .oneshot(Request::get("/api/data/car").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-data-degraded"], "false");
+    assert_eq!(response.headers()["x-data-stale"], "false");
+}
+
+#[tokio::test]
+async fn embed_card_endpoint() {
+    load_fixture();
+
+    let response = embed::router::<()>()
+        .oneshot(Request::get("/api/embed?location=18").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["name"], "Armidale");
+    assert_eq!(json["earliest_slot"], "28/05/2025 09:05");
+    assert_eq!(json["last_updated"], "2025-05-20T10:00:00Z");
+}
+
+#[tokio::test]
+async fn embed_unknown_location() {
+    load_fixture();
+
+    let response = embed::router::<()>()
+        .oneshot(Request::get("/embed?location=999999").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn rollback_restores_previous_dataset() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bookings.json");
+
+    assert!(BookingManager::rollback(TestType::Hpt).is_err());
+    BookingManager::init_from_file(TestType::Hpt, &path).unwrap();
+    assert!(BookingManager::rollback(TestType::Hpt).is_err(), "nothing to roll back to after the first load");
+
+    BookingManager::update_data(TestType::Hpt, Vec::new(), UnavailableSlots::Drop);
+    assert!(BookingManager::get_data(TestType::Hpt).0.results.is_empty());
+
+    BookingManager::rollback(TestType::Hpt).unwrap();
+    assert_eq!(BookingManager::get_data(TestType::Hpt).0.results.len(), 2);
+}
+
+#[tokio::test]
+async fn legacy_data_file_is_migrated_with_backup() {
+    let dir = temp_dir("migration-test");
+    let path = dir.join("bookings-heavy_vehicle.json");
+    std::fs::write(
+        &path,
+        r#"{"results":[{"location":18,"slots":[],"next_available_date":null}],"last_updated":"2025-05-20T10:00:00+00:00"}"#,
+    )
+    .unwrap();
+
+    BookingManager::init_from_file(TestType::HeavyVehicle, &path).unwrap();
+    assert_eq!(BookingManager::get_data(TestType::HeavyVehicle).0.results.len(), 1);
+
+    let backup = dir.join("bookings-heavy_vehicle.json.v1.bak");
+    assert!(backup.exists());
+    let rewritten: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(rewritten["schema_version"], 2);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn status_includes_announcement() {
+    let mut settings = settings("announcement-test");
+
+    announcement::set(&settings, "  Portal maintenance tonight  ", NotificationLevel::Warning).unwrap();
+    let response = api::router::<()>()
+        .oneshot(Request::get("/api/status").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["announcement"]["message"], "Portal maintenance tonight");
+    assert_eq!(json["announcement"]["level"], "warning");
+
+    // A cleared announcement stays cleared on the next start
+    announcement::set(&settings, "", NotificationLevel::Info).unwrap();
+    settings.announcement = Some("From settings".into());
+    announcement::load(&settings).unwrap();
+    assert_eq!(announcement::current(), None);
+
+    std::fs::remove_dir_all(&settings.data_dir).ok();
+}
+
+#[test]
+fn announcement_key_follows_the_message() {
+    use nsw_closest_display::announcement::Announcement;
+
+    let announcement = |message: &str, hour: u32| Announcement {
+        message: message.to_string(),
+        level: NotificationLevel::Warning,
+        updated_at: chrono::TimeZone::with_ymd_and_hms(&chrono::Utc, 2025, 6, 10, hour, 0, 0).unwrap(),
+    };
+
+    // Loading from settings again on restart doesn't bring a dismissed one back
+    assert_eq!(announcement("Portal down tonight", 1).key(), announcement("Portal down tonight", 2).key());
+    assert_ne!(announcement("Portal down tonight", 1).key(), announcement("Portal down tomorrow", 1).key());
+}
+
+#[tokio::test]
+async fn status_is_reported_per_test_type() {
+    let response = api::router::<()>()
+        .oneshot(Request::get("/api/status/rider").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["degraded"], false);
+    assert_eq!(json["consecutive_failures"], 0);
+}
+
+#[tokio::test]
+async fn capacity_endpoint() {
+    load_fixture();
+
+    let response = api::router::<()>()
+        .oneshot(Request::get("/api/capacity/car").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let centres = json.as_array().unwrap();
+    assert!(!centres.is_empty());
+    for centre in centres {
+        assert!(centre["available_slots"].as_u64() <= centre["total_slots"].as_u64());
+    }
+}
+
+#[tokio::test]
+async fn status_reports_maintenance_window() {
+    use nsw_closest_display::data::maintenance::{self, MaintenanceWindow};
+
+    let now = chrono::Local::now().naive_local();
+    maintenance::record(MaintenanceWindow {
+        message: "myRTA is undergoing maintenance".to_string(),
+        detected_at: now,
+        until: now + chrono::Duration::hours(2),
+    });
+
+    let response = api::router::<()>()
+        .oneshot(Request::get("/api/status").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["maintenance"]["message"], "myRTA is undergoing maintenance");
+
+    maintenance::clear();
+}
+
+#[tokio::test]
+async fn status_reports_blocked_window() {
+    use nsw_closest_display::data::challenge::{self, BlockedWindow};
+
+    let now = chrono::Local::now().naive_local();
+    challenge::record(BlockedWindow {
+        marker: "g-recaptcha".to_string(),
+        detected_at: now,
+        until: now + chrono::Duration::hours(2),
+    });
+
+    let response = api::router::<()>()
+        .oneshot(Request::get("/api/status").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["blocked"]["marker"], "g-recaptcha");
+
+    challenge::clear();
+}
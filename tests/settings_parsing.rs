@@ -0,0 +1,277 @@
+//! Property and edge-case tests for `${VAR}` interpolation and YAML settings.
+
+use std::time::Duration;
+
+use proptest::prelude::*;
+
+use nsw_closest_display::data::fingerprint;
+use nsw_closest_display::data::location::{LocationId, LocationManager};
+use nsw_closest_display::data::rta_http::ScrapeBackend;
+use nsw_closest_display::data::schedule::ScrapeWindow;
+use nsw_closest_display::data::selectors::{self, Selectors};
+use nsw_closest_display::settings::{interpolate_with, EnvVarError, LoginMode, Settings, Step};
+
+fn lookup(name: &str) -> Option<String> {
+    match name {
+        "BOOKING_ID" => Some("12345678".to_string()),
+        "LAST_NAME" => Some("Doe".to_string()),
+        "EMPTY" => Some(String::new()),
+        _ => None,
+    }
+}
+
+const MINIMAL_YAML: &str = r#"
+headless: true
+booking_id: "${BOOKING_ID}"
+last_name: "Doe"
+have_booking: false
+selenium_driver_url: "http://localhost:4444"
+selenium_element_timout: 20000
+selenium_element_polling: 100
+retries: 3
+scrape_refresh_minutes: 20
+"#;
+
+#[test]
+fn whole_value_reference() {
+    assert_eq!(interpolate_with("${BOOKING_ID}", lookup), Ok("12345678".to_string()));
+}
+
+#[test]
+fn embedded_and_repeated_references() {
+    assert_eq!(
+        interpolate_with("id=${BOOKING_ID}, name=${LAST_NAME}${LAST_NAME}", lookup),
+        Ok("id=12345678, name=DoeDoe".to_string())
+    );
+}
+
+#[test]
+fn lone_dollar_and_braces_are_literal() {
+    assert_eq!(interpolate_with("$5 {x} $ {y}", lookup), Ok("$5 {x} $ {y}".to_string()));
+}
+
+#[test]
+fn error_positions() {
+    assert_eq!(interpolate_with("abc${BOOKING_ID", lookup), Err(EnvVarError::Unterminated { position: 3 }));
+    assert_eq!(interpolate_with("x${}", lookup), Err(EnvVarError::EmptyName { position: 1 }));
+    assert_eq!(interpolate_with("${A${B}}", lookup), Err(EnvVarError::Nested { position: 3 }));
+    assert_eq!(
+        interpolate_with("${BOOKING_ID}-${NOPE}", lookup),
+        Err(EnvVarError::Missing { position: 14, name: "NOPE".to_string() })
+    );
+    assert_eq!(
+        interpolate_with("${EMPTY}", lookup),
+        Err(EnvVarError::EmptyValue { position: 0, name: "EMPTY".to_string() })
+    );
+    assert_eq!(
+        interpolate_with("${1BAD}", lookup),
+        Err(EnvVarError::InvalidName { position: 0, name: "1BAD".to_string() })
+    );
+}
+
+#[test]
+fn missing_field_is_named() {
+    let yaml = MINIMAL_YAML.replace("retries: 3\n", "");
+    let err = Settings::from_yaml_str(&yaml).err().expect("missing field should fail").to_string();
+    assert!(err.contains("retries"), "{}", err);
+}
+
+#[test]
+fn interpolation_error_names_the_field() {
+    let yaml = MINIMAL_YAML.replace("${BOOKING_ID}", "${NSW_DT_SURELY_UNSET_VARIABLE}");
+    let err = Settings::from_yaml_str(&yaml).err().expect("unset variable should fail").to_string();
+    assert!(err.starts_with("booking_id:"), "{}", err);
+}
+
+#[test]
+fn optional_fields_default() {
+    let yaml = MINIMAL_YAML.replace("${BOOKING_ID}", "12345678");
+    let settings = Settings::from_yaml_str(&yaml).unwrap();
+    assert!(settings.notification_webhooks.is_empty());
+    assert_eq!(settings.remote_api_url, None);
+}
+
+#[test]
+fn scrape_area_needs_every_field() {
+    let yaml = MINIMAL_YAML.replace("${BOOKING_ID}", "12345678");
+    let partial = format!("{}home_lat: -33.87\nhome_lng: 151.21\n", yaml);
+    let err = Settings::from_yaml_str(&partial).err().expect("partial area should fail").to_string();
+    assert!(err.contains("scrape_radius_km"), "{}", err);
+
+    let full = format!("{}scrape_radius_km: 30\n", partial);
+    assert_eq!(Settings::from_yaml_str(&full).unwrap().scrape_area(), Some((-33.87, 151.21, 30.0)));
+}
+
+#[test]
+fn licence_login_needs_credentials() {
+    let yaml = format!("{}login_mode: licence\n", MINIMAL_YAML.replace("${BOOKING_ID}", "12345678"));
+    let err = Settings::from_yaml_str(&yaml).err().expect("missing password should fail").to_string();
+    assert!(err.contains("licence_number and password"), "{}", err);
+
+    let full = format!("{}licence_number: \"12345678\"\npassword: \"hunter2\"\n", yaml);
+    assert_eq!(Settings::from_yaml_str(&full).unwrap().login_mode, LoginMode::Licence);
+}
+
+#[test]
+fn held_slots_need_a_public_url() {
+    let yaml = format!("{}confirm_bookings: true\n", MINIMAL_YAML.replace("${BOOKING_ID}", "12345678"));
+    let err = Settings::from_yaml_str(&yaml).err().expect("relative links should fail").to_string();
+    assert!(err.contains("public_url"), "{}", err);
+
+    let full = format!("{}public_url: \"https://example.com\"\n", yaml);
+    assert!(Settings::from_yaml_str(&full).unwrap().confirm_bookings);
+}
+
+#[test]
+fn auto_find_interval_is_bounded() {
+    let yaml = MINIMAL_YAML.replace("${BOOKING_ID}", "12345678");
+    let settings = Settings::from_yaml_str(&yaml).unwrap();
+    assert_eq!((settings.auto_find_interval_minutes, settings.auto_find_jitter_minutes), (10, 2));
+
+    let zero = format!("{}auto_find_interval_minutes: 0\n", yaml);
+    let err = Settings::from_yaml_str(&zero).err().expect("zero interval should fail").to_string();
+    assert!(err.contains("between 1 and 1440"), "{}", err);
+
+    let jittery = format!("{}auto_find_interval_minutes: 5\nauto_find_jitter_minutes: 6\n", yaml);
+    let err = Settings::from_yaml_str(&jittery).err().expect("jitter over the interval should fail").to_string();
+    assert!(err.contains("auto_find_jitter_minutes"), "{}", err);
+}
+
+#[test]
+fn scrape_window_needs_both_bounds() {
+    let yaml = MINIMAL_YAML.replace("${BOOKING_ID}", "12345678");
+    let half = format!("{}scrape_window_start: \"06:00\"\n", yaml);
+    let err = Settings::from_yaml_str(&half).err().expect("half a window should fail").to_string();
+    assert!(err.contains("set together"), "{}", err);
+
+    let full = format!("{}scrape_window_end: \"23:00\"\n", half);
+    let window = Settings::from_yaml_str(&full).unwrap().scrape_window().unwrap();
+    assert_eq!(window, ScrapeWindow::parse("06:00", "23:00").unwrap());
+}
+
+#[test]
+fn step_timeouts_fall_back_to_the_element_timeout() {
+    let yaml = MINIMAL_YAML.replace("${BOOKING_ID}", "12345678");
+    let patched = format!("{}timeouts:\n  dropdown: 60000\n", yaml);
+    let settings = Settings::from_yaml_str(&patched).unwrap();
+    assert_eq!(settings.step_timeout(Step::Dropdown), Duration::from_millis(60000));
+    assert_eq!(
+        settings.step_timeout(Step::Login),
+        Duration::from_millis(settings.selenium_element_timout)
+    );
+}
+
+#[test]
+fn backend_accepts_either_key() {
+    let yaml = MINIMAL_YAML.replace("${BOOKING_ID}", "12345678");
+    assert_eq!(Settings::from_yaml_str(&yaml).unwrap().scrape_backend, ScrapeBackend::Browser);
+    for key in ["scrape_backend", "backend"] {
+        let patched = format!("{}{}: cdp\n", yaml, key);
+        assert_eq!(Settings::from_yaml_str(&patched).unwrap().scrape_backend, ScrapeBackend::Cdp);
+    }
+}
+
+#[test]
+fn booking_queue_entries() {
+    let yaml = MINIMAL_YAML.replace("${BOOKING_ID}", "12345678");
+    assert!(Settings::from_yaml_str(&yaml).unwrap().booking_queue.is_empty());
+
+    let patched = format!(
+        "{}booking_queue:\n  - booking_id: \"111\"\n    last_name: Doe\n    target_date: 2025-07-01\n    locations: [18]\n  - booking_id: \"222\"\n    last_name: Roe\n    target_date: 2025-08-01\n",
+        yaml
+    );
+    let queue = Settings::from_yaml_str(&patched).unwrap().booking_queue;
+    assert_eq!(queue.len(), 2);
+    assert_eq!(queue[0].locations, vec![LocationId(18)]);
+    assert_eq!(queue[1].last_name, "Roe");
+    assert!(queue[1].locations.is_empty());
+}
+
+#[test]
+fn selectors_override_only_what_is_set() {
+    let yaml = MINIMAL_YAML.replace("${BOOKING_ID}", "12345678");
+    let patched = format!("{}selectors:\n  next_button: \"button.next\"\n", yaml);
+    let selectors = Settings::from_yaml_str(&patched).unwrap().selectors;
+    assert_eq!(selectors.next_button, "button.next");
+    assert_eq!(selectors.location_dropdown, Selectors::default().location_dropdown);
+}
+
+#[test]
+fn shipped_selectors_match_the_defaults() {
+    let yaml = include_str!("../settings.yaml")
+        .replace("${BOOKING_ID}", "12345678")
+        .replace("${LAST_NAME}", "Doe");
+    let settings = Settings::from_yaml_str(&yaml).unwrap();
+    assert_eq!(settings.selectors, Selectors::default());
+    assert_eq!(settings.user_agents, fingerprint::default_user_agents());
+    assert_eq!(settings.window_sizes, fingerprint::default_window_sizes());
+}
+
+#[test]
+fn window_sizes_must_parse() {
+    let yaml = MINIMAL_YAML.replace("${BOOKING_ID}", "12345678");
+    let patched = format!("{}window_sizes: [\"1920x1080\", \"wide\"]\n", yaml);
+    let err = Settings::from_yaml_str(&patched).err().expect("bad size should fail").to_string();
+    assert!(err.contains("\"wide\""), "{}", err);
+}
+
+#[test]
+fn selector_kinds_and_placeholders() {
+    assert!(selectors::is_xpath("//*[text()=\"Book test\"]"));
+    assert!(selectors::is_xpath("(//button)[2]"));
+    assert!(!selectors::is_xpath("#nextButton"));
+    assert_eq!(selectors::fill("#slot-{slot}", "slot", "7"), "#slot-7");
+}
+
+#[test]
+fn scrape_list_is_limited_to_radius() {
+    let yaml = MINIMAL_YAML.replace("${BOOKING_ID}", "12345678");
+    let mut settings = Settings::from_yaml_str(&yaml).unwrap();
+    let manager = LocationManager::new();
+    let all = manager.scrape_list(&settings);
+    assert_eq!(all.len(), manager.get_all().len());
+
+    // Sydney CBD, with Albury added by hand
+    settings.home_lat = Some(-33.8688);
+    settings.home_lng = Some(151.2093);
+    settings.scrape_radius_km = Some(30.0);
+    settings.priority_locations = vec![LocationId(17)];
+    let nearby = manager.scrape_list(&settings);
+
+    assert!(nearby.len() > 1 && nearby.len() < all.len());
+    assert!(nearby.contains(&LocationId(17)));
+    for id in nearby.iter().filter(|&&id| id != LocationId(17)) {
+        assert!(manager.get_by_id(*id).unwrap().distance_from(-33.8688, 151.2093) <= 30.0);
+    }
+}
+
+proptest! {
+    #[test]
+    fn never_panics(value in ".*") {
+        let _ = interpolate_with(&value, lookup);
+    }
+
+    #[test]
+    fn text_without_references_is_unchanged(value in "[^$]*") {
+        prop_assert_eq!(interpolate_with(&value, lookup), Ok(value.clone()));
+    }
+
+    #[test]
+    fn reference_is_replaced(
+        prefix in "[^$]*",
+        suffix in "[^$]*",
+        name in "[A-Z_][A-Z0-9_]{0,15}",
+        replacement in ".+",
+    ) {
+        let value = format!("{}${{{}}}{}", prefix, name, suffix);
+        let result = interpolate_with(&value, |_| Some(replacement.clone()));
+        prop_assert_eq!(result, Ok(format!("{}{}{}", prefix, replacement, suffix)));
+    }
+
+    #[test]
+    fn unterminated_reports_opening_position(prefix in "[^$]*", tail in "[^}]*") {
+        let value = format!("{}${{{}", prefix, tail);
+        let result = interpolate_with(&value, lookup);
+        prop_assert_eq!(result, Err(EnvVarError::Unterminated { position: prefix.len() }));
+    }
+}
@@ -0,0 +1,86 @@
+//! Joining and sorting the locations table rows.
+
+use chrono::NaiveDate;
+
+use nsw_closest_display::data::location::{Location, LocationId};
+use nsw_closest_display::data::shared_booking::{LocationBookingViewModel, TestType, TimeSlot};
+use nsw_closest_display::utils::sort::{sort_locations, LocationsQuery, SortColumn, SortDirection};
+
+fn location(id: u32, name: &str, pass_rate: f64) -> Location {
+    Location {
+        id: LocationId(id),
+        name: name.to_string(),
+        latitude: 0.0,
+        longitude: 0.0,
+        passes: 0,
+        failures: 0,
+        pass_rate,
+    }
+}
+
+fn slot(day: u32) -> TimeSlot {
+    TimeSlot {
+        availability: true,
+        slot_number: None,
+        start_time: NaiveDate::from_ymd_opt(2025, 6, day).unwrap().and_hms_opt(9, 0, 0).unwrap(),
+    }
+}
+
+fn query(sort: SortColumn, direction: SortDirection, max_distance_km: Option<f64>) -> LocationsQuery {
+    LocationsQuery {
+        test_type: TestType::Car,
+        lat: -33.87,
+        lng: 151.21,
+        sort,
+        direction,
+        max_distance_km,
+        hide_near_holidays: false,
+    }
+}
+
+fn fixture() -> (Vec<(Location, f64)>, Vec<LocationBookingViewModel>) {
+    let locations = vec![
+        (location(1, "Burwood", 60.0), 5.0),
+        (location(2, "Auburn", 50.0), 10.0),
+        (location(3, "Chatswood", 70.0), 20.0),
+    ];
+    let summaries = vec![
+        LocationBookingViewModel { location: LocationId(1), earliest_slot: Some(slot(20)) },
+        LocationBookingViewModel { location: LocationId(3), earliest_slot: Some(slot(3)) },
+    ];
+    (locations, summaries)
+}
+
+fn ids(rows: &[nsw_closest_display::utils::sort::SortedLocation]) -> Vec<u32> {
+    rows.iter().map(|row| row.location.id.0).collect()
+}
+
+#[test]
+fn earliest_sort_puts_centres_without_slots_last() {
+    let (locations, summaries) = fixture();
+    let rows = sort_locations(locations, &summaries, &query(SortColumn::EarliestSlot, SortDirection::Ascending, None));
+
+    assert_eq!(ids(&rows), vec![3, 1, 2]);
+    assert_eq!(rows[0].earliest_slot, Some(slot(3)));
+    assert_eq!(rows[0].distance_km, 20.0);
+    assert_eq!(rows[2].earliest_slot, None);
+}
+
+#[test]
+fn max_distance_filters_before_sorting() {
+    let (locations, summaries) = fixture();
+    let rows = sort_locations(locations, &summaries, &query(SortColumn::Name, SortDirection::Descending, Some(10.0)));
+
+    assert_eq!(ids(&rows), vec![1, 2]);
+}
+
+#[test]
+fn query_string_names() {
+    let query: LocationsQuery =
+        serde_json::from_str(r#"{"lat": -33.8, "lng": 151.2, "sort": "earliest", "direction": "desc"}"#).unwrap();
+
+    assert_eq!(query.sort, SortColumn::EarliestSlot);
+    assert_eq!(query.direction, SortDirection::Descending);
+    assert_eq!(query.test_type, TestType::Car);
+    assert_eq!(query.max_distance_km, None);
+}
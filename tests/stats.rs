@@ -0,0 +1,62 @@
+//! Per-location availability figures from raw and compacted history.
+
+mod common;
+
+use chrono::NaiveDateTime;
+
+use nsw_closest_display::data::history::{daily_aggregates, HistoryEntry};
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::shared_booking::{LocationBookings, TestType, TimeSlot};
+use nsw_closest_display::data::stats::availability_stats;
+
+use common::at;
+
+const ARMIDALE: LocationId = LocationId(18);
+
+fn entry(location: LocationId, test_type: TestType, recorded_at: NaiveDateTime, slots: &[NaiveDateTime]) -> HistoryEntry {
+    let bookings = LocationBookings {
+        location,
+        test_type,
+        slots: slots
+            .iter()
+            .map(|&start_time| TimeSlot { availability: true, slot_number: None, start_time })
+            .collect(),
+        next_available_date: None,
+    };
+    HistoryEntry::from_bookings(&bookings, recorded_at)
+}
+
+#[test]
+fn combines_compacted_and_raw_days() {
+    // 2 and 4 days away on the 2nd, compacted; 6 days away on the 3rd, raw
+    let compacted = daily_aggregates(&[
+        entry(ARMIDALE, TestType::Car, at(2, 9), &[at(4, 9), at(5, 9)]),
+        entry(ARMIDALE, TestType::Car, at(2, 15), &[at(6, 15)]),
+    ]);
+    let raw = [
+        entry(ARMIDALE, TestType::Car, at(3, 9), &[at(9, 9), at(10, 9), at(11, 9)]),
+        entry(ARMIDALE, TestType::Rider, at(3, 9), &[at(4, 9)]),
+    ];
+
+    let stats = availability_stats(&raw, &compacted, TestType::Car);
+    assert_eq!(stats.len(), 1);
+    let armidale = &stats[0];
+    assert_eq!((armidale.location, armidale.days, armidale.observations), (ARMIDALE, 2, 3));
+    // (2 + 4 + 6) / 3 observations
+    assert_eq!(armidale.mean_lead_days, Some(4.0));
+    assert_eq!(armidale.mean_available_slots, 2.0);
+    // Daily means of 3 and 6
+    assert_eq!(armidale.lead_volatility_days, Some(1.5));
+}
+
+#[test]
+fn one_day_has_no_volatility() {
+    let raw = [entry(ARMIDALE, TestType::Car, at(3, 9), &[]), entry(LocationId(20), TestType::Car, at(3, 9), &[at(5, 9)])];
+
+    let stats = availability_stats(&raw, &[], TestType::Car);
+    assert_eq!(stats.iter().map(|stats| stats.location).collect::<Vec<_>>(), vec![ARMIDALE, LocationId(20)]);
+    assert_eq!(stats[0].mean_lead_days, None);
+    assert_eq!(stats[0].mean_available_slots, 0.0);
+    assert_eq!(stats[1].mean_lead_days, Some(2.0));
+    assert_eq!(stats[1].lead_volatility_days, None);
+}
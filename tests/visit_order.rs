@@ -0,0 +1,88 @@
+//! Per-run ordering of the centres the scraper visits, and how runs are
+//! spread across sessions and proxies and retried.
+
+mod common;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use nsw_closest_display::data::location::LocationId;
+use nsw_closest_display::data::rta::{location_backoff, next_proxy, partition, visit_order};
+
+use common::settings_with;
+
+fn ids(ids: &[u32]) -> Vec<LocationId> {
+    ids.iter().copied().map(LocationId).collect()
+}
+
+#[test]
+fn priority_locations_come_first() {
+    let locations = ids(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    let priority = ids(&[6, 3]);
+
+    for seed in 0..20 {
+        let order = visit_order(locations.clone(), &priority, true, &mut StdRng::seed_from_u64(seed));
+
+        let mut first: Vec<_> = order[..2].to_vec();
+        first.sort();
+        assert_eq!(first, ids(&[3, 6]));
+
+        let mut all = order.clone();
+        all.sort();
+        assert_eq!(all, locations);
+    }
+}
+
+#[test]
+fn runs_use_different_orders() {
+    let locations = ids(&[1, 2, 3, 4, 5, 6, 7, 8]);
+
+    let first = visit_order(locations.clone(), &[], true, &mut StdRng::seed_from_u64(1));
+    let second = visit_order(locations.clone(), &[], true, &mut StdRng::seed_from_u64(2));
+    assert_ne!(first, second);
+}
+
+#[test]
+fn order_is_kept_without_shuffling() {
+    let order = visit_order(ids(&[1, 2, 3, 4]), &ids(&[4]), false, &mut StdRng::seed_from_u64(0));
+    assert_eq!(order, ids(&[4, 1, 2, 3]));
+}
+
+#[test]
+fn partition_deals_locations_in_turn() {
+    let parts = partition(ids(&[1, 2, 3, 4, 5]), 2);
+    assert_eq!(parts, vec![ids(&[1, 3, 5]), ids(&[2, 4])]);
+
+    // More workers than locations leaves no empty parts
+    let parts = partition(ids(&[1, 2]), 4);
+    assert_eq!(parts, vec![ids(&[1]), ids(&[2])]);
+
+    assert_eq!(partition(ids(&[1, 2]), 0), vec![ids(&[1, 2])]);
+}
+
+#[test]
+fn proxies_rotate_per_session() {
+    let settings = settings_with(
+        r#"
+proxies:
+  - "http://10.0.0.1:8080"
+  - "socks5://10.0.0.2:1080"
+"#,
+    );
+
+    let first = next_proxy(&settings).unwrap().to_string();
+    let second = next_proxy(&settings).unwrap().to_string();
+    assert_ne!(first, second);
+    assert_eq!(next_proxy(&settings).unwrap(), first);
+
+    let mut without = settings.clone();
+    without.proxies.clear();
+    assert_eq!(next_proxy(&without), None);
+}
+
+#[test]
+fn location_retries_back_off_exponentially() {
+    let waits: Vec<_> = (1..=4).map(|attempt| location_backoff(attempt, 2000).as_millis()).collect();
+    assert_eq!(waits, vec![2000, 4000, 8000, 16000]);
+    assert_eq!(location_backoff(30, 2000).as_secs(), 60);
+}